@@ -0,0 +1,266 @@
+//! Provides the derive macros `harmony::ShallowEq` and `harmony::Lens`.
+extern crate proc_macro;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DeriveInput, Fields, GenericParam,
+    Ident, Index, Visibility,
+};
+
+/// Implements `harmony::ShallowEq` by comparing each field using
+/// `ShallowEq`. Each type parameter is required to implement `ShallowEq`.
+///
+/// Types implementing `PartialEq` already implement `ShallowEq` through a
+/// blanket impl. Deriving `ShallowEq` for such a type overrides it, which
+/// requires `#![feature(specialization)]` in the crate defining the type.
+#[proc_macro_derive(ShallowEq)]
+pub fn derive_shallow_eq(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input: DeriveInput = parse_macro_input!(input);
+
+    for param in input.generics.params.iter_mut() {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(::harmony::ShallowEq));
+        }
+    }
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pat_self, pat_other, cmp) = fields_shallow_eq(&data.fields);
+            quote! {
+                let Self #pat_self = self;
+                let Self #pat_other = other;
+                #cmp
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let (pat_self, pat_other, cmp) = fields_shallow_eq(&variant.fields);
+                quote! {
+                    (Self::#ident #pat_self, Self::#ident #pat_other) => #cmp,
+                }
+            });
+            quote! {
+                #[allow(unreachable_patterns)]
+                match (self, other) {
+                    #(#arms)*
+                    _ => false,
+                }
+            }
+        }
+        Data::Union(data) => {
+            return syn::Error::new(
+                data.union_token.span(),
+                "`ShallowEq` can't be derived for unions",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    (quote! {
+        impl #impl_generics ::harmony::ShallowEq for #name #ty_generics #where_clause {
+            fn shallow_eq(&self, other: &Self) -> bool {
+                #body
+            }
+        }
+    })
+    .into()
+}
+
+/// Generate patterns to destructure two values having `fields` and an
+/// expression comparing the fields of the two values.
+fn fields_shallow_eq(fields: &Fields) -> (TokenStream, TokenStream, TokenStream) {
+    let bindings = |prefix: &str| -> Vec<Ident> {
+        (0..fields.len())
+            .map(|i| format_ident!("{}{}", prefix, i))
+            .collect()
+    };
+    let self_bindings = bindings("__self_");
+    let other_bindings = bindings("__other_");
+
+    let pattern = |bindings: &[Ident]| match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|f| &f.ident);
+            quote! { { #(#names: #bindings),* } }
+        }
+        Fields::Unnamed(fields) => {
+            let indices = (0..fields.unnamed.len()).map(Index::from);
+            quote! { { #(#indices: #bindings),* } }
+        }
+        Fields::Unit => quote! {},
+    };
+
+    let cmp = quote! {
+        true #(&& ::harmony::ShallowEq::shallow_eq(#self_bindings, #other_bindings))*
+    };
+
+    (pattern(&self_bindings), pattern(&other_bindings), cmp)
+}
+
+/// Generates a lens (`harmony::Lens`) for each field of a struct with named
+/// fields.
+///
+/// The lenses are defined in a module named after the struct (e.g.,
+/// `app_state_lenses` for `AppState`) and can be accessed through associated
+/// constants of the struct having the same names as the fields (e.g.,
+/// `AppState::accounts`). They take `Elem` of the struct as a source, so the
+/// struct must implement `Clone`.
+///
+/// Each lens has the same visibility as the field it refers to, except that
+/// it's never more visible than the struct.
+#[proc_macro_derive(Lens)]
+pub fn derive_lens(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = parse_macro_input!(input);
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            fields => {
+                return syn::Error::new(
+                    fields.span(),
+                    "`Lens` can only be derived for structs with named fields",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        _ => {
+            return syn::Error::new(
+                Span::call_site(),
+                "`Lens` can only be derived for structs with named fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return syn::Error::new(
+            input.generics.span(),
+            "`Lens` can't be derived for generic structs",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let mod_name = format_ident!("{}_lenses", to_snake_case(&name.to_string()));
+
+    let field_names: Vec<&Ident> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let field_tys = fields.iter().map(|f| &f.ty);
+
+    // Each lens is as visible as the field it refers to, but not more visible
+    // than the struct. `field_vises` is relative to the module containing the
+    // struct, and `lens_vises` is relative to `#mod_name`.
+    let field_vises: Vec<Visibility> = fields.iter().map(|f| narrower_vis(vis, &f.vis)).collect();
+    let lens_vises = field_vises.iter().map(vis_in_child_mod);
+
+    let field_docs: Vec<String> = field_names
+        .iter()
+        .map(|ident| format!("A lens referring to `{}::{}`.", name, ident))
+        .collect();
+    let mod_doc = format!("Lenses for the fields of `{}`.", name);
+
+    (quote! {
+        #[doc = #mod_doc]
+        #vis mod #mod_name {
+            #(
+                #[doc = #field_docs]
+                #[allow(non_camel_case_types)]
+                #[derive(Debug, Clone, Copy)]
+                #lens_vises struct #field_names;
+            )*
+        }
+
+        #[allow(non_upper_case_globals)]
+        impl #name {
+            #(
+                #[doc = #field_docs]
+                #field_vises const #field_names: #mod_name::#field_names = #mod_name::#field_names;
+            )*
+        }
+
+        #(
+            impl ::harmony::Lens for #mod_name::#field_names {
+                type Source = ::harmony::Elem<#name>;
+                type Target = #field_tys;
+
+                fn get<'a>(&'a self, source: &'a Self::Source) -> Option<&'a Self::Target> {
+                    Some(&source.#field_names)
+                }
+
+                fn get_mut<'a>(
+                    &'a self,
+                    source: &'a mut Self::Source,
+                ) -> Option<&'a mut Self::Target> {
+                    Some(&mut ::harmony::Elem::make_mut(source).#field_names)
+                }
+            }
+        )*
+    })
+    .into()
+}
+
+/// The rank of a visibility used by `narrower_vis`. A larger value means more
+/// visible. Visibilities restricted to a module other than the crate root
+/// share the lowest rank.
+fn vis_rank(vis: &Visibility) -> u8 {
+    match vis {
+        Visibility::Public(_) => 2,
+        Visibility::Crate(_) => 1,
+        Visibility::Restricted(r) if r.path.is_ident("crate") => 1,
+        _ => 0,
+    }
+}
+
+/// Get the narrower one of two visibilities. If they can't be compared, this
+/// function conservatively returns `Visibility::Inherited`.
+fn narrower_vis(x: &Visibility, y: &Visibility) -> Visibility {
+    match (vis_rank(x), vis_rank(y)) {
+        (rx, ry) if rx < ry => x.clone(),
+        (rx, ry) if rx > ry => y.clone(),
+        (0, 0) if quote!(#x).to_string() != quote!(#y).to_string() => Visibility::Inherited,
+        _ => x.clone(),
+    }
+}
+
+/// Convert a visibility relative to a module to the equivalent one relative
+/// to a child module of that module.
+fn vis_in_child_mod(vis: &Visibility) -> Visibility {
+    match vis {
+        Visibility::Inherited => parse_quote!(pub(super)),
+        Visibility::Restricted(r) => {
+            let mut path = (*r.path).clone();
+            let first = &path.segments[0].ident;
+            if first == "self" {
+                path.segments[0].ident = Ident::new("super", first.span());
+            } else if first == "super" {
+                path.segments.insert(0, parse_quote!(super));
+            }
+            parse_quote!(pub(in #path))
+        }
+        _ => vis.clone(),
+    }
+}
+
+/// Convert `CamelCase` to `snake_case`.
+fn to_snake_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 4);
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,178 @@
+//! Provides [`Lens`], a composable reference to a part of a state tree.
+use std::{fmt, marker::PhantomData};
+
+use super::{Elem, ShallowEq};
+
+/// Refers to a part (`Target`) of a state object (`Source`), allowing it to
+/// be read and updated. A lens may refer to nothing, e.g., when it refers to a
+/// vector element satisfying a predicate and there is no such element.
+///
+/// Lenses for struct fields can be generated by `#[derive(Lens)]`. They take
+/// `Elem<T>` as a source and can be composed by [`Lens::then`] to refer to a
+/// deeply nested field. Updating a value through such a lens copies the
+/// `Elem`s on the path as needed (copy-on-write).
+///
+/// # Examples
+///
+/// ```
+/// use harmony::{lens::Find, Elem, Lens};
+///
+/// #[derive(Clone, Lens)]
+/// struct App {
+///     accounts: Elem<Vec<Elem<Account>>>,
+/// }
+///
+/// #[derive(Clone, Lens)]
+/// struct Account {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// let state1 = Elem::new(App {
+///     accounts: Elem::new(vec![
+///         Elem::new(Account { id: 1, name: "Alice".to_owned() }),
+///         Elem::new(Account { id: 2, name: "Bob".to_owned() }),
+///     ]),
+/// });
+///
+/// let name_of = |id| {
+///     App::accounts
+///         .then(Find::new(move |account: &Elem<Account>| account.id == id))
+///         .then(Account::name)
+/// };
+///
+/// let mut state2 = Elem::clone(&state1);
+/// name_of(2).set(&mut state2, "Carol".to_owned());
+///
+/// assert_eq!(state1.accounts[1].name, "Bob");
+/// assert_eq!(state2.accounts[1].name, "Carol");
+///
+/// // Unaffected parts are shared
+/// assert!(Elem::ptr_eq(&state1.accounts[0], &state2.accounts[0]));
+///
+/// // Nothing is copied if nothing is changed
+/// let mut state3 = Elem::clone(&state2);
+/// name_of(2).set(&mut state3, "Carol".to_owned());
+/// name_of(3).set(&mut state3, "Dave".to_owned());
+/// assert!(Elem::ptr_eq(&state2, &state3));
+/// ```
+pub trait Lens {
+    type Source;
+    type Target;
+
+    /// Get a reference to the target. Returns `None` if the lens refers to
+    /// nothing in `source`.
+    fn get<'a>(&'a self, source: &'a Self::Source) -> Option<&'a Self::Target>;
+
+    /// Get a mutable reference to the target. Returns `None` if the lens
+    /// refers to nothing in `source`.
+    ///
+    /// This may copy the `Elem`s on the path to the target even if the
+    /// returned reference is not used to modify the target.
+    fn get_mut<'a>(&'a self, source: &'a mut Self::Source) -> Option<&'a mut Self::Target>;
+
+    /// Replace the target with `value`. Does nothing (and does not copy
+    /// `Elem`s) if the lens refers to nothing or `value` is identical to the
+    /// current value according to [`ShallowEq`].
+    fn set(&self, source: &mut Self::Source, value: Self::Target)
+    where
+        Self::Target: ShallowEq,
+    {
+        match self.get(source) {
+            Some(old_value) if old_value.shallow_ne(&value) => {}
+            _ => return,
+        }
+
+        *self.get_mut(source).unwrap() = value;
+    }
+
+    /// Replace the target with the result of `f`. Does nothing (and does not
+    /// copy `Elem`s) if the lens refers to nothing or the result is identical
+    /// to the current value according to [`ShallowEq`].
+    fn update(&self, source: &mut Self::Source, f: impl FnOnce(&Self::Target) -> Self::Target)
+    where
+        Self::Target: ShallowEq,
+    {
+        if let Some(old_value) = self.get(source) {
+            let new_value = f(old_value);
+            self.set(source, new_value);
+        }
+    }
+
+    /// Construct a lens referring to the target of `other` in the target of
+    /// `self`.
+    fn then<Other>(self, other: Other) -> Then<Self, Other>
+    where
+        Self: Sized,
+        Other: Lens<Source = Self::Target>,
+    {
+        Then(self, other)
+    }
+}
+
+/// The lens returned by [`Lens::then`].
+#[derive(Debug, Clone, Copy)]
+pub struct Then<A, B>(A, B);
+
+impl<A, B> Lens for Then<A, B>
+where
+    A: Lens,
+    B: Lens<Source = A::Target>,
+{
+    type Source = A::Source;
+    type Target = B::Target;
+
+    fn get<'a>(&'a self, source: &'a Self::Source) -> Option<&'a Self::Target> {
+        self.1.get(self.0.get(source)?)
+    }
+
+    fn get_mut<'a>(&'a self, source: &'a mut Self::Source) -> Option<&'a mut Self::Target> {
+        // Don't copy `Elem`s if `self.1` refers to nothing
+        self.get(source)?;
+
+        self.1.get_mut(self.0.get_mut(source)?)
+    }
+}
+
+/// A lens referring to the first element of `Elem<Vec<T>>` satisfying a
+/// predicate.
+pub struct Find<T, F> {
+    pred: F,
+    _phantom: PhantomData<fn(&T)>,
+}
+
+impl<T, F> Find<T, F>
+where
+    F: Fn(&T) -> bool,
+{
+    /// Construct a `Find` with the specified predicate.
+    pub fn new(pred: F) -> Self {
+        Self {
+            pred,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for Find<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Find").finish()
+    }
+}
+
+impl<T: Clone, F> Lens for Find<T, F>
+where
+    F: Fn(&T) -> bool,
+{
+    type Source = Elem<Vec<T>>;
+    type Target = T;
+
+    fn get<'a>(&'a self, source: &'a Elem<Vec<T>>) -> Option<&'a T> {
+        source.iter().find(|e| (self.pred)(e))
+    }
+
+    fn get_mut<'a>(&'a self, source: &'a mut Elem<Vec<T>>) -> Option<&'a mut T> {
+        let i = source.iter().position(|e| (self.pred)(e))?;
+        Some(&mut Elem::make_mut(source)[i])
+    }
+}
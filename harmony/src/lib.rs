@@ -2,6 +2,16 @@
 //! [Redux](https://redux.js.org) in Rust.
 //!
 //!  - *Elements* ([`Elem`]) store state data, updated through *reducers*.
+//!  - A *store* ([`Store`]) owns the state and applies *actions* to it in
+//!    batches. *Middlewares* ([`Middleware`]) intercept actions and state
+//!    changes, and *subscribers* are notified of changes in the parts of the
+//!    state they are interested in.
+//!  - *Lenses* ([`Lens`]) refer to parts of the state, which can be updated
+//!    in a copy-on-write manner.
+//!  - A *recorder* ([`Recorder`]) records actions and states for debugging.
+//!
+//! `#[derive(ShallowEq)]` and `#[derive(Lens)]` implement [`ShallowEq`] and
+//! lenses for user types.
 //!
 //! # Usage
 //!
@@ -57,21 +67,75 @@
 //!     let state = App::reduce(state, &Action::Wnd(WndAction::Increment));
 //!     assert_eq!(state.main_wnd.count, 2);
 //!
+//! # Lenses
+//!
+//! A lens derived by `#[derive(Lens)]` is only accessible where the field it
+//! refers to is accessible:
+//!
+//!     mod model {
+//!         #[derive(Clone, harmony::Lens)]
+//!         pub struct Wnd {
+//!             pub count: usize,
+//!             secret: usize,
+//!         }
+//!
+//!         impl Wnd {
+//!             pub fn new() -> Self {
+//!                 Self { count: 1, secret: 2 }
+//!             }
+//!         }
+//!     }
+//!
+//!     use harmony::{Elem, Lens};
+//!     let wnd = Elem::new(model::Wnd::new());
+//!     assert_eq!(model::Wnd::count.get(&wnd), Some(&1));
+//!
+//! ```compile_fail
+//! mod model {
+//!     #[derive(Clone, harmony::Lens)]
+//!     pub struct Wnd {
+//!         pub count: usize,
+//!         secret: usize,
+//!     }
+//! }
+//!
+//! // error: `secret` is private
+//! let _ = model::wnd_lenses::secret;
+//! ```
+//!
 #![feature(specialization)]
 use std::{fmt, rc::Rc};
 
 #[cfg(feature = "miniserde")]
 mod miniserde;
 
+pub mod lens;
+pub mod recorder;
+mod store;
+pub use self::{
+    lens::Lens,
+    recorder::Recorder,
+    store::{Middleware, Store, Subscription},
+};
+pub use harmony_derive::{Lens, ShallowEq};
+
 /// A container type for state data.
 ///
 /// `Elem` is conceptually immutable, but may perform in-place mutation when
 /// there are no other owners.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Elem<T: ?Sized> {
     inner: Rc<T>,
 }
 
+impl<T: ?Sized> Clone for Elem<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
 impl<T> Elem<T> {
     /// Construct a `Elem` with the specified inner value.
     pub fn new(x: T) -> Self {
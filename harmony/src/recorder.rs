@@ -0,0 +1,404 @@
+//! Provides [`Recorder`], a middleware to record actions and states for
+//! debugging.
+use std::{cell::RefCell, collections::VecDeque, fmt, rc::Rc};
+
+use super::{Elem, Middleware, Store};
+
+/// A [`Middleware`] that records the dispatched actions and the resulting
+/// states in a ring buffer.
+///
+/// State snapshots are cheap to keep because unchanged parts of the state
+/// tree are shared between snapshots.
+///
+/// The recorder should be added to the end of the middleware chain so that
+/// it records the actions as they are passed to the reducer.
+///
+/// A recorded state can be restored by [`Store::set_state`] to "travel in
+/// time". If an action is dispatched after that, the recorded actions
+/// following the restored state are discarded.
+///
+/// # Examples
+///
+/// ```
+/// use harmony::{Elem, Recorder, Store};
+///
+/// let reduce = |state: Elem<i32>, action: &i32| Elem::new(*state + *action);
+/// let store = Store::new(Elem::new(0), reduce, |f| f());
+/// let recorder = Recorder::new(100);
+/// store.add_middleware(recorder.clone());
+///
+/// store.dispatch(1);
+/// store.dispatch(2);
+/// assert_eq!(*store.state(), 3);
+///
+/// // Go back to the initial state
+/// store.set_state(recorder.state(0).unwrap());
+/// assert_eq!(*store.state(), 0);
+///
+/// // Replay the actions
+/// let log = recorder.log().unwrap();
+/// assert_eq!(log.actions, [1, 2]);
+/// assert_eq!(*log.replay(reduce), 3);
+/// ```
+pub struct Recorder<S, A> {
+    inner: Rc<RefCell<RecorderState<S, A>>>,
+}
+
+struct RecorderState<S, A> {
+    capacity: usize,
+    /// The state before the first recorded action was applied. `None` if
+    /// nothing has been recorded yet.
+    initial_state: Option<Elem<S>>,
+    entries: VecDeque<Entry<S, A>>,
+}
+
+struct Entry<S, A> {
+    action: A,
+    /// The state after `action` was applied.
+    state: Elem<S>,
+}
+
+/// A sequence of actions and the state to which they are applied.
+#[derive(Debug, Clone)]
+pub struct Log<S, A> {
+    pub initial_state: Elem<S>,
+    pub actions: Vec<A>,
+}
+
+impl<S, A> Clone for Recorder<S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<S, A> fmt::Debug for Recorder<S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.borrow();
+        f.debug_struct("Recorder")
+            .field("capacity", &state.capacity)
+            .field("len", &state.entries.len())
+            .finish()
+    }
+}
+
+impl<S, A> Recorder<S, A> {
+    /// Construct a `Recorder` that retains up to `capacity` most recent
+    /// actions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert_ne!(capacity, 0);
+        Self {
+            inner: Rc::new(RefCell::new(RecorderState {
+                capacity,
+                initial_state: None,
+                entries: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Get the number of recorded actions.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.len()
+    }
+
+    /// Get a flag indicating whether no actions have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the state after the first `i` recorded actions were applied.
+    /// `state(0)` returns the state before the first recorded action was
+    /// applied.
+    ///
+    /// Returns `None` if `i > self.len()` or nothing has been recorded yet.
+    pub fn state(&self, i: usize) -> Option<Elem<S>> {
+        let state = self.inner.borrow();
+        if i == 0 {
+            state.initial_state.clone()
+        } else {
+            state.entries.get(i - 1).map(|e| Elem::clone(&e.state))
+        }
+    }
+
+    /// Find the index of `state` among the recorded states, i.e., the value
+    /// `i` such that `self.state(i)` returns `state`. The states are compared
+    /// by identity.
+    pub fn position(&self, state: &Elem<S>) -> Option<usize> {
+        self.inner.borrow().position(state)
+    }
+
+    /// Discard the recorded actions and states.
+    pub fn clear(&self) {
+        let mut state = self.inner.borrow_mut();
+        state.initial_state = None;
+        state.entries.clear();
+    }
+
+    /// Get the recorded actions and the state to which they were applied.
+    /// Returns `None` if nothing has been recorded yet.
+    pub fn log(&self) -> Option<Log<S, A>>
+    where
+        A: Clone,
+    {
+        let state = self.inner.borrow();
+        Some(Log {
+            initial_state: state.initial_state.clone()?,
+            actions: state.entries.iter().map(|e| e.action.clone()).collect(),
+        })
+    }
+}
+
+impl<S, A> RecorderState<S, A> {
+    fn position(&self, state: &Elem<S>) -> Option<usize> {
+        if Elem::ptr_eq(self.initial_state.as_ref()?, state) {
+            return Some(0);
+        }
+        // Search from the end because the current state is usually the
+        // latest one
+        (self.entries.iter())
+            .rposition(|e| Elem::ptr_eq(&e.state, state))
+            .map(|i| i + 1)
+    }
+
+    fn push(&mut self, old_state: Elem<S>, action: A, new_state: Elem<S>) {
+        match self.position(&old_state) {
+            Some(i) => {
+                // Discard the future states if we have travelled in time
+                self.entries.truncate(i);
+            }
+            None => {
+                // The state was changed without passing through the
+                // recorder. The recorded actions can't reproduce the current
+                // state anymore, so start over.
+                self.initial_state = Some(old_state);
+                self.entries.clear();
+            }
+        }
+
+        if self.entries.len() == self.capacity {
+            let oldest = self.entries.pop_front().unwrap();
+            self.initial_state = Some(oldest.state);
+        }
+
+        self.entries.push_back(Entry {
+            action,
+            state: new_state,
+        });
+    }
+}
+
+impl<S: 'static, A: Clone + 'static> Middleware<S, A> for Recorder<S, A> {
+    fn dispatch(&self, store: &Store<S, A>, action: A, next: &mut dyn FnMut(A)) {
+        let old_state = store.state();
+        let recorded_action = action.clone();
+
+        next(action);
+
+        (self.inner.borrow_mut()).push(old_state, recorded_action, store.state());
+    }
+}
+
+impl<S, A> Log<S, A> {
+    /// Apply the actions to the initial state using `reducer` and return the
+    /// final state.
+    pub fn replay(&self, reducer: impl Fn(Elem<S>, &A) -> Elem<S>) -> Elem<S> {
+        self.actions
+            .iter()
+            .fold(Elem::clone(&self.initial_state), |state, action| {
+                reducer(state, action)
+            })
+    }
+}
+
+#[cfg(feature = "miniserde")]
+mod json_impl {
+    use miniserde::json;
+
+    use super::{Elem, Log};
+
+    /// Conversion to and from a JSON value, used to export and import [`Log`].
+    pub trait JsonRepr: Sized {
+        fn to_json(&self) -> json::Value;
+
+        /// Returns `None` if `value` is malformed.
+        fn from_json(value: &json::Value) -> Option<Self>;
+    }
+
+    impl<S: JsonRepr, A: JsonRepr> Log<S, A> {
+        /// Serialize `self` as a JSON string.
+        pub fn to_json(&self) -> String {
+            let mut actions = json::Array::new();
+            for action in self.actions.iter() {
+                actions.push(action.to_json());
+            }
+
+            let mut obj = json::Object::new();
+            obj.insert("initial_state".to_owned(), self.initial_state.to_json());
+            obj.insert("actions".to_owned(), json::Value::Array(actions));
+            json::to_string(&json::Value::Object(obj))
+        }
+
+        /// Deserialize a JSON string created by [`Log::to_json`]. Returns
+        /// `None` if it's malformed.
+        pub fn from_json(json: &str) -> Option<Self> {
+            let obj = match json::from_str(json).ok()? {
+                json::Value::Object(obj) => obj,
+                _ => return None,
+            };
+
+            let initial_state = S::from_json(obj.get("initial_state")?)?;
+            let actions = match obj.get("actions")? {
+                json::Value::Array(actions) => actions
+                    .iter()
+                    .map(A::from_json)
+                    .collect::<Option<Vec<_>>>()?,
+                _ => return None,
+            };
+
+            Some(Self {
+                initial_state: Elem::new(initial_state),
+                actions,
+            })
+        }
+    }
+}
+
+#[cfg(feature = "miniserde")]
+pub use self::json_impl::JsonRepr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_store() -> (Store<Vec<u32>, u32>, Recorder<Vec<u32>, u32>) {
+        let store = Store::new(
+            Elem::new(Vec::new()),
+            |mut state: Elem<Vec<u32>>, action: &u32| {
+                state.make_mut().push(*action);
+                state
+            },
+            |f| f(),
+        );
+        let recorder = Recorder::new(3);
+        store.add_middleware(recorder.clone());
+        (store, recorder)
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let (store, recorder) = new_store();
+        assert!(recorder.log().is_none());
+
+        for i in 0..5 {
+            store.dispatch(i);
+        }
+
+        assert_eq!(recorder.len(), 3);
+        assert_eq!(*recorder.state(0).unwrap(), [0, 1]);
+        assert_eq!(*recorder.state(3).unwrap(), [0, 1, 2, 3, 4]);
+        assert!(recorder.state(4).is_none());
+
+        let log = recorder.log().unwrap();
+        assert_eq!(log.actions, [2, 3, 4]);
+    }
+
+    #[test]
+    fn time_travel() {
+        let (store, recorder) = new_store();
+        store.dispatch(1);
+        store.dispatch(2);
+
+        store.set_state(recorder.state(1).unwrap());
+        assert_eq!(recorder.position(&store.state()), Some(1));
+
+        // The future is rewritten
+        store.dispatch(3);
+        assert_eq!(recorder.log().unwrap().actions, [1, 3]);
+        assert_eq!(*store.state(), [1, 3]);
+    }
+
+    #[test]
+    fn external_change() {
+        let (store, recorder) = new_store();
+        store.dispatch(1);
+
+        // The recorder can't reproduce this state, so it starts over
+        store.set_state(Elem::new(vec![42]));
+        store.dispatch(2);
+
+        let log = recorder.log().unwrap();
+        assert_eq!(*log.initial_state, [42]);
+        assert_eq!(log.actions, [2]);
+    }
+
+    #[cfg(feature = "miniserde")]
+    mod json {
+        use miniserde::json::{Array, Number, Value};
+
+        use super::*;
+
+        impl JsonRepr for u32 {
+            fn to_json(&self) -> Value {
+                Value::Number(Number::U64(*self as u64))
+            }
+
+            fn from_json(value: &Value) -> Option<Self> {
+                match value {
+                    Value::Number(Number::U64(x)) => Some(*x as u32),
+                    _ => None,
+                }
+            }
+        }
+
+        impl JsonRepr for Vec<u32> {
+            fn to_json(&self) -> Value {
+                let mut array = Array::new();
+                for x in self.iter() {
+                    array.push(x.to_json());
+                }
+                Value::Array(array)
+            }
+
+            fn from_json(value: &Value) -> Option<Self> {
+                match value {
+                    Value::Array(array) => array.iter().map(u32::from_json).collect(),
+                    _ => None,
+                }
+            }
+        }
+
+        #[test]
+        fn export_import_replay() {
+            let (store, recorder) = new_store();
+            for i in 0..5 {
+                store.dispatch(i);
+            }
+
+            let exported = recorder.log().unwrap().to_json();
+            let log: Log<Vec<u32>, u32> = Log::from_json(&exported).unwrap();
+            assert_eq!(*log.initial_state, [0, 1]);
+            assert_eq!(log.actions, [2, 3, 4]);
+
+            // Replaying the imported log reproduces the final state
+            let final_state = log.replay(|mut state, action| {
+                state.make_mut().push(*action);
+                state
+            });
+            assert_eq!(*final_state, *store.state());
+        }
+
+        #[test]
+        fn import_malformed() {
+            assert!(Log::<Vec<u32>, u32>::from_json("[]").is_none());
+            assert!(
+                Log::<Vec<u32>, u32>::from_json(r#"{"initial_state":[],"actions":["x"]}"#)
+                    .is_none()
+            );
+        }
+    }
+}
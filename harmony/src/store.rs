@@ -0,0 +1,454 @@
+//! Provides [`Store`], which owns an application state and applies actions
+//! to it.
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use super::{Elem, ShallowEq};
+
+/// Owns a state tree (`Elem<S>`) and updates it by applying actions (`A`)
+/// through a reducer function.
+///
+/// Actions passed to [`Store::dispatch`] are queued and processed in a batch
+/// by a function scheduled through the scheduler function supplied to
+/// [`Store::new`]. For each batch, the actions are passed through the chain
+/// of [`Middleware`]s and then applied to the state, after which the
+/// subscribers registered by [`Store::subscribe`] are notified of the
+/// changes.
+///
+/// `Store` is a reference-counted handle; cloning it creates another handle to
+/// the same store.
+///
+/// # Examples
+///
+/// ```
+/// use harmony::{Elem, Store};
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// // Run scheduled functions when requested
+/// let queue: Rc<RefCell<Vec<Box<dyn FnOnce()>>>> = Rc::default();
+/// let queue2 = Rc::clone(&queue);
+/// let run_queue = || {
+///     let fns: Vec<_> = queue.borrow_mut().drain(..).collect();
+///     fns.into_iter().for_each(|f| f());
+/// };
+///
+/// let store = Store::new(
+///     Elem::new(1),
+///     |state: Elem<i32>, action: &i32| Elem::new(*state + *action),
+///     move |f| queue2.borrow_mut().push(f),
+/// );
+///
+/// let log = Rc::new(RefCell::new(Vec::new()));
+/// let log2 = Rc::clone(&log);
+/// store.subscribe(|state| **state % 2 == 0, move |is_even| {
+///     log2.borrow_mut().push(*is_even);
+/// });
+///
+/// store.dispatch(1);
+/// store.dispatch(2);
+/// assert_eq!(*store.state(), 1); // not processed yet
+///
+/// run_queue();
+/// assert_eq!(*store.state(), 4);
+/// assert_eq!(*log.borrow(), [true]);
+/// ```
+pub struct Store<S, A> {
+    inner: Rc<Inner<S, A>>,
+}
+
+struct Inner<S, A> {
+    state: RefCell<Elem<S>>,
+    reducer: Box<dyn Fn(Elem<S>, &A) -> Elem<S>>,
+    schedule: Box<dyn Fn(ScheduledFn)>,
+    pending_actions: RefCell<Vec<A>>,
+    /// `true` while `Store::flush` is running.
+    flushing: Cell<bool>,
+    middlewares: RefCell<Vec<Rc<dyn Middleware<S, A>>>>,
+    subscribers: RefCell<Vec<(u64, SubscriberFn<S>)>>,
+    next_subscriber_id: Cell<u64>,
+}
+
+type ScheduledFn = Box<dyn FnOnce()>;
+
+type SubscriberFn<S> = Rc<RefCell<dyn FnMut(&Elem<S>)>>;
+
+/// Intercepts the actions dispatched to [`Store`] and the resulting state
+/// changes. Middlewares can be used to implement logging, persistence, and
+/// other side effects.
+pub trait Middleware<S, A> {
+    /// Process an action before it's applied to the state.
+    ///
+    /// The implementation should call `next` to pass the action to the next
+    /// middleware in the chain (or the reducer if there are no more
+    /// middlewares). It may instead pass a different action, call `next`
+    /// more than once, or not call `next` at all to discard the action.
+    /// [`Store::state`] returns the updated state after `next` returns.
+    ///
+    /// The default implementation just calls `next`.
+    fn dispatch(&self, store: &Store<S, A>, action: A, next: &mut dyn FnMut(A)) {
+        let _ = store;
+        next(action);
+    }
+
+    /// Called after a batch of actions has changed the state, before the
+    /// subscribers are notified.
+    ///
+    /// The default implementation does nothing.
+    fn update(&self, store: &Store<S, A>, old_state: &Elem<S>, new_state: &Elem<S>) {
+        let _ = (store, old_state, new_state);
+    }
+}
+
+/// A token for unregistering a function registered by [`Store::subscribe`].
+///
+/// Dropping `Subscription` does *not* unregister the function.
+pub struct Subscription {
+    unsubscribe: Box<dyn FnOnce()>,
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription").finish()
+    }
+}
+
+impl Subscription {
+    /// Unregister the function. Does nothing if the store has already been
+    /// dropped.
+    pub fn unsubscribe(self) {
+        (self.unsubscribe)();
+    }
+}
+
+impl<S, A> Clone for Store<S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<S, A> fmt::Debug for Store<S, A>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Store")
+            .field("state", &*self.inner.state.borrow())
+            .finish()
+    }
+}
+
+impl<S: 'static, A: 'static> Store<S, A> {
+    /// Construct a `Store` with an initial state and a reducer function.
+    ///
+    /// `schedule` is used to schedule the processing of dispatched actions.
+    /// It should arrange the given function to be called later on the same
+    /// thread, e.g., by `tcw3::pal::Wm::invoke`.
+    pub fn new(
+        state: Elem<S>,
+        reducer: impl Fn(Elem<S>, &A) -> Elem<S> + 'static,
+        schedule: impl Fn(ScheduledFn) + 'static,
+    ) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                state: RefCell::new(state),
+                reducer: Box::new(reducer),
+                schedule: Box::new(schedule),
+                pending_actions: RefCell::new(Vec::new()),
+                flushing: Cell::new(false),
+                middlewares: RefCell::new(Vec::new()),
+                subscribers: RefCell::new(Vec::new()),
+                next_subscriber_id: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Get the current state.
+    pub fn state(&self) -> Elem<S> {
+        Elem::clone(&self.inner.state.borrow())
+    }
+
+    /// Append a middleware to the end of the middleware chain. Middlewares
+    /// added earlier see actions first.
+    pub fn add_middleware(&self, middleware: impl Middleware<S, A> + 'static) {
+        self.inner
+            .middlewares
+            .borrow_mut()
+            .push(Rc::new(middleware));
+    }
+
+    /// Queue an action. The action will be processed later by a function
+    /// scheduled by the scheduler function.
+    pub fn dispatch(&self, action: A) {
+        let mut pending_actions = self.inner.pending_actions.borrow_mut();
+        pending_actions.push(action);
+
+        if pending_actions.len() == 1 {
+            drop(pending_actions);
+
+            let inner_weak = Rc::downgrade(&self.inner);
+            (self.inner.schedule)(Box::new(move || {
+                if let Some(inner) = inner_weak.upgrade() {
+                    Store { inner }.flush();
+                }
+            }));
+        }
+    }
+
+    /// Process all queued actions immediately.
+    ///
+    /// This method does nothing if it's called while the store is already
+    /// processing actions (e.g., by a middleware). Actions dispatched during
+    /// that time will be processed in the current batch or the next one.
+    pub fn flush(&self) {
+        if self.inner.flushing.replace(true) {
+            return;
+        }
+
+        let old_state = self.state();
+
+        loop {
+            let actions = std::mem::take(&mut *self.inner.pending_actions.borrow_mut());
+            if actions.is_empty() {
+                break;
+            }
+
+            for action in actions {
+                self.apply(action, 0);
+            }
+        }
+
+        self.notify(&old_state);
+
+        self.inner.flushing.set(false);
+    }
+
+    /// Replace the state, e.g., to restore a state recorded by
+    /// [`Recorder`](crate::Recorder). The middlewares and subscribers are
+    /// notified of the change in the same way as when the state is changed by
+    /// actions.
+    ///
+    /// If this method is called while the store is processing actions, the
+    /// notification is delayed until the processing is complete.
+    pub fn set_state(&self, state: Elem<S>) {
+        let old_state = self.inner.state.replace(state);
+
+        if self.inner.flushing.replace(true) {
+            return;
+        }
+
+        self.notify(&old_state);
+
+        self.inner.flushing.set(false);
+    }
+
+    /// Notify the middlewares and subscribers of the state change from
+    /// `old_state` to the current state.
+    fn notify(&self, old_state: &Elem<S>) {
+        let new_state = self.state();
+
+        if Elem::ptr_eq(old_state, &new_state) {
+            return;
+        }
+
+        let middlewares = self.inner.middlewares.borrow().clone();
+        for middleware in middlewares.iter() {
+            middleware.update(self, old_state, &new_state);
+        }
+
+        // Clone the list so that subscribers can subscribe or unsubscribe
+        // in the callbacks
+        let subscribers: Vec<_> = (self.inner.subscribers.borrow().iter())
+            .map(|(_, subscriber)| Rc::clone(subscriber))
+            .collect();
+        for subscriber in subscribers {
+            (&mut *subscriber.borrow_mut())(&new_state);
+        }
+    }
+
+    /// Pass `action` to `middlewares[i..]` and then the reducer.
+    fn apply(&self, action: A, i: usize) {
+        let middleware = self.inner.middlewares.borrow().get(i).cloned();
+
+        if let Some(middleware) = middleware {
+            middleware.dispatch(self, action, &mut |action| self.apply(action, i + 1));
+        } else {
+            let state = self.state();
+            let new_state = (self.inner.reducer)(state, &action);
+            self.inner.state.replace(new_state);
+        }
+    }
+
+    /// Register a function to be called whenever the part of the state
+    /// selected by `select` changes.
+    ///
+    /// After each batch of actions, `select` is applied to the new state, and
+    /// the result is compared against the previous one using [`ShallowEq`].
+    /// `cb` is called with the new value if they are not equal. `cb` is not
+    /// called for the state at the point of registration.
+    pub fn subscribe<T: ShallowEq + 'static>(
+        &self,
+        select: impl Fn(&Elem<S>) -> T + 'static,
+        cb: impl Fn(&T) + 'static,
+    ) -> Subscription {
+        let mut last_value = select(&self.inner.state.borrow());
+
+        let subscriber: SubscriberFn<S> = Rc::new(RefCell::new(move |state: &Elem<S>| {
+            let new_value = select(state);
+            if new_value.shallow_ne(&last_value) {
+                last_value = new_value;
+                cb(&last_value);
+            }
+        }));
+
+        let id = self.inner.next_subscriber_id.get();
+        self.inner.next_subscriber_id.set(id + 1);
+        self.inner.subscribers.borrow_mut().push((id, subscriber));
+
+        let inner_weak: Weak<Inner<S, A>> = Rc::downgrade(&self.inner);
+        Subscription {
+            unsubscribe: Box::new(move || {
+                if let Some(inner) = inner_weak.upgrade() {
+                    inner.subscribers.borrow_mut().retain(|(i, _)| *i != id);
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Queue = Rc<RefCell<Vec<ScheduledFn>>>;
+
+    fn new_store(queue: &Queue) -> Store<Vec<u32>, u32> {
+        let queue = Rc::clone(queue);
+        Store::new(
+            Elem::new(Vec::new()),
+            |mut state, action| {
+                state.make_mut().push(*action);
+                state
+            },
+            move |f| queue.borrow_mut().push(f),
+        )
+    }
+
+    fn run_queue(queue: &Queue) {
+        let fns: Vec<_> = queue.borrow_mut().drain(..).collect();
+        fns.into_iter().for_each(|f| f());
+    }
+
+    struct LogMiddleware(Rc<RefCell<Vec<String>>>, &'static str);
+
+    impl Middleware<Vec<u32>, u32> for LogMiddleware {
+        fn dispatch(&self, store: &Store<Vec<u32>, u32>, action: u32, next: &mut dyn FnMut(u32)) {
+            self.0
+                .borrow_mut()
+                .push(format!("{} before {}", self.1, action));
+            if action != 0 {
+                next(action);
+            }
+            self.0
+                .borrow_mut()
+                .push(format!("{} after {:?}", self.1, *store.state()));
+        }
+
+        fn update(
+            &self,
+            _: &Store<Vec<u32>, u32>,
+            old_state: &Elem<Vec<u32>>,
+            new_state: &Elem<Vec<u32>>,
+        ) {
+            (self.0.borrow_mut()).push(format!(
+                "{} update {:?} {:?}",
+                self.1, **old_state, **new_state
+            ));
+        }
+    }
+
+    #[test]
+    fn middleware_chain() {
+        let queue = Queue::default();
+        let store = new_store(&queue);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        store.add_middleware(LogMiddleware(Rc::clone(&log), "a"));
+        store.add_middleware(LogMiddleware(Rc::clone(&log), "b"));
+
+        store.dispatch(1);
+        store.dispatch(0);
+        store.dispatch(2);
+        assert_eq!(queue.borrow().len(), 1);
+        run_queue(&queue);
+
+        assert_eq!(*store.state(), [1, 2]);
+        assert_eq!(
+            *log.borrow(),
+            [
+                "a before 1",
+                "b before 1",
+                "b after [1]",
+                "a after [1]",
+                "a before 0",
+                "a after [1]",
+                "a before 2",
+                "b before 2",
+                "b after [1, 2]",
+                "a after [1, 2]",
+                "a update [] [1, 2]",
+                "b update [] [1, 2]",
+            ]
+        );
+    }
+
+    #[test]
+    fn subscribe() {
+        let queue = Queue::default();
+        let store = new_store(&queue);
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let log2 = Rc::clone(&log);
+        let sub = store.subscribe(
+            |state| state.len() / 2,
+            move |half_len| log2.borrow_mut().push(*half_len),
+        );
+
+        for i in 0..5 {
+            store.dispatch(i);
+            run_queue(&queue);
+        }
+        assert_eq!(*log.borrow(), [1, 2]);
+
+        sub.unsubscribe();
+        store.dispatch(5);
+        run_queue(&queue);
+        assert_eq!(*log.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn dispatch_from_subscriber() {
+        let queue = Queue::default();
+        let store = new_store(&queue);
+
+        let store2 = store.clone();
+        store.subscribe(
+            |state| state.len(),
+            move |&len| {
+                if len < 3 {
+                    store2.dispatch(len as u32);
+                }
+            },
+        );
+
+        store.dispatch(0);
+        run_queue(&queue);
+        assert_eq!(*store.state(), [0]);
+        run_queue(&queue);
+        run_queue(&queue);
+        assert_eq!(*store.state(), [0, 1, 2]);
+    }
+}
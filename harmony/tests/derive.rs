@@ -0,0 +1,211 @@
+use harmony::{lens::Find, Elem, Lens, ShallowEq};
+
+#[derive(Debug, ShallowEq)]
+struct Named {
+    x: u32,
+    elem: Elem<Vec<u32>>,
+}
+
+#[derive(Debug, ShallowEq)]
+struct Tuple(u32, Elem<Vec<u32>>);
+
+#[derive(Debug, ShallowEq)]
+struct Unit;
+
+#[derive(Debug, ShallowEq)]
+enum Enum {
+    A,
+    B(u32),
+    C { elem: Elem<Vec<u32>> },
+}
+
+#[derive(Debug, ShallowEq)]
+struct Generic<T> {
+    x: T,
+}
+
+#[test]
+fn shallow_eq_struct() {
+    let elem = Elem::new(vec![1]);
+    let a = Named {
+        x: 1,
+        elem: Elem::clone(&elem),
+    };
+    assert!(a.shallow_eq(&Named {
+        x: 1,
+        elem: Elem::clone(&elem),
+    }));
+    assert!(a.shallow_ne(&Named {
+        x: 2,
+        elem: Elem::clone(&elem),
+    }));
+    assert!(a.shallow_ne(&Named {
+        x: 1,
+        elem: Elem::new(vec![1]),
+    }));
+
+    assert!(Tuple(1, Elem::clone(&elem)).shallow_eq(&Tuple(1, Elem::clone(&elem))));
+    assert!(Tuple(1, Elem::clone(&elem)).shallow_ne(&Tuple(1, Elem::new(vec![1]))));
+
+    assert!(Unit.shallow_eq(&Unit));
+
+    assert!(Generic { x: 1 }.shallow_eq(&Generic { x: 1 }));
+    assert!(Generic { x: 1 }.shallow_ne(&Generic { x: 2 }));
+}
+
+#[test]
+fn shallow_eq_enum() {
+    let elem = Elem::new(vec![1]);
+    assert!(Enum::A.shallow_eq(&Enum::A));
+    assert!(Enum::A.shallow_ne(&Enum::B(1)));
+    assert!(Enum::B(1).shallow_eq(&Enum::B(1)));
+    assert!(Enum::B(1).shallow_ne(&Enum::B(2)));
+    assert!(Enum::C {
+        elem: Elem::clone(&elem)
+    }
+    .shallow_eq(&Enum::C {
+        elem: Elem::clone(&elem)
+    }));
+    assert!(Enum::C {
+        elem: Elem::clone(&elem)
+    }
+    .shallow_ne(&Enum::C {
+        elem: Elem::new(vec![1])
+    }));
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct Root {
+    pub counter: u32,
+    pub items: Elem<Vec<Elem<Item>>>,
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct Item {
+    pub id: u32,
+    pub tags: Elem<Vec<String>>,
+}
+
+fn new_root() -> Elem<Root> {
+    Elem::new(Root {
+        counter: 0,
+        items: Elem::new(
+            (0..3)
+                .map(|id| {
+                    Elem::new(Item {
+                        id,
+                        tags: Elem::new(Vec::new()),
+                    })
+                })
+                .collect(),
+        ),
+    })
+}
+
+fn item_tags(id: u32) -> impl Lens<Source = Elem<Root>, Target = Elem<Vec<String>>> {
+    Root::items
+        .then(Find::new(move |item: &Elem<Item>| item.id == id))
+        .then(Item::tags)
+}
+
+#[test]
+fn lens_get() {
+    let root = new_root();
+    assert_eq!(Root::counter.get(&root), Some(&0));
+    assert_eq!(
+        Root::items
+            .then(Find::new(|item: &Elem<Item>| item.id == 1))
+            .then(Item::id)
+            .get(&root),
+        Some(&1)
+    );
+    assert!(item_tags(3).get(&root).is_none());
+}
+
+#[test]
+fn lens_set() {
+    let root1 = new_root();
+
+    let mut root2 = Elem::clone(&root1);
+    Root::counter.set(&mut root2, 1);
+    assert_eq!(root1.counter, 0);
+    assert_eq!(root2.counter, 1);
+    assert!(Elem::ptr_eq(&root1.items, &root2.items));
+
+    // Setting the same value doesn't copy anything
+    let mut root3 = Elem::clone(&root2);
+    Root::counter.set(&mut root3, 1);
+    assert!(Elem::ptr_eq(&root2, &root3));
+}
+
+#[test]
+fn lens_update_nested() {
+    let root1 = new_root();
+
+    let mut root2 = Elem::clone(&root1);
+    item_tags(1).update(&mut root2, |tags| {
+        let mut tags = Vec::clone(tags);
+        tags.push("hoge".to_owned());
+        Elem::new(tags)
+    });
+
+    assert!(root1.items[1].tags.is_empty());
+    assert_eq!(*root2.items[1].tags, ["hoge"]);
+    assert!(Elem::ptr_eq(&root1.items[0], &root2.items[0]));
+    assert!(Elem::ptr_eq(&root1.items[2], &root2.items[2]));
+
+    // Updating a nonexistent item doesn't copy anything
+    let mut root3 = Elem::clone(&root2);
+    item_tags(3).update(&mut root3, |_| unreachable!());
+    assert!(Elem::ptr_eq(&root2, &root3));
+}
+
+mod vis {
+    pub mod inner {
+        #[derive(Debug, Clone, harmony::Lens)]
+        pub struct Mixed {
+            pub a: u32,
+            pub(crate) b: u32,
+            pub(super) c: u32,
+            pub(in crate::vis) d: u32,
+            e: u32,
+        }
+
+        impl Mixed {
+            pub fn new() -> Self {
+                Self {
+                    a: 1,
+                    b: 2,
+                    c: 3,
+                    d: 4,
+                    e: 5,
+                }
+            }
+
+            pub fn get_e(this: &harmony::Elem<Self>) -> Option<&u32> {
+                harmony::Lens::get(&Self::e, this)
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, harmony::Lens)]
+    pub(crate) struct Restricted {
+        pub a: u32,
+        b: u32,
+    }
+
+    #[test]
+    fn lens_vis() {
+        use harmony::{Elem, Lens};
+        let mixed = Elem::new(inner::Mixed::new());
+        assert_eq!(inner::Mixed::a.get(&mixed), Some(&1));
+        assert_eq!(inner::Mixed::b.get(&mixed), Some(&2));
+        assert_eq!(inner::Mixed::c.get(&mixed), Some(&3));
+        assert_eq!(inner::Mixed::d.get(&mixed), Some(&4));
+        assert_eq!(inner::Mixed::get_e(&mixed), Some(&5));
+
+        let restricted = Elem::new(Restricted { a: 1, b: 2 });
+        assert_eq!(Restricted::a.get(&restricted), Some(&1));
+        assert_eq!(Restricted::b.get(&restricted), Some(&2));
+    }
+}
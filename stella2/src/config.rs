@@ -1,5 +1,7 @@
 //! Configuration system
 pub mod cmdline;
+pub mod history;
 pub mod lock;
 pub mod profile;
+pub mod settings;
 pub mod viewpersistence;
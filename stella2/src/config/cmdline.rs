@@ -2,6 +2,7 @@
 use std::{
     env::{args_os, ArgsOs},
     ffi::OsString,
+    fmt,
     path::PathBuf,
 };
 
@@ -10,6 +11,101 @@ use std::{
 pub struct Args {
     /// the path to a custom profile directory
     pub profile: Option<PathBuf>,
+    /// the IRC servers to connect to
+    pub irc_servers: Vec<IrcServer>,
+    /// the path to write the recorded actions to on exit
+    pub record_actions: Option<PathBuf>,
+    /// the level of the log records written to the log files
+    pub log_level: Option<log::LevelFilter>,
+}
+
+/// An IRC server specified by `--irc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrcServer {
+    pub host: String,
+    pub port: u16,
+    pub nick: String,
+    /// The channels to join after connecting to the server.
+    pub channels: Vec<String>,
+}
+
+/// The error type for `IrcServer::parse`.
+#[derive(Debug, displaydoc::Display)]
+pub enum IrcServerParseError {
+    /// The URL must start with `irc://`
+    BadScheme,
+    /// The host name is missing
+    NoHost,
+    /// The port number is invalid
+    BadPort,
+}
+
+impl IrcServer {
+    /// Parse an URL of the form `irc://[nick@]host[:port][/channel,...]`. The
+    /// leading `#` of each channel name may be omitted.
+    pub fn parse(url: &str) -> Result<Self, IrcServerParseError> {
+        const SCHEME: &str = "irc://";
+        if !url.starts_with(SCHEME) {
+            return Err(IrcServerParseError::BadScheme);
+        }
+        let url = &url[SCHEME.len()..];
+
+        let mut parts = url.splitn(2, '/');
+        let authority = parts.next().unwrap();
+        let path = parts.next().unwrap_or("");
+
+        let (nick, host_port) = match authority.find('@') {
+            Some(i) => (&authority[..i], &authority[i + 1..]),
+            None => ("stella2", authority),
+        };
+
+        let (host, port) = match host_port.rfind(':') {
+            Some(i) => (
+                &host_port[..i],
+                host_port[i + 1..]
+                    .parse()
+                    .map_err(|_| IrcServerParseError::BadPort)?,
+            ),
+            None => (host_port, 6667),
+        };
+
+        if host.is_empty() {
+            return Err(IrcServerParseError::NoHost);
+        }
+
+        let channels = path
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                if name.starts_with(|c| "#&+!".contains(c)) {
+                    name.to_owned()
+                } else {
+                    format!("#{}", name)
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            nick: nick.to_owned(),
+            channels,
+        })
+    }
+}
+
+/// Formats the server as an URL accepted by [`IrcServer::parse`].
+impl fmt::Display for IrcServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "irc://{}@{}:{}/{}",
+            self.nick,
+            self.host,
+            self.port,
+            self.channels.join(",")
+        )
+    }
 }
 
 impl Args {
@@ -47,24 +143,76 @@ static HANDLER_TABLE: &[(&str, &(dyn ArgHandler<Args> + Send + Sync))] = &[
     ("-h", &(handle_help as fn(&mut Args))),
     ("--help", &(handle_help as fn(&mut Args))),
     ("--profile", &(handle_profile as fn(&mut Args, OsString))),
+    ("--irc", &(handle_irc as fn(&mut Args, OsString))),
+    (
+        "--log-level",
+        &(handle_log_level as fn(&mut Args, OsString)),
+    ),
+    (
+        "--record-actions",
+        &(handle_record_actions as fn(&mut Args, OsString)),
+    ),
 ];
 
 fn display_help_and_exit() -> ! {
+    let locale = crate::i18n::Locale::default();
+
     // TODO: Display the message in a window when running on Windows
-    println!(
-        "Stella 2
-A lightweight instant messaging client.
+    println!("Stella 2");
+    println!("{}", locale.text("cmdline-about"));
+    println!();
+    println!("{}:", locale.text("cmdline-usage"));
+    println!("    stella2 [OPTIONS]");
+    println!();
+    print_help_section(
+        &locale.text("cmdline-flags"),
+        &[("-h, --help", locale.text("cmdline-opt-help"))],
+    );
+    println!();
+    print_help_section(
+        &locale.text("cmdline-options"),
+        &[
+            ("--profile <PROFILE>", locale.text("cmdline-opt-profile")),
+            ("--irc <URL>...", locale.text("cmdline-opt-irc")),
+            ("--log-level <LEVEL>", locale.text("cmdline-opt-log-level")),
+            (
+                "--record-actions <FILE>",
+                locale.text("cmdline-opt-record-actions"),
+            ),
+        ],
+    );
+    std::process::exit(0);
+}
 
-USAGE:
-    stella2 [OPTIONS]
+/// Print a list of options and their (possibly multi-line) descriptions.
+fn print_help_section(title: &str, options: &[(&str, String)]) {
+    const DESC_COLUMN: usize = 27;
+    const INDENT: &str = "    ";
 
-FLAGS:
-    -h, --help       display help information
+    println!("{}:", title);
+    for (name, desc) in options.iter() {
+        let mut lines = desc.lines();
+        let first_line = lines.next().unwrap_or("");
 
-OPTIONS:
-    --profile <PROFILE>    the path to a custom profile directory"
-    );
-    std::process::exit(0);
+        if INDENT.len() + name.len() + 2 > DESC_COLUMN {
+            // Start the description on the next line
+            println!("{}{}", INDENT, name);
+            println!("{:width$}{}", "", first_line, width = DESC_COLUMN);
+        } else {
+            let name_width = DESC_COLUMN - INDENT.len();
+            println!(
+                "{}{:width$}{}",
+                INDENT,
+                name,
+                first_line,
+                width = name_width
+            );
+        }
+
+        for line in lines {
+            println!("{:width$}{}", "", line, width = DESC_COLUMN);
+        }
+    }
 }
 
 trait ArgHandler<Ctx> {
@@ -95,3 +243,36 @@ fn handle_help<T>(_: &mut T) {
 fn handle_profile(args: &mut Args, value: OsString) {
     args.profile = Some(value.into());
 }
+
+fn handle_log_level(args: &mut Args, value: OsString) {
+    match value.to_str().and_then(|level| level.parse().ok()) {
+        Some(level) => args.log_level = Some(level),
+        None => {
+            eprintln!("error: Invalid value for '--log-level': {:?}", value);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_record_actions(args: &mut Args, value: OsString) {
+    args.record_actions = Some(value.into());
+}
+
+fn handle_irc(args: &mut Args, value: OsString) {
+    let server = value
+        .to_str()
+        .ok_or(None)
+        .and_then(|url| IrcServer::parse(url).map_err(Some));
+
+    match server {
+        Ok(server) => args.irc_servers.push(server),
+        Err(Some(e)) => {
+            eprintln!("error: Invalid value for '--irc': {}", e);
+            std::process::exit(1);
+        }
+        Err(None) => {
+            eprintln!("error: Invalid value for '--irc': The URL is not valid UTF-8");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Message history storage
+use chrono::TimeZone;
+use miniserde::{json, Deserialize, Serialize};
+use std::{
+    io,
+    sync::{mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use super::profile::Profile;
+use crate::model;
+
+pub use seglog::RecordPos;
+
+/// The persistent message log of all conversations. The messages are stored
+/// in `seglog::Store`, which survives application crashes and storage
+/// exhaustion without corrupting the existing messages.
+///
+/// Messages are only read on demand, so the history does not have to fit in
+/// memory.
+///
+/// The messages are also added to a full-text search index as they are
+/// recorded. This is done by a background thread so that the callers of
+/// [`History::append`] aren't blocked by it.
+#[derive(Debug)]
+pub struct History {
+    store: seglog::Store,
+    /// The search index, or `None` if it couldn't be opened. Documents are
+    /// identified by `RecordPos::to_bytes`.
+    indexer: Option<Indexer>,
+}
+
+#[derive(Debug)]
+struct Indexer {
+    index: Arc<Mutex<searchindex::Index>>,
+    /// Sends documents to the indexer thread. This is `None` only while
+    /// dropping `Indexer`.
+    sender: Option<mpsc::Sender<(RecordPos, String)>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Indexer {
+    fn new(index: searchindex::Index) -> Self {
+        let index = Arc::new(Mutex::new(index));
+        let (sender, receiver) = mpsc::channel::<(RecordPos, String)>();
+
+        let index2 = Arc::clone(&index);
+        let thread = thread::Builder::new()
+            .name("indexer".to_owned())
+            .spawn(move || {
+                for (pos, text) in receiver {
+                    let mut index = index2.lock().unwrap();
+                    if let Err(e) = index.add(&pos.to_bytes(), &text) {
+                        log::warn!("Failed to add {:?} to the search index: {:?}", pos, e);
+                    }
+                }
+
+                if let Err(e) = index2.lock().unwrap().sync() {
+                    log::warn!("Failed to flush the search index: {:?}", e);
+                }
+            })
+            .unwrap();
+
+        Self {
+            index,
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Indexer {
+    /// Index the pending messages before closing the search index.
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A message found by [`History::search`].
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub server: String,
+    pub channel: String,
+    pub message: model::Message,
+}
+
+/// The projection of a message to be persisted to disk.
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedMessage {
+    /// The number of milliseconds since the Unix epoch.
+    time: i64,
+    sender: String,
+    kind: String,
+    body: String,
+}
+
+impl PersistedMessage {
+    fn new(msg: &model::Message) -> Self {
+        Self {
+            time: msg.time.timestamp_millis(),
+            sender: msg.sender.clone(),
+            kind: match msg.kind {
+                model::MessageKind::Normal => "normal",
+                model::MessageKind::Action => "action",
+                model::MessageKind::Notice => "notice",
+            }
+            .to_owned(),
+            body: msg.body.clone(),
+        }
+    }
+
+    fn into_message(self, pos: RecordPos) -> model::Message {
+        model::Message {
+            time: chrono::Utc.timestamp_millis(self.time),
+            sender: self.sender,
+            kind: match self.kind.as_str() {
+                "action" => model::MessageKind::Action,
+                "notice" => model::MessageKind::Notice,
+                _ => model::MessageKind::Normal,
+            },
+            body: self.body,
+            history_pos: Some(pos),
+        }
+    }
+}
+
+/// The stream key for a conversation. The server name is a host name and thus
+/// never contains NUL.
+fn conversation_key(server: &str, channel: &str) -> Vec<u8> {
+    format!("{}\0{}", server, channel).into_bytes()
+}
+
+/// The inverse of `conversation_key`.
+fn parse_conversation_key(key: &[u8]) -> Option<(String, String)> {
+    let key = std::str::from_utf8(key).ok()?;
+    let i = key.find('\0')?;
+    Some((key[..i].to_owned(), key[i + 1..].to_owned()))
+}
+
+/// Decode a record of the message log. Returns `None` if the record is
+/// malformed.
+fn decode_message(record: seglog::Record) -> Option<model::Message> {
+    let msg = std::str::from_utf8(&record.data)
+        .ok()
+        .and_then(|data| json::from_str::<PersistedMessage>(data).ok());
+    if msg.is_none() {
+        log::warn!("Skipping a malformed message at {:?}", record.pos);
+    }
+    Some(msg?.into_message(record.pos))
+}
+
+impl History {
+    /// Open the message history of a given profile.
+    pub fn open(profile: &Profile) -> io::Result<Self> {
+        let dir = profile.data_dir().join("history");
+        log::info!("Opening the message history at {:?}", dir);
+        let store = seglog::Store::open(dir, seglog::Options::default())?;
+
+        // The history is still usable without the search index
+        let dir = profile.data_dir().join("search");
+        log::info!("Opening the search index at {:?}", dir);
+        let indexer = match searchindex::Index::open(dir, searchindex::Options::default()) {
+            Ok(index) => Some(Indexer::new(index)),
+            Err(e) => {
+                log::error!("Could not open the search index: {:?}", e);
+                None
+            }
+        };
+
+        Ok(Self { store, indexer })
+    }
+
+    /// Append a message to the specified conversation.
+    pub fn append(
+        &mut self,
+        server: &str,
+        channel: &str,
+        msg: &model::Message,
+    ) -> io::Result<RecordPos> {
+        let data = json::to_string(&PersistedMessage::new(msg));
+        let pos = self
+            .store
+            .append(&conversation_key(server, channel), data.as_bytes())?;
+
+        // The message is safely recorded at this point. The indexer thread
+        // reports its own failures.
+        if let Some(indexer) = &self.indexer {
+            let text = format!("{} {}", msg.sender, msg.body);
+            let _ = indexer.sender.as_ref().unwrap().send((pos, text));
+        }
+
+        Ok(pos)
+    }
+
+    /// Read at most `limit` messages of the specified conversation sent
+    /// before `before` (or the latest ones if `before` is `None`), from the
+    /// oldest to the newest.
+    pub fn read_before(
+        &mut self,
+        server: &str,
+        channel: &str,
+        before: Option<RecordPos>,
+        limit: usize,
+    ) -> io::Result<Vec<model::Message>> {
+        let records = self
+            .store
+            .read_before(&conversation_key(server, channel), before, limit)?;
+
+        Ok(records.into_iter().filter_map(decode_message).collect())
+    }
+
+    /// Find at most `limit` messages whose sender or body contains all words
+    /// in `query` as prefixes. The messages are returned from the newest to
+    /// the oldest.
+    ///
+    /// Recently appended messages might not be found yet because they are
+    /// indexed asynchronously.
+    pub fn search(&mut self, query: &str, limit: usize) -> io::Result<Vec<SearchHit>> {
+        let docs = match &self.indexer {
+            Some(indexer) => indexer.index.lock().unwrap().search(query, limit)?,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut hits = Vec::with_capacity(docs.len());
+        for doc in docs {
+            let record = match RecordPos::from_bytes(&doc).map(|pos| self.store.read(pos)) {
+                Some(Ok(record)) => record,
+                Some(Err(ref e)) if e.kind() == io::ErrorKind::InvalidData => continue,
+                Some(Err(e)) => return Err(e),
+                None => continue,
+            };
+
+            let (server, channel) = match parse_conversation_key(&record.key) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            if let Some(message) = decode_message(record) {
+                hits.push(SearchHit {
+                    server,
+                    channel,
+                    message,
+                });
+            }
+        }
+
+        Ok(hits)
+    }
+}
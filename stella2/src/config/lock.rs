@@ -7,24 +7,80 @@ pub struct LockGuard {
     _inner: LockFile,
 }
 
+/// The result of [`try_lock`].
+pub enum TryLockResult {
+    /// The lock was acquired.
+    Acquired(LockGuard),
+    /// Another process holds the lock. `owner` is the PID of the process if
+    /// it's known.
+    Locked { owner: Option<u32> },
+    /// The lock is held, but the process recorded as its owner doesn't exist
+    /// anymore. This may happen if the lock was inherited by a child process
+    /// or the file system doesn't release locks reliably. The lock might be
+    /// released soon, so the caller may retry.
+    Stale { owner: u32 },
+}
+
 fn lockfile_path(profile: &Profile) -> PathBuf {
     profile.data_dir().join("lock")
 }
 
+/// The file to record the PID of the process holding the lock.
+fn pidfile_path(profile: &Profile) -> PathBuf {
+    profile.data_dir().join("lock.pid")
+}
+
 /// Create a file in the specified profile directory and attempt to lock it
 /// to ensure only one instance of the application has access to the profile.
-pub fn try_lock(profile: &Profile) -> Result<Option<LockGuard>, fslock::Error> {
+pub fn try_lock(profile: &Profile) -> Result<TryLockResult, fslock::Error> {
     let path = lockfile_path(profile);
     log::info!("Locking {:?}", path);
     let mut file = LockFile::open(&path)?;
 
+    let pid_path = pidfile_path(profile);
+
     if file.try_lock()? {
-        Ok(Some(LockGuard { _inner: file }))
-    } else {
-        log::warn!(
-            "Could not lock {:?} - another instance may be already running",
-            path
-        );
-        Ok(None)
+        let pid = std::process::id();
+        log::debug!("Writing the PID ({}) to {:?}", pid, pid_path);
+        if let Err(e) = std::fs::write(&pid_path, format!("{}\n", pid)) {
+            // It's only used for diagnostics, so this isn't fatal
+            log::warn!("Could not write the PID to {:?}: {}", pid_path, e);
+        }
+
+        return Ok(TryLockResult::Acquired(LockGuard { _inner: file }));
+    }
+
+    // The owner might not have written the PID yet, so this may fail
+    let owner = std::fs::read_to_string(&pid_path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok());
+
+    log::warn!(
+        "Could not lock {:?} - another instance (PID {:?}) may be already running",
+        path,
+        owner
+    );
+
+    match owner {
+        Some(owner) if !is_process_alive(owner) => {
+            log::warn!("The process {} does not exist; the lock is stale", owner);
+            Ok(TryLockResult::Stale { owner })
+        }
+        _ => Ok(TryLockResult::Locked { owner }),
     }
 }
+
+/// Check if a process with the specified PID exists.
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    // Signal 0 only checks if the signal can be sent to the process
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Check if a process with the specified PID exists.
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    // Assume the worst
+    true
+}
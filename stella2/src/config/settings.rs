@@ -0,0 +1,124 @@
+//! Persistence of the user preferences (`model::Settings`)
+use harmony::{set_field, Elem};
+use miniserde::{json, Serialize};
+use std::str::FromStr;
+
+use super::viewpersistence::{read_field, Projection};
+use crate::model;
+
+/// The projection of `model::Settings` to be persisted to disk. The enums
+/// are stored as strings (see `as_str` of each type) so that the file stays
+/// readable when variants are added or removed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PersistedSettings {
+    version: u64,
+    check_updates: bool,
+    time_format: String,
+    color_theme: String,
+    font_size: u32,
+    proxy: String,
+    /// `None` means the system language.
+    language: Option<String>,
+    log_level: String,
+}
+
+impl Projection for PersistedSettings {
+    const FILE_NAME: &'static str = "settings.json";
+    const CURRENT_VERSION: u64 = 0;
+    const MIGRATIONS: &'static [fn(&mut json::Object)] = &[];
+
+    fn new(app_state: &model::AppState) -> Elem<Self> {
+        let settings = &app_state.settings;
+        Elem::new(Self {
+            version: Self::CURRENT_VERSION,
+            check_updates: settings.check_updates,
+            time_format: settings.time_format.as_str().to_owned(),
+            color_theme: settings.color_theme.as_str().to_owned(),
+            font_size: settings.font_size,
+            proxy: settings.proxy.as_str().to_owned(),
+            language: settings.language.clone(),
+            log_level: settings.log_level.to_string(),
+        })
+    }
+
+    fn merge_into_app(self, app_state: Elem<model::AppState>) -> Elem<model::AppState> {
+        let default = model::Settings::new();
+
+        // The values have already been validated by `read_json`
+        let settings = model::Settings {
+            check_updates: self.check_updates,
+            time_format: self.time_format.parse().unwrap_or(default.time_format),
+            color_theme: self.color_theme.parse().unwrap_or(default.color_theme),
+            font_size: self.font_size,
+            proxy: self.proxy.parse().unwrap_or(default.proxy),
+            language: self.language,
+            log_level: self.log_level.parse().unwrap_or(default.log_level),
+        };
+
+        set_field! {
+            settings: Elem::new(settings),
+            ..app_state
+        }
+    }
+
+    fn merge_from_app(this: &Elem<Self>, app_state: &model::AppState) -> Option<Elem<Self>> {
+        let new = Self::new(app_state);
+        if *new != **this {
+            Some(new)
+        } else {
+            None
+        }
+    }
+
+    fn read_json(&mut self, obj: &json::Object) -> Vec<String> {
+        let mut bad_fields = Vec::new();
+        let mut check = |ok: bool, name: &str| {
+            if !ok {
+                bad_fields.push(name.to_owned());
+            }
+        };
+
+        check(
+            read_field(obj, "check_updates", &mut self.check_updates),
+            "check_updates",
+        );
+        check(
+            read_parsed_field::<model::TimeFormat>(obj, "time_format", &mut self.time_format),
+            "time_format",
+        );
+        check(
+            read_parsed_field::<model::ColorTheme>(obj, "color_theme", &mut self.color_theme),
+            "color_theme",
+        );
+        let mut font_size = self.font_size;
+        if read_field(obj, "font_size", &mut font_size)
+            && model::Settings::FONT_SIZES.contains(&font_size)
+        {
+            self.font_size = font_size;
+        } else {
+            check(false, "font_size");
+        }
+        check(
+            read_parsed_field::<model::Proxy>(obj, "proxy", &mut self.proxy),
+            "proxy",
+        );
+        check(read_field(obj, "language", &mut self.language), "language");
+        check(
+            read_parsed_field::<log::LevelFilter>(obj, "log_level", &mut self.log_level),
+            "log_level",
+        );
+
+        bad_fields
+    }
+}
+
+/// Like `read_field`, but also rejects the strings that can't be parsed as
+/// `T`.
+fn read_parsed_field<T: FromStr>(obj: &json::Object, name: &str, out: &mut String) -> bool {
+    let mut value = out.clone();
+    if !read_field(obj, name, &mut value) || value.parse::<T>().is_err() {
+        return false;
+    }
+    *out = value;
+    true
+}
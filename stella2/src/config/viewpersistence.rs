@@ -1,8 +1,9 @@
-use harmony::{set_field, Elem, ShallowEq};
+use harmony::{set_field, Elem, Middleware, Store};
 use miniserde::{json, Deserialize, Serialize};
 use std::{
     cell::Cell,
     path::{Path, PathBuf},
+    rc::Rc,
     sync::{Arc, Condvar, Mutex},
     time::Duration,
 };
@@ -16,91 +17,341 @@ use crate::model;
 const DEBOUNCE_LATENCY_MIN: Duration = Duration::from_secs(5);
 const DEBOUNCE_LATENCY_MAX: Duration = Duration::from_secs(20);
 
+/// A projection of an app state persisted to its own file by
+/// [`PersistenceScheduler`].
+pub trait Projection: Serialize + Clone + 'static {
+    /// The name of the file in the profile's data directory.
+    const FILE_NAME: &'static str;
+
+    /// The current version of the file format. Increment this and add a
+    /// function to `MIGRATIONS` when changing the format in a way that old
+    /// files can't be read as they are.
+    const CURRENT_VERSION: u64;
+
+    /// The functions to upgrade the persisted data from older versions.
+    /// `MIGRATIONS[i]` converts the version `i` into the version `i + 1`.
+    /// The length must be equal to `CURRENT_VERSION`.
+    const MIGRATIONS: &'static [fn(&mut json::Object)];
+
+    /// Construct a projection of `app_state`.
+    fn new(app_state: &model::AppState) -> Elem<Self>;
+
+    /// Apply the persisted data to `app_state`.
+    fn merge_into_app(self, app_state: Elem<model::AppState>) -> Elem<model::AppState>;
+
+    /// Construct a projection of `app_state` if it's different from `this`.
+    fn merge_from_app(this: &Elem<Self>, app_state: &model::AppState) -> Option<Elem<Self>>;
+
+    /// Update `self` with the fields found in `obj`, which must be of
+    /// `CURRENT_VERSION`. Missing fields are left unchanged. Returns the names
+    /// of the fields that are present but couldn't be deserialized.
+    fn read_json(&mut self, obj: &json::Object) -> Vec<String>;
+}
+
+/// The version 0 predates the `version` field and is otherwise identical to
+/// the version 1.
+fn migrate_v0_to_v1(_: &mut json::Object) {}
+
+/// The version 1 has only one window (`main_wnd`), which is replaced with a
+/// list of windows (`wnds`) in the version 2.
+fn migrate_v1_to_v2(obj: &mut json::Object) {
+    if let Some(main_wnd) = obj.remove("main_wnd") {
+        let mut wnds = json::Array::new();
+        wnds.push(main_wnd);
+        obj.insert("wnds".to_owned(), json::Value::Array(wnds));
+    }
+}
+
 /// The projection of an app state to be persisted to disk.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct PersistedState {
-    main_wnd: Elem<model::WndState>,
+#[derive(Debug, Clone, Serialize)]
+pub struct PersistedState {
+    version: u64,
+    /// The main windows. Never empty.
+    wnds: Vec<PersistedWnd>,
+}
+
+/// The projection of a window state to be persisted to disk.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct PersistedWnd {
+    sidebar_width: f32,
+    editor_height: f32,
+    sidebar_visible: bool,
+    /// The selected channel. It's selected again when it's joined after
+    /// restoration. Added without a version bump because older files just
+    /// lack this field.
+    selected_channel: Option<PersistedChannel>,
+}
+
+/// The projection of `model::ChannelPath` to be persisted to disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PersistedChannel {
+    server: String,
+    channel: String,
 }
 
-impl PersistedState {
+impl Projection for PersistedState {
+    const FILE_NAME: &'static str = "view.json";
+    const CURRENT_VERSION: u64 = 2;
+    const MIGRATIONS: &'static [fn(&mut json::Object)] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
     fn new(app_state: &model::AppState) -> Elem<Self> {
         Elem::new(Self {
-            main_wnd: app_state.main_wnd.clone(),
+            version: Self::CURRENT_VERSION,
+            wnds: app_state
+                .wnds
+                .iter()
+                .map(|wnd| PersistedWnd::new(wnd, app_state))
+                .collect(),
         })
     }
 
     fn merge_into_app(self, app_state: Elem<model::AppState>) -> Elem<model::AppState> {
+        let wnds = self
+            .wnds
+            .into_iter()
+            .map(|wnd| Elem::new(wnd.into_wnd_state()))
+            .collect();
+
         set_field! {
-            main_wnd: self.main_wnd,
+            wnds: Elem::new(wnds),
             ..app_state
         }
     }
 
     fn merge_from_app(this: &Elem<Self>, app_state: &model::AppState) -> Option<Elem<Self>> {
-        if this.main_wnd.shallow_ne(&app_state.main_wnd) {
-            Some(Self::new(app_state))
+        let new = Self::new(app_state);
+        if new.wnds != this.wnds {
+            Some(new)
         } else {
             None
         }
     }
+
+    fn read_json(&mut self, obj: &json::Object) -> Vec<String> {
+        let mut bad_fields = Vec::new();
+
+        match obj.get("wnds") {
+            Some(json::Value::Array(wnd_values)) if !wnd_values.is_empty() => {
+                // Fill missing fields with the values of the default window
+                let template = self.wnds[0].clone();
+
+                self.wnds = wnd_values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, wnd_value)| {
+                        let mut wnd = template.clone();
+                        if let json::Value::Object(wnd_obj) = wnd_value {
+                            for name in wnd.read_json(wnd_obj) {
+                                bad_fields.push(format!("wnds[{}].{}", i, name));
+                            }
+                        } else {
+                            bad_fields.push(format!("wnds[{}]", i));
+                        }
+                        wnd
+                    })
+                    .collect();
+            }
+            Some(_) => bad_fields.push("wnds".to_owned()),
+            None => {}
+        }
+
+        bad_fields
+    }
+}
+
+impl PersistedWnd {
+    fn new(wnd_state: &model::WndState, app_state: &model::AppState) -> Self {
+        // If the channel to restore isn't joined yet, keep it for the next
+        // session
+        let selected_channel = (wnd_state.selected_channel.as_ref())
+            .and_then(|channel_ref| model::ChannelPath::new(app_state, channel_ref))
+            .or_else(|| wnd_state.channel_to_restore.clone());
+
+        Self {
+            sidebar_width: wnd_state.sidebar_width,
+            editor_height: wnd_state.editor_height,
+            sidebar_visible: wnd_state.sidebar_visible,
+            selected_channel: selected_channel.map(|path| PersistedChannel {
+                server: path.server,
+                channel: path.channel,
+            }),
+        }
+    }
+
+    fn into_wnd_state(self) -> model::WndState {
+        model::WndState {
+            sidebar_width: self.sidebar_width,
+            editor_height: self.editor_height,
+            sidebar_visible: self.sidebar_visible,
+            // The channel may not exist anymore, in which case it's never
+            // selected
+            channel_to_restore: self.selected_channel.map(|channel| model::ChannelPath {
+                server: channel.server,
+                channel: channel.channel,
+            }),
+            ..model::WndState::new(model::WndId::new_unique())
+        }
+    }
+
+    /// Update `self` with the fields found in `obj`. Returns the names of the
+    /// fields that are present but couldn't be deserialized.
+    fn read_json(&mut self, obj: &json::Object) -> Vec<&'static str> {
+        let mut bad_fields = Vec::new();
+        if !read_field(obj, "sidebar_width", &mut self.sidebar_width) {
+            bad_fields.push("sidebar_width");
+        }
+        if !read_field(obj, "editor_height", &mut self.editor_height) {
+            bad_fields.push("editor_height");
+        }
+        if !read_field(obj, "sidebar_visible", &mut self.sidebar_visible) {
+            bad_fields.push("sidebar_visible");
+        }
+        if !read_field(obj, "selected_channel", &mut self.selected_channel) {
+            bad_fields.push("selected_channel");
+        }
+        bad_fields
+    }
 }
 
-/// The file path to store the application state.
-fn state_path(profile: &Profile) -> PathBuf {
-    profile.data_dir().join("view.json")
+/// Deserialize the field `name` of `obj` into `out`. `out` is left unchanged
+/// if the field is missing or malformed. Returns `false` if the field is
+/// malformed.
+pub fn read_field<T: Deserialize>(obj: &json::Object, name: &str, out: &mut T) -> bool {
+    if let Some(value) = obj.get(name) {
+        // `miniserde` can't deserialize `json::Value` directly, so take a
+        // detour through a string
+        match json::from_str(&json::to_string(value)) {
+            Ok(x) => *out = x,
+            Err(_) => return false,
+        }
+    }
+    true
 }
 
-/// The temporary file path used during saving the application state.
-fn state_tmp_path(profile: &Profile) -> PathBuf {
-    profile.data_dir().join(".view.json.tmp")
+/// The file path to store the projection `T`.
+fn state_path<T: Projection>(profile: &Profile) -> PathBuf {
+    profile.data_dir().join(T::FILE_NAME)
 }
 
-/// Restore the application state from a given profile. `state` will be
-/// updated with the restored state.
-pub fn restore_state(profile: &Profile, app_state: Elem<model::AppState>) -> Elem<model::AppState> {
-    let state_path = state_path(profile);
+/// The temporary file path used during saving the projection `T`.
+fn state_tmp_path<T: Projection>(profile: &Profile) -> PathBuf {
+    profile.data_dir().join(format!(".{}.tmp", T::FILE_NAME))
+}
 
-    if state_path.is_file() {
-        log::info!("Loading a persisted state from {:?}.", state_path);
+/// The file path to move an unreadable file of the projection `T` to.
+fn state_backup_path<T: Projection>(profile: &Profile) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    profile
+        .data_dir()
+        .join(format!("{}.{}.bak", T::FILE_NAME, timestamp))
+}
 
-        // Load `PersistedState` from the file
-        match load_persisted_state(&state_path) {
-            Ok(st) => {
-                return st.merge_into_app(app_state);
-            }
-            Err(e) => {
-                // TODO: Report the error to the user
-                log::error!("Could not read the persisted state: {}", e);
-            }
-        }
-    } else {
+/// Restore the part of the application state represented by the projection
+/// `T` from a given profile. `state` will be updated with the restored state.
+pub fn restore_state<T: Projection>(
+    profile: &Profile,
+    app_state: Elem<model::AppState>,
+) -> Elem<model::AppState> {
+    let state_path = state_path::<T>(profile);
+
+    if !state_path.is_file() {
         log::info!(
             "The persisted state file was not found at {:?}.",
             state_path
         );
+        return app_state;
+    }
+
+    log::info!("Loading a persisted state from {:?}.", state_path);
+
+    let json = match std::fs::read_to_string(&state_path) {
+        Ok(json) => json,
+        Err(e) => {
+            // TODO: Report the error to the user
+            log::error!("Could not read the persisted state: {}", e);
+            return app_state;
+        }
+    };
+
+    // Start with the default state so that the fields that can't be restored
+    // keep the default values
+    let mut st = T::clone(&T::new(&app_state));
+
+    if let Err(e) = load_persisted_state(&json, &mut st) {
+        // TODO: Report the error to the user
+        log::error!("Could not fully restore the persisted state: {}", e);
+
+        // The file will be overwritten with the restored state soon. Keep
+        // the original file so that the lost settings can be recovered
+        let backup_path = state_backup_path::<T>(profile);
+        log::warn!(
+            "Moving the persisted state file {:?} to {:?}",
+            state_path,
+            backup_path
+        );
+        if let Err(e) = std::fs::rename(&state_path, &backup_path) {
+            log::error!(
+                "Could not move {:?} to {:?}: {}",
+                state_path,
+                backup_path,
+                e
+            );
+        }
     }
 
-    app_state
+    st.merge_into_app(app_state)
 }
 
-/// Load `PersistedState` from the specified path.
-fn load_persisted_state(path: &Path) -> Result<PersistedState, std::io::Error> {
-    let json = std::fs::read_to_string(path)?;
+#[derive(Debug, displaydoc::Display)]
+enum LoadError {
+    /// The file is not a valid JSON object.
+    Malformed,
+    /// The file was created by a newer version of the application (format version {version}).
+    UnsupportedVersion { version: u64 },
+    /// The following fields are malformed and were reset: {fields}
+    MalformedFields { fields: String },
+}
 
-    #[derive(Debug, displaydoc::Display)]
-    enum Error {
-        /// Deserialization failed.
-        DeserializationFailure,
+impl std::error::Error for LoadError {}
+
+/// Load a projection from the JSON string `json` into `st`, upgrading the
+/// data from an older format if necessary.
+///
+/// The fields that are loaded successfully are written to `st` even if an
+/// error is returned.
+fn load_persisted_state<T: Projection>(json: &str, st: &mut T) -> Result<(), LoadError> {
+    debug_assert_eq!(T::MIGRATIONS.len() as u64, T::CURRENT_VERSION);
+
+    let mut obj = match json::from_str(json) {
+        Ok(json::Value::Object(obj)) => obj,
+        _ => return Err(LoadError::Malformed),
+    };
+
+    let version = match obj.get("version") {
+        Some(json::Value::Number(json::Number::U64(version))) => *version,
+        Some(_) => return Err(LoadError::Malformed),
+        None => 0,
+    };
+
+    if version > T::CURRENT_VERSION {
+        // Try to load it anyway; the format might be compatible
+        let _ = st.read_json(&obj);
+        return Err(LoadError::UnsupportedVersion { version });
     }
 
-    impl std::error::Error for Error {}
+    for (i, migrate) in T::MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log::info!("Migrating the persisted state from version {}", i);
+        migrate(&mut obj);
+    }
 
-    json::from_str(&json).map_err(|_| {
-        std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            Error::DeserializationFailure,
-        )
-    })
+    let bad_fields = st.read_json(&obj);
+    if bad_fields.is_empty() {
+        Ok(())
+    } else {
+        Err(LoadError::MalformedFields {
+            fields: bad_fields.join(", "),
+        })
+    }
 }
 
 /// Write a file atomically.
@@ -113,18 +364,19 @@ fn write_atomically(path: &Path, tmp_path: &Path, contents: &str) -> Result<(),
     std::fs::rename(tmp_path, path)
 }
 
-/// Schedules the asynchronous persistence operations of an app state.
-pub struct PersistenceScheduler {
-    persisted: Cell<Option<Elem<PersistedState>>>,
+/// Schedules the asynchronous persistence operations of an app state,
+/// projected by `T`.
+pub struct PersistenceScheduler<T: Projection = PersistedState> {
+    persisted: Cell<Option<Elem<T>>>,
     shared: Arc<PersistenceSchedulerShared>,
 }
 
-impl PersistenceScheduler {
+impl<T: Projection> PersistenceScheduler<T> {
     /// Construct a `PersistenceScheduler` with an initial app state, which it
     /// will *not* persist to disk.
     pub fn new(app_state: &model::AppState) -> Self {
         Self {
-            persisted: Cell::new(Some(PersistedState::new(app_state))),
+            persisted: Cell::new(Some(T::new(app_state))),
             shared: Arc::new(PersistenceSchedulerShared {
                 timer: MtLock::new(Cell::new(None)),
                 mt_gen: MtLock::new(Cell::new(0)),
@@ -140,7 +392,7 @@ impl PersistenceScheduler {
     pub fn flush(&self, wm: Wm, app_state: &model::AppState, profile: &'static Profile) {
         let mut persisted = self.persisted.take().unwrap();
 
-        if let Some(new_persisted) = PersistedState::merge_from_app(&persisted, app_state) {
+        if let Some(new_persisted) = T::merge_from_app(&persisted, app_state) {
             persisted = new_persisted;
         } else {
             // There might be already an active timer waiting to persist
@@ -164,9 +416,7 @@ impl PersistenceScheduler {
         // Temporarily take `persisted`
         let mut persisted = self.persisted.take();
 
-        if let Some(new_persisted) =
-            PersistedState::merge_from_app(persisted.as_ref().unwrap(), app_state)
-        {
+        if let Some(new_persisted) = T::merge_from_app(persisted.as_ref().unwrap(), app_state) {
             persisted = Some(new_persisted.clone());
 
             PersistenceSchedulerShared::commit_new_generation_lazily(
@@ -182,6 +432,31 @@ impl PersistenceScheduler {
     }
 }
 
+/// A [`Middleware`] that schedules the persistence of the app state using
+/// [`PersistenceScheduler`] whenever the state changes.
+pub struct PersistenceMiddleware<T: Projection = PersistedState> {
+    wm: Wm,
+    profile: &'static Profile,
+    sched: Rc<PersistenceScheduler<T>>,
+}
+
+impl<T: Projection> PersistenceMiddleware<T> {
+    pub fn new(wm: Wm, profile: &'static Profile, sched: Rc<PersistenceScheduler<T>>) -> Self {
+        Self { wm, profile, sched }
+    }
+}
+
+impl<T: Projection> Middleware<model::AppState, model::AppAction> for PersistenceMiddleware<T> {
+    fn update(
+        &self,
+        _: &Store<model::AppState, model::AppAction>,
+        _: &Elem<model::AppState>,
+        new_state: &Elem<model::AppState>,
+    ) {
+        self.sched.handle_update(self.wm, new_state, self.profile);
+    }
+}
+
 /// Shared by (1) `PersistenceScheduler`, (2) the timer handlers, and (3) the
 /// working thread where I/O takes place
 struct PersistenceSchedulerShared {
@@ -202,13 +477,13 @@ type Gen = u64;
 impl PersistenceSchedulerShared {
     /// Commit a new generation and block the current thread until it's
     /// persisted.
-    fn commit_new_generation_blocking(
+    fn commit_new_generation_blocking<T: Projection>(
         wm: Wm,
         this: Arc<Self>,
         profile: &'static Profile,
-        ps: Elem<PersistedState>,
+        ps: Elem<T>,
     ) {
-        // The `PersistedState` owned by the timer handler is supposed to be of
+        // The projection owned by the timer handler is supposed to be of
         // the latest generation, so the timer handler must be dropped as we
         // commit a new generation.
         if let Some(timer) = this.timer.get_with_wm(wm).take() {
@@ -241,15 +516,15 @@ impl PersistenceSchedulerShared {
     }
 
     /// Commit a new generation, persist later.
-    fn commit_new_generation_lazily(
+    fn commit_new_generation_lazily<T: Projection>(
         wm: Wm,
         this: Arc<Self>,
         profile: &'static Profile,
-        ps: Elem<PersistedState>,
+        ps: Elem<T>,
     ) {
         // Cancel the previous invocation so that if the state was updated
         // for many times within a short time, the state is saved only once.
-        // Also, the `PersistedState` owned by the timer handler must be of
+        // Also, the projection owned by the timer handler must be of
         // the latest generation, so the timer handler must be dropped as we
         // commit a new generation.
         if let Some(timer) = this.timer.get_with_wm(wm).take() {
@@ -266,11 +541,11 @@ impl PersistenceSchedulerShared {
     }
 
     /// Schedule `Self::persist_timer_handler` to run later.
-    fn schedule_persist_timer(
+    fn schedule_persist_timer<T: Projection>(
         wm: Wm,
         this: Arc<Self>,
         profile: &'static Profile,
-        ps: Elem<PersistedState>,
+        ps: Elem<T>,
     ) {
         // Schedule a persist task
         let this2 = Arc::clone(&this);
@@ -287,11 +562,11 @@ impl PersistenceSchedulerShared {
         this.timer.get_with_wm(wm).set(Some(timer));
     }
 
-    fn persist_timer_handler(
+    fn persist_timer_handler<T: Projection>(
         wm: Wm,
         this: Arc<Self>,
         profile: &'static Profile,
-        ps: Elem<PersistedState>,
+        ps: Elem<T>,
     ) {
         log::trace!("The state persistence timer has fired");
 
@@ -313,13 +588,13 @@ impl PersistenceSchedulerShared {
     ///
     /// `ps` must pertain to `mt_gen`. There must not be an ongoing persistence
     /// operation.
-    fn start_persist_latest_gen(
+    fn start_persist_latest_gen<T: Projection>(
         wm: Wm,
         this: Arc<Self>,
         profile: &'static Profile,
-        ps: &PersistedState,
+        ps: &T,
     ) {
-        // `PersistedState` might be `!Send`, so serialization must happen
+        // `T` might be `!Send`, so serialization must happen
         // on the main thread
         let json = json::to_string(ps);
 
@@ -331,8 +606,8 @@ impl PersistenceSchedulerShared {
 
         // Do the I/O in a worker thread
         nativedispatch::Queue::global_bg().invoke(move || {
-            let path = state_path(profile);
-            let tmp_path = state_tmp_path(profile);
+            let path = state_path::<T>(profile);
+            let tmp_path = state_tmp_path::<T>(profile);
 
             log::info!(
                 "Writing the state (gen {:?}) to {:?} using a temporary file at {:?}",
@@ -377,3 +652,284 @@ impl PersistenceSchedulerShared {
         *self.persistent_gen.lock().unwrap() >= gen
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> PersistedState {
+        let mut st = PersistedState::clone(&PersistedState::new(&model::AppState::new()));
+        load_persisted_state(json, &mut st).unwrap();
+        st
+    }
+
+    fn join(
+        app_state: Elem<model::AppState>,
+        account: model::AccountId,
+        channel: &str,
+    ) -> Elem<model::AppState> {
+        let channel_ref = model::ChannelRef {
+            account,
+            channel: channel.to_owned(),
+        };
+        model::AppState::reduce(app_state, &model::AppAction::JoinChannel(channel_ref))
+    }
+
+    fn selected_channel(app_state: &model::AppState, i: usize) -> Option<&str> {
+        (app_state.wnds[i].selected_channel.as_ref()).map(|channel_ref| &channel_ref.channel[..])
+    }
+
+    fn new_app_with_account() -> (Elem<model::AppState>, model::AccountId) {
+        let account = model::AccountId::new_unique();
+        let server = model::Server {
+            name: "Example".to_owned(),
+        };
+        let app_state = Elem::new(model::AppState::new());
+        let app_state =
+            model::AppState::reduce(app_state, &model::AppAction::AddAccount(account, server));
+        (app_state, account)
+    }
+
+    #[test]
+    fn restore_v2_selected_channel() {
+        let st = load(
+            r##"{
+                "version": 2,
+                "wnds": [
+                    {
+                        "sidebar_width": 100.0,
+                        "editor_height": 80.0,
+                        "sidebar_visible": false,
+                        "selected_channel": { "server": "Example", "channel": "#b" }
+                    },
+                    { "sidebar_width": 120.0 }
+                ]
+            }"##,
+        );
+        assert_eq!(st.wnds.len(), 2);
+        assert_eq!(st.wnds[0].sidebar_width, 100.0);
+        assert_eq!(st.wnds[0].editor_height, 80.0);
+        assert!(!st.wnds[0].sidebar_visible);
+        assert_eq!(st.wnds[1].sidebar_width, 120.0);
+        assert_eq!(st.wnds[1].selected_channel, None);
+
+        let (app_state, account) = new_app_with_account();
+        let app_state = st.merge_into_app(app_state);
+
+        // The first joined channel is selected while the restored one is
+        // yet to be joined
+        let app_state = join(app_state, account, "#a");
+        assert_eq!(selected_channel(&app_state, 0), Some("#a"));
+        assert_eq!(selected_channel(&app_state, 1), Some("#a"));
+
+        // The restored channel is selected once it's joined. Channel names
+        // are case-insensitive.
+        let app_state = join(app_state, account, "#B");
+        assert_eq!(selected_channel(&app_state, 0), Some("#B"));
+        assert!(app_state.wnds[0].channel_to_restore.is_none());
+        assert_eq!(selected_channel(&app_state, 1), Some("#a"));
+
+        // Persist the selection
+        let st = PersistedState::new(&app_state);
+        assert_eq!(
+            st.wnds[0].selected_channel,
+            Some(PersistedChannel {
+                server: "Example".to_owned(),
+                channel: "#B".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn restore_nonexistent_channel() {
+        let st = load(
+            r##"{
+                "version": 2,
+                "wnds": [
+                    { "selected_channel": { "server": "Example", "channel": "#gone" } }
+                ]
+            }"##,
+        );
+
+        let (app_state, account) = new_app_with_account();
+        let app_state = st.merge_into_app(app_state);
+
+        // The channel to restore doesn't exist, so the window is left as if
+        // there were nothing to restore
+        assert_eq!(selected_channel(&app_state, 0), None);
+        let app_state = join(app_state, account, "#a");
+        assert_eq!(selected_channel(&app_state, 0), Some("#a"));
+
+        // The channel selected instead is persisted
+        let st = PersistedState::new(&app_state);
+        assert_eq!(
+            st.wnds[0].selected_channel,
+            Some(PersistedChannel {
+                server: "Example".to_owned(),
+                channel: "#a".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_selected_channel() {
+        let mut st = PersistedState::clone(&PersistedState::new(&model::AppState::new()));
+        let result = load_persisted_state(
+            r#"{ "version": 2, "wnds": [ { "sidebar_width": 100.0, "selected_channel": 42 } ] }"#,
+            &mut st,
+        );
+        assert!(matches!(result, Err(LoadError::MalformedFields { .. })));
+        assert_eq!(st.wnds[0].sidebar_width, 100.0);
+        assert_eq!(st.wnds[0].selected_channel, None);
+    }
+
+    #[test]
+    fn migrate_from_v0() {
+        let st = load(
+            r#"{
+                "main_wnd": {
+                    "sidebar_width": 100.0,
+                    "editor_height": 80.0,
+                    "sidebar_visible": false
+                }
+            }"#,
+        );
+        assert_eq!(st.version, PersistedState::CURRENT_VERSION);
+        assert_eq!(st.wnds.len(), 1);
+        assert_eq!(st.wnds[0].sidebar_width, 100.0);
+        assert_eq!(st.wnds[0].editor_height, 80.0);
+        assert!(!st.wnds[0].sidebar_visible);
+        assert_eq!(st.wnds[0].selected_channel, None);
+    }
+
+    #[test]
+    fn migrate_from_v1() {
+        let st = load(
+            r#"{
+                "version": 1,
+                "main_wnd": { "sidebar_width": 100.0, "sidebar_visible": false }
+            }"#,
+        );
+        let default_wnd = PersistedState::new(&model::AppState::new()).wnds[0].clone();
+        assert_eq!(st.wnds.len(), 1);
+        assert_eq!(st.wnds[0].sidebar_width, 100.0);
+        assert_eq!(st.wnds[0].editor_height, default_wnd.editor_height);
+        assert!(!st.wnds[0].sidebar_visible);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut st = PersistedState::clone(&PersistedState::new(&model::AppState::new()));
+        let result = load_persisted_state(
+            r#"{ "version": 3, "wnds": [ { "sidebar_width": 100.0 } ] }"#,
+            &mut st,
+        );
+        assert!(matches!(
+            result,
+            Err(LoadError::UnsupportedVersion { version: 3 })
+        ));
+
+        // The compatible fields are loaded anyway
+        assert_eq!(st.wnds[0].sidebar_width, 100.0);
+    }
+
+    /// A profile in a temporary directory, which is deleted on drop.
+    struct TestProfile {
+        profile: Profile,
+        dir: PathBuf,
+    }
+
+    impl TestProfile {
+        fn new() -> Self {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+            let dir = std::env::temp_dir().join(format!(
+                "stella2_viewpersistence_test_{}_{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let profile = Profile::from_custom_dir(&dir);
+            profile.prepare().unwrap();
+            Self { profile, dir }
+        }
+
+        /// Write a state file and restore the state from it.
+        fn restore(&self, json: &str) -> Elem<model::AppState> {
+            let path = state_path::<PersistedState>(&self.profile);
+            std::fs::write(&path, json).unwrap();
+
+            restore_state::<PersistedState>(&self.profile, Elem::new(model::AppState::new()))
+        }
+
+        /// Get the files in the data directory.
+        fn file_names(&self) -> Vec<String> {
+            let mut names: Vec<String> = std::fs::read_dir(self.profile.data_dir())
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    impl Drop for TestProfile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn is_backup(name: &str) -> bool {
+        name.starts_with("view.json.") && name.ends_with(".bak")
+    }
+
+    #[test]
+    fn restore_v1_file() {
+        let profile = TestProfile::new();
+        let app_state =
+            profile.restore(r#"{ "version": 1, "main_wnd": { "sidebar_width": 100.0 } }"#);
+        assert_eq!(app_state.wnds[0].sidebar_width, 100.0);
+
+        // The file is left in place
+        assert_eq!(profile.file_names(), vec!["view.json"]);
+    }
+
+    #[test]
+    fn restore_unsupported_version_file() {
+        let profile = TestProfile::new();
+        let app_state =
+            profile.restore(r#"{ "version": 3, "wnds": [ { "sidebar_width": 100.0 } ] }"#);
+        assert_eq!(app_state.wnds[0].sidebar_width, 100.0);
+
+        // The file is moved to a backup so that it's not overwritten
+        let names = profile.file_names();
+        assert_eq!(names.len(), 1, "{:?}", names);
+        assert!(is_backup(&names[0]), "{:?}", names);
+    }
+
+    #[test]
+    fn restore_file_with_malformed_field() {
+        let profile = TestProfile::new();
+        let app_state = profile.restore(
+            r#"{ "version": 2, "wnds": [ { "sidebar_width": 100.0, "editor_height": "x" } ] }"#,
+        );
+        let default_wnd = model::WndState::new(model::WndId::new_unique());
+        assert_eq!(app_state.wnds[0].sidebar_width, 100.0);
+        assert_eq!(app_state.wnds[0].editor_height, default_wnd.editor_height);
+
+        // The file is moved to a backup because the malformed field is lost
+        // when the state is persisted again
+        let names = profile.file_names();
+        assert_eq!(names.len(), 1, "{:?}", names);
+        assert!(is_backup(&names[0]), "{:?}", names);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (app_state, account) = new_app_with_account();
+        let app_state = join(app_state, account, "#a");
+        let st = PersistedState::new(&app_state);
+
+        assert_eq!(load(&json::to_string(&*st)).wnds, st.wnds);
+    }
+}
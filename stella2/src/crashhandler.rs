@@ -70,16 +70,31 @@ cfg_if! {
             // Tell CrashReporter the panic's details.
             __crashreporter_info__.store(msg.as_ptr() as _, Ordering::Relaxed);
 
-            // Output to Apple System Log as well.
-            // TODO: Add a logging system to the application
+            // Output to Apple System Log as well. Don't use `log` here because
+            // the panic might have occurred inside the logger.
             nslog(&msg);
             nslog("Escalating panic to abort. Run with the environment variable \
                    'RUST_BACKTRACE' to disable this behavior.");
 
+            // Write the queued log records before the process dies
+            crate::logging::shutdown();
+
             // Crash the application
             std::process::abort();
         }
+
+        pub fn set_profile(_: &'static crate::config::profile::Profile) {}
+
+        pub fn check_reports(_: tcw3::pal::Wm, _: &crate::config::profile::Profile) {}
+    } else if #[cfg(not(target_os = "windows"))] {
+        // Write crash reports into the profile directory
+        mod gtkhandler;
+        pub use self::gtkhandler::{check_reports, init, set_profile};
     } else {
         pub fn init() {}
+
+        pub fn set_profile(_: &'static crate::config::profile::Profile) {}
+
+        pub fn check_reports(_: tcw3::pal::Wm, _: &crate::config::profile::Profile) {}
     }
 }
@@ -0,0 +1,301 @@
+//! The crash handler for the gtk backend. On panic, it writes a crash report
+//! into the profile directory and aborts the application. The report is
+//! brought to the user's attention on the next launch.
+use gtk::prelude::*;
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    fs,
+    io::{self, Write as _},
+    panic,
+    path::{Path, PathBuf},
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+use tcw3::pal;
+
+use crate::{config::profile::Profile, i18n, logging, opener};
+
+/// The profile where crash reports are written. Set by `set_profile`.
+static PROFILE: AtomicPtr<Profile> = AtomicPtr::new(null_mut());
+
+/// Crash reports are written by default, unlike the other platforms' panic
+/// hooks, because there's no system crash reporter to fall back to. Setting
+/// the environment variable `RUST_BACKTRACE` to a non-empty value disables
+/// the hook to aid debugging.
+fn should_install_panic_hook() -> bool {
+    std::env::var_os("RUST_BACKTRACE").map_or(true, |value| value.is_empty())
+}
+
+pub fn init() {
+    if should_install_panic_hook() {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |panic_info| {
+            panic_handler(panic_info);
+
+            // Output the panic message to stderr as well
+            default_hook(panic_info);
+
+            // Write the queued log records before the process dies
+            logging::shutdown();
+
+            // Unwinding through the main loop is undefined behavior, so crash
+            // the application
+            eprintln!(
+                "Escalating panic to abort. Run with the environment variable \
+                 'RUST_BACKTRACE' to disable this behavior."
+            );
+            std::process::abort();
+        }));
+    }
+}
+
+/// Set the profile where crash reports are written. Crash reports are not
+/// written until this function is called.
+pub fn set_profile(profile: &'static Profile) {
+    PROFILE.store(profile as *const _ as *mut _, Ordering::Release);
+}
+
+fn report_dir(profile: &Profile) -> PathBuf {
+    profile.data_dir().join("crashes")
+}
+
+/// Reports that were already presented to the user are moved here.
+fn seen_report_dir(profile: &Profile) -> PathBuf {
+    report_dir(profile).join("seen")
+}
+
+fn panic_handler(panic_info: &panic::PanicInfo<'_>) {
+    let profile = PROFILE.load(Ordering::Acquire);
+    if profile.is_null() {
+        return;
+    }
+    let profile = unsafe { &*profile };
+
+    let report = format_report(panic_info);
+
+    let dir = report_dir(profile);
+
+    match fs::create_dir_all(&dir).and_then(|()| write_new_report(&dir, &report)) {
+        Ok(path) => eprintln!("A crash report was written to {:?}", path),
+        Err(e) => eprintln!("Could not write a crash report to {:?}: {}", dir, e),
+    }
+}
+
+/// The number of attempts to find an unused report file name.
+const MAX_REPORT_NAME_ATTEMPTS: u32 = 100;
+
+/// Write `report` to a new file in `dir`, named after the current time.
+/// Never overwrites an existing report, e.g., one written by another thread
+/// or process panicking in the same second.
+fn write_new_report(dir: &Path, report: &str) -> io::Result<PathBuf> {
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+
+    for i in 0..MAX_REPORT_NAME_ATTEMPTS {
+        // Zero-padded so that the file names sort by the time of creation
+        let path = dir.join(format!("crash-{}-{:02}.txt", timestamp, i));
+
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+
+        file.write_all(report.as_bytes())?;
+        return Ok(path);
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "Could not find an unused file name",
+    ))
+}
+
+fn format_report(panic_info: &panic::PanicInfo<'_>) -> String {
+    let payload;
+
+    if let Some(s) = panic_info.payload().downcast_ref::<&'static str>() {
+        payload = *s;
+    } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
+        payload = &*s;
+    } else {
+        payload = "Box<Any>";
+    }
+
+    let mut report = String::new();
+
+    // `write!` to `String` never fails
+    let _ = writeln!(report, "Stella2 crash report");
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "OS: {}", os_description());
+    let _ = writeln!(report, "Time: {}", chrono::Local::now().to_rfc3339());
+    let _ = writeln!(
+        report,
+        "Thread: {}",
+        std::thread::current().name().unwrap_or("<unnamed>")
+    );
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "Panic: {}", payload);
+    if let Some(loc) = panic_info.location() {
+        let _ = writeln!(
+            report,
+            "Location: {}:{}:{}",
+            loc.file(),
+            loc.line(),
+            loc.column()
+        );
+    }
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "Backtrace:");
+    let _ = writeln!(report, "{}", Backtrace::force_capture());
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "Recent log records:");
+    // The panic might have occurred while the buffer was locked
+    if let Some(records) = logging::try_recent_records(0) {
+        for record in records.iter() {
+            let _ = writeln!(report, "{}", record);
+        }
+    } else {
+        let _ = writeln!(report, "(unavailable)");
+    }
+
+    report
+}
+
+/// Describe the operating system, e.g., `Ubuntu 20.04 LTS (linux 5.4.0-33
+/// x86_64)`.
+fn os_description() -> String {
+    let distro = fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|os_release| {
+            os_release
+                .lines()
+                .find(|line| line.starts_with("PRETTY_NAME="))
+                .map(|line| line["PRETTY_NAME=".len()..].trim_matches('"').to_owned())
+        })
+        .unwrap_or_else(|| "Unknown".to_owned());
+
+    let kernel = fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+
+    format!(
+        "{} ({} {} {})",
+        distro,
+        std::env::consts::OS,
+        kernel.trim(),
+        std::env::consts::ARCH
+    )
+}
+
+/// Find the crash reports which haven't been presented to the user yet,
+/// sorted by the time of creation.
+fn find_new_reports(profile: &Profile) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(report_dir(profile)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut reports = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_report = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with("crash-") && name.ends_with(".txt"))
+            .unwrap_or(false);
+        if is_report && path.is_file() {
+            reports.push(path);
+        }
+    }
+
+    // The file names include timestamps
+    reports.sort();
+
+    Ok(reports)
+}
+
+const RESPONSE_SHOW: gtk::ResponseType = gtk::ResponseType::Other(1);
+const RESPONSE_DELETE: gtk::ResponseType = gtk::ResponseType::Other(2);
+
+/// Check for crash reports written by previous runs. If there are any, ask
+/// the user whether to show or delete them.
+pub fn check_reports(_: pal::Wm, profile: &Profile) {
+    let reports = match find_new_reports(profile) {
+        Ok(reports) => reports,
+        Err(e) => {
+            log::warn!("Could not check for crash reports: {}", e);
+            return;
+        }
+    };
+
+    let latest = if let Some(latest) = reports.last() {
+        latest
+    } else {
+        return;
+    };
+
+    log::info!("Found crash report(s): {:?}", reports);
+
+    let locale = i18n::Locale::default();
+    let message = locale.format_plural(
+        "crash-message",
+        reports.len() as u64,
+        &[("count", &reports.len())],
+    );
+
+    let dialog = gtk::MessageDialog::new(
+        None::<&gtk::Window>,
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Warning,
+        gtk::ButtonsType::None,
+        &message,
+    );
+    dialog.add_button(&locale.text("crash-delete"), RESPONSE_DELETE);
+    dialog.add_button(&locale.text("crash-show"), RESPONSE_SHOW);
+    dialog.add_button(&locale.text("crash-close"), gtk::ResponseType::Close);
+    dialog.set_default_response(gtk::ResponseType::Close);
+
+    let response = dialog.run();
+    dialog.destroy();
+
+    if response == RESPONSE_DELETE {
+        for path in reports.iter() {
+            if let Err(e) = fs::remove_file(path) {
+                log::warn!("Could not delete the crash report {:?}: {}", path, e);
+            }
+        }
+        return;
+    }
+
+    // Keep the reports, but don't bring them up again
+    let seen_dir = seen_report_dir(profile);
+    let latest = match move_reports(&reports, &seen_dir) {
+        Ok(()) => seen_dir.join(latest.file_name().unwrap()),
+        Err(e) => {
+            log::warn!("Could not move the crash reports to {:?}: {}", seen_dir, e);
+            latest.clone()
+        }
+    };
+
+    if response == RESPONSE_SHOW {
+        if let Err(e) = opener::open_path(&latest) {
+            log::warn!("Could not open {:?}: {}", latest, e);
+        }
+    }
+}
+
+fn move_reports(reports: &[PathBuf], dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for path in reports.iter() {
+        fs::rename(path, dir.join(path.file_name().unwrap()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! UI translation.
+//!
+//! The messages displayed in the user interface are defined in the message
+//! catalogs in `stella2/i18n` (see the `msgcat` crate for the format), which
+//! are embedded into the executable. `en.msgcat` must define every message
+//! because it's used for the messages missing from other catalogs.
+//!
+//! A designer component displaying messages has a prop of type [`Locale`]
+//! and refers to the messages by their IDs:
+//!
+//! ```text
+//! pub prop locale: crate::i18n::Locale = crate::i18n::Locale::default();
+//!
+//! const title = Label::new! {
+//!     style_manager,
+//!     text = get!(&locale).text("pref-title"),
+//! };
+//! ```
+//!
+//! Such expressions are re-evaluated when `locale` changes, so setting a new
+//! `Locale` re-renders the component in the new language.
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+/// The languages having a built-in message catalog, given as pairs of a
+/// language tag and the name of the language in itself.
+pub const LANGUAGES: &[(&str, &str)] = &[("en", "English"), ("ja", "日本語")];
+
+/// The language used if none of the user's preferred languages is
+/// available.
+const DEFAULT_LANGUAGE: &str = "en";
+
+fn catalog_source(language: &str) -> Option<&'static str> {
+    match language {
+        "en" => Some(include_str!("../i18n/en.msgcat")),
+        "ja" => Some(include_str!("../i18n/ja.msgcat")),
+        _ => None,
+    }
+}
+
+/// The messages in a particular UI language. Cheap to clone.
+///
+/// Two `Locale`s compare equal if they were created for the same language.
+#[derive(Clone)]
+pub struct Locale {
+    bundle: Rc<msgcat::Bundle>,
+}
+
+#[derive(Default)]
+struct LocaleCache {
+    /// Indexed by the language tag in `LANGUAGES`.
+    by_language: HashMap<&'static str, Locale>,
+    /// Indexed by the argument of `Locale::new`.
+    by_request: HashMap<Option<String>, Locale>,
+}
+
+thread_local! {
+    static LOCALES: RefCell<LocaleCache> = RefCell::new(LocaleCache::default());
+}
+
+impl Locale {
+    /// Get a `Locale` for `language`, a language tag such as the ones in
+    /// [`LANGUAGES`]. If it's `None` or unavailable, the language is chosen
+    /// based on the environment.
+    pub fn new(language: Option<&str>) -> Self {
+        LOCALES.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let key = language.map(str::to_owned);
+            if let Some(locale) = cache.by_request.get(&key) {
+                return locale.clone();
+            }
+
+            let resolved = resolve_language(language);
+            let locale = (cache.by_language.entry(resolved))
+                .or_insert_with(|| Self::load(resolved))
+                .clone();
+            cache.by_request.insert(key, locale.clone());
+            locale
+        })
+    }
+
+    fn load(language: &'static str) -> Self {
+        log::debug!("Loading the message catalog for {:?}", language);
+
+        // Fall back to the default language for missing messages
+        let mut languages = vec![language];
+        if language != DEFAULT_LANGUAGE {
+            languages.push(DEFAULT_LANGUAGE);
+        }
+
+        let mut bundle = msgcat::Bundle::new();
+        for lang in languages {
+            match msgcat::Catalog::parse(lang, catalog_source(lang).unwrap()) {
+                Ok(catalog) => bundle.push(catalog),
+                Err(e) => log::error!("The message catalog for {:?} is malformed: {}", lang, e),
+            }
+        }
+
+        Self {
+            bundle: Rc::new(bundle),
+        }
+    }
+
+    /// Get the language tag of the locale.
+    pub fn language(&self) -> &str {
+        self.bundle.language().unwrap_or(DEFAULT_LANGUAGE)
+    }
+
+    /// Get the message `id`, which shouldn't have placeholders.
+    pub fn text(&self, id: &str) -> String {
+        self.bundle.text(id)
+    }
+
+    /// Get the message `id` with its placeholders replaced with `args`.
+    pub fn format(&self, id: &str, args: &msgcat::Args<'_>) -> String {
+        self.bundle.format(id, args)
+    }
+
+    /// Like [`Locale::format`], but chooses a plural form based on `n`.
+    pub fn format_plural(&self, id: &str, n: u64, args: &msgcat::Args<'_>) -> String {
+        self.bundle.format_plural(id, n, args)
+    }
+}
+
+/// Choose a language in `LANGUAGES` for `Locale::new`.
+fn resolve_language(language: Option<&str>) -> &'static str {
+    let mut requested: Vec<_> = language
+        .and_then(msgcat::LanguageId::parse)
+        .into_iter()
+        .collect();
+    requested.extend(msgcat::languages_from_env());
+
+    let available: Vec<&'static str> = LANGUAGES.iter().map(|&(tag, _)| tag).collect();
+
+    msgcat::negotiate(&requested, &available).unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// Uses the language chosen based on the environment.
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl PartialEq for Locale {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.bundle, &other.bundle)
+    }
+}
+
+impl fmt::Debug for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Locale").field(&self.language()).finish()
+    }
+}
@@ -0,0 +1,155 @@
+//! Forwards requests from other application instances to the running one
+//!
+//! Only one application instance can use a profile at once (see
+//! `config::lock`). The instance holding the profile lock listens on a Unix
+//! domain socket in the profile directory. When the application is launched
+//! again with the same profile, the new instance sends its command-line
+//! arguments through the socket and exits.
+use miniserde::{json, Deserialize, Serialize};
+use std::{io, path::PathBuf};
+use tcw3::pal;
+
+use crate::config::{cmdline::Args, profile::Profile};
+
+/// A request sent by another application instance.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Request {
+    /// The IRC servers to connect to, formatted as URLs.
+    pub irc_servers: Vec<String>,
+    /// Bring the main window to the front.
+    pub activate: bool,
+}
+
+impl Request {
+    /// Construct a `Request` representing the command-line arguments.
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            irc_servers: args.irc_servers.iter().map(ToString::to_string).collect(),
+            activate: true,
+        }
+    }
+}
+
+/// Requests larger than this are rejected.
+#[cfg(unix)]
+const MAX_REQUEST_LEN: u64 = 1 << 16;
+
+/// The timeout for sending or receiving a request.
+#[cfg(unix)]
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+fn socket_path(profile: &Profile) -> PathBuf {
+    profile.data_dir().join("ipc.sock")
+}
+
+/// Send a request to the application instance listening on the specified
+/// profile.
+#[cfg(unix)]
+pub fn send_request(profile: &Profile, request: &Request) -> io::Result<()> {
+    use std::{io::prelude::*, net::Shutdown, os::unix::net::UnixStream};
+
+    let path = socket_path(profile);
+    log::info!("Sending {:?} to {:?}", request, path);
+
+    let mut stream = UnixStream::connect(&path)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(json::to_string(request).as_bytes())?;
+    stream.shutdown(Shutdown::Write)
+}
+
+#[cfg(not(unix))]
+pub fn send_request(_profile: &Profile, _request: &Request) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "IPC is not supported on this platform",
+    ))
+}
+
+/// Start accepting requests in a background thread. `handler` is called on
+/// the main thread for each received request.
+///
+/// The caller must hold the profile lock. A socket file left by a previous
+/// instance is removed.
+#[cfg(unix)]
+pub fn listen(
+    wm: pal::Wm,
+    profile: &Profile,
+    handler: impl Fn(pal::Wm, Request) + 'static,
+) -> io::Result<()> {
+    use std::{
+        fs,
+        io::prelude::*,
+        os::unix::{fs::PermissionsExt, net::UnixListener},
+        sync::Arc,
+        thread,
+    };
+    use tcw3::pal::{prelude::*, MtSticky};
+
+    let path = socket_path(profile);
+
+    // We hold the profile lock, so nobody is listening on the existing socket
+    match fs::remove_file(&path) {
+        Ok(()) => log::debug!("Removed a stale socket file at {:?}", path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    log::info!("Listening on {:?}", path);
+    let listener = UnixListener::bind(&path)?;
+
+    // Don't let other users send requests
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+    let handler: Box<dyn Fn(pal::Wm, Request)> = Box::new(handler);
+    let handler = Arc::new(MtSticky::with_wm(wm, handler));
+
+    thread::Builder::new()
+        .name("ipc".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("Failed to accept an IPC connection: {}", e);
+                        continue;
+                    }
+                };
+
+                let mut json = String::new();
+                let result = stream
+                    .set_read_timeout(Some(TIMEOUT))
+                    .and_then(|()| stream.take(MAX_REQUEST_LEN).read_to_string(&mut json));
+                if let Err(e) = result {
+                    log::warn!("Failed to receive an IPC request: {}", e);
+                    continue;
+                }
+
+                let request: Request = match json::from_str(&json) {
+                    Ok(request) => request,
+                    Err(_) => {
+                        log::warn!("Received a malformed IPC request: {:?}", json);
+                        continue;
+                    }
+                };
+
+                log::info!("Received an IPC request: {:?}", request);
+
+                let handler = Arc::clone(&handler);
+                pal::Wm::invoke_on_main_thread(move |wm| {
+                    handler.get_with_wm(wm)(wm, request);
+                });
+            }
+        })?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen(
+    _wm: pal::Wm,
+    _profile: &Profile,
+    _handler: impl Fn(pal::Wm, Request) + 'static,
+) -> io::Result<()> {
+    log::info!("IPC is not supported on this platform");
+    Ok(())
+}
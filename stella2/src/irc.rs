@@ -0,0 +1,402 @@
+//! Connects IRC servers to the app state
+use harmony::{Elem, Middleware, Store};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+    sync::{Arc, Mutex},
+    thread,
+};
+use stella2_irc::{client::conversation_name, message::is_channel_name, Connection, Event};
+use tcw3::pal::{self, prelude::*, MtSticky};
+
+use crate::{
+    config::{
+        cmdline::IrcServer,
+        history::{History, RecordPos},
+    },
+    model,
+};
+
+/// The maximum number of messages loaded from the history when joining a
+/// channel.
+const BACKLOG_LEN: usize = 100;
+
+/// The connections to IRC servers established by [`spawn_connections`],
+/// owned by the main thread.
+///
+/// The messages in the outboxes of joined channels (`Channel::outbox`) are
+/// sent through the connections by the middleware created by
+/// [`IrcConnections::middleware`].
+pub struct IrcConnections {
+    dispatch: Box<dyn Fn(model::AppAction)>,
+    connections: RefCell<HashMap<model::AccountId, Connection>>,
+    /// The latest app state seen by the middleware.
+    state: RefCell<Option<Elem<model::AppState>>>,
+}
+
+impl IrcConnections {
+    /// Construct an `IrcConnections`. `dispatch` is used to dispatch the
+    /// `AppAction`s converted from the events from the servers.
+    pub fn new(dispatch: impl Fn(model::AppAction) + 'static) -> Rc<Self> {
+        Rc::new(Self {
+            dispatch: Box::new(dispatch),
+            connections: RefCell::new(HashMap::new()),
+            state: RefCell::new(None),
+        })
+    }
+
+    /// Create a middleware that sends the messages in the outboxes as soon as
+    /// they are added. It must be added to the store whose actions are
+    /// dispatched by `dispatch`.
+    pub fn middleware(this: &Rc<Self>) -> impl Middleware<model::AppState, model::AppAction> {
+        OutboxMiddleware {
+            connections: Rc::downgrade(this),
+        }
+    }
+
+    fn add_connection(&self, account: model::AccountId, connection: Connection) {
+        // The connection might have been closed before reaching here, in which
+        // case `remove_connection` was already called
+        if !connection.is_connected() {
+            return;
+        }
+
+        self.connections.borrow_mut().insert(account, connection);
+        (self.dispatch)(model::AppAction::SetConnected(account, true));
+
+        // Send the messages composed while connecting
+        let state = self.state.borrow().clone();
+        if let Some(state) = state {
+            if let Some(account) = state.accounts.iter().find(|a| a.id == account) {
+                self.send_outbox(account);
+            }
+        }
+    }
+
+    /// Forget the closed connection of `account`. The messages in its outboxes
+    /// are kept.
+    fn remove_connection(&self, account: model::AccountId) {
+        self.connections.borrow_mut().remove(&account);
+        (self.dispatch)(model::AppAction::SetConnected(account, false));
+    }
+
+    /// Send the messages in the outboxes of the channels of `account` and
+    /// dispatch actions to remove them from the outboxes.
+    fn send_outbox(&self, account: &model::Account) {
+        let connections = self.connections.borrow();
+        let connection = match connections.get(&account.id) {
+            // Don't lose the messages to a closed connection. They stay in
+            // the outboxes until a new connection is added.
+            Some(connection) if connection.is_connected() => connection,
+            _ => return,
+        };
+
+        for channel in account.channels.iter() {
+            if channel.outbox.is_empty() {
+                continue;
+            }
+
+            // This only queues the messages. They are sent and recorded to
+            // the history by the connection's background thread, so the main
+            // thread doesn't block on the network or the disk.
+            for text in channel.outbox.iter() {
+                connection.privmsg(&channel.name, text);
+            }
+
+            (self.dispatch)(model::AppAction::RemoveFromOutbox(
+                model::ChannelRef {
+                    account: account.id,
+                    channel: channel.name.clone(),
+                },
+                channel.outbox.len(),
+            ));
+        }
+    }
+}
+
+/// Created by [`IrcConnections::middleware`].
+struct OutboxMiddleware {
+    connections: Weak<IrcConnections>,
+}
+
+impl Middleware<model::AppState, model::AppAction> for OutboxMiddleware {
+    fn update(
+        &self,
+        _: &Store<model::AppState, model::AppAction>,
+        _: &Elem<model::AppState>,
+        new_state: &Elem<model::AppState>,
+    ) {
+        let connections = if let Some(connections) = self.connections.upgrade() {
+            connections
+        } else {
+            return;
+        };
+
+        connections.state.replace(Some(Elem::clone(new_state)));
+
+        // The sent messages are removed by the next batch of actions, so the
+        // next call sees the outboxes without them
+        for account in new_state.accounts.iter() {
+            connections.send_outbox(account);
+        }
+    }
+}
+
+/// Start connecting to the specified IRC servers in background threads.
+/// The events from the servers are converted to `AppAction`s, which are
+/// dispatched by `connections` on the main thread. The established
+/// connections are added to `connections`.
+///
+/// Each server is assigned a new account (`AccountId::new_unique`).
+///
+/// Received messages are recorded to `history` if it's specified. The
+/// recent messages are loaded from `history` when joining a channel.
+pub fn spawn_connections(
+    wm: pal::Wm,
+    connections: &Rc<IrcConnections>,
+    servers: &[IrcServer],
+    history: Option<Arc<Mutex<History>>>,
+) {
+    let connections = Arc::new(MtSticky::with_wm(wm, Rc::clone(connections)));
+
+    for server in servers.iter() {
+        let account = model::AccountId::new_unique();
+
+        (connections.get_with_wm(wm).dispatch)(model::AppAction::AddAccount(
+            account,
+            model::Server {
+                name: server.host.clone(),
+            },
+        ));
+
+        let config = stella2_irc::Config {
+            autojoin: server.channels.clone(),
+            ..stella2_irc::Config::with_nick(&server.nick)
+        };
+
+        // Our current nickname, which is used to tell which conversation a
+        // private message belongs to
+        let mut own_nick = server.nick.clone();
+
+        let dispatch = Arc::clone(&connections);
+        let history = history.clone();
+        let server_name = server.host.clone();
+        let handler = move |event: Event| {
+            match &event {
+                Event::Registered { nick } => own_nick = nick.clone(),
+                Event::NickChanged {
+                    new_nick,
+                    is_self: true,
+                    ..
+                } => own_nick = new_nick.clone(),
+                _ => {}
+            }
+
+            let time = chrono::Utc::now();
+
+            // The location of the received message in the history
+            let mut history_pos = None;
+
+            // Messages loaded from the history when joining a channel
+            let mut backlog = Vec::new();
+
+            if let Some(history) = &history {
+                let mut history = history.lock().unwrap();
+                match &event {
+                    Event::Message {
+                        target,
+                        sender,
+                        kind,
+                        body,
+                    } => {
+                        let conversation = conversation_name(target, sender, &own_nick);
+                        let msg = model::Message {
+                            time,
+                            sender: sender.clone(),
+                            kind: message_kind(*kind),
+                            body: body.clone(),
+                            history_pos: None,
+                        };
+                        match history.append(&server_name, conversation, &msg) {
+                            Ok(pos) => history_pos = Some(pos),
+                            Err(e) => {
+                                log::warn!("Failed to record a message to the history: {:?}", e);
+                            }
+                        }
+                    }
+                    Event::Join {
+                        channel,
+                        is_self: true,
+                        ..
+                    } => match history.read_before(&server_name, channel, None, BACKLOG_LEN) {
+                        Ok(messages) => backlog = messages,
+                        Err(e) => {
+                            log::warn!("Failed to read the history of {:?}: {:?}", channel, e);
+                        }
+                    },
+                    _ => {}
+                }
+            }
+
+            // `AppAction` is `!Send`, so convert `event` on the main thread
+            let own_nick = own_nick.clone();
+            let dispatch = Arc::clone(&dispatch);
+            pal::Wm::invoke_on_main_thread(move |wm| {
+                let backlog_channel = match &event {
+                    Event::Join { channel, .. } => Some(channel.clone()),
+                    _ => None,
+                };
+
+                let connections = dispatch.get_with_wm(wm);
+                let dispatch = &connections.dispatch;
+
+                if event == Event::Disconnected {
+                    connections.remove_connection(account);
+                }
+
+                for action in actions_for_event(account, &own_nick, time, history_pos, event) {
+                    dispatch(action);
+                }
+
+                if let Some(channel) = backlog_channel {
+                    let channel_ref = model::ChannelRef { account, channel };
+                    for msg in backlog {
+                        dispatch(model::AppAction::ReceiveMessage(
+                            channel_ref.clone(),
+                            Elem::new(msg),
+                        ));
+                    }
+                }
+            });
+        };
+
+        let server = server.clone();
+        let connections = Arc::clone(&connections);
+        thread::Builder::new()
+            .name("irc connect".to_owned())
+            .spawn(move || {
+                log::info!("Connecting to {}:{}", server.host, server.port);
+
+                match Connection::connect((server.host.as_str(), server.port), config, handler) {
+                    Ok(connection) => {
+                        pal::Wm::invoke_on_main_thread(move |wm| {
+                            connections
+                                .get_with_wm(wm)
+                                .add_connection(account, connection);
+                        });
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to connect to {}:{}: {:?}",
+                            server.host,
+                            server.port,
+                            e
+                        );
+                    }
+                }
+            })
+            .unwrap();
+    }
+}
+
+/// Convert an IRC client event to `AppAction`s.
+fn actions_for_event(
+    account: model::AccountId,
+    own_nick: &str,
+    time: chrono::DateTime<chrono::Utc>,
+    history_pos: Option<RecordPos>,
+    event: Event,
+) -> Vec<model::AppAction> {
+    let channel_ref = |channel: String| model::ChannelRef { account, channel };
+
+    match event {
+        Event::Registered { nick } => {
+            log::info!("{:?}: Registered as {:?}", account, nick);
+            vec![]
+        }
+        Event::Join {
+            channel,
+            nick,
+            is_self,
+        } => {
+            if is_self {
+                vec![model::AppAction::JoinChannel(channel_ref(channel))]
+            } else {
+                vec![model::AppAction::AddMember(channel_ref(channel), nick)]
+            }
+        }
+        Event::Part {
+            channel,
+            nick,
+            is_self,
+            ..
+        } => {
+            if is_self {
+                vec![model::AppAction::PartChannel(channel_ref(channel))]
+            } else {
+                vec![model::AppAction::RemoveMember(channel_ref(channel), nick)]
+            }
+        }
+        Event::Quit { nick, .. } => vec![model::AppAction::RemoveMemberFromAll(account, nick)],
+        Event::NickChanged {
+            old_nick, new_nick, ..
+        } => vec![model::AppAction::RenameMember {
+            account,
+            old_nick,
+            new_nick,
+        }],
+        Event::Message {
+            target,
+            sender,
+            kind,
+            body,
+        } => {
+            let conversation = conversation_name(&target, &sender, own_nick).to_owned();
+            let conversation = channel_ref(conversation);
+            let mut actions = Vec::new();
+
+            // Private messages are displayed as a channel named after the
+            // other party. Create one if it doesn't exist yet.
+            if !is_channel_name(&conversation.channel) {
+                actions.push(model::AppAction::JoinChannel(conversation.clone()));
+            }
+
+            actions.push(model::AppAction::ReceiveMessage(
+                conversation,
+                Elem::new(model::Message {
+                    time,
+                    sender,
+                    kind: message_kind(kind),
+                    body,
+                    history_pos,
+                }),
+            ));
+
+            actions
+        }
+        Event::Topic { channel, topic } => {
+            vec![model::AppAction::SetTopic(channel_ref(channel), topic)]
+        }
+        Event::Names { channel, nicks } => {
+            vec![model::AppAction::SetMembers(channel_ref(channel), nicks)]
+        }
+        Event::ServerError { message } => {
+            log::warn!("{:?}: The server reported an error: {}", account, message);
+            vec![]
+        }
+        Event::Disconnected => {
+            // `IrcConnections::remove_connection` updates the model
+            log::info!("{:?}: Disconnected", account);
+            vec![]
+        }
+    }
+}
+
+fn message_kind(kind: stella2_irc::MessageKind) -> model::MessageKind {
+    match kind {
+        stella2_irc::MessageKind::Privmsg => model::MessageKind::Normal,
+        stella2_irc::MessageKind::Action => model::MessageKind::Action,
+        stella2_irc::MessageKind::Notice => model::MessageKind::Notice,
+    }
+}
@@ -0,0 +1,406 @@
+//! The application log.
+//!
+//! Log records are retained in a bounded in-memory buffer (used by crash
+//! reports and the log viewer) and written to size-rotated files in the
+//! profile directory. The files are written by a background thread, so
+//! logging never blocks on disk I/O.
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    ptr::null_mut,
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::config::profile::Profile;
+
+/// The number of log records retained in memory.
+pub const RECENT_RECORD_COUNT: usize = 1000;
+
+/// A log file is rotated when it would grow beyond this size.
+const MAX_FILE_LEN: u64 = 1 << 20;
+
+/// The number of rotated log files to keep in addition to the current one.
+const NUM_ROTATED_FILES: usize = 4;
+
+/// The default level of the records written to the log files.
+pub const DEFAULT_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+
+/// A log record retained by the application logger.
+#[derive(Debug)]
+pub struct LogRecord {
+    /// A number identifying the record, assigned in an ascending order.
+    pub seq: u64,
+    pub time: chrono::DateTime<chrono::Local>,
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Formats the record as a line of a log file.
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}: {}",
+            self.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+struct AppLogger {
+    /// Additionally outputs the records to a console or a debugger.
+    console: Option<Box<dyn log::Log>>,
+    console_level: log::LevelFilter,
+    /// `log::LevelFilter as usize`
+    level: AtomicUsize,
+    state: Mutex<LoggerState>,
+}
+
+struct LoggerState {
+    next_seq: u64,
+    recent: VecDeque<Arc<LogRecord>>,
+    /// Sends the records to the thread writing the log files. `None` until
+    /// `set_profile` is called.
+    writer: Option<mpsc::Sender<Arc<LogRecord>>>,
+    /// The thread writing the log files. Joined by [`shutdown`].
+    writer_thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Set by `init`.
+static LOGGER: AtomicPtr<AppLogger> = AtomicPtr::new(null_mut());
+
+fn logger() -> Option<&'static AppLogger> {
+    let logger = LOGGER.load(Ordering::Acquire);
+    if logger.is_null() {
+        None
+    } else {
+        Some(unsafe { &*logger })
+    }
+}
+
+/// Install the application logger as the global logger.
+///
+/// `console` additionally receives the records enabled by itself, regardless
+/// of `level`. The log files aren't written until [`set_profile`] is called.
+pub fn init(level: log::LevelFilter, console: Option<(Box<dyn log::Log>, log::LevelFilter)>) {
+    let (console, console_level) = match console {
+        Some((console, console_level)) => (Some(console), console_level),
+        None => (None, log::LevelFilter::Off),
+    };
+
+    let logger: &'static AppLogger = Box::leak(Box::new(AppLogger {
+        console,
+        console_level,
+        level: AtomicUsize::new(level as usize),
+        state: Mutex::new(LoggerState {
+            next_seq: 0,
+            recent: VecDeque::with_capacity(RECENT_RECORD_COUNT),
+            writer: None,
+            writer_thread: None,
+        }),
+    }));
+    log::set_logger(logger).unwrap();
+    log::set_max_level(level.max(console_level));
+
+    LOGGER.store(logger as *const _ as *mut _, Ordering::Release);
+}
+
+/// Get the level of the records retained by the application logger.
+pub fn level() -> log::LevelFilter {
+    logger()
+        .map(|logger| level_from_usize(logger.level.load(Ordering::Relaxed)))
+        .unwrap_or(log::LevelFilter::Off)
+}
+
+/// Change the level of the records retained by the application logger.
+pub fn set_level(level: log::LevelFilter) {
+    let logger = if let Some(logger) = logger() {
+        logger
+    } else {
+        return;
+    };
+
+    logger.level.store(level as usize, Ordering::Relaxed);
+
+    // Don't drop the records enabled by the console logger
+    log::set_max_level(level.max(logger.console_level));
+}
+
+fn level_from_usize(x: usize) -> log::LevelFilter {
+    use log::LevelFilter::*;
+    [Off, Error, Warn, Info, Debug, Trace]
+        .get(x)
+        .cloned()
+        .unwrap_or(DEFAULT_LEVEL)
+}
+
+/// Start writing the log files in the profile directory. The records logged
+/// before calling this function are written too, as long as they are still
+/// in the in-memory buffer.
+pub fn set_profile(profile: &Profile) {
+    let logger = if let Some(logger) = logger() {
+        logger
+    } else {
+        return;
+    };
+
+    let dir = profile.data_dir().join("logs");
+    let (send, recv) = mpsc::channel();
+
+    let mut state = logger.state.lock().unwrap_or_else(|e| e.into_inner());
+    if state.writer.is_some() {
+        return;
+    }
+
+    for record in state.recent.iter() {
+        let _ = send.send(Arc::clone(record));
+    }
+
+    let spawn_result = thread::Builder::new()
+        .name("log writer".to_owned())
+        .spawn(move || writer_main(dir, recv));
+    let writer_thread = match spawn_result {
+        Ok(x) => x,
+        Err(e) => {
+            drop(state);
+            log::error!("Could not start the log writer thread: {}", e);
+            return;
+        }
+    };
+
+    state.writer = Some(send);
+    state.writer_thread = Some(writer_thread);
+}
+
+/// Stop writing the log files and wait until all records logged so far are
+/// written. Call this before the process exits, or the last records might be
+/// lost.
+///
+/// The records logged after calling this function are only retained in the
+/// in-memory buffer.
+pub fn shutdown() {
+    let logger = if let Some(logger) = logger() {
+        logger
+    } else {
+        return;
+    };
+
+    let (writer, writer_thread) = {
+        let mut state = logger.state.lock().unwrap_or_else(|e| e.into_inner());
+        (state.writer.take(), state.writer_thread.take())
+    };
+
+    // Disconnecting the channel makes the writer thread exit after writing
+    // the queued records
+    drop(writer);
+
+    if let Some(writer_thread) = writer_thread {
+        // The writer thread can't wait for itself (e.g., if it panicked)
+        if writer_thread.thread().id() != thread::current().id() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Get the records in the in-memory buffer, whose sequence numbers are not
+/// less than `first_seq`.
+pub fn recent_records(first_seq: u64) -> Vec<Arc<LogRecord>> {
+    if let Some(logger) = logger() {
+        let state = logger.state.lock().unwrap_or_else(|e| e.into_inner());
+        records_since(&state.recent, first_seq)
+    } else {
+        Vec::new()
+    }
+}
+
+/// Like [`recent_records`], but returns `None` instead of blocking if the
+/// buffer is locked, e.g., by a panicking thread.
+pub fn try_recent_records(first_seq: u64) -> Option<Vec<Arc<LogRecord>>> {
+    let state = logger()?.state.try_lock().ok()?;
+    Some(records_since(&state.recent, first_seq))
+}
+
+fn records_since(recent: &VecDeque<Arc<LogRecord>>, first_seq: u64) -> Vec<Arc<LogRecord>> {
+    // The sequence numbers are consecutive
+    let start = recent
+        .front()
+        .map(|record| first_seq.saturating_sub(record.seq) as usize)
+        .unwrap_or(0)
+        .min(recent.len());
+    recent.iter().skip(start).cloned().collect()
+}
+
+impl log::Log for AppLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= level_from_usize(self.level.load(Ordering::Relaxed))
+            || self
+                .console
+                .as_ref()
+                .map(|console| console.enabled(metadata))
+                .unwrap_or(false)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if let Some(console) = &self.console {
+            if console.enabled(record.metadata()) {
+                console.log(record);
+            }
+        }
+
+        if record.level() > level_from_usize(self.level.load(Ordering::Relaxed)) {
+            return;
+        }
+
+        // Format the message before taking the lock. Formatting can take an
+        // arbitrary amount of time and even log records by itself.
+        let time = chrono::Local::now();
+        let level = record.level();
+        let target = record.target().to_owned();
+        let message = record.args().to_string();
+
+        // Keep logging even if a thread panicked while holding the lock
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let record = Arc::new(LogRecord {
+            seq: state.next_seq,
+            time,
+            level,
+            target,
+            message,
+        });
+        state.next_seq += 1;
+
+        if state.recent.len() >= RECENT_RECORD_COUNT {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(Arc::clone(&record));
+
+        if let Some(writer) = &state.writer {
+            // This fails only if the writer thread is gone, in which case
+            // there's nothing we can do
+            let _ = writer.send(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(console) = &self.console {
+            console.flush();
+        }
+
+        // The writer thread flushes the log file as soon as it runs out of
+        // records to write
+    }
+}
+
+fn rotated_log_path(dir: &Path, i: usize) -> PathBuf {
+    if i == 0 {
+        dir.join("stella2.log")
+    } else {
+        dir.join(format!("stella2.{}.log", i))
+    }
+}
+
+/// The entry point of the thread writing the log files.
+fn writer_main(dir: PathBuf, recv: mpsc::Receiver<Arc<LogRecord>>) {
+    let mut file = LogFile {
+        dir,
+        file: None,
+        len: 0,
+    };
+
+    // Errors are reported to stderr because logging them would produce more
+    // records to write
+    while let Ok(mut record) = recv.recv() {
+        loop {
+            if let Err(e) = file.write(&record) {
+                eprintln!("Could not write to the log file: {}", e);
+            }
+
+            // Write the queued records before flushing
+            match recv.try_recv() {
+                Ok(next_record) => record = next_record,
+                Err(_) => break,
+            }
+        }
+
+        if let Err(e) = file.flush() {
+            eprintln!("Could not write to the log file: {}", e);
+        }
+    }
+}
+
+struct LogFile {
+    dir: PathBuf,
+    file: Option<io::BufWriter<fs::File>>,
+    /// The current length of the log file.
+    len: u64,
+}
+
+impl LogFile {
+    fn write(&mut self, record: &LogRecord) -> io::Result<()> {
+        let line = format!("{}\n", record);
+
+        if self.file.is_none() {
+            self.open()?;
+        }
+
+        if self.len > 0 && self.len + line.len() as u64 > MAX_FILE_LEN {
+            self.rotate()?;
+        }
+
+        self.file.as_mut().unwrap().write_all(line.as_bytes())?;
+        self.len += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(rotated_log_path(&self.dir, 0))?;
+        self.len = file.metadata()?.len();
+        self.file = Some(io::BufWriter::new(file));
+
+        Ok(())
+    }
+
+    /// Rename `stella2.log` to `stella2.1.log`, `stella2.1.log` to
+    /// `stella2.2.log`, and so on, and open a new `stella2.log`.
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        for i in (0..NUM_ROTATED_FILES).rev() {
+            match fs::rename(
+                rotated_log_path(&self.dir, i),
+                rotated_log_path(&self.dir, i + 1),
+            ) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.open()
+    }
+}
@@ -7,13 +7,23 @@
 #![allow(clippy::let_unit_value)]
 #![allow(clippy::float_cmp)]
 #![feature(const_if_match)] // `match` in `const fn`
+#![feature(backtrace)] // `Backtrace` in crash reports
 
 use log::debug;
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 use tcw3::pal::{self, prelude::*};
 
 mod config;
 mod crashhandler;
+mod i18n;
+mod ipc;
+mod irc;
+mod logging;
 mod model;
+mod opener;
 mod stylesheet;
 mod view;
 
@@ -22,33 +32,46 @@ mod windres {
     stella2_windres::attach_windres!();
 }
 
+/// The number of times to retry locking the profile if the lock appears to be
+/// stale.
+const LOCK_RETRY_COUNT: u32 = 10;
+
+/// The interval between retries of locking the profile.
+const LOCK_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
 fn main() {
     crashhandler::init();
 
-    // Enable logging only in debug builds
+    // Parse command-line arguments. Exit on parsing error or after displaying
+    // a help message. This is done first because they specify the log level.
+    let args = config::cmdline::Args::from_env_or_exit();
+
+    // Output log records to the console (or the debugger on Windows) only in
+    // debug builds
     #[cfg(debug_assertions)]
-    {
+    let console: Option<(Box<dyn log::Log>, log::LevelFilter)> = {
         #[cfg(target_os = "windows")]
         {
             use std::str::FromStr;
 
             let cfg = std::env::var("RUST_LOG").ok();
             let cfg = cfg.as_deref().unwrap_or("info");
-            if let Ok(level) = log::Level::from_str(&cfg) {
-                windebug_logger::init_with_level(level).unwrap();
-            } else {
-                windebug_logger::init_with_level(log::Level::Info).unwrap();
-                log::warn!(
-                    "Invalid log level was specified by `RUST_LOG` ({:>}). \
-                     Defaulting to `info`",
-                    cfg
-                );
-            }
+            let level = log::LevelFilter::from_str(&cfg).unwrap_or(log::LevelFilter::Info);
+            let logger = windebug_logger::WinDebugLogger;
+            Some((Box::new(logger) as Box<dyn log::Log>, level))
         }
 
         #[cfg(not(target_os = "windows"))]
-        env_logger::init();
-    }
+        {
+            let logger = env_logger::Builder::from_default_env().build();
+            let level = logger.filter();
+            Some((Box::new(logger) as Box<dyn log::Log>, level))
+        }
+    };
+    #[cfg(not(debug_assertions))]
+    let console = None;
+
+    logging::init(args.log_level.unwrap_or(logging::DEFAULT_LEVEL), console);
 
     log::info!("Logging started");
 
@@ -63,10 +86,6 @@ fn main() {
         pal::windows::set_app_hicon(winuser::LoadIconW(hinstance, 0x101 as _));
     }
 
-    // Parse command-line arguments. Exit on parsing error or after displaying
-    // a help message.
-    let args = config::cmdline::Args::from_env_or_exit();
-
     // Load the default profile
     let profile = if let Some(profile_path) = &args.profile {
         config::profile::Profile::from_custom_dir(profile_path)
@@ -76,17 +95,52 @@ fn main() {
     let profile = Box::leak(Box::new(profile));
     log::info!("Profile: {:?}", profile);
     profile.prepare().unwrap();
+    crashhandler::set_profile(profile);
+
+    // Prevent multiple instances of the application from running. If there's
+    // already one, pass the command-line arguments to it.
+    let mut lock_result = config::lock::try_lock(profile).unwrap();
+    for _ in 0..LOCK_RETRY_COUNT {
+        if let config::lock::TryLockResult::Stale { .. } = lock_result {
+            // The lock might be released soon
+            std::thread::sleep(LOCK_RETRY_INTERVAL);
+            lock_result = config::lock::try_lock(profile).unwrap();
+        } else {
+            break;
+        }
+    }
 
-    // Prevent multiple instances of the application from running
-    let lock_guard = config::lock::try_lock(profile).unwrap();
-    if lock_guard.is_none() {
-        log::warn!(
-            "Exiting because it appears that another application instance \
-            using the same profile is already running"
-        );
+    let lock_owner = match lock_result {
+        config::lock::TryLockResult::Acquired(lock_guard) => {
+            std::mem::forget(lock_guard); // let the system do unlocking
+            None
+        }
+        config::lock::TryLockResult::Locked { owner } => Some(owner),
+        config::lock::TryLockResult::Stale { owner } => Some(Some(owner)),
+    };
+
+    if let Some(owner) = lock_owner {
+        let request = ipc::Request::from_args(&args);
+        if let Err(e) = ipc::send_request(profile, &request) {
+            log::warn!(
+                "Exiting because it appears that another application instance \
+                (PID {:?}) using the same profile is already running, but \
+                the command-line arguments could not be passed to it: {}",
+                owner,
+                e
+            );
+        } else {
+            log::info!(
+                "Passed the command-line arguments to the running instance \
+                (PID {:?}). Exiting",
+                owner
+            );
+        }
         return;
     }
-    std::mem::forget(lock_guard); // let the system do unlocking
+
+    // Now that we own the profile, start writing the log files
+    logging::set_profile(profile);
 
     debug!("Initializing WM");
     let wm = pal::Wm::global();
@@ -95,7 +149,82 @@ fn main() {
     let style_manager = tcw3::ui::theming::Manager::global(wm);
     stylesheet::register_stylesheet(style_manager);
 
-    let _view = self::view::AppView::new(wm, profile);
+    // Tell the user if the application crashed last time
+    crashhandler::check_reports(wm, profile);
+
+    // Open the message history. The application is still usable without it,
+    // so just log the error if it fails.
+    let history = match config::history::History::open(profile) {
+        Ok(history) => Some(Arc::new(Mutex::new(history))),
+        Err(e) => {
+            log::error!("Could not open the message history: {:?}", e);
+            None
+        }
+    };
+
+    let app_view = self::view::AppView::new(
+        wm,
+        profile,
+        history.clone(),
+        args.record_actions.clone(),
+        args.log_level,
+    );
+
+    if args.irc_servers.is_empty() {
+        // Populate the app state with mock-up data
+        for action in model::demo::initial_actions() {
+            self::view::AppView::dispatch(&app_view, action);
+        }
+    }
+
+    // The connections to IRC servers, which send the messages composed by the
+    // user
+    let app_view_weak = Rc::downgrade(&app_view);
+    let irc_connections = irc::IrcConnections::new(move |action| {
+        if let Some(app_view) = app_view_weak.upgrade() {
+            self::view::AppView::dispatch(&app_view, action);
+        }
+    });
+    self::view::AppView::add_middleware(
+        &app_view,
+        irc::IrcConnections::middleware(&irc_connections),
+    );
+    irc::spawn_connections(wm, &irc_connections, &args.irc_servers, history.clone());
+
+    // Accept requests from application instances launched later
+    let app_view_weak = Rc::downgrade(&app_view);
+    let irc_connections2 = Rc::clone(&irc_connections);
+    let result = ipc::listen(wm, profile, move |wm, request| {
+        let app_view = if let Some(app_view) = app_view_weak.upgrade() {
+            app_view
+        } else {
+            return;
+        };
+
+        let servers: Vec<_> = request
+            .irc_servers
+            .iter()
+            .filter_map(|url| match config::cmdline::IrcServer::parse(url) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    log::warn!("Ignoring an invalid IRC server URL {:?}: {}", url, e);
+                    None
+                }
+            })
+            .collect();
+
+        irc::spawn_connections(wm, &irc_connections2, &servers, history.clone());
+
+        if request.activate {
+            self::view::AppView::dispatch(&app_view, model::AppAction::ActivateMainWnd);
+        }
+    });
+    if let Err(e) = result {
+        log::error!(
+            "Could not start accepting requests from other instances: {}",
+            e
+        );
+    }
 
     debug!("Entering the main loop");
     wm.enter_main_loop();
@@ -1,42 +1,416 @@
-use harmony::{set_field, Elem};
-use miniserde::{Deserialize, Serialize};
+use harmony::{lens::Find, set_field, Elem, Lens};
+use std::{
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use stella2_irc::message::{hash_name, name_eq};
 
-#[derive(Debug, Clone)]
+pub mod demo;
+pub mod record;
+
+#[derive(Debug, Clone, Lens)]
 pub struct AppState {
-    pub main_wnd: Elem<WndState>,
+    /// The main windows, sorted by the order in which they were opened. There
+    /// is at least one window.
+    pub wnds: Elem<Vec<Elem<WndState>>>,
     /// Indicates whether the Preferences window is visible.
     pub pref_visible: bool,
+    /// Indicates whether the log viewer window is visible.
+    pub log_visible: bool,
+    pub settings: Elem<Settings>,
+    /// The accounts, sorted by the order in which they were added.
+    pub accounts: Elem<Vec<Elem<Account>>>,
+    pub search: Elem<SearchState>,
+}
+
+/// The user preferences, which are edited in the Preferences window.
+#[derive(Debug, Clone, Lens)]
+pub struct Settings {
+    /// Indicates whether to check for updates automatically.
+    pub check_updates: bool,
+    pub time_format: TimeFormat,
+    pub color_theme: ColorTheme,
+    /// The font size of messages, measured in points. Must be in
+    /// `Settings::FONT_SIZES`.
+    pub font_size: u32,
+    pub proxy: Proxy,
+    /// The UI language, given as a language tag in `i18n::LANGUAGES`. `None`
+    /// means the language is chosen based on the system settings.
+    pub language: Option<String>,
+    /// The level of the log records written to the log files.
+    pub log_level: log::LevelFilter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeFormat {
+    Hour12,
+    Hour24,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorTheme {
+    Light,
+}
+
+/// Specifies how to connect to servers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Proxy {
+    /// Connect directly without a proxy.
+    Direct,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Implement conversion between a fieldless enum and the strings identifying
+/// its variants in the settings file.
+macro_rules! impl_enum_str {
+    ($ty:ident { $($variant:ident => $s:literal),* $(,)? }) => {
+        impl $ty {
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $( $ty::$variant => $s, )*
+                }
+            }
+        }
+
+        impl std::str::FromStr for $ty {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $( $s => Ok($ty::$variant), )*
+                    _ => Err(()),
+                }
+            }
+        }
+    };
+}
+
+impl_enum_str!(TimeFormat { Hour12 => "12h", Hour24 => "24h" });
+impl_enum_str!(ColorTheme { Light => "light" });
+impl_enum_str!(Proxy { Direct => "direct" });
+
+#[derive(Debug, Clone, Lens)]
 pub struct WndState {
+    pub id: WndId,
     // UI state - It could be a local state of widget controllers, but we store
     // it here instead so that it can be intercepted by a persistence middleware
     pub sidebar_width: f32,
+    /// The maximum height of the composing area. The composing area grows
+    /// with the text up to this height.
     pub editor_height: f32,
     pub sidebar_visible: bool,
+    /// The channel displayed in the window.
+    pub selected_channel: Option<ChannelRef>,
+    /// The channel to select when it's joined, restored from the previous
+    /// session. Cleared when a channel is selected by the user.
+    pub channel_to_restore: Option<ChannelPath>,
+    /// The message to bring into view in the log view. This is set when a
+    /// search result is chosen.
+    pub focused_message: Option<seglog::RecordPos>,
+    /// Incremented whenever the window is requested to be brought to the
+    /// front.
+    pub activation: u64,
+}
+
+/// Uniquely identifies a [`WndState`] in [`AppState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WndId(pub u64);
+
+impl WndId {
+    /// Allocate a `WndId` that is unique within the process.
+    pub fn new_unique() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Uniquely identifies an [`Account`] in [`AppState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AccountId(pub u64);
+
+impl AccountId {
+    /// Allocate an `AccountId` that is unique within the process.
+    pub fn new_unique() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Identifies a [`Channel`] in [`AppState`].
+///
+/// Channel names are compared case-insensitively using the `rfc1459` case
+/// mapping.
+#[derive(Debug, Clone)]
+pub struct ChannelRef {
+    pub account: AccountId,
+    /// The name of the channel, e.g., `#general`.
+    pub channel: String,
+}
+
+impl PartialEq for ChannelRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.account == other.account && name_eq(&self.channel, &other.channel)
+    }
+}
+
+impl Eq for ChannelRef {}
+
+impl Hash for ChannelRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.account.hash(state);
+        hash_name(&self.channel, state);
+    }
+}
+
+/// Identifies a channel by the server name and the channel name. Unlike
+/// [`ChannelRef`], this remains valid across sessions.
+#[derive(Debug, Clone)]
+pub struct ChannelPath {
+    /// The name of the server (`Server::name`).
+    pub server: String,
+    /// The name of the channel, e.g., `#general`.
+    pub channel: String,
+}
+
+impl ChannelPath {
+    /// Construct a `ChannelPath` referring to the same channel as
+    /// `channel_ref`. Returns `None` if the account doesn't exist.
+    pub fn new(app_state: &AppState, channel_ref: &ChannelRef) -> Option<Self> {
+        Some(Self {
+            server: app_state.account(channel_ref.account)?.server.name.clone(),
+            channel: channel_ref.channel.clone(),
+        })
+    }
+
+    /// Check if `self` refers to the channel named `channel` in the server
+    /// named `server`.
+    pub fn matches(&self, server: &str, channel: &str) -> bool {
+        self.server == server && name_eq(&self.channel, channel)
+    }
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct Account {
+    pub id: AccountId,
+    /// The server this account is connected to.
+    pub server: Elem<Server>,
+    /// Indicates whether the connection to the server is established. The
+    /// messages in the outboxes are kept until it is.
+    pub connected: bool,
+    /// The joined channels, sorted by the order in which they were joined.
+    pub channels: Elem<Vec<Elem<Channel>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Server {
+    /// The name displayed in the channel list, e.g., `FreeNode`.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct Channel {
+    pub name: String,
+    pub topic: Option<String>,
+    /// The nicknames of the members, sorted by the order in which they were
+    /// reported by the server.
+    pub members: Elem<Vec<String>>,
+    /// The messages, sorted by the order in which they arrived. This may be
+    /// only a suffix of the channel's message history; see `history`.
+    pub messages: Elem<Vec<Elem<Message>>>,
+    /// Indicates whether `messages` includes the oldest message in the
+    /// message history.
+    pub history: HistoryState,
+    /// The messages composed by the user and waiting to be sent to the
+    /// server, sorted by the order in which they were composed.
+    pub outbox: Elem<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryState {
+    /// There may be older messages in the message history.
+    Partial,
+    /// Older messages are being loaded from the message history.
+    Loading,
+    /// `Channel::messages` starts with the oldest message in the message
+    /// history.
+    Complete,
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// The time when the message was sent.
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// The nickname of the sender.
+    pub sender: String,
+    pub kind: MessageKind,
+    pub body: String,
+    /// The location of the message in the message history
+    /// (`config::history`). `None` if the message isn't recorded.
+    pub history_pos: Option<seglog::RecordPos>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Normal,
+    /// An action performed by the sender (`/me`).
+    Action,
+    /// A notice, which must not trigger automatic replies.
+    Notice,
+}
+
+#[derive(Debug, Clone, Lens)]
+pub struct SearchState {
+    /// The text entered in the search bar.
+    pub query: String,
+    /// The messages matching `query`, sorted from the newest to the oldest.
+    /// `None` if the search is in progress.
+    pub hits: Option<Elem<Vec<SearchHit>>>,
+}
+
+/// A message found by a search.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub channel: ChannelRef,
+    pub message: Elem<Message>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            main_wnd: Elem::new(WndState {
-                sidebar_width: 200.0,
-                editor_height: 50.0,
-                sidebar_visible: true,
-            }),
+            wnds: Elem::new(vec![Elem::new(WndState::new(WndId::new_unique()))]),
             pref_visible: false,
+            log_visible: false,
+            settings: Elem::new(Settings::new()),
+            accounts: Elem::new(Vec::new()),
+            search: Elem::new(SearchState {
+                query: String::new(),
+                hits: Some(Elem::new(Vec::new())),
+            }),
         }
     }
+
+    /// Find the window with the specified ID.
+    pub fn wnd(&self, id: WndId) -> Option<&Elem<WndState>> {
+        self.wnds.iter().find(|wnd| wnd.id == id)
+    }
+
+    /// Find the account with the specified ID.
+    pub fn account(&self, id: AccountId) -> Option<&Elem<Account>> {
+        self.accounts.iter().find(|account| account.id == id)
+    }
+
+    /// Find the channel referenced by `channel_ref`.
+    pub fn channel(&self, channel_ref: &ChannelRef) -> Option<&Elem<Channel>> {
+        self.account(channel_ref.account)?
+            .channel(&channel_ref.channel)
+    }
+}
+
+impl Account {
+    /// Find the joined channel with the specified name.
+    pub fn channel(&self, name: &str) -> Option<&Elem<Channel>> {
+        self.channels
+            .iter()
+            .find(|channel| name_eq(&channel.name, name))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum AppAction {
-    Wnd(WndAction),
+    /// Updates the window with the specified ID.
+    Wnd(WndId, WndAction),
+    /// Opens a new window with the specified ID, which must be unique. The
+    /// new window inherits the layout and the selected channel of the most
+    /// recently opened window.
+    NewWnd(WndId),
+    /// Closes a window. Does nothing if it's the only window.
+    CloseWnd(WndId),
+    /// Brings the first window to the front.
+    ActivateMainWnd,
     /// Hides the Preferences window.
     HidePref,
     /// Toggles the visibility of the Preferences window.
     TogglePref,
+    /// Opens the log viewer window.
+    ShowLog,
+    /// Closes the log viewer window.
+    HideLog,
+    /// Updates the user preferences.
+    Settings(SettingsAction),
+    /// Adds an account connected to the specified server. Does nothing if
+    /// there already is an account with the same ID. The account is initially
+    /// disconnected.
+    AddAccount(AccountId, Server),
+    /// Updates `Account::connected`.
+    SetConnected(AccountId, bool),
+    /// Joins a channel. Selects the channel in every window where no channel
+    /// is selected.
+    JoinChannel(ChannelRef),
+    /// Leaves a channel. Clears the selection of every window where the
+    /// channel is selected.
+    PartChannel(ChannelRef),
+    /// Appends a message to a joined channel.
+    ReceiveMessage(ChannelRef, Elem<Message>),
+    /// Requests older messages of a joined channel to be loaded from the
+    /// message history. Does nothing unless `Channel::history` is `Partial`.
+    LoadOlderMessages(ChannelRef),
+    /// Inserts messages loaded from the message history before the existing
+    /// messages of a joined channel. `complete` indicates whether the oldest
+    /// message in the message history was reached. Does nothing unless
+    /// `Channel::history` is `Loading`.
+    PrependMessages {
+        channel: ChannelRef,
+        messages: Vec<Elem<Message>>,
+        complete: bool,
+    },
+    /// Removes up to the specified number of the oldest messages from a
+    /// joined channel. Only messages recorded in the message history are
+    /// removed so that they can be loaded again by `LoadOlderMessages`. Does
+    /// nothing while older messages are being loaded.
+    UnloadOlderMessages(ChannelRef, usize),
+    /// Appends a message composed by the user to the outbox of a joined
+    /// channel.
+    SendMessage(ChannelRef, String),
+    /// Removes up to the specified number of the oldest messages from the
+    /// outbox of a joined channel. This is dispatched after the messages are
+    /// sent to the server.
+    RemoveFromOutbox(ChannelRef, usize),
+    /// Replaces the search query. Clears the search results if the query
+    /// changes, marking the search as in progress.
+    SetSearchQuery(String),
+    /// Replaces the search results. Does nothing if `query` is not the
+    /// current search query.
+    SetSearchHits { query: String, hits: Vec<SearchHit> },
+    /// Replaces the topic of a joined channel.
+    SetTopic(ChannelRef, Option<String>),
+    /// Replaces the member list of a joined channel.
+    SetMembers(ChannelRef, Vec<String>),
+    /// Adds a member to a joined channel. Does nothing if the member is
+    /// already in the channel.
+    AddMember(ChannelRef, String),
+    /// Removes a member from a joined channel.
+    RemoveMember(ChannelRef, String),
+    /// Removes a member from every channel of an account.
+    RemoveMemberFromAll(AccountId, String),
+    /// Renames a member in every channel of an account.
+    RenameMember {
+        account: AccountId,
+        old_nick: String,
+        new_nick: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum SettingsAction {
+    SetCheckUpdates(bool),
+    SetTimeFormat(TimeFormat),
+    SetColorTheme(ColorTheme),
+    /// Changes the font size. The value is clamped to `Settings::FONT_SIZES`.
+    SetFontSize(u32),
+    SetProxy(Proxy),
+    /// Changes the UI language. See `Settings::language`.
+    SetLanguage(Option<String>),
+    /// Changes the level of the log records written to the log files.
+    SetLogLevel(log::LevelFilter),
 }
 
 #[derive(Debug, Clone)]
@@ -44,15 +418,79 @@ pub enum WndAction {
     SetSidebarWidth(f32),
     SetEditorHeight(f32),
     ToggleSidebar,
+    /// Selects a joined channel.
+    SelectChannel(ChannelRef),
+    /// Selects the channel containing a search result and brings the message
+    /// into view.
+    ShowSearchHit(SearchHit),
 }
 
 impl AppState {
-    pub fn reduce(this: Elem<Self>, action: &AppAction) -> Elem<Self> {
+    pub fn reduce(mut this: Elem<Self>, action: &AppAction) -> Elem<Self> {
         match action {
-            AppAction::Wnd(wnd_action) => set_field! {
-                main_wnd: WndState::reduce(Elem::clone(&this.main_wnd), wnd_action),
-                ..this
-            },
+            AppAction::Wnd(id, wnd_action) => {
+                // Only joined channels can be selected
+                let channel_ref = match wnd_action {
+                    WndAction::SelectChannel(channel_ref) => Some(channel_ref),
+                    WndAction::ShowSearchHit(hit) => Some(&hit.channel),
+                    _ => None,
+                };
+                if let Some(channel_ref) = channel_ref {
+                    if this.channel(channel_ref).is_none() {
+                        return this;
+                    }
+                }
+
+                wnd_lens(*id).update(&mut this, |wnd| {
+                    WndState::reduce(Elem::clone(wnd), wnd_action)
+                });
+                this
+            }
+            AppAction::NewWnd(id) => {
+                let last_wnd = this.wnds.last().unwrap();
+                let wnd = Elem::new(WndState {
+                    id: *id,
+                    sidebar_width: last_wnd.sidebar_width,
+                    editor_height: last_wnd.editor_height,
+                    sidebar_visible: last_wnd.sidebar_visible,
+                    selected_channel: last_wnd.selected_channel.clone(),
+                    channel_to_restore: last_wnd.channel_to_restore.clone(),
+                    ..WndState::new(*id)
+                });
+
+                set_field! {
+                    wnds: vec_elem_push(&this.wnds, wnd),
+                    ..this
+                }
+            }
+            AppAction::CloseWnd(id) => {
+                if this.wnds.len() <= 1 {
+                    return this;
+                }
+
+                let wnds: Vec<_> = this
+                    .wnds
+                    .iter()
+                    .filter(|wnd| wnd.id != *id)
+                    .cloned()
+                    .collect();
+
+                if wnds.len() == this.wnds.len() {
+                    return this;
+                }
+
+                set_field! {
+                    wnds: Elem::new(wnds),
+                    ..this
+                }
+            }
+            AppAction::ActivateMainWnd => {
+                let id = this.wnds[0].id;
+                wnd_lens(id)
+                    .then(WndState::activation)
+                    .update(&mut this, |activation| activation.wrapping_add(1));
+                this
+            }
             AppAction::HidePref => set_field! {
                 pref_visible: false,
                 ..this
@@ -61,11 +499,449 @@ impl AppState {
                 pref_visible: !this.pref_visible,
                 ..this
             },
+            AppAction::ShowLog => set_field! {
+                log_visible: true,
+                ..this
+            },
+            AppAction::HideLog => set_field! {
+                log_visible: false,
+                ..this
+            },
+            AppAction::Settings(settings_action) => {
+                AppState::settings.update(&mut this, |settings| {
+                    Settings::reduce(Elem::clone(settings), settings_action)
+                });
+                this
+            }
+            AppAction::AddAccount(id, server) => {
+                if this.account(*id).is_some() {
+                    return this;
+                }
+
+                let account = Elem::new(Account {
+                    id: *id,
+                    server: Elem::new(server.clone()),
+                    connected: false,
+                    channels: Elem::new(Vec::new()),
+                });
+
+                set_field! {
+                    accounts: vec_elem_push(&this.accounts, account),
+                    ..this
+                }
+            }
+            AppAction::SetConnected(id, connected) => {
+                account_lens(*id)
+                    .then(Account::connected)
+                    .set(&mut this, *connected);
+                this
+            }
+            AppAction::JoinChannel(channel_ref) => {
+                let server_name = if let Some(account) = this.account(channel_ref.account) {
+                    account.server.name.clone()
+                } else {
+                    return this;
+                };
+
+                if this.channel(channel_ref).is_none() {
+                    let channel = Elem::new(Channel {
+                        name: channel_ref.channel.clone(),
+                        topic: None,
+                        members: Elem::new(Vec::new()),
+                        messages: Elem::new(Vec::new()),
+                        history: HistoryState::Partial,
+                        outbox: Elem::new(Vec::new()),
+                    });
+
+                    account_lens(channel_ref.account)
+                        .then(Account::channels)
+                        .update(&mut this, |channels| vec_elem_push(channels, channel));
+                }
+
+                set_field! {
+                    wnds: map_vec_elem(&this.wnds, |wnd| {
+                        // Select the channel if it was selected in the
+                        // previous session. Otherwise, select it only if
+                        // nothing is selected yet.
+                        let is_restored = wnd.channel_to_restore.as_ref().map_or(false, |path| {
+                            path.matches(&server_name, &channel_ref.channel)
+                        });
+
+                        if is_restored {
+                            set_field! {
+                                selected_channel: Some(channel_ref.clone()),
+                                channel_to_restore: None,
+                                ..wnd
+                            }
+                        } else if wnd.selected_channel.is_none() {
+                            set_field! {
+                                selected_channel: Some(channel_ref.clone()),
+                                ..wnd
+                            }
+                        } else {
+                            wnd
+                        }
+                    }),
+                    ..this
+                }
+            }
+            AppAction::PartChannel(channel_ref) => {
+                if this.channel(channel_ref).is_some() {
+                    account_lens(channel_ref.account)
+                        .then(Account::channels)
+                        .update(&mut this, |channels| {
+                            let channels: Vec<_> = channels
+                                .iter()
+                                .filter(|channel| !name_eq(&channel.name, &channel_ref.channel))
+                                .cloned()
+                                .collect();
+                            Elem::new(channels)
+                        });
+                }
+
+                set_field! {
+                    wnds: map_vec_elem(&this.wnds, |wnd| {
+                        if wnd.selected_channel.as_ref() != Some(channel_ref) {
+                            return wnd;
+                        }
+
+                        set_field! {
+                            selected_channel: None,
+                            focused_message: None,
+                            ..wnd
+                        }
+                    }),
+                    ..this
+                }
+            }
+            AppAction::ReceiveMessage(channel_ref, message) => {
+                channel_lens(channel_ref)
+                    .then(Channel::messages)
+                    .update(&mut this, |messages| {
+                        vec_elem_push(messages, Elem::clone(message))
+                    });
+                this
+            }
+            AppAction::LoadOlderMessages(channel_ref) => {
+                channel_lens(channel_ref)
+                    .then(Channel::history)
+                    .update(&mut this, |history| {
+                        if *history == HistoryState::Partial {
+                            HistoryState::Loading
+                        } else {
+                            *history
+                        }
+                    });
+                this
+            }
+            AppAction::PrependMessages {
+                channel: channel_ref,
+                messages: new_messages,
+                complete,
+            } => {
+                channel_lens(channel_ref).update(&mut this, |channel| {
+                    if channel.history != HistoryState::Loading {
+                        return Elem::clone(channel);
+                    }
+
+                    let all_messages = if new_messages.is_empty() {
+                        Elem::clone(&channel.messages)
+                    } else {
+                        let messages = new_messages.iter().chain(channel.messages.iter());
+                        Elem::new(messages.cloned().collect())
+                    };
+                    set_field! {
+                        messages: all_messages,
+                        history: if *complete {
+                            HistoryState::Complete
+                        } else {
+                            HistoryState::Partial
+                        },
+                        ..Elem::clone(channel)
+                    }
+                });
+                this
+            }
+            AppAction::UnloadOlderMessages(channel_ref, count) => {
+                channel_lens(channel_ref).update(&mut this, |channel| {
+                    if channel.history == HistoryState::Loading {
+                        return Elem::clone(channel);
+                    }
+
+                    let count = channel
+                        .messages
+                        .iter()
+                        .take(*count)
+                        .take_while(|message| message.history_pos.is_some())
+                        .count();
+                    if count == 0 {
+                        return Elem::clone(channel);
+                    }
+
+                    set_field! {
+                        messages: Elem::new(channel.messages[count..].to_vec()),
+                        history: HistoryState::Partial,
+                        ..Elem::clone(channel)
+                    }
+                });
+                this
+            }
+            AppAction::SendMessage(channel_ref, text) => {
+                channel_lens(channel_ref)
+                    .then(Channel::outbox)
+                    .update(&mut this, |outbox| {
+                        let mut outbox = Vec::clone(outbox);
+                        outbox.push(text.clone());
+                        Elem::new(outbox)
+                    });
+                this
+            }
+            AppAction::RemoveFromOutbox(channel_ref, count) => {
+                channel_lens(channel_ref)
+                    .then(Channel::outbox)
+                    .update(&mut this, |outbox| {
+                        let count = (*count).min(outbox.len());
+                        Elem::new(outbox[count..].to_vec())
+                    });
+                this
+            }
+            AppAction::SetSearchQuery(query) => {
+                if this.search.query == *query {
+                    return this;
+                }
+
+                // An empty query matches nothing, so there's nothing to wait
+                // for
+                let hits = if query.trim().is_empty() {
+                    Some(Elem::new(Vec::new()))
+                } else {
+                    None
+                };
+
+                set_field! {
+                    search: Elem::new(SearchState {
+                        query: query.clone(),
+                        hits,
+                    }),
+                    ..this
+                }
+            }
+            AppAction::SetSearchHits { query, hits } => {
+                if this.search.query != *query {
+                    return this;
+                }
+
+                AppState::search.update(&mut this, |search| {
+                    Elem::new(SearchState {
+                        hits: Some(Elem::new(hits.clone())),
+                        ..SearchState::clone(search)
+                    })
+                });
+                this
+            }
+            AppAction::SetTopic(channel_ref, topic) => {
+                channel_lens(channel_ref)
+                    .then(Channel::topic)
+                    .set(&mut this, topic.clone());
+                this
+            }
+            AppAction::SetMembers(channel_ref, members) => {
+                channel_lens(channel_ref)
+                    .then(Channel::members)
+                    .set(&mut this, Elem::new(members.clone()));
+                this
+            }
+            AppAction::AddMember(channel_ref, nick) => {
+                channel_lens(channel_ref)
+                    .then(Channel::members)
+                    .update(&mut this, |members| {
+                        if members.iter().any(|m| name_eq(m, nick)) {
+                            return Elem::clone(members);
+                        }
+
+                        let mut members = Vec::clone(members);
+                        members.push(nick.clone());
+                        Elem::new(members)
+                    });
+                this
+            }
+            AppAction::RemoveMember(channel_ref, nick) => {
+                channel_lens(channel_ref).update(&mut this, |channel| {
+                    remove_member(Elem::clone(channel), nick)
+                });
+                this
+            }
+            AppAction::RemoveMemberFromAll(account, nick) => {
+                account_lens(*account)
+                    .then(Account::channels)
+                    .update(&mut this, |channels| {
+                        map_vec_elem(channels, |channel| remove_member(channel, nick))
+                    });
+                this
+            }
+            AppAction::RenameMember {
+                account,
+                old_nick,
+                new_nick,
+            } => {
+                account_lens(*account)
+                    .then(Account::channels)
+                    .update(&mut this, |channels| {
+                        map_vec_elem(channels, |channel| {
+                            if !channel.members.iter().any(|m| name_eq(m, old_nick)) {
+                                return channel;
+                            }
+
+                            let members: Vec<String> = channel
+                                .members
+                                .iter()
+                                .map(|m| if name_eq(m, old_nick) { new_nick } else { m })
+                                .cloned()
+                                .collect();
+
+                            set_field! {
+                                members: Elem::new(members),
+                                ..channel
+                            }
+                        })
+                    });
+                this
+            }
+        }
+    }
+}
+
+fn remove_member(channel: Elem<Channel>, nick: &str) -> Elem<Channel> {
+    if !channel.members.iter().any(|m| name_eq(m, nick)) {
+        return channel;
+    }
+
+    let members: Vec<String> = channel
+        .members
+        .iter()
+        .filter(|m| !name_eq(m, nick))
+        .cloned()
+        .collect();
+
+    set_field! {
+        members: Elem::new(members),
+        ..channel
+    }
+}
+
+/// Create a copy of `elems` with `new_elem` appended.
+fn vec_elem_push<T: Clone>(elems: &Elem<Vec<Elem<T>>>, new_elem: Elem<T>) -> Elem<Vec<Elem<T>>> {
+    let mut elems = Vec::clone(elems);
+    elems.push(new_elem);
+    Elem::new(elems)
+}
+
+/// Apply `f` to every element of `elems`. Returns `elems` as-is if `f`
+/// doesn't change any elements.
+fn map_vec_elem<T: Clone>(
+    elems: &Elem<Vec<Elem<T>>>,
+    mut f: impl FnMut(Elem<T>) -> Elem<T>,
+) -> Elem<Vec<Elem<T>>> {
+    let new_elems: Vec<Elem<T>> = elems.iter().map(|e| f(Elem::clone(e))).collect();
+
+    if new_elems
+        .iter()
+        .zip(elems.iter())
+        .all(|(new, old)| Elem::ptr_eq(new, old))
+    {
+        Elem::clone(elems)
+    } else {
+        Elem::new(new_elems)
+    }
+}
+
+/// A lens referring to the window with the specified ID.
+fn wnd_lens(id: WndId) -> impl Lens<Source = Elem<AppState>, Target = Elem<WndState>> {
+    AppState::wnds.then(Find::new(move |wnd: &Elem<WndState>| wnd.id == id))
+}
+
+/// A lens referring to the account with the specified ID.
+fn account_lens(id: AccountId) -> impl Lens<Source = Elem<AppState>, Target = Elem<Account>> {
+    AppState::accounts.then(Find::new(move |account: &Elem<Account>| account.id == id))
+}
+
+/// A lens referring to the joined channel referenced by `channel_ref`.
+fn channel_lens(
+    channel_ref: &ChannelRef,
+) -> impl Lens<Source = Elem<AppState>, Target = Elem<Channel>> + '_ {
+    account_lens(channel_ref.account)
+        .then(Account::channels)
+        .then(Find::new(move |channel: &Elem<Channel>| {
+            name_eq(&channel.name, &channel_ref.channel)
+        }))
+}
+
+impl Settings {
+    /// The range of `Settings::font_size`.
+    pub const FONT_SIZES: std::ops::RangeInclusive<u32> = 10..=17;
+
+    pub fn new() -> Self {
+        Self {
+            check_updates: true,
+            time_format: TimeFormat::Hour24,
+            color_theme: ColorTheme::Light,
+            font_size: 12,
+            proxy: Proxy::Direct,
+            language: None,
+            log_level: crate::logging::DEFAULT_LEVEL,
+        }
+    }
+
+    fn reduce(this: Elem<Self>, action: &SettingsAction) -> Elem<Self> {
+        match action {
+            SettingsAction::SetCheckUpdates(x) => set_field! {
+                check_updates: *x,
+                ..this
+            },
+            SettingsAction::SetTimeFormat(x) => set_field! {
+                time_format: *x,
+                ..this
+            },
+            SettingsAction::SetColorTheme(x) => set_field! {
+                color_theme: *x,
+                ..this
+            },
+            SettingsAction::SetFontSize(x) => set_field! {
+                font_size: (*x)
+                    .max(*Self::FONT_SIZES.start())
+                    .min(*Self::FONT_SIZES.end()),
+                ..this
+            },
+            SettingsAction::SetProxy(x) => set_field! {
+                proxy: *x,
+                ..this
+            },
+            SettingsAction::SetLanguage(x) => set_field! {
+                language: x.clone(),
+                ..this
+            },
+            SettingsAction::SetLogLevel(x) => set_field! {
+                log_level: *x,
+                ..this
+            },
         }
     }
 }
 
 impl WndState {
+    pub fn new(id: WndId) -> Self {
+        Self {
+            id,
+            sidebar_width: 200.0,
+            editor_height: 150.0,
+            sidebar_visible: true,
+            selected_channel: None,
+            channel_to_restore: None,
+            focused_message: None,
+            activation: 0,
+        }
+    }
+
     fn reduce(this: Elem<Self>, action: &WndAction) -> Elem<Self> {
         match action {
             WndAction::SetSidebarWidth(x) => set_field! {
@@ -80,6 +956,18 @@ impl WndState {
                 sidebar_visible: !this.sidebar_visible,
                 ..this
             },
+            WndAction::SelectChannel(channel_ref) => set_field! {
+                selected_channel: Some(channel_ref.clone()),
+                channel_to_restore: None,
+                focused_message: None,
+                ..this
+            },
+            WndAction::ShowSearchHit(hit) => set_field! {
+                selected_channel: Some(hit.channel.clone()),
+                channel_to_restore: None,
+                focused_message: hit.message.history_pos,
+                ..this
+            },
         }
     }
 }
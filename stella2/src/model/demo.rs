@@ -0,0 +1,90 @@
+//! Provides mock-up data to be displayed when no IRC servers are specified.
+use chrono::TimeZone;
+use harmony::Elem;
+
+use super::{AccountId, AppAction, ChannelRef, Message, MessageKind, Server};
+
+/// Construct actions to populate an initial `AppState` with mock-up data.
+pub fn initial_actions() -> Vec<AppAction> {
+    let server_names = [
+        "randomserver — Slack",
+        "workplace — Slack",
+        "thawedpeach — GNU Social",
+        "FreeNode",
+    ];
+    let channel_names = ["#general", "#prolang", "#random"];
+
+    let mut actions = Vec::new();
+    let mut accounts = Vec::new();
+
+    for &server_name in server_names.iter() {
+        let account = AccountId::new_unique();
+        accounts.push(account);
+        actions.push(AppAction::AddAccount(
+            account,
+            Server {
+                name: server_name.to_owned(),
+            },
+        ));
+        actions.push(AppAction::SetConnected(account, true));
+
+        for &channel_name in channel_names.iter() {
+            actions.push(AppAction::JoinChannel(ChannelRef {
+                account,
+                channel: channel_name.to_owned(),
+            }));
+        }
+    }
+
+    let lipsum = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. \
+                  Pellentesque ultricies diam sit amet ante auctor, et \
+                  pretium orci molestie. Aenean facilisis justo ac tincidunt \
+                  lobortis. Nulla molestie sem vel vehicula scelerisque. \
+                  Quisque in viverra lacus, a suscipit lectus. Integer \
+                  dignissim lacus neque, a condimentum tellus tempus ac. \
+                  Praesent interdum, velit id mattis fringilla, tortor velit \
+                  bibendum lorem, eget blandit augue nibh vel nunc. Duis ex \
+                  ligula, porttitor ultricies velit vel, porta lacinia lectus. \
+                  In pharetra auctor lorem, a efficitur tellus. Maecenas \
+                  feugiat dapibus dolor quis dignissim. Quisque sed tortor \
+                  sagittis, pretium mauris sit amet, ullamcorper turpis. \
+                  Suspendisse potenti."
+        .split_whitespace();
+    let mk_lipsum = |num_words| lipsum.clone().take(num_words).collect::<Vec<_>>().join(" ");
+
+    let messages = [
+        ((2018, 3, 1), (13, 0), "bob", 20),
+        ((2018, 3, 1), (13, 32), "alice", 25),
+        ((2018, 3, 1), (14, 4), "bob", 35),
+        ((2018, 3, 1), (14, 36), "alice", 5),
+        ((2018, 3, 1), (15, 8), "bob", 12),
+        ((2018, 3, 2), (10, 40), "bob", 12),
+        ((2018, 3, 2), (11, 12), "alice", 15),
+        ((2018, 3, 2), (11, 44), "bob", 17),
+        ((2018, 3, 2), (14, 16), "alice", 40),
+        ((2018, 3, 2), (14, 48), "bob", 20),
+    ];
+
+    let channel = ChannelRef {
+        account: accounts[0],
+        channel: channel_names[0].to_owned(),
+    };
+
+    for &((y, mo, d), (h, mi), sender, num_words) in messages.iter() {
+        actions.push(AppAction::ReceiveMessage(
+            channel.clone(),
+            Elem::new(Message {
+                time: chrono::Local
+                    .ymd(y, mo, d)
+                    .and_hms(h, mi, 0)
+                    .with_timezone(&chrono::Utc),
+                sender: sender.to_owned(),
+                kind: MessageKind::Normal,
+                body: mk_lipsum(num_words),
+                history_pos: None,
+            }),
+        ));
+    }
+
+    actions
+}
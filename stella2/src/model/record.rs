@@ -0,0 +1,876 @@
+//! Converts the app state and actions to and from JSON values so that a
+//! session recorded by `harmony::Recorder` can be exported and replayed.
+use harmony::{recorder::JsonRepr, Elem};
+use miniserde::json::{self, Number, Value};
+
+use super::{
+    Account, AccountId, AppAction, AppState, Channel, ChannelPath, ChannelRef, HistoryState,
+    Message, MessageKind, SearchHit, SearchState, Server, Settings, SettingsAction, WndAction,
+    WndId, WndState,
+};
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    let mut obj = json::Object::new();
+    for (key, value) in fields {
+        obj.insert(key.to_owned(), value);
+    }
+    Value::Object(obj)
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(obj) => obj.get(key),
+        _ => None,
+    }
+}
+
+fn u64_to_json(x: u64) -> Value {
+    Value::Number(Number::U64(x))
+}
+
+fn u64_from_json(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(Number::U64(x)) => Some(*x),
+        _ => None,
+    }
+}
+
+fn f32_to_json(x: f32) -> Value {
+    Value::Number(Number::F64(x as f64))
+}
+
+fn f32_from_json(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(Number::U64(x)) => Some(*x as f32),
+        Value::Number(Number::I64(x)) => Some(*x as f32),
+        Value::Number(Number::F64(x)) => Some(*x as f32),
+        _ => None,
+    }
+}
+
+fn bool_from_json(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(x) => Some(*x),
+        _ => None,
+    }
+}
+
+fn str_to_json(x: &str) -> Value {
+    Value::String(x.to_owned())
+}
+
+fn string_from_json(value: &Value) -> Option<String> {
+    match value {
+        Value::String(x) => Some(x.clone()),
+        _ => None,
+    }
+}
+
+fn enum_from_json<T: std::str::FromStr>(value: &Value) -> Option<T> {
+    string_from_json(value)?.parse().ok()
+}
+
+fn level_to_json(x: log::LevelFilter) -> Value {
+    Value::String(x.to_string())
+}
+
+fn level_from_json(value: &Value) -> Option<log::LevelFilter> {
+    match value {
+        Value::String(x) => x.parse().ok(),
+        _ => None,
+    }
+}
+
+fn option_to_json<T>(x: Option<T>, f: impl FnOnce(T) -> Value) -> Value {
+    x.map(f).unwrap_or(Value::Null)
+}
+
+/// The inverse of `option_to_json`. The outer `Option` indicates an error.
+fn option_from_json<T>(value: &Value, f: impl FnOnce(&Value) -> Option<T>) -> Option<Option<T>> {
+    match value {
+        Value::Null => Some(None),
+        _ => f(value).map(Some),
+    }
+}
+
+fn array_to_json<T>(items: impl IntoIterator<Item = T>, f: impl Fn(T) -> Value) -> Value {
+    let mut array = json::Array::new();
+    for item in items {
+        array.push(f(item));
+    }
+    Value::Array(array)
+}
+
+fn array_from_json<T>(value: &Value, f: impl Fn(&Value) -> Option<T>) -> Option<Vec<T>> {
+    match value {
+        Value::Array(array) => array.iter().map(f).collect(),
+        _ => None,
+    }
+}
+
+fn elem_from_json<T: JsonRepr>(value: &Value) -> Option<Elem<T>> {
+    T::from_json(value).map(Elem::new)
+}
+
+impl JsonRepr for AppState {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("wnds", array_to_json(self.wnds.iter(), |wnd| wnd.to_json())),
+            ("pref_visible", Value::Bool(self.pref_visible)),
+            ("log_visible", Value::Bool(self.log_visible)),
+            ("settings", self.settings.to_json()),
+            (
+                "accounts",
+                array_to_json(self.accounts.iter(), |account| account.to_json()),
+            ),
+            ("search", self.search.to_json()),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            wnds: Elem::new(array_from_json(field(value, "wnds")?, elem_from_json)?),
+            pref_visible: bool_from_json(field(value, "pref_visible")?)?,
+            log_visible: bool_from_json(field(value, "log_visible")?)?,
+            settings: elem_from_json(field(value, "settings")?)?,
+            accounts: Elem::new(array_from_json(field(value, "accounts")?, elem_from_json)?),
+            search: elem_from_json(field(value, "search")?)?,
+        })
+    }
+}
+
+impl JsonRepr for Settings {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("check_updates", Value::Bool(self.check_updates)),
+            ("time_format", str_to_json(self.time_format.as_str())),
+            ("color_theme", str_to_json(self.color_theme.as_str())),
+            ("font_size", u64_to_json(self.font_size as u64)),
+            ("proxy", str_to_json(self.proxy.as_str())),
+            (
+                "language",
+                option_to_json(self.language.as_deref(), str_to_json),
+            ),
+            ("log_level", level_to_json(self.log_level)),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            check_updates: bool_from_json(field(value, "check_updates")?)?,
+            time_format: enum_from_json(field(value, "time_format")?)?,
+            color_theme: enum_from_json(field(value, "color_theme")?)?,
+            font_size: u64_from_json(field(value, "font_size")?)? as u32,
+            proxy: enum_from_json(field(value, "proxy")?)?,
+            language: option_from_json(field(value, "language")?, string_from_json)?,
+            log_level: level_from_json(field(value, "log_level")?)?,
+        })
+    }
+}
+
+impl JsonRepr for WndState {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("id", u64_to_json(self.id.0)),
+            ("sidebar_width", f32_to_json(self.sidebar_width)),
+            ("editor_height", f32_to_json(self.editor_height)),
+            ("sidebar_visible", Value::Bool(self.sidebar_visible)),
+            (
+                "selected_channel",
+                option_to_json(self.selected_channel.as_ref(), ChannelRef::to_json),
+            ),
+            (
+                "channel_to_restore",
+                option_to_json(self.channel_to_restore.as_ref(), ChannelPath::to_json),
+            ),
+            (
+                "focused_message",
+                option_to_json(self.focused_message, record_pos_to_json),
+            ),
+            ("activation", u64_to_json(self.activation)),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: WndId(u64_from_json(field(value, "id")?)?),
+            sidebar_width: f32_from_json(field(value, "sidebar_width")?)?,
+            editor_height: f32_from_json(field(value, "editor_height")?)?,
+            sidebar_visible: bool_from_json(field(value, "sidebar_visible")?)?,
+            selected_channel: option_from_json(
+                field(value, "selected_channel")?,
+                ChannelRef::from_json,
+            )?,
+            channel_to_restore: option_from_json(
+                field(value, "channel_to_restore")?,
+                ChannelPath::from_json,
+            )?,
+            focused_message: option_from_json(
+                field(value, "focused_message")?,
+                record_pos_from_json,
+            )?,
+            activation: u64_from_json(field(value, "activation")?)?,
+        })
+    }
+}
+
+impl JsonRepr for ChannelRef {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("account", u64_to_json(self.account.0)),
+            ("channel", str_to_json(&self.channel)),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            account: AccountId(u64_from_json(field(value, "account")?)?),
+            channel: string_from_json(field(value, "channel")?)?,
+        })
+    }
+}
+
+impl JsonRepr for ChannelPath {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("server", str_to_json(&self.server)),
+            ("channel", str_to_json(&self.channel)),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            server: string_from_json(field(value, "server")?)?,
+            channel: string_from_json(field(value, "channel")?)?,
+        })
+    }
+}
+
+impl JsonRepr for Account {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("id", u64_to_json(self.id.0)),
+            ("server", self.server.to_json()),
+            ("connected", Value::Bool(self.connected)),
+            (
+                "channels",
+                array_to_json(self.channels.iter(), |channel| channel.to_json()),
+            ),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: AccountId(u64_from_json(field(value, "id")?)?),
+            server: elem_from_json(field(value, "server")?)?,
+            connected: bool_from_json(field(value, "connected")?)?,
+            channels: Elem::new(array_from_json(field(value, "channels")?, elem_from_json)?),
+        })
+    }
+}
+
+impl JsonRepr for Server {
+    fn to_json(&self) -> Value {
+        object(vec![("name", str_to_json(&self.name))])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            name: string_from_json(field(value, "name")?)?,
+        })
+    }
+}
+
+impl JsonRepr for Channel {
+    fn to_json(&self) -> Value {
+        let history = match self.history {
+            HistoryState::Partial => "partial",
+            HistoryState::Loading => "loading",
+            HistoryState::Complete => "complete",
+        };
+
+        object(vec![
+            ("name", str_to_json(&self.name)),
+            ("topic", option_to_json(self.topic.as_deref(), str_to_json)),
+            (
+                "members",
+                array_to_json(self.members.iter(), |m| str_to_json(m)),
+            ),
+            (
+                "messages",
+                array_to_json(self.messages.iter(), |message| message.to_json()),
+            ),
+            ("history", str_to_json(history)),
+            (
+                "outbox",
+                array_to_json(self.outbox.iter(), |text| str_to_json(text)),
+            ),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let history = match string_from_json(field(value, "history")?)?.as_str() {
+            "partial" => HistoryState::Partial,
+            "loading" => HistoryState::Loading,
+            "complete" => HistoryState::Complete,
+            _ => return None,
+        };
+
+        Some(Self {
+            name: string_from_json(field(value, "name")?)?,
+            topic: option_from_json(field(value, "topic")?, string_from_json)?,
+            members: Elem::new(array_from_json(field(value, "members")?, string_from_json)?),
+            messages: Elem::new(array_from_json(field(value, "messages")?, elem_from_json)?),
+            history,
+            outbox: Elem::new(array_from_json(field(value, "outbox")?, string_from_json)?),
+        })
+    }
+}
+
+impl JsonRepr for Message {
+    fn to_json(&self) -> Value {
+        let kind = match self.kind {
+            MessageKind::Normal => "normal",
+            MessageKind::Action => "action",
+            MessageKind::Notice => "notice",
+        };
+
+        object(vec![
+            ("time", Value::String(self.time.to_rfc3339())),
+            ("sender", str_to_json(&self.sender)),
+            ("kind", str_to_json(kind)),
+            ("body", str_to_json(&self.body)),
+            (
+                "history_pos",
+                option_to_json(self.history_pos, record_pos_to_json),
+            ),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let time = string_from_json(field(value, "time")?)?;
+        let time = chrono::DateTime::parse_from_rfc3339(&time).ok()?;
+
+        let kind = match string_from_json(field(value, "kind")?)?.as_str() {
+            "normal" => MessageKind::Normal,
+            "action" => MessageKind::Action,
+            "notice" => MessageKind::Notice,
+            _ => return None,
+        };
+
+        Some(Self {
+            time: time.with_timezone(&chrono::Utc),
+            sender: string_from_json(field(value, "sender")?)?,
+            kind,
+            body: string_from_json(field(value, "body")?)?,
+            history_pos: option_from_json(field(value, "history_pos")?, record_pos_from_json)?,
+        })
+    }
+}
+
+fn record_pos_to_json(pos: seglog::RecordPos) -> Value {
+    object(vec![
+        ("segment", u64_to_json(pos.segment as u64)),
+        ("offset", u64_to_json(pos.offset)),
+    ])
+}
+
+fn record_pos_from_json(value: &Value) -> Option<seglog::RecordPos> {
+    let segment = u64_from_json(field(value, "segment")?)?;
+    Some(seglog::RecordPos {
+        segment: std::convert::TryFrom::try_from(segment).ok()?,
+        offset: u64_from_json(field(value, "offset")?)?,
+    })
+}
+
+impl JsonRepr for SearchState {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("query", str_to_json(&self.query)),
+            (
+                "hits",
+                option_to_json(self.hits.as_ref(), |hits| {
+                    array_to_json(hits.iter(), SearchHit::to_json)
+                }),
+            ),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            query: string_from_json(field(value, "query")?)?,
+            hits: option_from_json(field(value, "hits")?, |hits| {
+                array_from_json(hits, SearchHit::from_json).map(Elem::new)
+            })?,
+        })
+    }
+}
+
+impl JsonRepr for SearchHit {
+    fn to_json(&self) -> Value {
+        object(vec![
+            ("channel", self.channel.to_json()),
+            ("message", self.message.to_json()),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            channel: ChannelRef::from_json(field(value, "channel")?)?,
+            message: elem_from_json(field(value, "message")?)?,
+        })
+    }
+}
+
+impl JsonRepr for AppAction {
+    fn to_json(&self) -> Value {
+        let (ty, mut fields) = match self {
+            AppAction::Wnd(id, action) => (
+                "Wnd",
+                vec![("wnd", u64_to_json(id.0)), ("action", action.to_json())],
+            ),
+            AppAction::NewWnd(id) => ("NewWnd", vec![("wnd", u64_to_json(id.0))]),
+            AppAction::CloseWnd(id) => ("CloseWnd", vec![("wnd", u64_to_json(id.0))]),
+            AppAction::ActivateMainWnd => ("ActivateMainWnd", vec![]),
+            AppAction::HidePref => ("HidePref", vec![]),
+            AppAction::TogglePref => ("TogglePref", vec![]),
+            AppAction::ShowLog => ("ShowLog", vec![]),
+            AppAction::HideLog => ("HideLog", vec![]),
+            AppAction::Settings(action) => ("Settings", vec![("action", action.to_json())]),
+            AppAction::AddAccount(id, server) => (
+                "AddAccount",
+                vec![("account", u64_to_json(id.0)), ("server", server.to_json())],
+            ),
+            AppAction::SetConnected(id, connected) => (
+                "SetConnected",
+                vec![
+                    ("account", u64_to_json(id.0)),
+                    ("connected", Value::Bool(*connected)),
+                ],
+            ),
+            AppAction::JoinChannel(channel_ref) => {
+                ("JoinChannel", vec![("channel", channel_ref.to_json())])
+            }
+            AppAction::PartChannel(channel_ref) => {
+                ("PartChannel", vec![("channel", channel_ref.to_json())])
+            }
+            AppAction::ReceiveMessage(channel_ref, message) => (
+                "ReceiveMessage",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("message", message.to_json()),
+                ],
+            ),
+            AppAction::LoadOlderMessages(channel_ref) => (
+                "LoadOlderMessages",
+                vec![("channel", channel_ref.to_json())],
+            ),
+            AppAction::PrependMessages {
+                channel,
+                messages,
+                complete,
+            } => (
+                "PrependMessages",
+                vec![
+                    ("channel", channel.to_json()),
+                    (
+                        "messages",
+                        array_to_json(messages.iter(), |message| message.to_json()),
+                    ),
+                    ("complete", Value::Bool(*complete)),
+                ],
+            ),
+            AppAction::UnloadOlderMessages(channel_ref, count) => (
+                "UnloadOlderMessages",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("count", u64_to_json(*count as u64)),
+                ],
+            ),
+            AppAction::SendMessage(channel_ref, text) => (
+                "SendMessage",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("text", str_to_json(text)),
+                ],
+            ),
+            AppAction::RemoveFromOutbox(channel_ref, count) => (
+                "RemoveFromOutbox",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("count", u64_to_json(*count as u64)),
+                ],
+            ),
+            AppAction::SetSearchQuery(query) => {
+                ("SetSearchQuery", vec![("query", str_to_json(query))])
+            }
+            AppAction::SetSearchHits { query, hits } => (
+                "SetSearchHits",
+                vec![
+                    ("query", str_to_json(query)),
+                    ("hits", array_to_json(hits.iter(), SearchHit::to_json)),
+                ],
+            ),
+            AppAction::SetTopic(channel_ref, topic) => (
+                "SetTopic",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("topic", option_to_json(topic.as_deref(), str_to_json)),
+                ],
+            ),
+            AppAction::SetMembers(channel_ref, members) => (
+                "SetMembers",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("members", array_to_json(members.iter(), |m| str_to_json(m))),
+                ],
+            ),
+            AppAction::AddMember(channel_ref, nick) => (
+                "AddMember",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("nick", str_to_json(nick)),
+                ],
+            ),
+            AppAction::RemoveMember(channel_ref, nick) => (
+                "RemoveMember",
+                vec![
+                    ("channel", channel_ref.to_json()),
+                    ("nick", str_to_json(nick)),
+                ],
+            ),
+            AppAction::RemoveMemberFromAll(account, nick) => (
+                "RemoveMemberFromAll",
+                vec![
+                    ("account", u64_to_json(account.0)),
+                    ("nick", str_to_json(nick)),
+                ],
+            ),
+            AppAction::RenameMember {
+                account,
+                old_nick,
+                new_nick,
+            } => (
+                "RenameMember",
+                vec![
+                    ("account", u64_to_json(account.0)),
+                    ("old_nick", str_to_json(old_nick)),
+                    ("new_nick", str_to_json(new_nick)),
+                ],
+            ),
+        };
+
+        fields.push(("type", str_to_json(ty)));
+        object(fields)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let wnd = || Some(WndId(u64_from_json(field(value, "wnd")?)?));
+        let account = || Some(AccountId(u64_from_json(field(value, "account")?)?));
+        let channel = || ChannelRef::from_json(field(value, "channel")?);
+        let string = |key| string_from_json(field(value, key)?);
+
+        Some(match string("type")?.as_str() {
+            "Wnd" => AppAction::Wnd(wnd()?, WndAction::from_json(field(value, "action")?)?),
+            "NewWnd" => AppAction::NewWnd(wnd()?),
+            "CloseWnd" => AppAction::CloseWnd(wnd()?),
+            "ActivateMainWnd" => AppAction::ActivateMainWnd,
+            "HidePref" => AppAction::HidePref,
+            "TogglePref" => AppAction::TogglePref,
+            "ShowLog" => AppAction::ShowLog,
+            "HideLog" => AppAction::HideLog,
+            "Settings" => AppAction::Settings(SettingsAction::from_json(field(value, "action")?)?),
+            "AddAccount" => {
+                AppAction::AddAccount(account()?, Server::from_json(field(value, "server")?)?)
+            }
+            "SetConnected" => {
+                AppAction::SetConnected(account()?, bool_from_json(field(value, "connected")?)?)
+            }
+            "JoinChannel" => AppAction::JoinChannel(channel()?),
+            "PartChannel" => AppAction::PartChannel(channel()?),
+            "ReceiveMessage" => {
+                AppAction::ReceiveMessage(channel()?, elem_from_json(field(value, "message")?)?)
+            }
+            "LoadOlderMessages" => AppAction::LoadOlderMessages(channel()?),
+            "PrependMessages" => AppAction::PrependMessages {
+                channel: channel()?,
+                messages: array_from_json(field(value, "messages")?, elem_from_json)?,
+                complete: bool_from_json(field(value, "complete")?)?,
+            },
+            "UnloadOlderMessages" => AppAction::UnloadOlderMessages(
+                channel()?,
+                u64_from_json(field(value, "count")?)? as usize,
+            ),
+            "SendMessage" => AppAction::SendMessage(channel()?, string("text")?),
+            "RemoveFromOutbox" => AppAction::RemoveFromOutbox(
+                channel()?,
+                u64_from_json(field(value, "count")?)? as usize,
+            ),
+            "SetSearchQuery" => AppAction::SetSearchQuery(string("query")?),
+            "SetSearchHits" => AppAction::SetSearchHits {
+                query: string("query")?,
+                hits: array_from_json(field(value, "hits")?, SearchHit::from_json)?,
+            },
+            "SetTopic" => AppAction::SetTopic(
+                channel()?,
+                option_from_json(field(value, "topic")?, string_from_json)?,
+            ),
+            "SetMembers" => AppAction::SetMembers(
+                channel()?,
+                array_from_json(field(value, "members")?, string_from_json)?,
+            ),
+            "AddMember" => AppAction::AddMember(channel()?, string("nick")?),
+            "RemoveMember" => AppAction::RemoveMember(channel()?, string("nick")?),
+            "RemoveMemberFromAll" => AppAction::RemoveMemberFromAll(account()?, string("nick")?),
+            "RenameMember" => AppAction::RenameMember {
+                account: account()?,
+                old_nick: string("old_nick")?,
+                new_nick: string("new_nick")?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl JsonRepr for WndAction {
+    fn to_json(&self) -> Value {
+        let (ty, mut fields) = match self {
+            WndAction::SetSidebarWidth(x) => ("SetSidebarWidth", vec![("value", f32_to_json(*x))]),
+            WndAction::SetEditorHeight(x) => ("SetEditorHeight", vec![("value", f32_to_json(*x))]),
+            WndAction::ToggleSidebar => ("ToggleSidebar", vec![]),
+            WndAction::SelectChannel(channel_ref) => {
+                ("SelectChannel", vec![("channel", channel_ref.to_json())])
+            }
+            WndAction::ShowSearchHit(hit) => ("ShowSearchHit", vec![("hit", hit.to_json())]),
+        };
+
+        fields.push(("type", str_to_json(ty)));
+        object(fields)
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let ty = string_from_json(field(value, "type")?)?;
+        Some(match ty.as_str() {
+            "SetSidebarWidth" => WndAction::SetSidebarWidth(f32_from_json(field(value, "value")?)?),
+            "SetEditorHeight" => WndAction::SetEditorHeight(f32_from_json(field(value, "value")?)?),
+            "ToggleSidebar" => WndAction::ToggleSidebar,
+            "SelectChannel" => {
+                WndAction::SelectChannel(ChannelRef::from_json(field(value, "channel")?)?)
+            }
+            "ShowSearchHit" => {
+                WndAction::ShowSearchHit(SearchHit::from_json(field(value, "hit")?)?)
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl JsonRepr for SettingsAction {
+    fn to_json(&self) -> Value {
+        let (ty, value) = match self {
+            SettingsAction::SetCheckUpdates(x) => ("SetCheckUpdates", Value::Bool(*x)),
+            SettingsAction::SetTimeFormat(x) => ("SetTimeFormat", str_to_json(x.as_str())),
+            SettingsAction::SetColorTheme(x) => ("SetColorTheme", str_to_json(x.as_str())),
+            SettingsAction::SetFontSize(x) => ("SetFontSize", u64_to_json(*x as u64)),
+            SettingsAction::SetProxy(x) => ("SetProxy", str_to_json(x.as_str())),
+            SettingsAction::SetLanguage(x) => {
+                ("SetLanguage", option_to_json(x.as_deref(), str_to_json))
+            }
+            SettingsAction::SetLogLevel(x) => ("SetLogLevel", level_to_json(*x)),
+        };
+
+        object(vec![("type", str_to_json(ty)), ("value", value)])
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let ty = string_from_json(field(value, "type")?)?;
+        let value = field(value, "value")?;
+        Some(match ty.as_str() {
+            "SetCheckUpdates" => SettingsAction::SetCheckUpdates(bool_from_json(value)?),
+            "SetTimeFormat" => SettingsAction::SetTimeFormat(enum_from_json(value)?),
+            "SetColorTheme" => SettingsAction::SetColorTheme(enum_from_json(value)?),
+            "SetFontSize" => SettingsAction::SetFontSize(u64_from_json(value)? as u32),
+            "SetProxy" => SettingsAction::SetProxy(enum_from_json(value)?),
+            "SetLanguage" => {
+                SettingsAction::SetLanguage(option_from_json(value, string_from_json)?)
+            }
+            "SetLogLevel" => SettingsAction::SetLogLevel(level_from_json(value)?),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use harmony::{recorder::Log, Recorder, Store};
+
+    use super::*;
+    use crate::model::{ColorTheme, Proxy, TimeFormat};
+
+    fn to_string<T: JsonRepr>(x: &T) -> String {
+        json::to_string(&x.to_json())
+    }
+
+    fn channel_ref() -> ChannelRef {
+        ChannelRef {
+            account: AccountId(1),
+            channel: "#rust".to_owned(),
+        }
+    }
+
+    fn message() -> Elem<Message> {
+        Elem::new(Message {
+            time: chrono::Utc.timestamp_millis(1_500_000_000_123),
+            sender: "alice".to_owned(),
+            kind: MessageKind::Action,
+            body: "waves".to_owned(),
+            history_pos: Some(seglog::RecordPos {
+                segment: 1,
+                offset: 42,
+            }),
+        })
+    }
+
+    fn search_hit() -> SearchHit {
+        SearchHit {
+            channel: channel_ref(),
+            message: message(),
+        }
+    }
+
+    /// Get the index of the variant of `action`. This doesn't have a wildcard
+    /// pattern so that a new variant can't be left out of `sample_actions`.
+    fn variant_index(action: &AppAction) -> usize {
+        match action {
+            AppAction::Wnd(..) => 0,
+            AppAction::NewWnd(..) => 1,
+            AppAction::CloseWnd(..) => 2,
+            AppAction::ActivateMainWnd => 3,
+            AppAction::HidePref => 4,
+            AppAction::TogglePref => 5,
+            AppAction::ShowLog => 6,
+            AppAction::HideLog => 7,
+            AppAction::Settings(..) => 8,
+            AppAction::AddAccount(..) => 9,
+            AppAction::SetConnected(..) => 10,
+            AppAction::JoinChannel(..) => 11,
+            AppAction::PartChannel(..) => 12,
+            AppAction::ReceiveMessage(..) => 13,
+            AppAction::LoadOlderMessages(..) => 14,
+            AppAction::PrependMessages { .. } => 15,
+            AppAction::UnloadOlderMessages(..) => 16,
+            AppAction::SendMessage(..) => 17,
+            AppAction::RemoveFromOutbox(..) => 18,
+            AppAction::SetSearchQuery(..) => 19,
+            AppAction::SetSearchHits { .. } => 20,
+            AppAction::SetTopic(..) => 21,
+            AppAction::SetMembers(..) => 22,
+            AppAction::AddMember(..) => 23,
+            AppAction::RemoveMember(..) => 24,
+            AppAction::RemoveMemberFromAll(..) => 25,
+            AppAction::RenameMember { .. } => 26,
+        }
+    }
+
+    const NUM_VARIANTS: usize = 27;
+
+    fn sample_actions() -> Vec<AppAction> {
+        let wnd = WndId(3);
+        vec![
+            AppAction::Wnd(wnd, WndAction::SetSidebarWidth(123.5)),
+            AppAction::Wnd(wnd, WndAction::SetEditorHeight(45.25)),
+            AppAction::Wnd(wnd, WndAction::ToggleSidebar),
+            AppAction::Wnd(wnd, WndAction::SelectChannel(channel_ref())),
+            AppAction::Wnd(wnd, WndAction::ShowSearchHit(search_hit())),
+            AppAction::NewWnd(wnd),
+            AppAction::CloseWnd(wnd),
+            AppAction::ActivateMainWnd,
+            AppAction::HidePref,
+            AppAction::TogglePref,
+            AppAction::ShowLog,
+            AppAction::HideLog,
+            AppAction::Settings(SettingsAction::SetCheckUpdates(true)),
+            AppAction::Settings(SettingsAction::SetTimeFormat(TimeFormat::Hour12)),
+            AppAction::Settings(SettingsAction::SetColorTheme(ColorTheme::Light)),
+            AppAction::Settings(SettingsAction::SetFontSize(14)),
+            AppAction::Settings(SettingsAction::SetProxy(Proxy::Direct)),
+            AppAction::Settings(SettingsAction::SetLanguage(Some("ja".to_owned()))),
+            AppAction::Settings(SettingsAction::SetLanguage(None)),
+            AppAction::Settings(SettingsAction::SetLogLevel(log::LevelFilter::Debug)),
+            AppAction::AddAccount(
+                AccountId(1),
+                Server {
+                    name: "FreeNode".to_owned(),
+                },
+            ),
+            AppAction::SetConnected(AccountId(1), true),
+            AppAction::JoinChannel(channel_ref()),
+            AppAction::PartChannel(channel_ref()),
+            AppAction::ReceiveMessage(channel_ref(), message()),
+            AppAction::LoadOlderMessages(channel_ref()),
+            AppAction::PrependMessages {
+                channel: channel_ref(),
+                messages: vec![message(), message()],
+                complete: true,
+            },
+            AppAction::UnloadOlderMessages(channel_ref(), 10),
+            AppAction::SendMessage(channel_ref(), "hello\nworld".to_owned()),
+            AppAction::RemoveFromOutbox(channel_ref(), 2),
+            AppAction::SetSearchQuery("hello".to_owned()),
+            AppAction::SetSearchHits {
+                query: "hello".to_owned(),
+                hits: vec![search_hit()],
+            },
+            AppAction::SetTopic(channel_ref(), Some("Rust".to_owned())),
+            AppAction::SetTopic(channel_ref(), None),
+            AppAction::SetMembers(channel_ref(), vec!["alice".to_owned(), "bob".to_owned()]),
+            AppAction::AddMember(channel_ref(), "carol".to_owned()),
+            AppAction::RemoveMember(channel_ref(), "carol".to_owned()),
+            AppAction::RemoveMemberFromAll(AccountId(1), "bob".to_owned()),
+            AppAction::RenameMember {
+                account: AccountId(1),
+                old_nick: "alice".to_owned(),
+                new_nick: "alice_".to_owned(),
+            },
+        ]
+    }
+
+    #[test]
+    fn action_roundtrip() {
+        let actions = sample_actions();
+
+        let mut covered = [false; NUM_VARIANTS];
+        for action in actions.iter() {
+            covered[variant_index(action)] = true;
+        }
+        assert!(covered.iter().all(|&x| x), "{:?}", covered);
+
+        for action in actions.iter() {
+            let imported = AppAction::from_json(&action.to_json())
+                .unwrap_or_else(|| panic!("could not import {:?}", action));
+            assert_eq!(variant_index(&imported), variant_index(action));
+            assert_eq!(to_string(&imported), to_string(action));
+        }
+    }
+
+    #[test]
+    fn export_import_replay() {
+        let store = Store::new(Elem::new(AppState::new()), AppState::reduce, |f| f());
+        let recorder = Recorder::new(1000);
+        store.add_middleware(recorder.clone());
+
+        for action in crate::model::demo::initial_actions() {
+            store.dispatch(action);
+        }
+        store.dispatch(AppAction::TogglePref);
+
+        let exported = recorder.log().unwrap().to_json();
+        let log: Log<AppState, AppAction> = Log::from_json(&exported).unwrap();
+        assert_eq!(log.actions.len(), recorder.len());
+
+        // Replay the actions against a fresh copy of the initial state
+        let final_state = log.replay(AppState::reduce);
+        assert_eq!(to_string(&*final_state), to_string(&*store.state()));
+    }
+}
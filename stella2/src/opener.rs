@@ -0,0 +1,81 @@
+//! Opens URLs and files with the user's preferred applications
+use cfg_if::cfg_if;
+use std::{io, path::Path};
+
+/// Open `url` (e.g., `https://example.com/` or `mailto:alice@example.com`)
+/// with the default application associated with its scheme.
+///
+/// This function doesn't wait for the application to start. It refuses to
+/// open a URL rejected by [`stella2_irc::linkify::is_safe_url`].
+pub fn open_url(url: &str) -> io::Result<()> {
+    if !stella2_irc::linkify::is_safe_url(url) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Refusing to open {:?} with an unsupported scheme", url),
+        ));
+    }
+
+    log::debug!("Opening {:?}", url);
+    imp::open(url.as_ref())
+}
+
+/// Open the local file at `path` with the default application associated
+/// with its type.
+///
+/// This function doesn't wait for the application to start.
+#[allow(dead_code)] // Only used by the gtk crash handler
+pub fn open_path(path: &Path) -> io::Result<()> {
+    log::debug!("Opening {:?}", path);
+    imp::open(path.as_os_str())
+}
+
+cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        mod imp {
+            use std::{ffi::OsStr, io, iter::once, os::windows::ffi::OsStrExt, ptr::null_mut};
+            use winapi::um::{shellapi::ShellExecuteW, winuser::SW_SHOWNORMAL};
+
+            pub fn open(target: &OsStr) -> io::Result<()> {
+                let to_wide = |s: &OsStr| -> Vec<u16> {
+                    s.encode_wide().chain(once(0)).collect()
+                };
+                let (verb, target) = (to_wide("open".as_ref()), to_wide(target));
+
+                // "If the function succeeds, it returns a value greater than 32."
+                let result = unsafe {
+                    ShellExecuteW(
+                        null_mut(),
+                        verb.as_ptr(),
+                        target.as_ptr(),
+                        null_mut(),
+                        null_mut(),
+                        SW_SHOWNORMAL,
+                    )
+                };
+                if result as usize > 32 {
+                    Ok(())
+                } else {
+                    Err(io::Error::last_os_error())
+                }
+            }
+        }
+    } else {
+        mod imp {
+            use std::{ffi::OsStr, io, process::Command};
+
+            #[cfg(target_os = "macos")]
+            const OPENER: &str = "open";
+            #[cfg(not(target_os = "macos"))]
+            const OPENER: &str = "xdg-open";
+
+            pub fn open(target: &OsStr) -> io::Result<()> {
+                let mut child = Command::new(OPENER).arg(target).spawn()?;
+
+                // Reap the process when it exits
+                std::thread::spawn(move || child.wait());
+
+                Ok(())
+            }
+        }
+    }
+}
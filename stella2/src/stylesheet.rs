@@ -62,6 +62,9 @@ pub mod elem_id {
                 , PREF_CONTENT_GENERAL
                 , PREF_GENERAL_FONT_SIZE
 
+                , LOG_WND
+                , LOG_WND_RECORD
+
                 , WND
 
                 , STACK_HORZ_LEFT_TOP
@@ -92,6 +95,7 @@ pub mod my_roles {
     iota::iota! {
         pub const BULLET: Role = iota + 1;
                 , CLOSE
+                , PLACEHOLDER
     }
 }
 
@@ -248,11 +252,13 @@ fn new_custom_stylesheet() -> impl Stylesheet {
             layer_bg_color[0]: RGBAF32::new(0.93, 0.93, 0.93, 1.0),
 
             subview_layouter: Layouter::Table,
-            subview_padding: [5.0; 4],
+            subview_padding: [EDITOR_PADDING; 4],
 
-            // Text editor
+            // Text editor. It grows with the text up to the height of the
+            // composing area.
             subview_table_cell[0]: [0, 0],
-            subview_table_align[0]: AlignFlags::JUSTIFY,
+            subview_table_align[0]: AlignFlags::from_bits_truncate(
+                AlignFlags::HORZ_JUSTIFY.bits() | AlignFlags::BOTTOM.bits()),
             subview_table_col_spacing[0]: 5.0,
 
             // "Send button"
@@ -479,16 +485,23 @@ fn new_custom_stylesheet() -> impl Stylesheet {
             layer_center[1]: box2! { point: [0.5, 0.5] },
 
             // Text editor
-            subview_layouter: Layouter::Table,
-            subview_padding: [3.0; 4],
-            subview_table_cell[0]: [0, 0],
-            subview_table_align[0]: AlignFlags::from_bits_truncate(
-                AlignFlags::TOP.bits() | AlignFlags::LEFT.bits()),
+            subview_metrics[roles::GENERIC]: Metrics {
+                margin: [EDITOR_FIELD_MARGIN; 4],
+                ..Metrics::default()
+            },
+            // Placeholder, overlapping the text editor
+            subview_metrics[my_roles::PLACEHOLDER]: Metrics {
+                margin: [5.0, NAN, NAN, 6.0],
+                ..Metrics::default()
+            },
         },
         ([#EDITOR_FIELD.FOCUS]) (priority = 10500) {
             // Focus ring
             layer_opacity[0]: 0.5,
         },
+        ([] < [#EDITOR_FIELD]) (priority = 10000) {
+            padding: [2.0, 3.0, 2.0, 3.0],
+        },
         ([.LABEL] < [#EDITOR_FIELD]) (priority = 10000) {
             fg_color: RGBAF32::new(0.0, 0.0, 0.0, 0.4),
         },
@@ -767,6 +780,15 @@ fn new_custom_stylesheet() -> impl Stylesheet {
             font: SysFontType::Small,
         },
 
+        // Log viewer
+        ([#LOG_WND]) (priority = 10000) {
+            num_layers: 1,
+            layer_bg_color[0]: RGBAF32::new(1.0, 1.0, 1.0, 1.0),
+        },
+        ([#LOG_WND_RECORD]) (priority = 10000) {
+            font: SysFontType::UserMonospace,
+        },
+
         // Utilities
         ([#STACK_HORZ_LEFT_TOP]) (priority = 10000) {
             subview_layouter: Layouter::Table,
@@ -852,6 +874,15 @@ fn new_custom_platform_stylesheet() -> impl Stylesheet {
     stylesheet! {}
 }
 
+/// The padding of `#EDITOR`.
+const EDITOR_PADDING: f32 = 5.0;
+
+/// The margin between `#EDITOR_FIELD` and the text editor inside it.
+const EDITOR_FIELD_MARGIN: f32 = 3.0;
+
+/// The vertical space taken by the composing area besides the text editor.
+pub const EDITOR_CHROME_HEIGHT: f32 = (EDITOR_PADDING + EDITOR_FIELD_MARGIN) * 2.0;
+
 pub const ENABLE_BACKDROP_BLUR: bool = cfg!(any(target_os = "windows", target_os = "macos"));
 
 pub fn register_stylesheet(manager: &'static Manager) {
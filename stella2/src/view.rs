@@ -1,130 +1,337 @@
-use harmony::Elem;
+use harmony::{Elem, Middleware, Recorder, Store};
 use log::trace;
 use std::{
     cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::PathBuf,
     rc::{Rc, Weak},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
 };
 use tcw3::{
     pal,
-    pal::prelude::*,
+    pal::{prelude::*, MtSticky},
     ui::layouts::FillLayout,
     ui::theming,
     uicore::{ActionId, ActionStatus, HWnd, HWndRef, WndListener, WndStyleFlags},
 };
 
 use crate::{
-    config::{profile::Profile, viewpersistence},
-    model, stylesheet,
+    config::{history, profile::Profile, settings::PersistedSettings, viewpersistence},
+    i18n, model, stylesheet,
 };
 
 mod channellist;
 mod dpiscalewatcher;
 mod global;
 mod logview;
+mod logwnd;
 mod prefwnd;
 mod radiolist;
+mod searchresults;
 mod splitutils;
 mod tabbar;
 mod toolbar;
 
+/// The number of actions retained by `--record-actions`.
+const RECORDER_CAPACITY: usize = 100_000;
+
 pub struct AppView {
     wm: pal::Wm,
     profile: &'static Profile,
-    state: RefCell<Elem<model::AppState>>,
-    pending_actions: RefCell<Vec<model::AppAction>>,
-    persist_sched: viewpersistence::PersistenceScheduler,
-    main_wnd: Rc<WndView>,
+    store: Store<model::AppState, model::AppAction>,
+    persist_sched: Rc<viewpersistence::PersistenceScheduler>,
+    settings_persist_sched: Rc<viewpersistence::PersistenceScheduler<PersistedSettings>>,
+    /// The recorder enabled by `--record-actions` and the path to write the
+    /// recorded actions to.
+    recorder: Option<(Recorder<model::AppState, model::AppAction>, PathBuf)>,
+    history: Option<Arc<Mutex<history::History>>>,
+    /// The query for which the last search was started.
+    search_query: RefCell<String>,
+    /// The channels for which older messages are being loaded from `history`.
+    history_loads: RefCell<HashSet<model::ChannelRef>>,
+    /// The number of consecutive failures to load older messages for each
+    /// channel, which determines the delay before retrying.
+    history_failures: RefCell<HashMap<model::ChannelRef, u32>>,
+    /// The main windows, sorted in the same order as `AppState::wnds`.
+    wnds: RefCell<Vec<Rc<WndView>>>,
     pref_wnd: Cell<Option<Rc<prefwnd::PrefWndView>>>,
+    log_wnd: Cell<Option<Rc<logwnd::LogWndView>>>,
 }
 
 impl AppView {
-    pub fn new(wm: pal::Wm, profile: &'static Profile) -> Rc<Self> {
+    pub fn new(
+        wm: pal::Wm,
+        profile: &'static Profile,
+        history: Option<Arc<Mutex<history::History>>>,
+        record_actions: Option<PathBuf>,
+        log_level: Option<log::LevelFilter>,
+    ) -> Rc<Self> {
         let mut state = Elem::new(model::AppState::new());
 
         // Restore the app state from the user profile
-        state = viewpersistence::restore_state(profile, state);
+        state = viewpersistence::restore_state::<viewpersistence::PersistedState>(profile, state);
+        state = viewpersistence::restore_state::<PersistedSettings>(profile, state);
 
-        let persist_sched = viewpersistence::PersistenceScheduler::new(&state);
+        let persist_sched = Rc::new(viewpersistence::PersistenceScheduler::new(&state));
+        let settings_persist_sched = Rc::new(viewpersistence::PersistenceScheduler::new(&state));
 
-        global::set_main_menu(wm);
+        // The log level specified by the command-line arguments overrides the
+        // persisted one until the user changes it. It isn't stored in the
+        // state so that it isn't persisted.
+        crate::logging::set_level(log_level.unwrap_or(state.settings.log_level));
+
+        let store = Store::new(Elem::clone(&state), model::AppState::reduce, move |f| {
+            wm.invoke(move |_| f());
+        });
+        store.add_middleware(LogMiddleware);
+        store.add_middleware(viewpersistence::PersistenceMiddleware::new(
+            wm,
+            profile,
+            Rc::clone(&persist_sched),
+        ));
+        store.add_middleware(viewpersistence::PersistenceMiddleware::new(
+            wm,
+            profile,
+            Rc::clone(&settings_persist_sched),
+        ));
+
+        // The recorder comes last so that it sees the actions as they are
+        // passed to the reducer
+        let recorder = record_actions.map(|path| {
+            let recorder = Recorder::new(RECORDER_CAPACITY);
+            store.add_middleware(recorder.clone());
+            (recorder, path)
+        });
 
-        let main_wnd = WndView::new(wm, Elem::clone(&state.main_wnd));
+        global::set_main_menu(wm);
 
         let this = Rc::new(Self {
             wm,
             profile,
-            main_wnd,
-            state: RefCell::new(state),
-            pending_actions: RefCell::new(Vec::new()),
+            wnds: RefCell::new(Vec::new()),
+            store,
             persist_sched,
+            settings_persist_sched,
+            recorder,
+            history,
+            search_query: RefCell::new(String::new()),
+            history_loads: RefCell::new(HashSet::new()),
+            history_failures: RefCell::new(HashMap::new()),
             pref_wnd: Cell::new(None),
+            log_wnd: Cell::new(None),
         });
 
-        let this_weak = Rc::downgrade(&this);
-        this.main_wnd
-            .set_dispatch(move |app_action| Self::dispatch_weak(&this_weak, app_action));
+        this.update_wnds(&state);
 
+        // Update the views when the state changes
         let this_weak = Rc::downgrade(&this);
-        this.main_wnd.set_quit(move || {
-            // Persist the state to disk before quitting
+        this.store.subscribe(Elem::clone, move |state| {
             if let Some(this) = this_weak.upgrade() {
-                this.persist_sched.flush(wm, &this.state.borrow(), profile);
+                this.update_wnds(state);
             }
-
-            wm.terminate();
         });
 
+        let this_weak = Rc::downgrade(&this);
+        this.store.subscribe(
+            |state| Elem::clone(&state.search),
+            move |search| {
+                if let Some(this) = this_weak.upgrade() {
+                    this.update_search(search);
+                }
+            },
+        );
+
+        let this_weak = Rc::downgrade(&this);
+        this.store.subscribe(
+            |state| {
+                let mut channels = Vec::new();
+                for account in state.accounts.iter() {
+                    for channel in account.channels.iter() {
+                        if channel.history == model::HistoryState::Loading {
+                            channels.push(model::ChannelRef {
+                                account: account.id,
+                                channel: channel.name.clone(),
+                            });
+                        }
+                    }
+                }
+                channels
+            },
+            move |channels| {
+                if let Some(this) = this_weak.upgrade() {
+                    this.update_history_loads(channels);
+                }
+            },
+        );
+
+        let this_weak = Rc::downgrade(&this);
+        this.store.subscribe(
+            |state| state.pref_visible,
+            move |&pref_visible| {
+                if let Some(this) = this_weak.upgrade() {
+                    this.update_pref_wnd(pref_visible);
+                }
+            },
+        );
+
+        let this_weak = Rc::downgrade(&this);
+        this.store.subscribe(
+            |state| state.log_visible,
+            move |&log_visible| {
+                if let Some(this) = this_weak.upgrade() {
+                    this.update_log_wnd(log_visible);
+                }
+            },
+        );
+
+        let this_weak = Rc::downgrade(&this);
+        this.store.subscribe(
+            |state| Elem::clone(&state.settings),
+            move |settings| {
+                if let Some(this) = this_weak.upgrade() {
+                    this.update_settings(settings);
+                }
+            },
+        );
+
+        this.store.subscribe(
+            |state| state.settings.log_level,
+            move |&log_level| crate::logging::set_level(log_level),
+        );
+
+        let this_weak = Rc::downgrade(&this);
+        this.store.subscribe(
+            |state| state.settings.language.clone(),
+            move |language| {
+                if let Some(this) = this_weak.upgrade() {
+                    this.update_language(language.as_deref());
+                }
+            },
+        );
+
         this
     }
 
-    fn dispatch_weak(this_weak: &Weak<Self>, action: model::AppAction) {
-        if let Some(this) = this_weak.upgrade() {
-            Self::dispatch(&this, action);
-        }
-    }
+    /// Persist the state to disk and quit the application.
+    fn quit(&self) {
+        let state = self.store.state();
+        self.persist_sched.flush(self.wm, &state, self.profile);
+        self.settings_persist_sched
+            .flush(self.wm, &state, self.profile);
 
-    fn dispatch(this: &Rc<Self>, action: model::AppAction) {
-        trace!("Dispatching the action: {:?}", action);
+        self.save_recorded_actions();
 
-        let mut pending_actions = this.pending_actions.borrow_mut();
+        crate::logging::shutdown();
 
-        pending_actions.push(action);
+        self.wm.terminate();
+    }
+
+    /// Write the actions recorded by `--record-actions` to the file. The log
+    /// can be replayed by `harmony::recorder::Log::replay`.
+    fn save_recorded_actions(&self) {
+        let (recorder, path) = if let Some(x) = &self.recorder {
+            x
+        } else {
+            return;
+        };
 
-        if pending_actions.len() == 1 {
-            // Schedule polling
-            let this = Rc::clone(this);
-            this.wm.invoke(move |_| this.poll());
+        let json = match recorder.log() {
+            Some(log) => log.to_json(),
+            None => return,
+        };
+
+        log::info!(
+            "Writing {} recorded action(s) to {:?}",
+            recorder.len(),
+            path
+        );
+        if let Err(e) = std::fs::write(path, json) {
+            log::error!("Could not write the recorded actions: {}", e);
         }
     }
 
-    fn poll(self: Rc<Self>) {
-        // Update the state
-        {
-            let mut state = self.state.borrow_mut();
-            let mut pending_actions = self.pending_actions.borrow_mut();
+    /// Close the specified main window. Quits the application if it's the
+    /// last one.
+    fn close_wnd(&self, id: model::WndId) {
+        if self.store.state().wnds.len() <= 1 {
+            // Keep the window in the persisted state so that it's restored
+            // on the next launch
+            self.quit();
+        } else {
+            self.store.dispatch(model::AppAction::CloseWnd(id));
+        }
+    }
 
-            let mut new_state = Elem::clone(&*state);
-            for action in pending_actions.drain(..) {
-                new_state = model::AppState::reduce(new_state, &action);
-            }
-            *state = new_state;
+    /// Open or close `WndView`s to match `AppState::wnds` and update them
+    /// with the new state.
+    fn update_wnds(self: &Rc<Self>, state: &Elem<model::AppState>) {
+        let old_wnds = self.wnds.replace(Vec::new());
+
+        let new_wnds: Vec<Rc<WndView>> = state
+            .wnds
+            .iter()
+            .map(|wnd_state| {
+                if let Some(wnd) = old_wnds.iter().find(|wnd| wnd.id == wnd_state.id) {
+                    Rc::clone(wnd)
+                } else {
+                    self.new_wnd_view(wnd_state, state)
+                }
+            })
+            .collect();
 
-            // Persist the app state
-            self.persist_sched
-                .handle_update(self.wm, &state, self.profile);
+        for wnd in new_wnds.iter() {
+            wnd.poll(state);
         }
 
-        let state = self.state.borrow();
+        self.wnds.replace(new_wnds);
 
-        self.main_wnd.poll(&state.main_wnd);
+        // Windows removed from `state` are closed here
+        drop(old_wnds);
+    }
 
-        match (cell_is_some(&self.pref_wnd), state.pref_visible) {
+    fn new_wnd_view(
+        self: &Rc<Self>,
+        wnd_state: &Elem<model::WndState>,
+        app_state: &Elem<model::AppState>,
+    ) -> Rc<WndView> {
+        let id = wnd_state.id;
+        let wnd = WndView::new(self.wm, Elem::clone(wnd_state), Elem::clone(app_state));
+
+        let store = self.store.clone();
+        wnd.set_dispatch(move |app_action| store.dispatch(app_action));
+
+        let this_weak = Rc::downgrade(self);
+        wnd.set_close(move || {
+            if let Some(this) = this_weak.upgrade() {
+                this.close_wnd(id);
+            }
+        });
+
+        let this_weak = Rc::downgrade(self);
+        let wm = self.wm;
+        wnd.set_quit(move || {
+            if let Some(this) = this_weak.upgrade() {
+                this.quit();
+            } else {
+                wm.terminate();
+            }
+        });
+
+        wnd
+    }
+
+    /// Open or close the preferences window.
+    fn update_pref_wnd(&self, pref_visible: bool) {
+        match (cell_is_some(&self.pref_wnd), pref_visible) {
             (false, true) => {
-                let pref_wnd = prefwnd::PrefWndView::new(self.wm);
+                let settings = Elem::clone(&self.store.state().settings);
+                let pref_wnd = prefwnd::PrefWndView::new(self.wm, settings);
 
-                let this_weak = Rc::downgrade(&self);
-                pref_wnd
-                    .set_dispatch(move |app_action| Self::dispatch_weak(&this_weak, app_action));
+                let store = self.store.clone();
+                pref_wnd.set_dispatch(move |app_action| store.dispatch(app_action));
 
                 self.pref_wnd.set(Some(pref_wnd));
             }
@@ -134,24 +341,325 @@ impl AppView {
             _ => {}
         }
     }
+
+    /// Open or close the log viewer window.
+    fn update_log_wnd(&self, log_visible: bool) {
+        match (cell_is_some(&self.log_wnd), log_visible) {
+            (false, true) => {
+                let locale = i18n::Locale::new(self.store.state().settings.language.as_deref());
+                let log_wnd = logwnd::LogWndView::new(self.wm, &locale);
+
+                let store = self.store.clone();
+                log_wnd.set_dispatch(move |app_action| store.dispatch(app_action));
+
+                self.log_wnd.set(Some(log_wnd));
+            }
+            (true, false) => {
+                self.log_wnd.set(None);
+            }
+            _ => {}
+        }
+    }
+
+    /// Update the preferences window with the new settings.
+    fn update_settings(&self, settings: &Elem<model::Settings>) {
+        if let Some(pref_wnd) = self.pref_wnd.take() {
+            pref_wnd.set_settings(Elem::clone(settings));
+            self.pref_wnd.set(Some(pref_wnd));
+        }
+    }
+
+    /// Re-render the log viewer window in the new UI language. The main
+    /// windows and the preferences window derive the language from the state
+    /// by themselves.
+    fn update_language(&self, language: Option<&str>) {
+        let locale = i18n::Locale::new(language);
+        log::info!("Switching the UI language to {:?}", locale.language());
+
+        if let Some(log_wnd) = self.log_wnd.take() {
+            log_wnd.set_locale(&locale);
+            self.log_wnd.set(Some(log_wnd));
+        }
+    }
+
+    pub fn dispatch(this: &Rc<Self>, action: model::AppAction) {
+        this.store.dispatch(action);
+    }
+
+    /// Append a middleware to the end of the store's middleware chain.
+    pub fn add_middleware(
+        this: &Rc<Self>,
+        middleware: impl Middleware<model::AppState, model::AppAction> + 'static,
+    ) {
+        this.store.add_middleware(middleware);
+    }
+}
+
+/// A [`Middleware`] that logs dispatched actions.
+struct LogMiddleware;
+
+impl Middleware<model::AppState, model::AppAction> for LogMiddleware {
+    fn dispatch(
+        &self,
+        _: &Store<model::AppState, model::AppAction>,
+        action: model::AppAction,
+        next: &mut dyn FnMut(model::AppAction),
+    ) {
+        trace!("Dispatching the action: {:?}", action);
+        next(action);
+    }
+}
+
+/// The maximum number of search results.
+const SEARCH_LIMIT: usize = 200;
+
+/// The number of messages loaded from the history by each
+/// `AppAction::LoadOlderMessages`.
+const HISTORY_PAGE_LEN: usize = 100;
+
+/// Get the delay before retrying to load older messages after `failures`
+/// consecutive failures. The delay doubles for each failure, up to about a
+/// minute.
+fn history_retry_delay(failures: u32) -> Range<Duration> {
+    let secs = 1u64 << failures.min(6).saturating_sub(1);
+    Duration::from_secs(secs)..Duration::from_secs(secs * 2)
+}
+
+impl AppView {
+    /// Start a search if the search query has changed.
+    ///
+    /// The search is done in a background thread, and the result is
+    /// delivered by `AppAction::SetSearchHits`.
+    fn update_search(self: &Rc<Self>, search: &model::SearchState) {
+        let query = &search.query;
+        if *self.search_query.borrow() == *query {
+            return;
+        }
+        self.search_query.replace(query.clone());
+
+        if query.trim().is_empty() {
+            return;
+        }
+
+        let query = query.clone();
+
+        let history = if let Some(history) = &self.history {
+            Arc::clone(history)
+        } else {
+            // The history is unavailable, so there's nothing to find
+            let action = model::AppAction::SetSearchHits {
+                query,
+                hits: Vec::new(),
+            };
+            self.store.dispatch(action);
+            return;
+        };
+
+        let this_weak = MtSticky::with_wm(self.wm, Rc::downgrade(self));
+
+        thread::Builder::new()
+            .name("search".to_owned())
+            .spawn(move || {
+                let result = history.lock().unwrap().search(&query, SEARCH_LIMIT);
+
+                pal::Wm::invoke_on_main_thread(move |wm| {
+                    let this = if let Some(this) = this_weak.get_with_wm(wm).upgrade() {
+                        this
+                    } else {
+                        return;
+                    };
+
+                    let hits = match result {
+                        Ok(hits) => this.resolve_search_hits(hits),
+                        Err(e) => {
+                            log::error!("Search failed: {:?}", e);
+                            return;
+                        }
+                    };
+
+                    this.store
+                        .dispatch(model::AppAction::SetSearchHits { query, hits });
+                });
+            })
+            .unwrap();
+    }
+
+    /// Start loading older messages for the channels which have just entered
+    /// `HistoryState::Loading`.
+    ///
+    /// The messages are read in a background thread, and the result is
+    /// delivered by `AppAction::PrependMessages`.
+    fn update_history_loads(self: &Rc<Self>, channels: &[model::ChannelRef]) {
+        for channel_ref in channels.iter() {
+            if self.history_loads.borrow_mut().insert(channel_ref.clone()) {
+                self.load_older_messages(channel_ref.clone());
+            }
+        }
+    }
+
+    fn load_older_messages(self: &Rc<Self>, channel_ref: model::ChannelRef) {
+        let state = self.store.state();
+        let (server, messages) = match (
+            state.account(channel_ref.account),
+            state.channel(&channel_ref),
+        ) {
+            (Some(account), Some(channel)) => {
+                (account.server.name.clone(), Elem::clone(&channel.messages))
+            }
+            _ => {
+                self.history_loads.borrow_mut().remove(&channel_ref);
+                return;
+            }
+        };
+
+        // Read the messages preceding the oldest recorded one. If none of
+        // them is recorded, there's nothing to read because the latest
+        // messages are loaded when joining the channel (see `irc`).
+        let before = messages.iter().find_map(|message| message.history_pos);
+
+        let (history, before) = match (&self.history, before) {
+            (Some(history), Some(before)) => (Arc::clone(history), before),
+            _ => {
+                self.history_loads.borrow_mut().remove(&channel_ref);
+                self.store.dispatch(model::AppAction::PrependMessages {
+                    channel: channel_ref,
+                    messages: Vec::new(),
+                    complete: true,
+                });
+                return;
+            }
+        };
+
+        let this_weak = MtSticky::with_wm(self.wm, Rc::downgrade(self));
+
+        thread::Builder::new()
+            .name("history".to_owned())
+            .spawn(move || {
+                let result = history.lock().unwrap().read_before(
+                    &server,
+                    &channel_ref.channel,
+                    Some(before),
+                    HISTORY_PAGE_LEN,
+                );
+
+                pal::Wm::invoke_on_main_thread(move |wm| {
+                    let this = if let Some(this) = this_weak.get_with_wm(wm).upgrade() {
+                        this
+                    } else {
+                        return;
+                    };
+
+                    let messages = match result {
+                        Ok(messages) => messages,
+                        Err(e) => {
+                            log::error!("Failed to read the history of {:?}: {:?}", channel_ref, e);
+                            this.retry_load_older_messages_later(channel_ref);
+                            return;
+                        }
+                    };
+
+                    this.history_loads.borrow_mut().remove(&channel_ref);
+                    this.history_failures.borrow_mut().remove(&channel_ref);
+
+                    let complete = messages.len() < HISTORY_PAGE_LEN;
+
+                    this.store.dispatch(model::AppAction::PrependMessages {
+                        channel: channel_ref,
+                        messages: messages.into_iter().map(Elem::new).collect(),
+                        complete,
+                    });
+                });
+            })
+            .unwrap();
+    }
+
+    /// Leave the `Loading` state after a delay so that loading older messages
+    /// is retried without hammering the failing history.
+    fn retry_load_older_messages_later(self: &Rc<Self>, channel_ref: model::ChannelRef) {
+        let failures = {
+            let mut history_failures = self.history_failures.borrow_mut();
+            let failures = history_failures.entry(channel_ref.clone()).or_insert(0);
+            *failures += 1;
+            *failures
+        };
+
+        let delay = history_retry_delay(failures);
+        log::info!(
+            "Retrying to load the history of {:?} in {:?}",
+            channel_ref,
+            delay
+        );
+
+        let this_weak = Rc::downgrade(self);
+        self.wm.invoke_after(delay, move |_| {
+            let this = if let Some(this) = this_weak.upgrade() {
+                this
+            } else {
+                return;
+            };
+
+            // Go back to `Partial`. The messages are requested again if
+            // they are still needed.
+            this.history_loads.borrow_mut().remove(&channel_ref);
+            this.store.dispatch(model::AppAction::PrependMessages {
+                channel: channel_ref,
+                messages: Vec::new(),
+                complete: false,
+            });
+        });
+    }
+
+    /// Convert `history::SearchHit`s to `model::SearchHit`s. Hits from unknown
+    /// servers are removed.
+    fn resolve_search_hits(&self, hits: Vec<history::SearchHit>) -> Vec<model::SearchHit> {
+        let state = self.store.state();
+
+        hits.into_iter()
+            .filter_map(|hit| {
+                let account = state
+                    .accounts
+                    .iter()
+                    .find(|account| account.server.name == hit.server)?;
+
+                Some(model::SearchHit {
+                    channel: model::ChannelRef {
+                        account: account.id,
+                        channel: hit.channel,
+                    },
+                    message: Elem::new(hit.message),
+                })
+            })
+            .collect()
+    }
 }
 
 struct WndView {
+    id: model::WndId,
     hwnd: HWnd,
     dispatch: RefCell<Box<dyn Fn(model::AppAction)>>,
+    close: RefCell<Box<dyn Fn()>>,
     quit: RefCell<Box<dyn Fn()>>,
     wnd_state: RefCell<Elem<model::WndState>>,
+    /// The last known value of `WndState::activation`.
+    activation: Cell<u64>,
     main_view: MainView,
 }
 
 impl WndView {
-    pub fn new(wm: pal::Wm, wnd_state: Elem<model::WndState>) -> Rc<Self> {
+    pub fn new(
+        wm: pal::Wm,
+        wnd_state: Elem<model::WndState>,
+        app_state: Elem<model::AppState>,
+    ) -> Rc<Self> {
         let hwnd = HWnd::new(wm);
         let style_manager = theming::Manager::global(wm);
+        let id = wnd_state.id;
+        let activation = wnd_state.activation;
 
         let main_view = MainViewBuilder::new()
             .with_wm(wm)
             .with_wnd_state(Elem::clone(&wnd_state))
+            .with_app_state(app_state)
             .with_style_manager(style_manager)
             .build();
 
@@ -163,10 +671,13 @@ impl WndView {
         hwnd.set_visibility(true);
 
         let this = Rc::new(Self {
+            id,
             hwnd,
             dispatch: RefCell::new(Box::new(|_| {})),
+            close: RefCell::new(Box::new(|| {})),
             quit: RefCell::new(Box::new(|| {})),
             wnd_state: RefCell::new(wnd_state),
+            activation: Cell::new(activation),
             main_view,
         });
 
@@ -185,7 +696,7 @@ impl WndView {
         let this_weak = Rc::downgrade(&this);
         this.main_view.subscribe_close(Box::new(move || {
             if let Some(this) = this_weak.upgrade() {
-                this.quit.borrow()();
+                this.close.borrow()();
             }
         }));
 
@@ -205,6 +716,10 @@ impl WndView {
         *self.dispatch.borrow_mut() = Box::new(cb);
     }
 
+    fn set_close(&self, cb: impl Fn() + 'static) {
+        *self.close.borrow_mut() = Box::new(cb);
+    }
+
     fn set_quit(&self, cb: impl Fn() + 'static) {
         *self.quit.borrow_mut() = Box::new(cb);
     }
@@ -227,10 +742,21 @@ impl WndView {
         self.main_view.set_wnd_focused(is_focused);
     }
 
-    fn poll(&self, new_wnd_state: &Elem<model::WndState>) {
+    fn poll(&self, new_app_state: &Elem<model::AppState>) {
+        let new_wnd_state = if let Some(wnd_state) = new_app_state.wnd(self.id) {
+            wnd_state
+        } else {
+            return;
+        };
         *self.wnd_state.borrow_mut() = new_wnd_state.clone();
 
         self.main_view.set_wnd_state(new_wnd_state.clone());
+        self.main_view.set_app_state(new_app_state.clone());
+
+        if self.activation.get() != new_wnd_state.activation {
+            self.activation.set(new_wnd_state.activation);
+            self.hwnd.activate();
+        }
     }
 }
 
@@ -241,7 +767,7 @@ struct WndViewWndListener {
 impl WndListener for WndViewWndListener {
     fn close(&self, _: pal::Wm, _: HWndRef<'_>) {
         if let Some(owner) = self.owner.upgrade() {
-            owner.quit.borrow()();
+            owner.close.borrow()();
         }
     }
 
@@ -257,7 +783,7 @@ impl WndListener for WndViewWndListener {
     fn validate_action(&self, _: pal::Wm, _: HWndRef<'_>, action: ActionId) -> ActionStatus {
         let mut status = ActionStatus::empty();
         match action {
-            global::QUIT | global::SHOW_PREF => {
+            global::QUIT | global::SHOW_PREF | global::NEW_WND => {
                 status = ActionStatus::VALID | ActionStatus::ENABLED;
             }
             global::TOGGLE_SIDEBAR => {
@@ -283,7 +809,14 @@ impl WndListener for WndViewWndListener {
 
         match action {
             global::TOGGLE_SIDEBAR => {
-                owner.dispatch.borrow()(model::AppAction::Wnd(model::WndAction::ToggleSidebar));
+                owner.dispatch.borrow()(model::AppAction::Wnd(
+                    owner.id,
+                    model::WndAction::ToggleSidebar,
+                ));
+            }
+            global::NEW_WND => {
+                let id = model::WndId::new_unique();
+                owner.dispatch.borrow()(model::AppAction::NewWnd(id));
             }
             global::SHOW_PREF => {
                 owner.dispatch.borrow()(model::AppAction::TogglePref);
@@ -1,14 +1,20 @@
-use std::{ops::Range, rc::Rc};
+use std::{collections::HashSet, hash::Hash, ops::Range, rc::Rc};
 use tcw3::{
+    pal,
     ui::{
+        layouts::FillLayout,
+        mixins::{button::ButtonListener, ButtonMixin},
         prelude::*,
         theming,
         views::{table, table::LineTy, Button, Label},
     },
-    uicore::{HView, HViewRef},
+    uicore::{HView, HViewRef, MouseDragListener, ViewFlags, ViewListener},
 };
 
-use crate::stylesheet::{elem_id, my_roles};
+use crate::{
+    model,
+    stylesheet::{elem_id, my_roles},
+};
 
 stella2_meta::designer_impl! {
     crate::view::channellist::ChannelListView
@@ -16,17 +22,96 @@ stella2_meta::designer_impl! {
 
 impl ChannelListView {
     fn init(&self) {
+        let this_weak = self.downgrade();
+        let dispatch = move |action| {
+            if let Some(this) = this_weak.upgrade() {
+                this.raise_dispatch(action);
+            }
+        };
+
         // Set up the table model
         {
             let mut edit = self.table().table().edit().unwrap();
             edit.set_model(TableModelQuery {
                 style_manager: self.style_manager(),
                 elem: Rc::clone(self.elem()),
+                dispatch: Rc::new(dispatch),
+                rows: Vec::new(),
+                selected_channel: None,
             });
-            edit.insert(LineTy::Row, 0..29);
             edit.insert(LineTy::Col, 0..1);
             edit.set_scroll_pos([0.0, 0.0]);
         }
+
+        self.update_rows();
+    }
+
+    /// Update the table rows to reflect the accounts and channels in the app
+    /// state.
+    fn update_rows(&self) {
+        let app_state = self.app_state();
+
+        let mut new_rows = Vec::new();
+        for account in app_state.accounts.iter() {
+            new_rows.push(Row::Account {
+                id: account.id,
+                name: account.server.name.clone(),
+            });
+            new_rows.extend(account.channels.iter().map(|channel| Row::Channel {
+                channel_ref: model::ChannelRef {
+                    account: account.id,
+                    channel: channel.name.clone(),
+                },
+            }));
+        }
+
+        let mut edit = self.table().table().edit().unwrap();
+
+        // Insert or remove rows. Update `TableModelQuery::rows` in a lockstep
+        // with `TableModelEdit`.
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+        let old_rows = model.rows.clone();
+
+        diff_lines(&old_rows, &new_rows, |op| match op {
+            DiffOp::Remove(i) => {
+                edit.remove(LineTy::Row, i as u64..i as u64 + 1);
+                let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                model.rows.remove(i);
+            }
+            DiffOp::Insert(i, row) => {
+                let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                model.rows.insert(i, row.clone());
+                edit.insert(LineTy::Row, i as u64..i as u64 + 1);
+            }
+        });
+
+        // Update the selection
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+        let new_selected_channel = self.wnd_state().selected_channel.clone();
+        if model.selected_channel == new_selected_channel {
+            return;
+        }
+        let old_selected_channel =
+            std::mem::replace(&mut model.selected_channel, new_selected_channel);
+
+        // Re-create the rows to update their styles
+        let affected_rows: Vec<usize> = model
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| match row {
+                Row::Channel { channel_ref } => {
+                    Some(channel_ref) == old_selected_channel.as_ref()
+                        || Some(channel_ref) == model.selected_channel.as_ref()
+                }
+                Row::Account { .. } => false,
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        for i in affected_rows {
+            edit.renew_subviews(LineTy::Row, i as u64..i as u64 + 1);
+        }
     }
 }
 
@@ -40,60 +125,85 @@ impl theming::Widget for ChannelListView {
     }
 }
 
+/// Represents a row displayed in `ChannelListView`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Row {
+    /// The group header of an account.
+    Account {
+        id: model::AccountId,
+        name: String,
+    },
+    Channel {
+        channel_ref: model::ChannelRef,
+    },
+}
+
 struct TableModelQuery {
     style_manager: &'static theming::Manager,
     elem: Rc<theming::Elem>,
+    dispatch: Rc<dyn Fn(model::WndAction)>,
+    rows: Vec<Row>,
+    selected_channel: Option<model::ChannelRef>,
 }
 
 impl table::TableModelQuery for TableModelQuery {
     fn new_view(&mut self, cell: table::CellIdx) -> (HView, Box<dyn table::CellCtrler>) {
+        let row = &self.rows[cell[1] as usize];
+
         let label = Label::new(self.style_manager);
-        label.set_text(match (cell[1] % 4, (cell[1] / 4) % 4) {
-            (0, 0) => "randomserver — Slack",
-            (0, 1) => "workplace — Slack",
-            (0, 2) => "thawedpeach — GNU Social",
-            (0, 3) => "FreeNode",
-            (1, _) => "#general",
-            (2, _) => "#prolang",
-            (3, _) => "#random",
-            _ => unreachable!(),
+        label.set_text(match row {
+            Row::Account { name, .. } => name.as_str(),
+            Row::Channel { channel_ref } => channel_ref.channel.as_str(),
         });
 
         let wrap = theming::StyledBox::new(self.style_manager, Default::default());
         wrap.set_child(theming::roles::GENERIC, Some(&label));
-        wrap.set_class_set(
-            if cell[1] % 4 == 0 {
-                elem_id::SIDEBAR_GROUP_HEADER
-            } else {
-                elem_id::SIDEBAR_ITEM
-            } | if cell[1] == 1 || (cell[1] % 4 == 0 && cell[1] < 28) {
-                theming::ClassSet::ACTIVE
-            } else {
-                theming::ClassSet::empty()
-            },
-        );
+        wrap.set_class_set(match row {
+            // The group is always open
+            Row::Account { .. } => elem_id::SIDEBAR_GROUP_HEADER | theming::ClassSet::ACTIVE,
+            Row::Channel { channel_ref } => {
+                if Some(channel_ref) == self.selected_channel.as_ref() {
+                    elem_id::SIDEBAR_ITEM | theming::ClassSet::ACTIVE
+                } else {
+                    elem_id::SIDEBAR_ITEM
+                }
+            }
+        });
 
         self.elem.insert_child(wrap.style_elem());
 
-        let button = if cell[1] % 4 == 0 {
-            let button = Button::new(self.style_manager);
-            // Clear `.BUTTON` and replace with `#SIDEBAR_GROUP_BULLET`
-            button.set_class_set(elem_id::SIDEBAR_GROUP_BULLET);
+        match row {
+            Row::Account { .. } => {
+                let button = Button::new(self.style_manager);
+                // Clear `.BUTTON` and replace with `#SIDEBAR_GROUP_BULLET`
+                button.set_class_set(elem_id::SIDEBAR_GROUP_BULLET);
 
-            wrap.set_child(my_roles::BULLET, Some(&button));
+                wrap.set_child(my_roles::BULLET, Some(&button));
 
-            Some(button)
-        } else {
-            None
-        };
+                (wrap.view(), Box::new(((wrap, button),)))
+            }
+            Row::Channel { channel_ref } => {
+                // Select the channel when clicked
+                let view = HView::new(ViewFlags::ACCEPT_MOUSE_DRAG);
+                view.set_layout(FillLayout::new(wrap.view()));
+                view.set_listener(ActionRowViewListener::new(
+                    Rc::clone(&self.dispatch),
+                    model::WndAction::SelectChannel(channel_ref.clone()),
+                ));
 
-        (wrap.view(), Box::new(((wrap, button),)))
+                (view, Box::new((wrap,)))
+            }
+        }
     }
 
     fn range_size(&mut self, line_ty: LineTy, range: Range<u64>, _approx: bool) -> f64 {
         match line_ty {
-            LineTy::Row => (range.start..range.end)
-                .map(|i| if i % 4 == 0 { 25.0 } else { 20.0 })
+            LineTy::Row => self.rows[range.start as usize..range.end as usize]
+                .iter()
+                .map(|row| match row {
+                    Row::Account { .. } => 25.0,
+                    Row::Channel { .. } => 20.0,
+                })
                 .sum(),
 
             // `TableFlags::GROW_LAST_COL` expands the column to cover the region.
@@ -102,3 +212,80 @@ impl table::TableModelQuery for TableModelQuery {
         }
     }
 }
+
+/// A `ViewListener` for a row which dispatches an action when clicked.
+pub(super) struct ActionRowViewListener {
+    button_mixin: ButtonMixin,
+    dispatch: Rc<dyn Fn(model::WndAction)>,
+    action: model::WndAction,
+}
+
+impl ActionRowViewListener {
+    pub(super) fn new(dispatch: Rc<dyn Fn(model::WndAction)>, action: model::WndAction) -> Self {
+        Self {
+            button_mixin: ButtonMixin::new(),
+            dispatch,
+            action,
+        }
+    }
+
+    fn build_button_mixin_listener(&self) -> Box<dyn ButtonListener> {
+        Box::new(ActionRowButtonListener {
+            dispatch: Rc::clone(&self.dispatch),
+            action: self.action.clone(),
+        })
+    }
+}
+
+impl ViewListener for ActionRowViewListener {
+    fn mouse_drag(
+        &self,
+        _: pal::Wm,
+        _: HViewRef<'_>,
+        _loc: cgmath::Point2<f32>,
+        _button: u8,
+    ) -> Box<dyn MouseDragListener> {
+        self.button_mixin
+            .mouse_drag(self.build_button_mixin_listener())
+    }
+}
+
+struct ActionRowButtonListener {
+    dispatch: Rc<dyn Fn(model::WndAction)>,
+    action: model::WndAction,
+}
+
+impl ButtonListener for ActionRowButtonListener {
+    fn activate(&self, _: pal::Wm, _: HViewRef<'_>) {
+        (self.dispatch)(self.action.clone());
+    }
+}
+
+enum DiffOp<'a, T> {
+    /// Remove the element at the specified index.
+    Remove(usize),
+    /// Insert the element at the specified index.
+    Insert(usize, &'a T),
+}
+
+/// Calculate a sequence of operations to transform `old` into `new`.
+///
+/// It's optimized for the cases where elements are inserted or removed without
+/// changing the order of the remaining elements.
+fn diff_lines<'a, T: Eq + Hash>(old: &[T], new: &'a [T], mut f: impl FnMut(DiffOp<'a, T>)) {
+    let new_set: HashSet<&T> = new.iter().collect();
+
+    let (mut old_i, mut new_i) = (0, 0);
+    while old_i < old.len() || new_i < new.len() {
+        if old_i < old.len() && (new_i >= new.len() || !new_set.contains(&old[old_i])) {
+            f(DiffOp::Remove(new_i));
+            old_i += 1;
+        } else if old_i < old.len() && old[old_i] == new[new_i] {
+            old_i += 1;
+            new_i += 1;
+        } else {
+            f(DiffOp::Insert(new_i, &new[new_i]));
+            new_i += 1;
+        }
+    }
+}
@@ -11,6 +11,7 @@ iota::iota! {
     pub const QUIT: ActionId = iota + 1;
             , TOGGLE_SIDEBAR
             , SHOW_PREF
+            , NEW_WND
 }
 
 pub fn interpret_event(ctx: &mut InterpretEventCtx<'_>) {
@@ -59,6 +60,12 @@ pub fn interpret_event(ctx: &mut InterpretEventCtx<'_>) {
             macos_sel("terminate:")
         ),
         (TOGGLE_SIDEBAR, macos_sel("toggleSidebar:")),
+        (
+            NEW_WND,
+            windows("Ctrl+N"),
+            gtk("Ctrl+N"),
+            macos_sel("newWindow:")
+        ),
         (SHOW_PREF, macos_sel("orderFrontPreferencesPanel:")),
     ]);
 }
@@ -85,6 +92,14 @@ pub fn set_main_menu(_: pal::Wm) {
                 Item::leaf("Quit Stella 2", "terminate:").with_cmd("q"),
             ],
         ),
+        Item::Submenu(
+            "File",
+            &[
+                Item::leaf("New Window", "newWindow:").with_cmd("n"),
+                Item::Sep,
+                Item::leaf("Close Window", "performClose:").with_cmd("w"),
+            ],
+        ),
         Item::Submenu(
             "Edit",
             &[
@@ -1,71 +1,249 @@
 use cggeom::{box2, prelude::*, Box2};
 use cgmath::Matrix3;
-use std::{cell::Cell, ops::Range};
+use harmony::Elem;
+use std::{cell::Cell, ops::Range, rc::Rc};
+use stella2_irc::{
+    linkify::{self, LinkKind},
+    message::name_eq,
+};
 use tcw3::{
     pal,
     prelude::*,
     ui::{
+        layouts::AbsLayout,
+        mixins::{button::ButtonListener, ButtonMixin},
         prelude::*,
         views::{table, table::LineTy},
+        AlignFlags,
+    },
+    uicore::{
+        CursorShape, HView, HViewRef, HWndRef, MouseDragListener, SizeTraits, UpdateCtx, ViewFlags,
+        ViewListener,
     },
-    uicore::{HView, HViewRef, HWndRef, UpdateCtx, ViewListener},
 };
 
+use crate::{model, opener};
+
 stella2_meta::designer_impl! {
     crate::view::logview::LogView
 }
 
 const GUTTER_WIDTH: f32 = 100.0;
 
+/// Older messages are requested when the viewport is closer to the top of
+/// the log than this distance (in points).
+const LOAD_MARGIN: f64 = 1000.0;
+
+/// Messages are unloaded when more than this number of messages are loaded
+/// and the viewport isn't near them.
+const MAX_LOADED_MESSAGES: usize = 1000;
+
+/// Messages are only unloaded if they are farther above the viewport than
+/// this distance (in points). This must be larger than `LOAD_MARGIN` so that
+/// unloaded messages aren't immediately requested again.
+const UNLOAD_MARGIN: f64 = 3000.0;
+
 impl LogView {
     fn init(&self) {
-        // Set up the table model
-        // TODO: hook up with a network layer
-        let lipsum = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. \
-                      Pellentesque ultricies diam sit amet ante auctor, et \
-                      pretium orci molestie. Aenean facilisis justo ac tincidunt \
-                      lobortis. Nulla molestie sem vel vehicula scelerisque. \
-                      Quisque in viverra lacus, a suscipit lectus. Integer \
-                      dignissim lacus neque, a condimentum tellus tempus ac. \
-                      Praesent interdum, velit id mattis fringilla, tortor velit \
-                      bibendum lorem, eget blandit augue nibh vel nunc. Duis ex \
-                      ligula, porttitor ultricies velit vel, porta lacinia lectus. \
-                      In pharetra auctor lorem, a efficitur tellus. Maecenas \
-                      feugiat dapibus dolor quis dignissim. Quisque sed tortor \
-                      sagittis, pretium mauris sit amet, ullamcorper turpis. \
-                      Suspendisse potenti."
-            .split_whitespace();
-        let mk_lipsum = |num_words| lipsum.clone().take(num_words).collect::<Vec<_>>().join(" ");
-        let rows = vec![
-            Row::Date(chrono::NaiveDate::from_ymd(2018, 3, 1)),
-            Row::LogItem("bob", "13:00", mk_lipsum(20)),
-            Row::LogItem("alice", "13:32", mk_lipsum(25)),
-            Row::LogItem("bob", "14:04", mk_lipsum(35)),
-            Row::LogItem("alice", "14:36", mk_lipsum(5)),
-            Row::LogItem("bob", "15:08", mk_lipsum(12)),
-            Row::Date(chrono::NaiveDate::from_ymd(2018, 3, 2)),
-            Row::LogItem("bob", "10:40", mk_lipsum(12)),
-            Row::LogItem("alice", "11:12", mk_lipsum(15)),
-            Row::LogItem("bob", "11:44", mk_lipsum(17)),
-            Row::LogItem("alice", "14:16", mk_lipsum(40)),
-            Row::LogItem("bob", "14:48", mk_lipsum(20)),
-        ];
+        let this_weak = self.downgrade();
+        let activate_link = move |target: &LinkTarget| {
+            if let Some(this) = this_weak.upgrade() {
+                this.activate_link(target);
+            }
+        };
 
+        // Set up the table model
         {
             let mut edit = self.table().table().edit().unwrap();
-            let num_rows = rows.len() as u64;
             edit.set_model(TableModelQuery {
                 width: 100.0,
                 dpi_scale: 1.0,
-                row_visuals: rows
-                    .iter()
-                    .map(|row| RowVisual::from_row(row, 100.0, 1.0))
-                    .collect(),
-                rows,
+                row_visuals: Vec::new(),
+                rows: Vec::new(),
+                shown_messages: None,
+                members: Elem::new(Vec::new()),
+                focused_message: None,
+                activate_link: Rc::new(activate_link),
             });
-            edit.insert(LineTy::Row, 0..num_rows);
             edit.insert(LineTy::Col, 0..1);
         }
+
+        self.update_rows();
+    }
+
+    /// Update the table rows to reflect the messages in the selected channel.
+    fn update_rows(&self) {
+        let app_state = self.app_state();
+        let wnd_state = self.wnd_state();
+
+        let channel = wnd_state
+            .selected_channel
+            .as_ref()
+            .and_then(|channel_ref| Some((channel_ref, app_state.channel(channel_ref)?)));
+        let new_shown_messages = channel
+            .map(|(channel_ref, channel)| (channel_ref.clone(), Elem::clone(&channel.messages)));
+
+        let mut edit = self.table().table().edit().unwrap();
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+
+        // The member list is used to detect nick mentions in new rows
+        if let Some((_, channel)) = channel {
+            model.members = Elem::clone(&channel.members);
+        }
+
+        // Find how the messages have changed. Messages are usually appended to
+        // a channel, so most of the rows can be kept. Older messages are
+        // prepended or removed when they are loaded from or unloaded to the
+        // history, which is handled without disturbing the scroll position.
+        let diff = match (&model.shown_messages, &new_shown_messages) {
+            (None, None) => return,
+            (Some((old_ref, old_messages)), Some((new_ref, new_messages)))
+                if old_ref == new_ref =>
+            {
+                if Elem::ptr_eq(old_messages, new_messages) {
+                    return;
+                }
+
+                diff_messages(old_messages, new_messages).map(|(num_removed, num_prepended)| {
+                    let num_kept = old_messages.len() - num_removed;
+                    (num_removed, num_prepended, num_kept)
+                })
+            }
+            _ => None,
+        };
+
+        let (width, dpi_scale) = (model.width, model.dpi_scale);
+        let members = Elem::clone(&model.members);
+
+        let num_shown_messages = match diff {
+            None => {
+                // Remove all rows
+                let num_rows = model.rows.len() as u64;
+                edit.remove(LineTy::Row, 0..num_rows);
+
+                let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                model.rows.clear();
+                model.row_visuals.clear();
+                0
+            }
+            Some((num_removed, num_prepended, num_kept)) => {
+                if num_removed > 0 {
+                    // Remove the rows of the unloaded messages. The date row of
+                    // the first remaining message is kept or recreated.
+                    let first_kept = message_row_index(&model.rows, num_removed);
+                    let date = model.rows[first_kept].date();
+                    let num_removed_rows = match model.rows[first_kept - 1] {
+                        Row::Date(_) => first_kept - 1,
+                        Row::LogItem(_) => first_kept,
+                    };
+                    edit.remove(LineTy::Row, 0..num_removed_rows as u64);
+
+                    let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                    model.rows.drain(..num_removed_rows);
+                    model.row_visuals.drain(..num_removed_rows);
+
+                    if let Row::LogItem(_) = model.rows[0] {
+                        let row = Row::Date(date);
+                        let row_visual = RowVisual::from_row(&row, width, dpi_scale, &members);
+                        model.rows.insert(0, row);
+                        model.row_visuals.insert(0, row_visual);
+                        edit.insert(LineTy::Row, 0..1);
+                    }
+                }
+
+                if num_prepended > 0 {
+                    let (_, messages) = new_shown_messages.as_ref().unwrap();
+                    let mut new_rows = Vec::new();
+                    let mut last_date = None;
+                    for message in messages[..num_prepended].iter() {
+                        let date = local_date(message);
+                        if last_date != Some(date) {
+                            new_rows.push(Row::Date(date));
+                            last_date = Some(date);
+                        }
+                        new_rows.push(Row::LogItem(Elem::clone(message)));
+                    }
+
+                    // Remove the date row of the first old message if the
+                    // new rows cover the same date
+                    let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                    if model.rows.first().map(Row::date) == last_date {
+                        edit.remove(LineTy::Row, 0..1);
+
+                        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                        model.rows.remove(0);
+                        model.row_visuals.remove(0);
+                    }
+
+                    let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+                    let num_new_rows = new_rows.len();
+                    let new_row_visuals = new_rows
+                        .iter()
+                        .map(|row| RowVisual::from_row(row, width, dpi_scale, &members));
+                    model.row_visuals.splice(..0, new_row_visuals);
+                    model.rows.splice(..0, new_rows);
+
+                    // The displacement policy of `Table` moves the viewport
+                    // by the size of the inserted rows
+                    edit.insert(LineTy::Row, 0..num_new_rows as u64);
+                }
+
+                num_prepended + num_kept
+            }
+        };
+
+        // Append new rows
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+        let num_old_rows = model.rows.len();
+
+        if let Some((_, messages)) = &new_shown_messages {
+            let mut last_date = model.rows.last().map(Row::date);
+
+            for message in messages[num_shown_messages..].iter() {
+                let date = local_date(message);
+                if last_date != Some(date) {
+                    model.rows.push(Row::Date(date));
+                    last_date = Some(date);
+                }
+                model.rows.push(Row::LogItem(Elem::clone(message)));
+            }
+        }
+
+        let new_row_visuals = model.rows[num_old_rows..]
+            .iter()
+            .map(|row| RowVisual::from_row(row, width, dpi_scale, &members));
+        model.row_visuals.extend(new_row_visuals);
+
+        model.shown_messages = new_shown_messages;
+
+        let num_rows = model.rows.len() as u64;
+        edit.insert(LineTy::Row, num_old_rows as u64..num_rows);
+    }
+
+    /// Scroll to the message specified by `WndState::focused_message` if it
+    /// has changed.
+    fn scroll_to_focused_message(&self) {
+        let focused_message = self.wnd_state().focused_message;
+
+        let mut edit = self.table().table().edit().unwrap();
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+
+        if model.focused_message == focused_message {
+            return;
+        }
+        model.focused_message = focused_message;
+
+        let i = model.rows.iter().position(|row| match row {
+            Row::LogItem(message) => {
+                focused_message.is_some() && message.history_pos == focused_message
+            }
+            Row::Date(_) => false,
+        });
+
+        if let Some(i) = i {
+            let y: f32 = model.row_visuals[..i].iter().map(|v| v.height).sum();
+            edit.set_scroll_pos([0.0, y as f64]);
+        }
     }
 
     fn update_row_visuals(&self) {
@@ -83,16 +261,140 @@ impl LogView {
         model.width = width;
         model.dpi_scale = dpi_scale;
 
+        let members = &model.members;
         model.row_visuals = model
             .rows
             .iter()
-            .map(|row| RowVisual::from_row(row, width, dpi_scale))
+            .map(|row| RowVisual::from_row(row, width, dpi_scale, members))
             .collect();
 
         let num_rows = model.rows.len() as u64;
         edit.resize(LineTy::Row, 0..num_rows);
         edit.renew_subviews(LineTy::Row, 0..num_rows);
     }
+
+    /// Handle the `model_update` event of the table, which is raised when the
+    /// table is scrolled or edited.
+    fn handle_model_update(&self) {
+        // The handler may be called from `Layout`, where the table can't be
+        // edited
+        let this_weak = self.downgrade();
+        pal::Wm::global().invoke_on_update(move |_| {
+            if let Some(this) = this_weak.upgrade() {
+                this.update_loaded_messages();
+            }
+        });
+    }
+
+    /// Request older messages if the viewport is near the top of the log.
+    /// Unload messages far above the viewport if too many are loaded.
+    fn update_loaded_messages(&self) {
+        let app_state = self.app_state();
+        let wnd_state = self.wnd_state();
+
+        let (channel_ref, channel) = match wnd_state
+            .selected_channel
+            .as_ref()
+            .and_then(|channel_ref| Some((channel_ref, app_state.channel(channel_ref)?)))
+        {
+            Some(x) => x,
+            None => return,
+        };
+
+        let action = {
+            let mut edit = if let Ok(edit) = self.table().table().edit() {
+                edit
+            } else {
+                return;
+            };
+            let scroll_pos = edit.scroll_pos()[1];
+            let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+
+            // Wait until `update_rows` catches up with the state
+            match &model.shown_messages {
+                Some((shown_ref, shown_messages))
+                    if shown_ref == channel_ref
+                        && Elem::ptr_eq(shown_messages, &channel.messages) => {}
+                _ => return,
+            }
+
+            if scroll_pos < LOAD_MARGIN {
+                if channel.history == model::HistoryState::Partial {
+                    Some(model::AppAction::LoadOlderMessages(channel_ref.clone()))
+                } else {
+                    None
+                }
+            } else if channel.messages.len() > MAX_LOADED_MESSAGES {
+                // Count the messages far above the viewport. Unload them until
+                // the half of `MAX_LOADED_MESSAGES` remains so that this
+                // doesn't happen on every new message.
+                let mut y = 0.0;
+                let mut count = 0;
+                for (row, row_visual) in model.rows.iter().zip(model.row_visuals.iter()) {
+                    y += row_visual.height as f64;
+                    if y > scroll_pos - UNLOAD_MARGIN {
+                        break;
+                    }
+                    if let Row::LogItem(_) = row {
+                        count += 1;
+                    }
+                }
+
+                let count = count.min(channel.messages.len() - MAX_LOADED_MESSAGES / 2);
+                if count > 0 {
+                    Some(model::AppAction::UnloadOlderMessages(
+                        channel_ref.clone(),
+                        count,
+                    ))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        };
+
+        if let Some(action) = action {
+            self.raise_dispatch(action);
+        }
+    }
+
+    /// Handle a click on a link in a message.
+    fn activate_link(&self, target: &LinkTarget) {
+        match target {
+            LinkTarget::Url(url) => {
+                if let Err(e) = opener::open_url(url) {
+                    log::warn!("Could not open {:?}: {}", url, e);
+                }
+            }
+            LinkTarget::Channel(name) => {
+                // Look for the channel in the same account. Do nothing if
+                // it isn't joined.
+                let app_state = self.app_state();
+                let account = (self.wnd_state().selected_channel.as_ref())
+                    .and_then(|channel_ref| app_state.account(channel_ref.account));
+                let channel_ref = account.and_then(|account| {
+                    let channel = account
+                        .channels
+                        .iter()
+                        .find(|channel| name_eq(&channel.name, name))?;
+                    Some(model::ChannelRef {
+                        account: account.id,
+                        channel: channel.name.clone(),
+                    })
+                });
+                if let Some(channel_ref) = channel_ref {
+                    self.raise_dispatch(model::AppAction::Wnd(
+                        self.wnd_state().id,
+                        model::WndAction::SelectChannel(channel_ref),
+                    ));
+                }
+            }
+            LinkTarget::Nick(nick) => {
+                self.raise_mention(nick.clone());
+            }
+        }
+    }
 }
 
 struct TableModelQuery {
@@ -100,14 +402,37 @@ struct TableModelQuery {
     width: f32,
     dpi_scale: f32,
     rows: Vec<Row>,
+    /// The channel and messages represented by `rows`.
+    shown_messages: Option<(model::ChannelRef, Elem<Vec<Elem<model::Message>>>)>,
+    /// The members of the shown channel.
+    members: Elem<Vec<String>>,
+    /// The last value of `WndState::focused_message` handled by
+    /// `scroll_to_focused_message`.
+    focused_message: Option<seglog::RecordPos>,
+    activate_link: Rc<dyn Fn(&LinkTarget)>,
 }
 
 impl table::TableModelQuery for TableModelQuery {
     fn new_view(&mut self, cell: table::CellIdx) -> (HView, Box<dyn table::CellCtrler>) {
+        let row_visual = &self.row_visuals[cell[1] as usize];
+
+        // Put a clickable view on every link
+        let link_views = row_visual.links.iter().flat_map(|link| {
+            let activate_link = &self.activate_link;
+            link.bounds.iter().map(move |bounds| {
+                let view = HView::new(ViewFlags::ACCEPT_MOUSE_DRAG | ViewFlags::ACCEPT_MOUSE_OVER);
+                view.set_cursor_shape(Some(CursorShape::Hand));
+                view.set_listener(LinkViewListener::new(
+                    Rc::clone(activate_link),
+                    link.target.clone(),
+                ));
+                (view, *bounds, AlignFlags::JUSTIFY)
+            })
+        });
+
         let hview = HView::new(Default::default());
-        hview.set_listener(RowViewListener::new(
-            self.row_visuals[cell[1] as usize].clone(),
-        ));
+        hview.set_layout(AbsLayout::new(SizeTraits::default(), link_views));
+        hview.set_listener(RowViewListener::new(row_visual.clone()));
         (hview, Box::new(()))
     }
 
@@ -184,10 +509,113 @@ impl ViewListener for RowViewListener {
     }
 }
 
-// Mock-up log model
+/// A `ViewListener` for a link in a row, which calls `activate_link` when
+/// clicked.
+struct LinkViewListener {
+    button_mixin: ButtonMixin,
+    activate_link: Rc<dyn Fn(&LinkTarget)>,
+    target: LinkTarget,
+}
+
+impl LinkViewListener {
+    fn new(activate_link: Rc<dyn Fn(&LinkTarget)>, target: LinkTarget) -> Self {
+        Self {
+            button_mixin: ButtonMixin::new(),
+            activate_link,
+            target,
+        }
+    }
+}
+
+impl ViewListener for LinkViewListener {
+    fn mouse_drag(
+        &self,
+        _: pal::Wm,
+        _: HViewRef<'_>,
+        _loc: cgmath::Point2<f32>,
+        _button: u8,
+    ) -> Box<dyn MouseDragListener> {
+        self.button_mixin.mouse_drag(Box::new(LinkButtonListener {
+            activate_link: Rc::clone(&self.activate_link),
+            target: self.target.clone(),
+        }))
+    }
+}
+
+struct LinkButtonListener {
+    activate_link: Rc<dyn Fn(&LinkTarget)>,
+    target: LinkTarget,
+}
+
+impl ButtonListener for LinkButtonListener {
+    fn activate(&self, _: pal::Wm, _: HViewRef<'_>) {
+        (self.activate_link)(&self.target);
+    }
+}
+
 enum Row {
     Date(chrono::NaiveDate),
-    LogItem(&'static str, &'static str, String),
+    LogItem(Elem<model::Message>),
+}
+
+impl Row {
+    /// Get the local date associated with the row.
+    fn date(&self) -> chrono::NaiveDate {
+        match self {
+            Row::Date(date) => *date,
+            Row::LogItem(message) => local_date(message),
+        }
+    }
+}
+
+/// Compare two versions of a channel's message list. Returns
+/// `(num_removed, num_prepended)`, meaning `new` can be made by removing
+/// `num_removed` messages from the front of `old`, then prepending
+/// `num_prepended` messages and appending zero or more messages. Returns
+/// `None` if `new` can't be made this way.
+fn diff_messages(
+    old: &[Elem<model::Message>],
+    new: &[Elem<model::Message>],
+) -> Option<(usize, usize)> {
+    let (first, last) = match (old.first(), old.last()) {
+        (Some(first), Some(last)) => (first, last),
+        // All messages are new
+        _ => return Some((0, 0)),
+    };
+
+    let (num_removed, num_prepended) =
+        if let Some(i) = new.iter().position(|m| Elem::ptr_eq(m, first)) {
+            (0, i)
+        } else {
+            let i = old.iter().position(|m| Elem::ptr_eq(m, new.first()?))?;
+            (i, 0)
+        };
+
+    let num_kept = old.len() - num_removed;
+    if Elem::ptr_eq(new.get(num_prepended + num_kept - 1)?, last) {
+        Some((num_removed, num_prepended))
+    } else {
+        None
+    }
+}
+
+/// Find the index of the row representing the `i`-th message.
+fn message_row_index(rows: &[Row], i: usize) -> usize {
+    rows.iter()
+        .enumerate()
+        .filter(|(_, row)| matches!(row, Row::LogItem(_)))
+        .nth(i)
+        .unwrap()
+        .0
+}
+
+/// Get the date when the message was sent in the local time zone.
+fn local_date(message: &model::Message) -> chrono::NaiveDate {
+    message
+        .time
+        .with_timezone(&chrono::Local)
+        .date()
+        .naive_local()
 }
 
 #[derive(Clone)]
@@ -195,28 +623,104 @@ struct RowVisual {
     bmp: pal::Bitmap,
     bmp_bounds: Box2<f32>,
     height: f32,
+    links: Vec<RowLink>,
 }
 
+/// A clickable link in a row.
+#[derive(Clone)]
+struct RowLink {
+    /// The regions occupied by the link, relative to the row. A link may span
+    /// multiple lines.
+    bounds: Vec<Box2<f32>>,
+    target: LinkTarget,
+}
+
+#[derive(Debug, Clone)]
+enum LinkTarget {
+    /// A URL to open in the default application.
+    Url(String),
+    /// A channel to switch to.
+    Channel(String),
+    /// A nickname to insert into the composer.
+    Nick(String),
+}
+
+const LINK_COLOR: pal::RGBAF32 = pal::RGBAF32::new(0.1, 0.4, 0.8, 1.0);
+
 impl RowVisual {
     #[allow(clippy::possible_missing_comma)]
-    fn from_row(row: &Row, row_width: f32, dpi_scale: f32) -> Self {
+    fn from_row(row: &Row, row_width: f32, dpi_scale: f32, members: &[String]) -> Self {
         let v_margin = 3.0;
         let h_margin = 10.0;
         let scrollbar_margin = 12.0;
 
-        let text = match row {
-            Row::Date(d) => d.to_string(),
-            Row::LogItem(name, _, body) => format!("{} @{} {}", name, name, body),
+        let (text, body_start) = match row {
+            Row::Date(d) => (d.to_string(), None),
+            Row::LogItem(message) => {
+                let prefix = match message.kind {
+                    model::MessageKind::Normal => format!("{} ", message.sender),
+                    model::MessageKind::Action => format!("* {} ", message.sender),
+                    model::MessageKind::Notice => format!("-{}- ", message.sender),
+                };
+                (prefix.clone() + &message.body, Some(prefix.len()))
+            }
         };
+
+        // Find links in the message body
+        let links: Vec<linkify::Link> = if let Some(body_start) = body_start {
+            let is_nick = |word: &str| members.iter().any(|nick| name_eq(nick, word));
+            linkify::find_links(&text[body_start..], is_nick)
+                .into_iter()
+                .map(|link| linkify::Link {
+                    range: link.range.start + body_start..link.range.end + body_start,
+                    ..link
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         let char_style = pal::CharStyle::new(pal::CharStyleAttrs {
             ..Default::default()
         });
-        let text_layout = pal::TextLayout::from_text(
+        let link_style = pal::CharStyle::new(pal::CharStyleAttrs {
+            template: Some(char_style.clone()),
+            decor: Some(pal::TextDecorFlags::UNDERLINE),
+            color: Some(Some(LINK_COLOR)),
+            ..Default::default()
+        });
+        let runs: Vec<_> = links
+            .iter()
+            .map(|link| pal::TextRun {
+                range: link.range.clone(),
+                style: link_style.clone(),
+            })
+            .collect();
+
+        let text_layout = pal::TextLayout::from_attr_text(
             &text,
             &char_style,
+            &runs,
             Some(row_width - h_margin * 2.0 - GUTTER_WIDTH - scrollbar_margin),
         );
         let layout_bounds = text_layout.layout_bounds();
+        let text_origin =
+            cgmath::Vector2::new(h_margin + GUTTER_WIDTH, v_margin - layout_bounds.min.y);
+
+        let links = links
+            .iter()
+            .map(|link| RowLink {
+                bounds: range_bounds(&text_layout, link.range.clone())
+                    .into_iter()
+                    .map(|bx| bx.translate(text_origin))
+                    .collect(),
+                target: match link.kind {
+                    LinkKind::Url | LinkKind::Email => LinkTarget::Url(link.url(&text).unwrap()),
+                    LinkKind::Channel => LinkTarget::Channel(link.as_str(&text).to_owned()),
+                    LinkKind::Nick => LinkTarget::Nick(link.as_str(&text).to_owned()),
+                },
+            })
+            .collect();
 
         let row_height = layout_bounds.size().y.ceil() + v_margin * 2.0;
         let bmp_size = [
@@ -261,21 +765,17 @@ impl RowVisual {
                         pal::RGBAF32::new(0.0, 0.0, 0.0, 1.0),
                     );
                 }
-                Row::LogItem(author, time, _) => {
-                    let y = v_margin - text_layout.layout_bounds().min.y;
+                Row::LogItem(message) => {
+                    let y = text_origin.y;
                     builder.draw_text(
                         &text_layout,
-                        [h_margin + GUTTER_WIDTH, y].into(),
+                        [text_origin.x, text_origin.y].into(),
                         pal::RGBAF32::new(0.0, 0.0, 0.0, 1.0),
                     );
 
                     // Avatar
                     let avatar_size = 16.0;
-                    if *author == "bob" {
-                        builder.set_fill_rgb([0.8, 0.4, 0.3, 1.0].into());
-                    } else {
-                        builder.set_fill_rgb([0.1, 0.6, 0.6, 1.0].into());
-                    }
+                    builder.set_fill_rgb(avatar_color(&message.sender));
                     builder.begin_path();
                     builder.rounded_rect(
                         box2! {
@@ -287,7 +787,9 @@ impl RowVisual {
                     builder.fill();
 
                     // Time
-                    let time_text_layout = pal::TextLayout::from_text(time, &char_style, None);
+                    let time = message.time.with_timezone(&chrono::Local).format("%H:%M");
+                    let time_text_layout =
+                        pal::TextLayout::from_text(&time.to_string(), &char_style, None);
                     builder.draw_text(
                         &time_text_layout,
                         [
@@ -310,6 +812,48 @@ impl RowVisual {
             bmp,
             bmp_bounds,
             height: row_height,
+            links,
         }
     }
 }
+
+/// Get the bounding boxes of the text in `range`, one for each run.
+fn range_bounds(text_layout: &pal::TextLayout, range: Range<usize>) -> Vec<Box2<f32>> {
+    let mut boxes = Vec::new();
+    let mut line = text_layout.line_from_index(range.start);
+    let mut start = range.start;
+
+    // `run_metrics_of_range` requires the range to be in a single line
+    while start < range.end && line < text_layout.num_lines() {
+        let line_range = text_layout.line_index_range(line);
+        let end = range.end.min(line_range.end);
+        if start < end {
+            let y = text_layout.line_vertical_bounds(line);
+            boxes.extend(
+                text_layout
+                    .run_metrics_of_range(start..end)
+                    .into_iter()
+                    .map(|run| {
+                        box2! {
+                            min: [run.bounds.start, y.start],
+                            max: [run.bounds.end, y.end],
+                        }
+                    }),
+            );
+        }
+        start = start.max(line_range.end);
+        line += 1;
+    }
+
+    boxes
+}
+
+/// Choose the avatar color for the specified nickname.
+fn avatar_color(nick: &str) -> pal::RGBAF32 {
+    const COLORS: &[[f32; 4]] = &[[0.8, 0.4, 0.3, 1.0], [0.1, 0.6, 0.6, 1.0]];
+
+    let hash = nick
+        .bytes()
+        .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32));
+    COLORS[hash as usize % COLORS.len()].into()
+}
@@ -0,0 +1,208 @@
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+    rc::{Rc, Weak},
+    sync::Arc,
+    time::Duration,
+};
+
+use tcw3::{
+    pal,
+    pal::prelude::*,
+    ui::{
+        layouts::FillLayout,
+        mixins::scrollwheel::ScrollAxisFlags,
+        prelude::*,
+        theming,
+        views::{table, table::LineTy, Label, ScrollableTable},
+    },
+    uicore::{ActionId, HView, HWndRef, SizeTraits, WndListener},
+};
+
+use crate::{i18n, logging, model, stylesheet::elem_id, view::global};
+
+/// The interval at which the log viewer checks for new log records.
+const POLL_INTERVAL: Range<Duration> = Duration::from_millis(300)..Duration::from_millis(600);
+
+/// The height of a row in the log viewer.
+const ROW_HEIGHT: f64 = 18.0;
+
+/// The log viewer window. Displays the records in the in-memory buffer of
+/// the application logger and follows new records as they are logged.
+pub(super) struct LogWndView {
+    wm: pal::Wm,
+    hwnd: tcw3::uicore::HWnd,
+    dispatch: RefCell<Box<dyn Fn(model::AppAction)>>,
+    /// Keeps the styling element of the window alive.
+    _root: theming::StyledBox,
+    table: ScrollableTable,
+    /// The sequence number of the next record to fetch.
+    next_seq: Cell<u64>,
+    poll_timer: Cell<Option<pal::HInvoke>>,
+}
+
+impl LogWndView {
+    pub(super) fn new(wm: pal::Wm, locale: &i18n::Locale) -> Rc<Self> {
+        let hwnd = tcw3::uicore::HWnd::new(wm);
+        let style_manager = theming::Manager::global(wm);
+
+        let table = ScrollableTable::new(style_manager);
+        table.set_scrollable_axes(ScrollAxisFlags::VERTICAL);
+        table.set_flags(table::TableFlags::GROW_LAST_COL);
+        table.set_size_traits(SizeTraits {
+            preferred: [640.0, 400.0].into(),
+            min: [100.0, 60.0].into(),
+            ..Default::default()
+        });
+
+        let root = theming::StyledBox::new(style_manager, Default::default());
+        root.set_class_set(elem_id::LOG_WND);
+        root.set_child(theming::roles::GENERIC, Some(&table));
+
+        {
+            let mut edit = table.table().edit().unwrap();
+            edit.set_model(TableModelQuery {
+                style_manager,
+                records: Vec::new(),
+            });
+            edit.insert(LineTy::Col, 0..1);
+        }
+
+        hwnd.content_view().set_layout(FillLayout::new(root.view()));
+        hwnd.set_caption(locale.text("log-title"));
+        hwnd.set_visibility(true);
+
+        let this = Rc::new(Self {
+            wm,
+            hwnd,
+            dispatch: RefCell::new(Box::new(|_| {})),
+            _root: root,
+            table,
+            next_seq: Cell::new(0),
+            poll_timer: Cell::new(None),
+        });
+
+        this.hwnd.set_listener(LogWndViewWndListener {
+            owner: Rc::downgrade(&this),
+        });
+
+        this.poll();
+        Self::schedule_poll(&this);
+
+        this
+    }
+
+    pub(super) fn set_dispatch(&self, cb: impl Fn(model::AppAction) + 'static) {
+        *self.dispatch.borrow_mut() = Box::new(cb);
+    }
+
+    pub(super) fn set_locale(&self, locale: &i18n::Locale) {
+        self.hwnd.set_caption(locale.text("log-title"));
+    }
+
+    fn schedule_poll(this: &Rc<Self>) {
+        let this_weak = Rc::downgrade(this);
+        let timer = this.wm.invoke_after(POLL_INTERVAL, move |_| {
+            if let Some(this) = this_weak.upgrade() {
+                this.poll();
+                Self::schedule_poll(&this);
+            }
+        });
+        this.poll_timer.set(Some(timer));
+    }
+
+    /// Fetch new log records and append them to the table.
+    fn poll(&self) {
+        let new_records = logging::recent_records(self.next_seq.get());
+        let last_record = if let Some(record) = new_records.last() {
+            record
+        } else {
+            return;
+        };
+        self.next_seq.set(last_record.seq + 1);
+
+        let mut edit = self.table.table().edit().unwrap();
+
+        // Follow new records only if the table is scrolled to the bottom
+        let follow = edit.scroll_pos()[1] >= edit.scroll_limit()[1] - ROW_HEIGHT;
+
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+        let num_old_rows = model.records.len();
+        model.records.extend(new_records.iter().cloned());
+        let num_rows = model.records.len();
+        edit.insert(LineTy::Row, num_old_rows as u64..num_rows as u64);
+
+        // Don't grow beyond the in-memory buffer of the logger
+        let num_removed = num_rows.saturating_sub(logging::RECENT_RECORD_COUNT);
+        if num_removed > 0 {
+            let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+            model.records.drain(0..num_removed);
+            edit.remove(LineTy::Row, 0..num_removed as u64);
+        }
+
+        if follow {
+            let limit = edit.scroll_limit();
+            edit.set_scroll_pos([0.0, limit[1]]);
+        }
+    }
+}
+
+impl Drop for LogWndView {
+    fn drop(&mut self) {
+        if let Some(timer) = self.poll_timer.take() {
+            self.wm.cancel_invoke(&timer);
+        }
+    }
+}
+
+struct LogWndViewWndListener {
+    owner: Weak<LogWndView>,
+}
+
+impl WndListener for LogWndViewWndListener {
+    fn close(&self, _: pal::Wm, _: HWndRef<'_>) {
+        if let Some(owner) = self.owner.upgrade() {
+            owner.dispatch.borrow()(model::AppAction::HideLog);
+        }
+    }
+
+    fn interpret_event(
+        &self,
+        _: pal::Wm,
+        _: HWndRef<'_>,
+        ctx: &mut tcw3::uicore::InterpretEventCtx<'_>,
+    ) {
+        global::interpret_event(ctx);
+    }
+
+    fn perform_action(&self, _: pal::Wm, _: HWndRef<'_>, _: ActionId) {
+        // TODO: `global::QUIT` should be handled as an application-global action
+    }
+}
+
+struct TableModelQuery {
+    style_manager: &'static theming::Manager,
+    records: Vec<Arc<logging::LogRecord>>,
+}
+
+impl table::TableModelQuery for TableModelQuery {
+    fn new_view(&mut self, cell: table::CellIdx) -> (HView, Box<dyn table::CellCtrler>) {
+        let record = &self.records[cell[1] as usize];
+
+        let label = Label::new(self.style_manager);
+        label.set_class_set(elem_id::LOG_WND_RECORD);
+        label.set_text(record.to_string());
+
+        (label.view(), Box::new((label,)))
+    }
+
+    fn range_size(&mut self, line_ty: LineTy, range: Range<u64>, _approx: bool) -> f64 {
+        match line_ty {
+            LineTy::Row => (range.end - range.start) as f64 * ROW_HEIGHT,
+
+            // `TableFlags::GROW_LAST_COL` expands the column to cover the region.
+            // The column needs some width for this flag to work.
+            LineTy::Col => (range.end - range.start) as f64,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use harmony::Elem;
 use std::{
     cell::RefCell,
     rc::{Rc, Weak},
@@ -9,7 +10,7 @@ use tcw3::{
     uicore::{ActionId, HWnd, HWndRef, WndListener, WndStyleFlags},
 };
 
-use crate::{model, stylesheet, view::global};
+use crate::{i18n, model, stylesheet, view::global};
 
 // TODO: Most of these are copypasta of `WndView`, which hopefully we should
 //       refactor.
@@ -20,19 +21,21 @@ pub(super) struct PrefWndView {
 }
 
 impl PrefWndView {
-    pub(super) fn new(wm: pal::Wm) -> Rc<Self> {
+    pub(super) fn new(wm: pal::Wm, settings: Elem<model::Settings>) -> Rc<Self> {
         let hwnd = HWnd::new(wm);
         let style_manager = theming::Manager::global(wm);
+        let locale = i18n::Locale::new(settings.language.as_deref());
 
         let pref_view = PrefViewBuilder::new()
             .with_wm(wm)
             .with_style_manager(style_manager)
+            .with_settings(settings)
             .build();
 
         hwnd.content_view()
             .set_layout(FillLayout::new(pref_view.view().clone()));
 
-        hwnd.set_caption("Preferences");
+        hwnd.set_caption(locale.text("pref-title"));
         Self::update_wnd_style_flags(hwnd.as_ref(), false);
         hwnd.set_visibility(true);
 
@@ -77,6 +80,12 @@ impl PrefWndView {
         *self.dispatch.borrow_mut() = Box::new(cb);
     }
 
+    pub(super) fn set_settings(&self, settings: Elem<model::Settings>) {
+        let locale = i18n::Locale::new(settings.language.as_deref());
+        self.hwnd.set_caption(locale.text("pref-title"));
+        self.pref_view.set_settings(settings);
+    }
+
     fn update_wnd_style_flags(hwnd: HWndRef, is_focused: bool) {
         hwnd.set_style_flags(
             if stylesheet::ENABLE_BACKDROP_BLUR && is_focused {
@@ -124,3 +133,67 @@ impl WndListener for PrefWndViewWndListener {
 stella2_meta::designer_impl! {
     crate::view::prefwnd::PrefView
 }
+
+/// The time formats selectable in the preferences and the IDs of their
+/// captions.
+pub(crate) const TIME_FORMATS: &[(model::TimeFormat, &str)] = &[
+    (model::TimeFormat::Hour12, "pref-time-format-12h"),
+    (model::TimeFormat::Hour24, "pref-time-format-24h"),
+];
+
+/// The color themes selectable in the preferences and the IDs of their
+/// captions.
+pub(crate) const COLOR_THEMES: &[(model::ColorTheme, &str)] =
+    &[(model::ColorTheme::Light, "pref-color-theme-light")];
+
+/// The proxy settings selectable in the preferences and the IDs of their
+/// captions.
+pub(crate) const PROXIES: &[(model::Proxy, &str)] = &[(model::Proxy::Direct, "pref-proxy-none")];
+
+/// The log levels selectable in the preferences and the IDs of their
+/// captions.
+pub(crate) const LOG_LEVELS: &[(log::LevelFilter, &str)] = &[
+    (log::LevelFilter::Off, "pref-log-level-off"),
+    (log::LevelFilter::Error, "pref-log-level-error"),
+    (log::LevelFilter::Warn, "pref-log-level-warn"),
+    (log::LevelFilter::Info, "pref-log-level-info"),
+    (log::LevelFilter::Debug, "pref-log-level-debug"),
+    (log::LevelFilter::Trace, "pref-log-level-trace"),
+];
+
+/// Get the index of `value` in a list such as `LOG_LEVELS`.
+pub(crate) fn item_index<T: PartialEq>(items: &[(T, &str)], value: T) -> u32 {
+    (items.iter()).position(|(x, _)| *x == value).unwrap_or(0) as u32
+}
+
+/// Convert a font size to a position on the font size slider, which has a
+/// tick for each value in `Settings::FONT_SIZES`.
+pub(crate) fn font_size_to_slider(font_size: u32) -> f64 {
+    let range = model::Settings::FONT_SIZES;
+    (font_size - range.start()) as f64 / (range.end() - range.start()) as f64
+}
+
+/// The inverse of `font_size_to_slider`.
+pub(crate) fn font_size_from_slider(value: f64) -> u32 {
+    let range = model::Settings::FONT_SIZES;
+    range.start() + (value * (range.end() - range.start()) as f64).round() as u32
+}
+
+/// Get the index of `language` in the language list, where `None` (the
+/// system language) comes first and `i18n::LANGUAGES` follows.
+pub(crate) fn language_index(language: Option<&str>) -> u32 {
+    language
+        .and_then(|language| {
+            (i18n::LANGUAGES.iter())
+                .position(|&(tag, _)| tag == language)
+                .map(|i| i as u32 + 1)
+        })
+        .unwrap_or(0)
+}
+
+/// The inverse of `language_index`.
+pub(crate) fn language_from_index(i: u32) -> Option<String> {
+    (i as usize)
+        .checked_sub(1)
+        .map(|i| i18n::LANGUAGES[i].0.to_owned())
+}
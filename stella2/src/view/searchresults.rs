@@ -0,0 +1,179 @@
+use harmony::Elem;
+use std::{ops::Range, rc::Rc};
+use tcw3::{
+    ui::{
+        layouts::FillLayout,
+        prelude::*,
+        theming,
+        views::{table, table::LineTy, Label},
+    },
+    uicore::{HView, HViewRef, ViewFlags},
+};
+
+use crate::{model, stylesheet::elem_id, view::channellist::ActionRowViewListener};
+
+stella2_meta::designer_impl! {
+    crate::view::searchresults::SearchResultsView
+}
+
+impl SearchResultsView {
+    fn init(&self) {
+        let this_weak = self.downgrade();
+        let dispatch = move |action| {
+            if let Some(this) = this_weak.upgrade() {
+                this.raise_dispatch(action);
+            }
+        };
+
+        // Set up the table model
+        {
+            let mut edit = self.table().table().edit().unwrap();
+            edit.set_model(TableModelQuery {
+                style_manager: self.style_manager(),
+                elem: Rc::clone(self.elem()),
+                dispatch: Rc::new(dispatch),
+                rows: Vec::new(),
+                shown_search: None,
+            });
+            edit.insert(LineTy::Col, 0..1);
+            edit.set_scroll_pos([0.0, 0.0]);
+        }
+
+        self.update_rows();
+    }
+
+    /// Update the table rows to reflect the search results in the app state.
+    fn update_rows(&self) {
+        let search = Elem::clone(&self.app_state().search);
+
+        let mut edit = self.table().table().edit().unwrap();
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+
+        if let Some(shown_search) = &model.shown_search {
+            if Elem::ptr_eq(shown_search, &search) {
+                return;
+            }
+        }
+
+        // The results are replaced as a whole, so just re-create all rows
+        let num_old_rows = model.rows.len() as u64;
+        edit.remove(LineTy::Row, 0..num_old_rows);
+
+        let model: &mut TableModelQuery = edit.model_downcast_mut().unwrap();
+        model.rows.clear();
+
+        if let Some(hits) = &search.hits {
+            model.rows.push(Row::Status(match hits.len() {
+                0 => "No results".to_owned(),
+                1 => "1 result".to_owned(),
+                n => format!("{} results", n),
+            }));
+            model.rows.extend(hits.iter().cloned().map(Row::Hit));
+        } else {
+            model.rows.push(Row::Status("Searching...".to_owned()));
+        }
+
+        model.shown_search = Some(search);
+
+        let num_rows = model.rows.len() as u64;
+        edit.insert(LineTy::Row, 0..num_rows);
+        edit.set_scroll_pos([0.0, 0.0]);
+    }
+}
+
+impl theming::Widget for SearchResultsView {
+    fn view_ref(&self) -> HViewRef<'_> {
+        self.view().as_ref()
+    }
+
+    fn style_elem(&self) -> Option<theming::HElem> {
+        Some(self.style_elem())
+    }
+}
+
+/// Represents a row displayed in `SearchResultsView`.
+enum Row {
+    /// The number of results or the progress.
+    Status(String),
+    Hit(model::SearchHit),
+}
+
+struct TableModelQuery {
+    style_manager: &'static theming::Manager,
+    elem: Rc<theming::Elem>,
+    dispatch: Rc<dyn Fn(model::WndAction)>,
+    rows: Vec<Row>,
+    /// The search state represented by `rows`.
+    shown_search: Option<Elem<model::SearchState>>,
+}
+
+impl table::TableModelQuery for TableModelQuery {
+    fn new_view(&mut self, cell: table::CellIdx) -> (HView, Box<dyn table::CellCtrler>) {
+        let row = &self.rows[cell[1] as usize];
+
+        let label = Label::new(self.style_manager);
+        match row {
+            Row::Status(text) => label.set_text(text.as_str()),
+            Row::Hit(hit) => label.set_text(hit_text(hit)),
+        }
+
+        let wrap = theming::StyledBox::new(self.style_manager, Default::default());
+        wrap.set_child(theming::roles::GENERIC, Some(&label));
+        wrap.set_class_set(match row {
+            Row::Status(_) => elem_id::SIDEBAR_GROUP_HEADER | theming::ClassSet::ACTIVE,
+            Row::Hit(_) => elem_id::SIDEBAR_ITEM,
+        });
+
+        self.elem.insert_child(wrap.style_elem());
+
+        match row {
+            Row::Status(_) => (wrap.view(), Box::new((wrap,))),
+            Row::Hit(hit) => {
+                // Show the message when clicked
+                let view = HView::new(ViewFlags::ACCEPT_MOUSE_DRAG);
+                view.set_layout(FillLayout::new(wrap.view()));
+                view.set_listener(ActionRowViewListener::new(
+                    Rc::clone(&self.dispatch),
+                    model::WndAction::ShowSearchHit(hit.clone()),
+                ));
+
+                (view, Box::new((wrap,)))
+            }
+        }
+    }
+
+    fn range_size(&mut self, line_ty: LineTy, range: Range<u64>, _approx: bool) -> f64 {
+        match line_ty {
+            LineTy::Row => self.rows[range.start as usize..range.end as usize]
+                .iter()
+                .map(|row| match row {
+                    Row::Status(_) => 25.0,
+                    Row::Hit(_) => 20.0,
+                })
+                .sum(),
+
+            // `TableFlags::GROW_LAST_COL` expands the column to cover the region.
+            // The column needs some width for this flag to work.
+            LineTy::Col => (range.end - range.start) as f64,
+        }
+    }
+}
+
+/// Get the text displayed for a search result, e.g., `#general <alice> hi`.
+fn hit_text(hit: &model::SearchHit) -> String {
+    let message = &hit.message;
+    match message.kind {
+        model::MessageKind::Normal => format!(
+            "{} <{}> {}",
+            hit.channel.channel, message.sender, message.body
+        ),
+        model::MessageKind::Action => format!(
+            "{} * {} {}",
+            hit.channel.channel, message.sender, message.body
+        ),
+        model::MessageKind::Notice => format!(
+            "{} -{}- {}",
+            hit.channel.channel, message.sender, message.body
+        ),
+    }
+}
@@ -10,7 +10,10 @@ impl ToolbarView {
     /// Handle `toggle_sidebar_button.activate` event.
     fn toggle_sidebar(&self) {
         // Toggle the sidebar
-        self.raise_dispatch(model::AppAction::Wnd(model::WndAction::ToggleSidebar));
+        self.raise_dispatch(model::AppAction::Wnd(
+            self.wnd_state().id,
+            model::WndAction::ToggleSidebar,
+        ));
     }
 
     /// Handle `menu_button.activate` event.
@@ -21,6 +24,28 @@ impl ToolbarView {
     }
 }
 
+/// Get the text displayed by `member_count`.
+fn member_count_text(wnd_state: &model::WndState, app_state: &model::AppState) -> String {
+    selected_channel(wnd_state, app_state)
+        .map(|channel| channel.members.len().to_string())
+        .unwrap_or_default()
+}
+
+/// Get the text displayed by `topic`.
+fn topic_text(wnd_state: &model::WndState, app_state: &model::AppState) -> String {
+    selected_channel(wnd_state, app_state)
+        .and_then(|channel| channel.topic.clone())
+        .unwrap_or_default()
+}
+
+fn selected_channel<'a>(
+    wnd_state: &model::WndState,
+    app_state: &'a model::AppState,
+) -> Option<&'a model::Channel> {
+    let channel_ref = wnd_state.selected_channel.as_ref()?;
+    app_state.channel(channel_ref).map(|channel| &**channel)
+}
+
 impl theming::Widget for ToolbarView {
     fn view_ref(&self) -> HViewRef<'_> {
         self.view().as_ref()
@@ -0,0 +1,638 @@
+//! The protocol state machine of an IRC client
+//!
+//! [`Client`] doesn't perform I/O by itself. Incoming messages are fed through
+//! [`Client::handle_message`], and outgoing messages and client events are
+//! retrieved by [`Client::pop_outgoing`] and [`Client::pop_event`],
+//! respectively.
+use std::collections::{HashMap, VecDeque};
+
+use crate::message::{ctcp, is_channel_name, name_eq, parse_ctcp, Message};
+
+/// The parameters for connection registration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub nick: String,
+    pub user: String,
+    pub real_name: String,
+    /// The connection password (`PASS`).
+    pub password: Option<String>,
+    /// The channels to join after the registration completes.
+    pub autojoin: Vec<String>,
+}
+
+impl Config {
+    /// Construct a `Config` using `nick` for all names.
+    pub fn with_nick(nick: &str) -> Self {
+        Self {
+            nick: nick.to_owned(),
+            user: nick.to_owned(),
+            real_name: nick.to_owned(),
+            password: None,
+            autojoin: Vec::new(),
+        }
+    }
+}
+
+/// An event produced by [`Client`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The connection registration has completed.
+    Registered { nick: String },
+    /// Someone (possibly us) has joined a channel.
+    Join {
+        channel: String,
+        nick: String,
+        is_self: bool,
+    },
+    /// Someone (possibly us) has left a channel.
+    Part {
+        channel: String,
+        nick: String,
+        reason: Option<String>,
+        is_self: bool,
+    },
+    /// Someone has disconnected from the server.
+    Quit {
+        nick: String,
+        reason: Option<String>,
+    },
+    /// Someone (possibly us) has changed their nickname.
+    NickChanged {
+        old_nick: String,
+        new_nick: String,
+        is_self: bool,
+    },
+    /// A message has been sent to a channel or a user. This includes the
+    /// messages sent by us.
+    Message {
+        /// The channel name or our nickname (for private messages received)
+        /// or the recipient's nickname (for private messages sent by us).
+        target: String,
+        sender: String,
+        kind: MessageKind,
+        body: String,
+    },
+    /// The topic of a channel has been reported or changed.
+    Topic {
+        channel: String,
+        topic: Option<String>,
+    },
+    /// The member list of a channel has been reported.
+    Names { channel: String, nicks: Vec<String> },
+    /// The server has reported an error.
+    ServerError { message: String },
+    /// The connection has been closed.
+    Disconnected,
+}
+
+/// The kind of [`Event::Message`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Privmsg,
+    Notice,
+    /// CTCP `ACTION` (`/me`).
+    Action,
+}
+
+/// The protocol state machine of an IRC client.
+#[derive(Debug)]
+pub struct Client {
+    config: Config,
+    /// Our current nickname.
+    nick: String,
+    registered: bool,
+    /// The number of alternative nicknames tried because of
+    /// `ERR_NICKNAMEINUSE`.
+    nick_retries: u32,
+    outgoing: VecDeque<Message>,
+    events: VecDeque<Event>,
+    /// The `RPL_NAMREPLY` replies received so far, which are reported when
+    /// `RPL_ENDOFNAMES` is received.
+    pending_names: HashMap<String, Vec<String>>,
+}
+
+// Numeric replies
+const RPL_WELCOME: &str = "001";
+const RPL_NOTOPIC: &str = "331";
+const RPL_TOPIC: &str = "332";
+const RPL_NAMREPLY: &str = "353";
+const RPL_ENDOFNAMES: &str = "366";
+const ERR_NICKNAMEINUSE: &str = "433";
+
+/// The maximum length of a message including the trailing CR-LF.
+const MAX_MESSAGE_LEN: usize = 512;
+
+/// The maximum length of a hostname, which is assumed when estimating the
+/// length of the prefix added by the server.
+const MAX_HOST_LEN: usize = 63;
+
+/// The number of alternative nicknames to try before giving up the
+/// registration.
+const MAX_NICK_RETRIES: u32 = 5;
+
+impl Client {
+    /// Construct a `Client` and queue the messages for connection
+    /// registration.
+    pub fn new(config: Config) -> Self {
+        let mut this = Self {
+            nick: config.nick.clone(),
+            config,
+            registered: false,
+            nick_retries: 0,
+            outgoing: VecDeque::new(),
+            events: VecDeque::new(),
+            pending_names: HashMap::new(),
+        };
+
+        if let Some(password) = &this.config.password {
+            let msg = Message::new("PASS", &[password]);
+            this.outgoing.push_back(msg);
+        }
+        let msg = Message::new("NICK", &[&this.nick]);
+        this.outgoing.push_back(msg);
+        let msg = Message::new(
+            "USER",
+            &[&this.config.user, "0", "*", &this.config.real_name],
+        );
+        this.outgoing.push_back(msg);
+
+        this
+    }
+
+    /// Get our current nickname.
+    pub fn nick(&self) -> &str {
+        &self.nick
+    }
+
+    /// Get a flag indicating whether the connection registration has
+    /// completed.
+    pub fn is_registered(&self) -> bool {
+        self.registered
+    }
+
+    /// Remove and return the next message to be sent to the server.
+    pub fn pop_outgoing(&mut self) -> Option<Message> {
+        self.outgoing.pop_front()
+    }
+
+    /// Remove and return the next event.
+    pub fn pop_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// Queue an event. This is used to report conditions only known by the
+    /// transport layer, such as [`Event::Disconnected`].
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Send a raw message.
+    pub fn send(&mut self, msg: Message) {
+        self.outgoing.push_back(msg);
+    }
+
+    pub fn join(&mut self, channel: &str) {
+        self.send(Message::new("JOIN", &[channel]));
+    }
+
+    pub fn part(&mut self, channel: &str, reason: Option<&str>) {
+        if let Some(reason) = reason {
+            self.send(Message::new("PART", &[channel, reason]));
+        } else {
+            self.send(Message::new("PART", &[channel]));
+        }
+    }
+
+    /// Send `PRIVMSG`. Each line of `body` is sent as a separate message.
+    /// A line too long to fit in a message is split into multiple ones.
+    pub fn privmsg(&mut self, target: &str, body: &str) {
+        self.send_text(target, MessageKind::Privmsg, body);
+    }
+
+    /// Send `NOTICE`. Each line of `body` is sent as a separate message.
+    /// A line too long to fit in a message is split into multiple ones.
+    pub fn notice(&mut self, target: &str, body: &str) {
+        self.send_text(target, MessageKind::Notice, body);
+    }
+
+    /// Send CTCP `ACTION` (`/me`).
+    pub fn action(&mut self, target: &str, body: &str) {
+        self.send_text(target, MessageKind::Action, body);
+    }
+
+    pub fn quit(&mut self, reason: Option<&str>) {
+        if let Some(reason) = reason {
+            self.send(Message::new("QUIT", &[reason]));
+        } else {
+            self.send(Message::new("QUIT", &[]));
+        }
+    }
+
+    fn send_text(&mut self, target: &str, kind: MessageKind, body: &str) {
+        let max_len = self.max_text_len(target, kind);
+        for line in body.lines().flat_map(|line| split_text(line, max_len)) {
+            let msg = match kind {
+                MessageKind::Privmsg => Message::new("PRIVMSG", &[target, line]),
+                MessageKind::Notice => Message::new("NOTICE", &[target, line]),
+                MessageKind::Action => Message::new("PRIVMSG", &[target, &ctcp("ACTION", line)]),
+            };
+            self.send(msg);
+
+            // The server doesn't echo our messages back
+            self.events.push_back(Event::Message {
+                target: target.to_owned(),
+                sender: self.nick.clone(),
+                kind,
+                body: line.to_owned(),
+            });
+        }
+    }
+
+    /// Get the maximum length of the text that can be sent by a single
+    /// message of the specified kind, taking the prefix added by the server
+    /// (`:nick!user@host `) into account.
+    fn max_text_len(&self, target: &str, kind: MessageKind) -> usize {
+        // The server might prepend `~` to the user name
+        let prefix_len = ":!~@ ".len() + self.nick.len() + self.config.user.len() + MAX_HOST_LEN;
+        let command = match kind {
+            MessageKind::Privmsg | MessageKind::Action => "PRIVMSG",
+            MessageKind::Notice => "NOTICE",
+        };
+        // `<command> <target> :<text>\r\n`
+        let mut overhead = prefix_len + command.len() + target.len() + "  :\r\n".len();
+        if kind == MessageKind::Action {
+            overhead += "\x01ACTION \x01".len();
+        }
+
+        // Make sure any character fits
+        MAX_MESSAGE_LEN.saturating_sub(overhead).max(4)
+    }
+
+    fn is_self(&self, nick: &str) -> bool {
+        name_eq(nick, &self.nick)
+    }
+
+    /// Process a message received from the server.
+    pub fn handle_message(&mut self, msg: &Message) {
+        let nick = msg.source_nick().unwrap_or("").to_owned();
+
+        match msg.command.as_str() {
+            "PING" => {
+                let params: Vec<&str> = msg.params.iter().map(String::as_str).collect();
+                self.send(Message::new("PONG", &params));
+            }
+            RPL_WELCOME => {
+                // The first parameter is the nickname actually assigned to us
+                if let Some(assigned_nick) = msg.param(0) {
+                    self.nick = assigned_nick.to_owned();
+                }
+                self.registered = true;
+                self.events.push_back(Event::Registered {
+                    nick: self.nick.clone(),
+                });
+
+                if !self.config.autojoin.is_empty() {
+                    let channels = self.config.autojoin.join(",");
+                    self.join(&channels);
+                }
+            }
+            ERR_NICKNAMEINUSE if !self.registered => {
+                if self.nick_retries >= MAX_NICK_RETRIES {
+                    self.events.push_back(Event::ServerError {
+                        message: format!("The nickname {:?} is already in use", self.nick),
+                    });
+                    self.quit(None);
+                    return;
+                }
+
+                // Try another nickname
+                self.nick_retries += 1;
+                self.nick.push('_');
+                let msg = Message::new("NICK", &[&self.nick]);
+                self.send(msg);
+            }
+            "ERROR" => {
+                self.events.push_back(Event::ServerError {
+                    message: msg.param(0).unwrap_or("").to_owned(),
+                });
+            }
+            "JOIN" => {
+                if let Some(channel) = msg.param(0) {
+                    self.events.push_back(Event::Join {
+                        channel: channel.to_owned(),
+                        is_self: self.is_self(&nick),
+                        nick,
+                    });
+                }
+            }
+            "PART" => {
+                if let Some(channel) = msg.param(0) {
+                    self.events.push_back(Event::Part {
+                        channel: channel.to_owned(),
+                        reason: msg.param(1).map(ToOwned::to_owned),
+                        is_self: self.is_self(&nick),
+                        nick,
+                    });
+                }
+            }
+            "QUIT" => {
+                self.events.push_back(Event::Quit {
+                    nick,
+                    reason: msg.param(0).map(ToOwned::to_owned),
+                });
+            }
+            "NICK" => {
+                if let Some(new_nick) = msg.param(0) {
+                    let is_self = self.is_self(&nick);
+                    if is_self {
+                        self.nick = new_nick.to_owned();
+                    }
+                    self.events.push_back(Event::NickChanged {
+                        old_nick: nick,
+                        new_nick: new_nick.to_owned(),
+                        is_self,
+                    });
+                }
+            }
+            "PRIVMSG" | "NOTICE" => {
+                let (target, body) = match (msg.param(0), msg.param(1)) {
+                    (Some(target), Some(body)) => (target, body),
+                    _ => return,
+                };
+
+                let (kind, body) = match (msg.command.as_str(), parse_ctcp(body)) {
+                    ("PRIVMSG", Some(("ACTION", args))) => (MessageKind::Action, args),
+                    (_, Some((command, args))) => {
+                        log::debug!(
+                            "Ignoring an unsupported CTCP message from {:?}: {:?} {:?}",
+                            nick,
+                            command,
+                            args
+                        );
+                        return;
+                    }
+                    ("PRIVMSG", None) => (MessageKind::Privmsg, body),
+                    (_, None) => (MessageKind::Notice, body),
+                };
+
+                self.events.push_back(Event::Message {
+                    target: target.to_owned(),
+                    sender: nick,
+                    kind,
+                    body: body.to_owned(),
+                });
+            }
+            "TOPIC" => {
+                if let Some(channel) = msg.param(0) {
+                    let topic = msg.param(1).filter(|t| !t.is_empty());
+                    self.events.push_back(Event::Topic {
+                        channel: channel.to_owned(),
+                        topic: topic.map(ToOwned::to_owned),
+                    });
+                }
+            }
+            RPL_TOPIC => {
+                // `<client> <channel> :<topic>`
+                if let (Some(channel), Some(topic)) = (msg.param(1), msg.param(2)) {
+                    self.events.push_back(Event::Topic {
+                        channel: channel.to_owned(),
+                        topic: Some(topic.to_owned()),
+                    });
+                }
+            }
+            RPL_NOTOPIC => {
+                // `<client> <channel> :No topic is set`
+                if let Some(channel) = msg.param(1) {
+                    self.events.push_back(Event::Topic {
+                        channel: channel.to_owned(),
+                        topic: None,
+                    });
+                }
+            }
+            RPL_NAMREPLY => {
+                // `<client> <symbol> <channel> :[prefix]<nick>{ [prefix]<nick>}`
+                if let (Some(channel), Some(names)) = (msg.param(2), msg.param(3)) {
+                    let nicks = self.pending_names.entry(channel.to_owned()).or_default();
+                    nicks.extend(
+                        names
+                            .split(' ')
+                            .filter(|n| !n.is_empty())
+                            .map(|n| n.trim_start_matches(|c| "~&@%+".contains(c)).to_owned()),
+                    );
+                }
+            }
+            RPL_ENDOFNAMES => {
+                // `<client> <channel> :End of /NAMES list`
+                if let Some(channel) = msg.param(1) {
+                    let nicks = self.pending_names.remove(channel).unwrap_or_default();
+                    self.events.push_back(Event::Names {
+                        channel: channel.to_owned(),
+                        nicks,
+                    });
+                }
+            }
+            _ => {
+                log::trace!("Ignoring an unhandled message: {:?}", msg);
+            }
+        }
+    }
+}
+
+/// Get the name of the conversation a message belongs to. For channel
+/// messages, this is the channel name. For private messages, this is the
+/// nickname of the other party.
+pub fn conversation_name<'a>(target: &'a str, sender: &'a str, own_nick: &str) -> &'a str {
+    if is_channel_name(target) || !name_eq(target, own_nick) {
+        target
+    } else {
+        sender
+    }
+}
+
+/// Split `text` into chunks not longer than `max_len` bytes at character
+/// boundaries. `max_len` must be at least 4.
+fn split_text(mut text: &str, max_len: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    while text.len() > max_len {
+        let mut i = max_len;
+        while !text.is_char_boundary(i) {
+            i -= 1;
+        }
+        chunks.push(&text[..i]);
+        text = &text[i..];
+    }
+    chunks.push(text);
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered_client() -> Client {
+        let mut client = Client::new(Config::with_nick("alice"));
+        client.handle_message(&Message::new("001", &["alice", "Welcome"]));
+        while client.pop_outgoing().is_some() {}
+        while client.pop_event().is_some() {}
+        client
+    }
+
+    #[test]
+    fn registration() {
+        let mut client = Client::new(Config {
+            password: Some("hunter2".to_owned()),
+            ..Config::with_nick("alice")
+        });
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("PASS", &["hunter2"]))
+        );
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("NICK", &["alice"]))
+        );
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("USER", &["alice", "0", "*", "alice"]))
+        );
+        assert_eq!(client.pop_outgoing(), None);
+        assert!(!client.is_registered());
+
+        client.handle_message(&Message::new("433", &["*", "alice", "in use"]));
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("NICK", &["alice_"]))
+        );
+
+        client.handle_message(&Message::new("001", &["alice_", "Welcome"]));
+        assert!(client.is_registered());
+        assert_eq!(
+            client.pop_event(),
+            Some(Event::Registered {
+                nick: "alice_".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn autojoin() {
+        let mut client = Client::new(Config {
+            autojoin: vec!["#a".to_owned(), "#b".to_owned()],
+            ..Config::with_nick("alice")
+        });
+        while client.pop_outgoing().is_some() {}
+
+        client.handle_message(&Message::new("001", &["alice", "Welcome"]));
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("JOIN", &["#a,#b"]))
+        );
+    }
+
+    #[test]
+    fn ping() {
+        let mut client = registered_client();
+        client.handle_message(&Message::new("PING", &["token"]));
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("PONG", &["token"]))
+        );
+    }
+
+    #[test]
+    fn names_are_accumulated() {
+        let mut client = registered_client();
+        client.handle_message(&Message::new("353", &["alice", "=", "#a", "@alice +bob"]));
+        client.handle_message(&Message::new("353", &["alice", "=", "#a", "carol"]));
+        assert_eq!(client.pop_event(), None);
+        client.handle_message(&Message::new("366", &["alice", "#a", "End"]));
+        assert_eq!(
+            client.pop_event(),
+            Some(Event::Names {
+                channel: "#a".to_owned(),
+                nicks: vec!["alice".to_owned(), "bob".to_owned(), "carol".to_owned()],
+            })
+        );
+    }
+
+    #[test]
+    fn multiline_privmsg() {
+        let mut client = registered_client();
+        client.privmsg("#a", "hello\nworld");
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("PRIVMSG", &["#a", "hello"]))
+        );
+        assert_eq!(
+            client.pop_outgoing(),
+            Some(Message::new("PRIVMSG", &["#a", "world"]))
+        );
+        assert_eq!(client.pop_outgoing(), None);
+    }
+
+    #[test]
+    fn long_privmsg_is_split() {
+        let mut client = registered_client();
+        let body = "aあ".repeat(200);
+        client.privmsg("#a", &body);
+
+        let mut sent = String::new();
+        while let Some(msg) = client.pop_outgoing() {
+            let text = msg.param(1).unwrap();
+            assert!(!text.is_empty());
+            sent.push_str(text);
+
+            // The message relayed by the server must fit in the limit
+            let relayed = format!(":alice!~alice@{} {}\r\n", "h".repeat(MAX_HOST_LEN), msg);
+            assert!(relayed.len() <= MAX_MESSAGE_LEN, "{:?}", relayed);
+
+            // The local echo matches what was sent
+            assert_eq!(
+                client.pop_event(),
+                Some(Event::Message {
+                    target: "#a".to_owned(),
+                    sender: "alice".to_owned(),
+                    kind: MessageKind::Privmsg,
+                    body: text.to_owned(),
+                })
+            );
+        }
+        assert_eq!(sent, body);
+    }
+
+    #[test]
+    fn split_text_at_char_boundaries() {
+        assert_eq!(split_text("", 4), vec![""]);
+        assert_eq!(split_text("abcd", 4), vec!["abcd"]);
+        assert_eq!(split_text("abcde", 4), vec!["abcd", "e"]);
+        assert_eq!(split_text("aあい", 4), vec!["aあ", "い"]);
+    }
+
+    #[test]
+    fn nick_retries_are_bounded() {
+        let mut client = Client::new(Config::with_nick("alice"));
+        while client.pop_outgoing().is_some() {}
+
+        for _ in 0..MAX_NICK_RETRIES {
+            client.handle_message(&Message::new("433", &["*", "alice", "in use"]));
+            assert!(matches!(
+                client.pop_outgoing(),
+                Some(Message { ref command, .. }) if command == "NICK"
+            ));
+        }
+
+        client.handle_message(&Message::new("433", &["*", "alice", "in use"]));
+        assert!(matches!(
+            client.pop_event(),
+            Some(Event::ServerError { .. })
+        ));
+        assert_eq!(client.pop_outgoing(), Some(Message::new("QUIT", &[])));
+    }
+
+    #[test]
+    fn conversation() {
+        assert_eq!(conversation_name("#a", "bob", "alice"), "#a");
+        assert_eq!(conversation_name("Alice", "bob", "alice"), "bob");
+        assert_eq!(conversation_name("bob", "alice", "alice"), "bob");
+    }
+}
@@ -0,0 +1,239 @@
+//! Drives [`Client`] over a byte stream
+use std::{
+    io::{self, prelude::*, BufReader},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    client::{Client, Config, Event},
+    message::{Message, ParseError},
+};
+
+/// A connection to an IRC server.
+///
+/// Incoming messages are processed by a background thread. The command
+/// methods (e.g., [`Connection::privmsg`]) don't block; the commands are
+/// queued and sent by another background thread. Events are delivered to the
+/// handler supplied to the constructor, which is called by either of the
+/// background threads. The handler must not call the methods of `Connection`
+/// by itself.
+///
+/// Dropping `Connection` doesn't close the connection, though the queued
+/// commands are still sent. Use [`Connection::quit`] to disconnect
+/// gracefully.
+pub struct Connection {
+    shared: Arc<Shared>,
+    /// Sends commands to the writer thread.
+    commands: Mutex<mpsc::Sender<Command>>,
+}
+
+type Command = Box<dyn FnOnce(&mut Client) + Send>;
+
+struct Shared {
+    state: Mutex<State>,
+    handler: Mutex<Box<dyn FnMut(Event) + Send>>,
+    /// Set when the connection is closed, before `Event::Disconnected` is
+    /// delivered.
+    closed: AtomicBool,
+}
+
+struct State {
+    client: Client,
+    writer: Box<dyn Write + Send>,
+}
+
+impl Connection {
+    /// Connect to the IRC server at the specified address using TCP.
+    pub fn connect(
+        addr: impl ToSocketAddrs,
+        config: Config,
+        handler: impl FnMut(Event) + Send + 'static,
+    ) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let reader = stream.try_clone()?;
+        Self::spawn(reader, stream, config, handler)
+    }
+
+    /// Start a session on the given pair of streams.
+    pub fn spawn(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+        config: Config,
+        handler: impl FnMut(Event) + Send + 'static,
+    ) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                client: Client::new(config),
+                writer: Box::new(writer),
+            }),
+            handler: Mutex::new(Box::new(handler)),
+            closed: AtomicBool::new(false),
+        });
+
+        // Send the registration messages
+        shared.flush();
+
+        let shared2 = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("irc reader".to_owned())
+            .spawn(move || shared2.run_reader(reader))?;
+
+        let (commands, recv) = mpsc::channel();
+        let shared2 = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("irc writer".to_owned())
+            .spawn(move || shared2.run_writer(recv))?;
+
+        Ok(Self {
+            shared,
+            commands: Mutex::new(commands),
+        })
+    }
+
+    /// Check if the connection is still open. Commands issued after the
+    /// connection is closed are discarded.
+    pub fn is_connected(&self) -> bool {
+        !self.shared.closed.load(Ordering::Acquire)
+    }
+
+    /// Get our current nickname.
+    pub fn nick(&self) -> String {
+        self.shared.state.lock().unwrap().client.nick().to_owned()
+    }
+
+    /// Queue a command to be executed by the writer thread.
+    fn with_client(&self, f: impl FnOnce(&mut Client) + Send + 'static) {
+        // This fails only if the writer thread is gone, in which case the
+        // connection is unusable anyway
+        let _ = self.commands.lock().unwrap().send(Box::new(f));
+    }
+
+    /// Send a raw message.
+    pub fn send(&self, msg: Message) {
+        self.with_client(|c| c.send(msg));
+    }
+
+    pub fn join(&self, channel: &str) {
+        let channel = channel.to_owned();
+        self.with_client(move |c| c.join(&channel));
+    }
+
+    pub fn part(&self, channel: &str, reason: Option<&str>) {
+        let (channel, reason) = (channel.to_owned(), reason.map(str::to_owned));
+        self.with_client(move |c| c.part(&channel, reason.as_deref()));
+    }
+
+    pub fn privmsg(&self, target: &str, body: &str) {
+        let (target, body) = (target.to_owned(), body.to_owned());
+        self.with_client(move |c| c.privmsg(&target, &body));
+    }
+
+    pub fn notice(&self, target: &str, body: &str) {
+        let (target, body) = (target.to_owned(), body.to_owned());
+        self.with_client(move |c| c.notice(&target, &body));
+    }
+
+    pub fn action(&self, target: &str, body: &str) {
+        let (target, body) = (target.to_owned(), body.to_owned());
+        self.with_client(move |c| c.action(&target, &body));
+    }
+
+    pub fn quit(&self, reason: Option<&str>) {
+        let reason = reason.map(str::to_owned);
+        self.with_client(move |c| c.quit(reason.as_deref()));
+    }
+}
+
+impl Shared {
+    /// Send queued messages and deliver queued events.
+    fn flush(&self) {
+        // Lock `handler` first so that events are delivered in order
+        let mut handler = self.handler.lock().unwrap();
+
+        let mut events = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let State { client, writer } = &mut *state;
+
+            while let Some(msg) = client.pop_outgoing() {
+                if let Err(e) = msg.validate() {
+                    log::warn!("Not sending a malformed message {:?}: {}", msg, e);
+                    continue;
+                }
+                log::trace!("> {}", msg);
+                if let Err(e) = write!(writer, "{}\r\n", msg) {
+                    // The reader thread will notice the disconnection
+                    log::warn!("Failed to send a message: {:?}", e);
+                    break;
+                }
+            }
+            if let Err(e) = writer.flush() {
+                log::warn!("Failed to flush the stream: {:?}", e);
+            }
+
+            while let Some(event) = client.pop_event() {
+                events.push(event);
+            }
+        }
+
+        for event in events {
+            handler(event);
+        }
+    }
+
+    /// Execute the commands queued by `Connection` until it's dropped.
+    fn run_writer(&self, commands: mpsc::Receiver<Command>) {
+        for command in commands {
+            command(&mut self.state.lock().unwrap().client);
+            self.flush();
+        }
+    }
+
+    fn run_reader(&self, reader: impl Read) {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    log::warn!("Failed to receive a message: {:?}", e);
+                    break;
+                }
+            }
+
+            // IRC doesn't mandate any particular encoding, but UTF-8 is the
+            // de facto standard
+            let line = String::from_utf8_lossy(&line);
+            log::trace!("< {}", line.trim_end());
+
+            match Message::parse(&line) {
+                Ok(msg) => {
+                    self.state.lock().unwrap().client.handle_message(&msg);
+                    self.flush();
+                }
+                Err(ParseError::Empty) => {}
+                Err(e) => {
+                    log::warn!("Ignoring a malformed message {:?}: {}", line, e);
+                }
+            }
+        }
+
+        log::debug!("The connection was closed");
+        self.closed.store(true, Ordering::Release);
+        self.state
+            .lock()
+            .unwrap()
+            .client
+            .push_event(Event::Disconnected);
+        self.flush();
+    }
+}
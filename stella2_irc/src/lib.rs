@@ -0,0 +1,21 @@
+//! The IRC client subsystem of Stella 2.
+//!
+//!  - [`message`] parses and serializes IRC messages.
+//!  - [`Client`] implements the client side of the protocol without doing any
+//!    I/O by itself.
+//!  - [`Connection`] drives `Client` over a byte stream (e.g., `TcpStream`) in
+//!    a background thread.
+//!  - [`loopback`] provides an in-process IRC server for testing.
+//!  - [`linkify`] finds URLs, channel names, etc. in message bodies.
+//!
+pub mod client;
+pub mod connection;
+pub mod linkify;
+pub mod loopback;
+pub mod message;
+
+pub use self::{
+    client::{Client, Config, Event, MessageKind},
+    connection::Connection,
+    message::Message,
+};
@@ -0,0 +1,392 @@
+//! Detection of URLs, e-mail addresses, channel names, and nick mentions in
+//! message bodies
+use std::ops::Range;
+
+/// A link found by [`find_links`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The UTF-8 range of the link in the source text.
+    pub range: Range<usize>,
+    pub kind: LinkKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// A URL, e.g., `https://example.com/` or `www.example.com`. Only the
+    /// schemes in [`URL_SCHEMES`] are recognized.
+    Url,
+    /// An e-mail address, e.g., `alice@example.com`.
+    Email,
+    /// A channel name, e.g., `#rust`.
+    Channel,
+    /// A nickname accepted by the `is_nick` predicate of [`find_links`].
+    Nick,
+}
+
+/// The URL schemes recognized by [`find_links`]. Anything else (e.g.,
+/// `file://`) is treated as plain text because it could be used to trick the
+/// user into running something.
+pub const URL_SCHEMES: &[&str] = &["http", "https", "ftp"];
+
+/// Check if `url` uses one of the schemes recognized by [`find_links`],
+/// including `mailto:`.
+pub fn is_safe_url(url: &str) -> bool {
+    let scheme = match url.find(':') {
+        Some(i) => &url[..i],
+        None => return false,
+    };
+    if scheme.eq_ignore_ascii_case("mailto") {
+        return true;
+    }
+    url[scheme.len()..].starts_with("://")
+        && URL_SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(scheme))
+}
+
+impl Link {
+    /// Get the linked text in `text`, which must be the text passed to
+    /// [`find_links`].
+    pub fn as_str<'a>(&self, text: &'a str) -> &'a str {
+        &text[self.range.clone()]
+    }
+
+    /// Get the URL to open when the link is activated. Returns `None` for
+    /// channel names and nicknames.
+    pub fn url(&self, text: &str) -> Option<String> {
+        let s = self.as_str(text);
+        match self.kind {
+            LinkKind::Url if s.contains("://") => Some(s.to_owned()),
+            LinkKind::Url => Some(format!("http://{}", s)),
+            LinkKind::Email => Some(format!("mailto:{}", s)),
+            LinkKind::Channel | LinkKind::Nick => None,
+        }
+    }
+}
+
+/// Find links in `text`, sorted by position. The returned ranges don't
+/// overlap with each other.
+///
+/// A word is reported as [`LinkKind::Nick`] if `is_nick` returns `true` for
+/// the word stripped of surrounding punctuation (e.g., `alice` in `@alice:`).
+pub fn find_links(text: &str, is_nick: impl Fn(&str) -> bool) -> Vec<Link> {
+    words(text)
+        .filter_map(|word| {
+            let link = find_url(text, word.clone())
+                .map(|range| (range, LinkKind::Url))
+                .or_else(|| find_email(text, word.clone()).map(|range| (range, LinkKind::Email)))
+                .or_else(|| {
+                    find_channel(text, word.clone()).map(|range| (range, LinkKind::Channel))
+                })
+                .or_else(|| {
+                    let range = trim_nick(text, word);
+                    if is_nick(&text[range.clone()]) {
+                        Some((range, LinkKind::Nick))
+                    } else {
+                        None
+                    }
+                });
+            link.map(|(range, kind)| Link { range, kind })
+        })
+        .collect()
+}
+
+/// Iterate over the ranges of whitespace-separated words in `text`.
+fn words(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        let rest = &text[i..];
+        let start = i + rest.find(|c: char| !c.is_whitespace())?;
+        let end = text[start..]
+            .find(char::is_whitespace)
+            .map(|len| start + len)
+            .unwrap_or_else(|| text.len());
+        i = end;
+        Some(start..end)
+    })
+}
+
+/// The characters stripped from the beginning of a word.
+fn is_opening_punct(c: char) -> bool {
+    match c {
+        '(' | '[' | '{' | '<' | '"' | '\'' => true,
+        _ => false,
+    }
+}
+
+/// The characters stripped from the end of a word.
+fn is_closing_punct(c: char) -> bool {
+    match c {
+        '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '}' | '>' | '"' | '\'' => true,
+        _ => false,
+    }
+}
+
+/// Remove trailing punctuation from `range`. Closing brackets are kept if
+/// they have a matching opening bracket in the range, so that URLs like
+/// `https://en.wikipedia.org/wiki/Rust_(programming_language)` work.
+fn trim_end(text: &str, mut range: Range<usize>) -> Range<usize> {
+    while let Some(c) = text[range.clone()].chars().next_back() {
+        let s = &text[range.clone()];
+        let strip = match c {
+            ')' => s.matches('(').count() < s.matches(')').count(),
+            ']' => s.matches('[').count() < s.matches(']').count(),
+            '}' => s.matches('{').count() < s.matches('}').count(),
+            c => is_closing_punct(c),
+        };
+        if !strip {
+            break;
+        }
+        range.end -= c.len_utf8();
+    }
+    range
+}
+
+fn find_url(text: &str, word: Range<usize>) -> Option<Range<usize>> {
+    let s = &text[word.clone()];
+
+    let start = if let Some(sep) = s.find("://") {
+        // Find the start of the scheme, which consists of ASCII letters,
+        // digits, `+`, `-`, and `.`, and starts with a letter
+        let scheme_len = s[..sep]
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_alphanumeric() || "+-.".contains(*c))
+            .count();
+        let scheme_start = s[..sep].len() - scheme_len;
+        let scheme = &s[scheme_start..sep];
+        if !URL_SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+            return None;
+        }
+        // The scheme must not be preceded by a letter or digit
+        if s[..scheme_start].ends_with(char::is_alphanumeric) {
+            return None;
+        }
+        scheme_start
+    } else {
+        let start = s.len() - s.trim_start_matches(is_opening_punct).len();
+        let rest = &s[start..];
+        if !rest
+            .get(..4)
+            .map_or(false, |p| p.eq_ignore_ascii_case("www."))
+        {
+            return None;
+        }
+        start
+    };
+
+    // A URL can't contain these characters
+    let len = s[start..]
+        .find(|c| c == '<' || c == '>' || c == '"')
+        .unwrap_or(s.len() - start);
+
+    let range = trim_end(text, word.start + start..word.start + start + len);
+
+    // Reject a URL without anything after the scheme or `www.`
+    let s = &text[range.clone()];
+    let prefix_len = s.find("://").map(|i| i + 3).unwrap_or(4);
+    if s.len() <= prefix_len {
+        return None;
+    }
+
+    Some(range)
+}
+
+fn find_email(text: &str, word: Range<usize>) -> Option<Range<usize>> {
+    let s = &text[word.clone()];
+    let at = s.find('@')?;
+
+    let is_local_char = |c: char| c.is_ascii_alphanumeric() || "._%+-".contains(c);
+    let is_domain_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+
+    let local_len = s[..at]
+        .chars()
+        .rev()
+        .take_while(|&c| is_local_char(c))
+        .count();
+    let local = &s[at - local_len..at];
+    if local.is_empty() || local.starts_with('.') {
+        return None;
+    }
+
+    let domain_len = s[at + 1..]
+        .chars()
+        .take_while(|&c| is_domain_char(c))
+        .count();
+    let domain = s[at + 1..at + 1 + domain_len].trim_end_matches(|c| c == '.' || c == '-');
+
+    // The top-level domain must consist of two or more letters
+    let tld = match domain.rfind('.') {
+        Some(i) if i > 0 => &domain[i + 1..],
+        _ => return None,
+    };
+    if tld.len() < 2 || !tld.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let start = word.start + at - local_len;
+    Some(start..word.start + at + 1 + domain.len())
+}
+
+fn find_channel(text: &str, word: Range<usize>) -> Option<Range<usize>> {
+    let s = &text[word.clone()];
+    let start = s.len() - s.trim_start_matches(is_opening_punct).len();
+
+    let mut chars = s[start..].chars();
+    if !matches!(chars.next(), Some('#') | Some('&')) {
+        return None;
+    }
+    // Reject things like `#` and `&&`
+    if !chars.next().map_or(false, char::is_alphanumeric) {
+        return None;
+    }
+
+    // Channel names can't contain commas or control characters
+    let len = s[start..]
+        .find(|c: char| c == ',' || c.is_control())
+        .unwrap_or(s.len() - start);
+
+    Some(trim_end(text, word.start + start..word.start + start + len))
+}
+
+/// Remove punctuation around a possible nickname. Unlike other kinds of
+/// links, brackets are kept because they are valid in nicknames.
+fn trim_nick(text: &str, word: Range<usize>) -> Range<usize> {
+    let s = &text[word.clone()];
+    // `@` and `+` are the prefixes of channel operators and voiced members
+    let start = s.len()
+        - s.trim_start_matches(|c| matches!(c, '(' | '<' | '"' | '\'' | '@' | '+'))
+            .len();
+    let end = s
+        .trim_end_matches(|c| {
+            matches!(
+                c,
+                '.' | ',' | ';' | ':' | '!' | '?' | ')' | '>' | '"' | '\''
+            )
+        })
+        .len()
+        .max(start);
+    word.start + start..word.start + end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(text: &str) -> Vec<(&str, LinkKind)> {
+        find_links(text, |word| word == "alice" || word == "[bob]")
+            .into_iter()
+            .map(|link| (link.as_str(text), link.kind))
+            .collect()
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            links("see https://example.com/a?b=c#d."),
+            vec![("https://example.com/a?b=c#d", LinkKind::Url)]
+        );
+        assert_eq!(
+            links("(https://en.wikipedia.org/wiki/Rust_(programming_language))"),
+            vec![(
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                LinkKind::Url
+            )]
+        );
+        assert_eq!(
+            links("<www.example.com>, WWW.example.org"),
+            vec![
+                ("www.example.com", LinkKind::Url),
+                ("WWW.example.org", LinkKind::Url)
+            ]
+        );
+        assert_eq!(links("https:// www. x://y 1a://b"), vec![]);
+        assert_eq!(
+            links("file:///etc/passwd javascript://x FTP://example.com"),
+            vec![("FTP://example.com", LinkKind::Url)]
+        );
+        assert_eq!(links("xhttps://example.com"), vec![]);
+    }
+
+    #[test]
+    fn safe_urls() {
+        assert!(is_safe_url("https://example.com"));
+        assert!(is_safe_url("HTTP://example.com"));
+        assert!(is_safe_url("ftp://example.com"));
+        assert!(is_safe_url("mailto:alice@example.com"));
+        assert!(!is_safe_url("file:///etc/passwd"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url("http:example.com"));
+        assert!(!is_safe_url("example.com"));
+    }
+
+    #[test]
+    fn url_targets() {
+        let text = "www.example.com https://example.com mail@example.com";
+        let urls: Vec<_> = find_links(text, |_| false)
+            .iter()
+            .map(|link| link.url(text))
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                Some("http://www.example.com".to_owned()),
+                Some("https://example.com".to_owned()),
+                Some("mailto:mail@example.com".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn emails() {
+        assert_eq!(
+            links("mail me at <alice.b+irc@mail.example.com>."),
+            vec![("alice.b+irc@mail.example.com", LinkKind::Email)]
+        );
+        assert_eq!(
+            links("a@b @alice foo@bar.c1 .x@example.com"),
+            vec![("alice", LinkKind::Nick)]
+        );
+    }
+
+    #[test]
+    fn channels() {
+        assert_eq!(
+            links("join #rust, #rust-offtopic and &local!"),
+            vec![
+                ("#rust", LinkKind::Channel),
+                ("#rust-offtopic", LinkKind::Channel),
+                ("&local", LinkKind::Channel)
+            ]
+        );
+        assert_eq!(
+            links("# && #1 (#foo)"),
+            vec![("#1", LinkKind::Channel), ("#foo", LinkKind::Channel)]
+        );
+    }
+
+    #[test]
+    fn nicks() {
+        assert_eq!(
+            links("alice: hi @[bob], alicex and Alice"),
+            vec![("alice", LinkKind::Nick), ("[bob]", LinkKind::Nick)]
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let text = "  héllo  alice,  https://example.com ";
+        let links = find_links(text, |word| word == "alice");
+        assert_eq!(
+            links,
+            vec![
+                Link {
+                    range: 10..15,
+                    kind: LinkKind::Nick
+                },
+                Link {
+                    range: 18..37,
+                    kind: LinkKind::Url
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! An in-process IRC server for testing
+//!
+//! [`LoopbackServer`] implements a small subset of the server-side protocol
+//! sufficient to exercise [`Client`](crate::Client) without any network
+//! access. Clients are connected through in-memory pipes.
+use std::{
+    io::{self, prelude::*, BufReader},
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::message::{is_channel_name, name_eq, Message};
+
+/// The server name reported to clients.
+pub const SERVER_NAME: &str = "loopback.invalid";
+
+/// An in-process IRC server.
+#[derive(Clone)]
+pub struct LoopbackServer {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled when a message is appended to `State::log`.
+    log_cond: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    clients: Vec<ClientState>,
+    channels: Vec<ChannelState>,
+    /// The messages received from clients.
+    log: Vec<Message>,
+}
+
+struct ClientState {
+    nick: Option<String>,
+    user: Option<String>,
+    registered: bool,
+    /// `None` if the connection has been closed by the server.
+    writer: Option<PipeWriter>,
+}
+
+struct ChannelState {
+    name: String,
+    topic: Option<String>,
+    /// Indices into `State::clients`.
+    members: Vec<usize>,
+}
+
+impl Default for LoopbackServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoopbackServer {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::default()),
+                log_cond: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Accept a new client. Returns a pair of streams to be used by the
+    /// client.
+    pub fn connect(&self) -> (PipeReader, PipeWriter) {
+        let (client_writer, server_reader) = pipe();
+        let (server_writer, client_reader) = pipe();
+
+        let client_id = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.clients.push(ClientState {
+                nick: None,
+                user: None,
+                registered: false,
+                writer: Some(server_writer),
+            });
+            state.clients.len() - 1
+        };
+
+        let shared = Arc::clone(&self.shared);
+        thread::Builder::new()
+            .name("irc loopback server".to_owned())
+            .spawn(move || shared.run_client(client_id, server_reader))
+            .unwrap();
+
+        (client_reader, client_writer)
+    }
+
+    /// Send `PING` to all registered clients.
+    pub fn ping_all(&self, token: &str) {
+        let mut state = self.shared.state.lock().unwrap();
+        for i in 0..state.clients.len() {
+            if state.clients[i].registered {
+                state.send(i, Message::new("PING", &[token]).with_prefix(SERVER_NAME));
+            }
+        }
+    }
+
+    /// Get all messages received from clients so far.
+    pub fn received(&self) -> Vec<Message> {
+        self.shared.state.lock().unwrap().log.clone()
+    }
+
+    /// Wait until a message satisfying the given predicate is received from
+    /// any client. Returns `None` on timeout.
+    pub fn wait_for(
+        &self,
+        mut pred: impl FnMut(&Message) -> bool,
+        timeout: Duration,
+    ) -> Option<Message> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(msg) = state.log.iter().find(|m| pred(m)) {
+                return Some(msg.clone());
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            state = self
+                .shared
+                .log_cond
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+impl Shared {
+    fn run_client(&self, client_id: usize, reader: PipeReader) {
+        for line in BufReader::new(reader).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            let msg = match Message::parse(&line) {
+                Ok(msg) => msg,
+                Err(_) => continue,
+            };
+
+            let mut state = self.state.lock().unwrap();
+            state.log.push(msg.clone());
+            state.handle_message(client_id, &msg);
+            self.log_cond.notify_all();
+
+            if state.clients[client_id].writer.is_none() {
+                break;
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.remove_client(client_id, "Connection closed");
+    }
+}
+
+impl State {
+    fn send(&mut self, client_id: usize, msg: Message) {
+        if let Some(writer) = &mut self.clients[client_id].writer {
+            // The client may have gone away. Ignore the error in that case.
+            let _ = write!(writer, "{}\r\n", msg);
+        }
+    }
+
+    fn send_numeric(&mut self, client_id: usize, numeric: &str, params: &[&str]) {
+        let nick = self.clients[client_id]
+            .nick
+            .clone()
+            .unwrap_or_else(|| "*".to_owned());
+        let mut msg = Message::new(numeric, params).with_prefix(SERVER_NAME);
+        msg.params.insert(0, nick);
+        self.send(client_id, msg);
+    }
+
+    fn client_prefix(&self, client_id: usize) -> String {
+        let client = &self.clients[client_id];
+        format!(
+            "{}!{}@{}",
+            client.nick.as_deref().unwrap_or("*"),
+            client.user.as_deref().unwrap_or("*"),
+            SERVER_NAME
+        )
+    }
+
+    fn find_client(&self, nick: &str) -> Option<usize> {
+        self.clients.iter().position(|c| {
+            c.writer.is_some() && c.nick.as_ref().map(|n| name_eq(n, nick)) == Some(true)
+        })
+    }
+
+    fn find_channel(&self, name: &str) -> Option<usize> {
+        self.channels.iter().position(|c| name_eq(&c.name, name))
+    }
+
+    /// Send a message to all members of a channel, optionally excluding one
+    /// client.
+    fn broadcast(&mut self, channel_i: usize, msg: &Message, except: Option<usize>) {
+        for member in self.channels[channel_i].members.clone() {
+            if Some(member) != except {
+                self.send(member, msg.clone());
+            }
+        }
+    }
+
+    fn remove_client(&mut self, client_id: usize, reason: &str) {
+        if self.clients[client_id].registered {
+            let msg = Message::new("QUIT", &[reason]).with_prefix(&self.client_prefix(client_id));
+
+            // Notify every client sharing a channel, each of them only once
+            let mut peers: Vec<usize> = Vec::new();
+            for channel in self.channels.iter_mut() {
+                if let Some(i) = channel.members.iter().position(|&m| m == client_id) {
+                    channel.members.remove(i);
+                    peers.extend(&channel.members);
+                }
+            }
+            peers.sort_unstable();
+            peers.dedup();
+            for peer in peers {
+                self.send(peer, msg.clone());
+            }
+        }
+
+        let client = &mut self.clients[client_id];
+        client.registered = false;
+        client.nick = None;
+        client.writer = None;
+    }
+
+    fn handle_message(&mut self, client_id: usize, msg: &Message) {
+        let params: Vec<&str> = msg.params.iter().map(String::as_str).collect();
+
+        match (msg.command.as_str(), &params[..]) {
+            ("PASS", _) | ("PONG", _) => {}
+            ("NICK", &[nick, ..]) => {
+                if self.find_client(nick).is_some() {
+                    self.send_numeric(client_id, "433", &[nick, "Nickname is already in use"]);
+                    return;
+                }
+
+                if self.clients[client_id].registered {
+                    let msg =
+                        Message::new("NICK", &[nick]).with_prefix(&self.client_prefix(client_id));
+                    self.send(client_id, msg.clone());
+                    for channel_i in 0..self.channels.len() {
+                        if self.channels[channel_i].members.contains(&client_id) {
+                            self.broadcast(channel_i, &msg, Some(client_id));
+                        }
+                    }
+                }
+
+                self.clients[client_id].nick = Some(nick.to_owned());
+                self.try_register(client_id);
+            }
+            ("USER", &[user, ..]) => {
+                self.clients[client_id].user = Some(user.to_owned());
+                self.try_register(client_id);
+            }
+            ("PING", &[token, ..]) => {
+                let msg = Message::new("PONG", &[SERVER_NAME, token]).with_prefix(SERVER_NAME);
+                self.send(client_id, msg);
+            }
+            ("QUIT", _) => {
+                let reason = params.first().cloned().unwrap_or("Client quit");
+                let msg = Message::new("ERROR", &["Closing link"]);
+                self.send(client_id, msg);
+                self.remove_client(client_id, reason);
+            }
+            (_, _) if !self.clients[client_id].registered => {
+                self.send_numeric(client_id, "451", &["You have not registered"]);
+            }
+            ("JOIN", &[channels, ..]) => {
+                for channel in channels.split(',') {
+                    self.join(client_id, channel);
+                }
+            }
+            ("PART", &[channels, ..]) => {
+                for channel in channels.split(',') {
+                    self.part(client_id, channel, params.get(1).cloned());
+                }
+            }
+            ("PRIVMSG", &[target, body]) | ("NOTICE", &[target, body]) => {
+                let msg = Message::new(&msg.command, &[target, body])
+                    .with_prefix(&self.client_prefix(client_id));
+
+                if is_channel_name(target) {
+                    if let Some(channel_i) = self.find_channel(target) {
+                        self.broadcast(channel_i, &msg, Some(client_id));
+                    } else {
+                        self.send_numeric(client_id, "403", &[target, "No such channel"]);
+                    }
+                } else if let Some(peer) = self.find_client(target) {
+                    self.send(peer, msg);
+                } else {
+                    self.send_numeric(client_id, "401", &[target, "No such nick/channel"]);
+                }
+            }
+            ("TOPIC", &[channel]) => {
+                if let Some(channel_i) = self.find_channel(channel) {
+                    self.send_topic(client_id, channel_i);
+                } else {
+                    self.send_numeric(client_id, "403", &[channel, "No such channel"]);
+                }
+            }
+            ("TOPIC", &[channel, topic]) => {
+                if let Some(channel_i) = self.find_channel(channel) {
+                    self.channels[channel_i].topic =
+                        Some(topic.to_owned()).filter(|t| !t.is_empty());
+
+                    let name = self.channels[channel_i].name.clone();
+                    let msg = Message::new("TOPIC", &[&name, topic])
+                        .with_prefix(&self.client_prefix(client_id));
+                    self.broadcast(channel_i, &msg, None);
+                } else {
+                    self.send_numeric(client_id, "403", &[channel, "No such channel"]);
+                }
+            }
+            (command, _) => {
+                self.send_numeric(client_id, "421", &[command, "Unknown command"]);
+            }
+        }
+    }
+
+    fn try_register(&mut self, client_id: usize) {
+        let client = &mut self.clients[client_id];
+        if client.registered || client.nick.is_none() || client.user.is_none() {
+            return;
+        }
+        client.registered = true;
+
+        self.send_numeric(client_id, "001", &["Welcome to the loopback IRC server"]);
+    }
+
+    fn join(&mut self, client_id: usize, name: &str) {
+        if !is_channel_name(name) {
+            self.send_numeric(client_id, "403", &[name, "No such channel"]);
+            return;
+        }
+
+        let channel_i = if let Some(i) = self.find_channel(name) {
+            i
+        } else {
+            self.channels.push(ChannelState {
+                name: name.to_owned(),
+                topic: None,
+                members: Vec::new(),
+            });
+            self.channels.len() - 1
+        };
+
+        if self.channels[channel_i].members.contains(&client_id) {
+            return;
+        }
+        self.channels[channel_i].members.push(client_id);
+
+        let name = self.channels[channel_i].name.clone();
+        let msg = Message::new("JOIN", &[&name]).with_prefix(&self.client_prefix(client_id));
+        self.broadcast(channel_i, &msg, None);
+
+        if self.channels[channel_i].topic.is_some() {
+            self.send_topic(client_id, channel_i);
+        }
+
+        // The first member is the channel operator
+        let names: Vec<String> = self.channels[channel_i]
+            .members
+            .iter()
+            .enumerate()
+            .map(|(i, &member)| {
+                let nick = self.clients[member].nick.as_deref().unwrap_or("*");
+                if i == 0 {
+                    format!("@{}", nick)
+                } else {
+                    nick.to_owned()
+                }
+            })
+            .collect();
+        self.send_numeric(client_id, "353", &["=", &name, &names.join(" ")]);
+        self.send_numeric(client_id, "366", &[&name, "End of /NAMES list"]);
+    }
+
+    fn part(&mut self, client_id: usize, name: &str, reason: Option<&str>) {
+        let channel_i = match self.find_channel(name) {
+            Some(i) if self.channels[i].members.contains(&client_id) => i,
+            _ => {
+                self.send_numeric(client_id, "442", &[name, "You're not on that channel"]);
+                return;
+            }
+        };
+
+        let name = self.channels[channel_i].name.clone();
+        let prefix = self.client_prefix(client_id);
+        let msg = if let Some(reason) = reason {
+            Message::new("PART", &[&name, reason])
+        } else {
+            Message::new("PART", &[&name])
+        };
+        self.broadcast(channel_i, &msg.with_prefix(&prefix), None);
+
+        self.channels[channel_i].members.retain(|&m| m != client_id);
+    }
+
+    fn send_topic(&mut self, client_id: usize, channel_i: usize) {
+        let channel = &self.channels[channel_i];
+        let name = channel.name.clone();
+        if let Some(topic) = channel.topic.clone() {
+            self.send_numeric(client_id, "332", &[&name, &topic]);
+        } else {
+            self.send_numeric(client_id, "331", &[&name, "No topic is set"]);
+        }
+    }
+}
+
+/// Create an in-memory unidirectional pipe.
+fn pipe() -> (PipeWriter, PipeReader) {
+    let (send, recv) = mpsc::channel();
+    (
+        PipeWriter { send },
+        PipeReader {
+            recv,
+            buffer: Vec::new(),
+            pos: 0,
+        },
+    )
+}
+
+/// The reading end of an in-memory pipe. Reaches EOF when the corresponding
+/// [`PipeWriter`] is dropped.
+pub struct PipeReader {
+    recv: mpsc::Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+/// The writing end of an in-memory pipe.
+pub struct PipeWriter {
+    send: mpsc::Sender<Vec<u8>>,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.buffer.len() {
+            match self.recv.recv() {
+                Ok(data) => {
+                    self.buffer = data;
+                    self.pos = 0;
+                }
+                // EOF
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let data = &self.buffer[self.pos..];
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send
+            .send(buf.to_owned())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
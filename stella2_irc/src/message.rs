@@ -0,0 +1,349 @@
+//! IRC messages (RFC 1459 and RFC 2812)
+use std::{borrow::Cow, fmt, hash::Hasher};
+
+/// An IRC message, excluding the trailing CR-LF sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// The prefix (the origin of the message) without the leading colon,
+    /// e.g., `nick!user@host` or `irc.example.com`.
+    pub prefix: Option<String>,
+    /// The command name (e.g., `PRIVMSG`) or a three-digit numeric reply.
+    pub command: String,
+    pub params: Vec<String>,
+}
+
+/// Indicates a malformed IRC message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, displaydoc::Display)]
+pub enum ParseError {
+    /// The message is empty.
+    Empty,
+    /// The message doesn't have a command.
+    NoCommand,
+}
+
+impl std::error::Error for ParseError {}
+
+/// Indicates a `Message` that can't be represented in the wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, displaydoc::Display)]
+pub enum FormatError {
+    /// The parameter {0} is empty, contains a space, or starts with a colon, but is not the last one.
+    BadMiddleParam(usize),
+}
+
+impl std::error::Error for FormatError {}
+
+impl Message {
+    /// Construct a `Message` without a prefix.
+    pub fn new(command: &str, params: &[&str]) -> Self {
+        Self {
+            prefix: None,
+            command: command.to_owned(),
+            params: params.iter().map(|&p| p.to_owned()).collect(),
+        }
+    }
+
+    /// Replace the prefix of `self`.
+    pub fn with_prefix(self, prefix: &str) -> Self {
+        Self {
+            prefix: Some(prefix.to_owned()),
+            ..self
+        }
+    }
+
+    /// Parse a line received from a peer. Message tags (IRCv3) are ignored.
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let mut rest = line.trim_end_matches(&['\r', '\n'][..]);
+
+        // Skip message tags
+        if rest.starts_with('@') {
+            rest = rest.splitn(2, ' ').nth(1).unwrap_or("");
+        }
+
+        rest = rest.trim_start_matches(' ');
+        if rest.is_empty() {
+            return Err(ParseError::Empty);
+        }
+
+        let prefix = if rest.starts_with(':') {
+            let mut parts = rest[1..].splitn(2, ' ');
+            let prefix = parts.next().unwrap();
+            rest = parts.next().unwrap_or("").trim_start_matches(' ');
+            Some(prefix.to_owned())
+        } else {
+            None
+        };
+
+        let mut parts = rest.splitn(2, ' ');
+        let command = parts.next().unwrap();
+        if command.is_empty() {
+            return Err(ParseError::NoCommand);
+        }
+        rest = parts.next().unwrap_or("");
+
+        let mut params = Vec::new();
+        loop {
+            rest = rest.trim_start_matches(' ');
+            if rest.is_empty() {
+                break;
+            } else if rest.starts_with(':') {
+                // The trailing parameter
+                params.push(rest[1..].to_owned());
+                break;
+            }
+
+            let mut parts = rest.splitn(2, ' ');
+            params.push(parts.next().unwrap().to_owned());
+            rest = parts.next().unwrap_or("");
+        }
+
+        Ok(Self {
+            prefix,
+            command: command.to_ascii_uppercase(),
+            params,
+        })
+    }
+
+    /// Get the nickname part of the prefix.
+    pub fn source_nick(&self) -> Option<&str> {
+        self.prefix
+            .as_ref()
+            .map(|prefix| prefix.split(&['!', '@'][..]).next().unwrap())
+    }
+
+    /// Get the parameter at the specified index.
+    pub fn param(&self, i: usize) -> Option<&str> {
+        self.params.get(i).map(String::as_str)
+    }
+
+    /// Check if `self` can be formatted by `Display`. Only the last parameter
+    /// may be empty, contain a space, or start with a colon.
+    pub fn validate(&self) -> Result<(), FormatError> {
+        let num_middle_params = self.params.len().saturating_sub(1);
+        for (i, param) in self.params[..num_middle_params].iter().enumerate() {
+            if needs_colon(&sanitize_param(param)) {
+                return Err(FormatError::BadMiddleParam(i));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replace line breaks and NUL characters with spaces.
+fn sanitize_param(param: &str) -> Cow<'_, str> {
+    if param.contains(&['\r', '\n', '\0'][..]) {
+        param.replace(&['\r', '\n', '\0'][..], " ").into()
+    } else {
+        param.into()
+    }
+}
+
+/// Check if `param` can only be represented as a trailing parameter.
+fn needs_colon(param: &str) -> bool {
+    param.is_empty() || param.starts_with(':') || param.contains(' ')
+}
+
+/// Formats a `Message` in the wire format, excluding the trailing CR-LF
+/// sequence. Line breaks and NUL characters in the parameters are replaced
+/// with spaces.
+///
+/// Returns an error if the message is rejected by [`Message::validate`].
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Err(e) = self.validate() {
+            debug_assert!(false, "{:?} can't be formatted: {}", self, e);
+            return Err(fmt::Error);
+        }
+
+        if let Some(prefix) = &self.prefix {
+            write!(f, ":{} ", prefix)?;
+        }
+        write!(f, "{}", self.command)?;
+
+        for param in self.params.iter() {
+            let param = sanitize_param(param);
+            // Only the last parameter can need a colon (checked by `validate`)
+            if needs_colon(&param) {
+                f.write_str(" :")?;
+            } else {
+                f.write_str(" ")?;
+            }
+            f.write_str(&param)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The delimiter of a CTCP message.
+const CTCP_DELIM: char = '\x01';
+
+/// Parse a CTCP message embedded in the body of `PRIVMSG` or `NOTICE`.
+/// Returns the command name and the arguments.
+pub fn parse_ctcp(body: &str) -> Option<(&str, &str)> {
+    if !body.starts_with(CTCP_DELIM) {
+        return None;
+    }
+
+    // The closing delimiter is optional in practice
+    let body = body[1..].trim_end_matches(CTCP_DELIM);
+
+    let mut parts = body.splitn(2, ' ');
+    Some((parts.next().unwrap(), parts.next().unwrap_or("")))
+}
+
+/// Construct a CTCP message to be embedded in the body of `PRIVMSG` or
+/// `NOTICE`.
+pub fn ctcp(command: &str, args: &str) -> String {
+    if args.is_empty() {
+        format!("{}{}{}", CTCP_DELIM, command, CTCP_DELIM)
+    } else {
+        format!("{}{} {}{}", CTCP_DELIM, command, args, CTCP_DELIM)
+    }
+}
+
+/// Check if the given target name represents a channel.
+pub fn is_channel_name(target: &str) -> bool {
+    target.starts_with(&['#', '&', '+', '!'][..])
+}
+
+/// Compare two nicknames or channel names case-insensitively using the
+/// `rfc1459` case mapping.
+pub fn name_eq(x: &str, y: &str) -> bool {
+    x.len() == y.len()
+        && x.bytes()
+            .zip(y.bytes())
+            .all(|(a, b)| name_lower(a) == name_lower(b))
+}
+
+/// Feed a nickname or channel name into `state` in a way consistent with
+/// [`name_eq`], i.e., names that compare equal by [`name_eq`] produce the same
+/// hash value.
+pub fn hash_name<H: Hasher>(x: &str, state: &mut H) {
+    state.write_usize(x.len());
+    for c in x.bytes() {
+        state.write_u8(name_lower(c));
+    }
+}
+
+/// Lowercase a byte using the `rfc1459` case mapping.
+fn name_lower(c: u8) -> u8 {
+    match c {
+        b'[' => b'{',
+        b']' => b'}',
+        b'\\' => b'|',
+        b'~' => b'^',
+        _ => c.to_ascii_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_simple() {
+        let msg = Message::parse("PING irc.example.com\r\n").unwrap();
+        assert_eq!(msg, Message::new("PING", &["irc.example.com"]));
+    }
+
+    #[test]
+    fn parse_prefix_and_trailing() {
+        let msg = Message::parse(":alice!a@example.com PRIVMSG #test :hello, world").unwrap();
+        assert_eq!(msg.prefix.as_deref(), Some("alice!a@example.com"));
+        assert_eq!(msg.source_nick(), Some("alice"));
+        assert_eq!(msg.command, "PRIVMSG");
+        assert_eq!(msg.params, vec!["#test", "hello, world"]);
+    }
+
+    #[test]
+    fn parse_tags() {
+        let msg = Message::parse("@time=2020-01-01T00:00:00Z :a JOIN #test").unwrap();
+        assert_eq!(msg, Message::new("JOIN", &["#test"]).with_prefix("a"));
+    }
+
+    #[test]
+    fn parse_empty_trailing() {
+        let msg = Message::parse("TOPIC #test :").unwrap();
+        assert_eq!(msg.params, vec!["#test", ""]);
+    }
+
+    #[test]
+    fn parse_bad() {
+        assert_eq!(Message::parse(""), Err(ParseError::Empty));
+        assert_eq!(Message::parse("\r\n"), Err(ParseError::Empty));
+        assert_eq!(Message::parse(":prefix"), Err(ParseError::NoCommand));
+    }
+
+    #[test]
+    fn format() {
+        let msg = Message::new("PRIVMSG", &["#test", "hello, world"]).with_prefix("a");
+        assert_eq!(msg.to_string(), ":a PRIVMSG #test :hello, world");
+
+        let msg = Message::new("NICK", &["alice"]);
+        assert_eq!(msg.to_string(), "NICK alice");
+
+        let msg = Message::new("PRIVMSG", &["#test", "a\r\nb"]);
+        assert_eq!(msg.to_string(), "PRIVMSG #test :a  b");
+
+        let msg = Message::new("PRIVMSG", &["#test", "\n"]);
+        assert_eq!(msg.to_string(), "PRIVMSG #test : ");
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(Message::new("PRIVMSG", &["#test", ""]).validate(), Ok(()));
+        assert_eq!(
+            Message::new("PRIVMSG", &["", "hello"]).validate(),
+            Err(FormatError::BadMiddleParam(0))
+        );
+        assert_eq!(
+            Message::new("PRIVMSG", &["#a b", "hello"]).validate(),
+            Err(FormatError::BadMiddleParam(0))
+        );
+        assert_eq!(
+            Message::new("KICK", &["#test", ":bob", "bye"]).validate(),
+            Err(FormatError::BadMiddleParam(1))
+        );
+        assert_eq!(
+            Message::new("PRIVMSG", &["#a\nb", "hello"]).validate(),
+            Err(FormatError::BadMiddleParam(0))
+        );
+    }
+
+    #[test]
+    fn format_roundtrip() {
+        for line in &[
+            ":a!b@c PRIVMSG #test :hello, world",
+            "USER alice 0 * :Alice Liddell",
+            "TOPIC #test :",
+            "PRIVMSG #test ::)",
+        ] {
+            assert_eq!(Message::parse(line).unwrap().to_string(), *line);
+        }
+    }
+
+    #[test]
+    fn ctcp_action() {
+        let body = ctcp("ACTION", "waves");
+        assert_eq!(body, "\x01ACTION waves\x01");
+        assert_eq!(parse_ctcp(&body), Some(("ACTION", "waves")));
+        assert_eq!(parse_ctcp("\x01VERSION"), Some(("VERSION", "")));
+        assert_eq!(parse_ctcp("hello"), None);
+    }
+
+    #[test]
+    fn names() {
+        assert!(is_channel_name("#test"));
+        assert!(!is_channel_name("alice"));
+        assert!(name_eq("Alice[m]", "alice{M}"));
+        assert!(!name_eq("alice", "alice_"));
+
+        let hash = |x: &str| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            hash_name(x, &mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash("Alice[m]"), hash("alice{M}"));
+        assert_ne!(hash("alice"), hash("alice_"));
+    }
+}
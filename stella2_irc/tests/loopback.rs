@@ -0,0 +1,269 @@
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use stella2_irc::{loopback::LoopbackServer, Config, Connection, Event, Message, MessageKind};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct TestClient {
+    conn: Connection,
+    events: mpsc::Receiver<Event>,
+}
+
+impl TestClient {
+    fn connect(server: &LoopbackServer, nick: &str) -> Self {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (reader, writer) = server.connect();
+        let (send, events) = mpsc::channel();
+        let conn = Connection::spawn(reader, writer, Config::with_nick(nick), move |e| {
+            let _ = send.send(e);
+        })
+        .unwrap();
+
+        Self { conn, events }
+    }
+
+    /// Connect and wait until the registration completes.
+    fn register(server: &LoopbackServer, nick: &str) -> Self {
+        let this = Self::connect(server, nick);
+        this.wait_event(|e| matches!(e, Event::Registered { .. }));
+        this
+    }
+
+    /// Discard events until one satisfying `pred` is found.
+    fn wait_event(&self, mut pred: impl FnMut(&Event) -> bool) -> Event {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let event = self
+                .events
+                .recv_timeout(timeout)
+                .expect("timed out waiting for an event");
+            if pred(&event) {
+                return event;
+            }
+        }
+    }
+}
+
+#[test]
+fn registration() {
+    let server = LoopbackServer::new();
+    let client = TestClient::connect(&server, "alice");
+
+    let event = client.wait_event(|e| matches!(e, Event::Registered { .. }));
+    assert_eq!(
+        event,
+        Event::Registered {
+            nick: "alice".to_owned()
+        }
+    );
+    assert_eq!(client.conn.nick(), "alice");
+
+    let received = server.received();
+    assert_eq!(received[0].command, "NICK");
+    assert_eq!(received[1].command, "USER");
+}
+
+#[test]
+fn nick_collision() {
+    let server = LoopbackServer::new();
+    let _alice = TestClient::register(&server, "alice");
+    let alice2 = TestClient::connect(&server, "alice");
+
+    let event = alice2.wait_event(|e| matches!(e, Event::Registered { .. }));
+    assert_eq!(
+        event,
+        Event::Registered {
+            nick: "alice_".to_owned()
+        }
+    );
+}
+
+#[test]
+fn join_names_topic() {
+    let server = LoopbackServer::new();
+    let alice = TestClient::register(&server, "alice");
+
+    alice.conn.join("#test");
+    assert_eq!(
+        alice.wait_event(|e| matches!(e, Event::Join { .. })),
+        Event::Join {
+            channel: "#test".to_owned(),
+            nick: "alice".to_owned(),
+            is_self: true,
+        }
+    );
+    assert_eq!(
+        alice.wait_event(|e| matches!(e, Event::Names { .. })),
+        Event::Names {
+            channel: "#test".to_owned(),
+            nicks: vec!["alice".to_owned()],
+        }
+    );
+
+    alice
+        .conn
+        .send(Message::new("TOPIC", &["#test", "Testing things"]));
+    assert_eq!(
+        alice.wait_event(|e| matches!(e, Event::Topic { .. })),
+        Event::Topic {
+            channel: "#test".to_owned(),
+            topic: Some("Testing things".to_owned()),
+        }
+    );
+
+    // `bob` receives the topic and the member list on join
+    let bob = TestClient::register(&server, "bob");
+    bob.conn.join("#test");
+    assert_eq!(
+        bob.wait_event(|e| matches!(e, Event::Topic { .. })),
+        Event::Topic {
+            channel: "#test".to_owned(),
+            topic: Some("Testing things".to_owned()),
+        }
+    );
+    assert_eq!(
+        bob.wait_event(|e| matches!(e, Event::Names { .. })),
+        Event::Names {
+            channel: "#test".to_owned(),
+            nicks: vec!["alice".to_owned(), "bob".to_owned()],
+        }
+    );
+
+    // `alice` is notified of `bob`'s join
+    assert_eq!(
+        alice.wait_event(|e| matches!(e, Event::Join { .. })),
+        Event::Join {
+            channel: "#test".to_owned(),
+            nick: "bob".to_owned(),
+            is_self: false,
+        }
+    );
+}
+
+#[test]
+fn messages() {
+    let server = LoopbackServer::new();
+    let alice = TestClient::register(&server, "alice");
+    let bob = TestClient::register(&server, "bob");
+
+    alice.conn.join("#test");
+    alice.wait_event(|e| matches!(e, Event::Names { .. }));
+    bob.conn.join("#test");
+    bob.wait_event(|e| matches!(e, Event::Names { .. }));
+
+    let is_message = |e: &Event| matches!(e, Event::Message { .. });
+
+    alice.conn.privmsg("#test", "hello");
+    let expected = Event::Message {
+        target: "#test".to_owned(),
+        sender: "alice".to_owned(),
+        kind: MessageKind::Privmsg,
+        body: "hello".to_owned(),
+    };
+    // The sender receives its own message as an event, too
+    assert_eq!(alice.wait_event(is_message), expected);
+    assert_eq!(bob.wait_event(is_message), expected);
+
+    alice.conn.notice("#test", "beep");
+    assert_eq!(
+        bob.wait_event(is_message),
+        Event::Message {
+            target: "#test".to_owned(),
+            sender: "alice".to_owned(),
+            kind: MessageKind::Notice,
+            body: "beep".to_owned(),
+        }
+    );
+
+    let is_from_bob = |e: &Event| matches!(e, Event::Message { sender, .. } if sender == "bob");
+
+    bob.conn.action("#test", "waves");
+    assert_eq!(
+        alice.wait_event(is_from_bob),
+        Event::Message {
+            target: "#test".to_owned(),
+            sender: "bob".to_owned(),
+            kind: MessageKind::Action,
+            body: "waves".to_owned(),
+        }
+    );
+
+    // Private message
+    bob.conn.privmsg("alice", "psst");
+    assert_eq!(
+        alice.wait_event(is_from_bob),
+        Event::Message {
+            target: "alice".to_owned(),
+            sender: "bob".to_owned(),
+            kind: MessageKind::Privmsg,
+            body: "psst".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn part() {
+    let server = LoopbackServer::new();
+    let alice = TestClient::register(&server, "alice");
+    let bob = TestClient::register(&server, "bob");
+
+    alice.conn.join("#test");
+    alice.wait_event(|e| matches!(e, Event::Names { .. }));
+    bob.conn.join("#test");
+    bob.wait_event(|e| matches!(e, Event::Names { .. }));
+
+    bob.conn.part("#test", Some("bye"));
+    let expected = |is_self| Event::Part {
+        channel: "#test".to_owned(),
+        nick: "bob".to_owned(),
+        reason: Some("bye".to_owned()),
+        is_self,
+    };
+    assert_eq!(
+        alice.wait_event(|e| matches!(e, Event::Part { .. })),
+        expected(false)
+    );
+    assert_eq!(
+        bob.wait_event(|e| matches!(e, Event::Part { .. })),
+        expected(true)
+    );
+}
+
+#[test]
+fn quit() {
+    let server = LoopbackServer::new();
+    let alice = TestClient::register(&server, "alice");
+    let bob = TestClient::register(&server, "bob");
+
+    alice.conn.join("#test");
+    alice.wait_event(|e| matches!(e, Event::Names { .. }));
+    bob.conn.join("#test");
+    bob.wait_event(|e| matches!(e, Event::Names { .. }));
+
+    bob.conn.quit(Some("gone"));
+    assert_eq!(
+        alice.wait_event(|e| matches!(e, Event::Quit { .. })),
+        Event::Quit {
+            nick: "bob".to_owned(),
+            reason: Some("gone".to_owned()),
+        }
+    );
+    bob.wait_event(|e| *e == Event::Disconnected);
+    assert!(!bob.conn.is_connected());
+    assert!(alice.conn.is_connected());
+}
+
+#[test]
+fn ping_pong() {
+    let server = LoopbackServer::new();
+    let _alice = TestClient::register(&server, "alice");
+
+    server.ping_all("token123");
+    let pong = server.wait_for(|m| m.command == "PONG", TIMEOUT);
+    assert_eq!(pong, Some(Message::new("PONG", &["token123"])));
+}
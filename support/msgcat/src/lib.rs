@@ -0,0 +1,535 @@
+//! Message catalogs for translating user interfaces.
+//!
+//! A *message catalog* ([`Catalog`]) maps *message IDs* to the messages in a
+//! single language. Catalogs are written in a platform-independent text
+//! format:
+//!
+//! ```text
+//! # This is a comment.
+//! pref-title = Preferences
+//! pref-greeting = Hello, {name}!
+//!
+//! crash-count = {count} crash reports were saved.
+//! crash-count[one] = A crash report was saved.
+//! ```
+//!
+//!  - Each non-empty line that doesn't start with `#` defines a message in
+//!    the form `id = text`. Whitespace around the text is ignored.
+//!
+//!  - `{name}` in the text is a placeholder, which is replaced with the
+//!    argument named `name` when formatting the message. `{{` and `}}`
+//!    represent literal braces. `\n` represents a line break, and `\\` a
+//!    backslash.
+//!
+//!  - `id[category] = text` defines a plural variant of the message, used
+//!    when the number passed to [`Catalog::format_plural`] falls in the
+//!    [plural category](PluralCategory) according to the language's
+//!    [`PluralRule`]. The message without a category is used for the other
+//!    numbers and must be present.
+//!
+//! A [`Bundle`] combines the catalogs of multiple languages so that missing
+//! messages can fall back to another language.
+//!
+//! This crate also provides functions to detect the user's preferred
+//! languages ([`languages_from_env`]) and match them against the available
+//! catalogs ([`negotiate`]).
+use std::{collections::HashMap, error, fmt};
+
+mod locale;
+mod plural;
+pub use self::{locale::*, plural::*};
+
+/// The arguments for formatting a message, given as pairs of a placeholder
+/// name and a value.
+pub type Args<'a> = [(&'a str, &'a dyn fmt::Display)];
+
+/// A set of messages in a single language.
+///
+/// # Examples
+///
+/// ```
+/// use msgcat::Catalog;
+///
+/// let catalog = Catalog::parse("en", r"
+///     unread = {count} unread messages in {channel}
+///     unread[one] = An unread message in {channel}
+/// ").unwrap();
+///
+/// assert_eq!(
+///     catalog.format_plural("unread", 2, &[("count", &2), ("channel", &"#rust")]),
+///     Some("2 unread messages in #rust".to_owned()),
+/// );
+/// assert_eq!(
+///     catalog.format_plural("unread", 1, &[("count", &1), ("channel", &"#rust")]),
+///     Some("An unread message in #rust".to_owned()),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    language: String,
+    plural_rule: PluralRule,
+    messages: HashMap<String, Message>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Message {
+    /// The text without a plural category. `None` while parsing if only
+    /// plural variants have been found so far.
+    default: Option<Pattern>,
+    variants: Vec<(PluralCategory, Pattern)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Pattern(Vec<Segment>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// The error type for [`Catalog::parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The line number (starting from 1) where the error was found.
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// The line doesn't contain `=`.
+    MissingEquals,
+    /// The message ID is empty or contains an invalid character.
+    BadId,
+    /// The plural category isn't one of the ones defined by CLDR.
+    BadPluralCategory,
+    /// The message was defined more than once.
+    Duplicate,
+    /// The message has plural variants but no text without a category.
+    MissingDefault,
+    /// A placeholder is not closed or has an invalid name.
+    BadPlaceholder,
+    /// A `}` doesn't have a matching `{`.
+    UnmatchedBrace,
+    /// An unknown escape sequence was found.
+    BadEscape,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self.kind {
+            ParseErrorKind::MissingEquals => "expected `=`",
+            ParseErrorKind::BadId => "invalid message ID",
+            ParseErrorKind::BadPluralCategory => "unknown plural category",
+            ParseErrorKind::Duplicate => "duplicate message",
+            ParseErrorKind::MissingDefault => "the message has no text without a plural category",
+            ParseErrorKind::BadPlaceholder => "invalid placeholder",
+            ParseErrorKind::UnmatchedBrace => "unmatched `}`",
+            ParseErrorKind::BadEscape => "unknown escape sequence",
+        };
+        write!(f, "line {}: {}", self.line, msg)
+    }
+}
+
+impl error::Error for ParseError {}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'
+}
+
+impl Catalog {
+    /// Construct an empty `Catalog` for `language` (a language tag like
+    /// `en` or `pt-BR`), which determines the plural rule.
+    pub fn new(language: &str) -> Self {
+        let plural_rule = LanguageId::parse(language)
+            .map(|id| PluralRule::for_language(id.language()))
+            .unwrap_or(PluralRule::OneOther);
+        Self {
+            language: language.to_owned(),
+            plural_rule,
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Parse a message catalog for `language`. See [the crate
+    /// documentation](crate) for the format.
+    pub fn parse(language: &str, source: &str) -> Result<Self, ParseError> {
+        let mut this = Self::new(language);
+        let mut last_line = HashMap::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line_num = i + 1;
+            let error = |kind| ParseError {
+                line: line_num,
+                kind,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let eq = line
+                .find('=')
+                .ok_or_else(|| error(ParseErrorKind::MissingEquals))?;
+            let (key, text) = (line[..eq].trim(), line[eq + 1..].trim());
+
+            // Split `id[category]`
+            let (id, category) = if key.ends_with(']') {
+                let open = key.find('[').ok_or_else(|| error(ParseErrorKind::BadId))?;
+                let category = key[open + 1..key.len() - 1]
+                    .trim()
+                    .parse::<PluralCategory>()
+                    .map_err(|_| error(ParseErrorKind::BadPluralCategory))?;
+                (key[..open].trim(), Some(category))
+            } else {
+                (key, None)
+            };
+
+            if id.is_empty() || !id.chars().all(is_id_char) {
+                return Err(error(ParseErrorKind::BadId));
+            }
+
+            let pattern = Pattern::parse(text).map_err(error)?;
+
+            let message = this.messages.entry(id.to_owned()).or_default();
+            let duplicate = match category {
+                // `other` is the same as the text without a category
+                None | Some(PluralCategory::Other) => message.default.replace(pattern).is_some(),
+                Some(category) => {
+                    let dup = message.variants.iter().any(|&(c, _)| c == category);
+                    message.variants.push((category, pattern));
+                    dup
+                }
+            };
+            if duplicate {
+                return Err(error(ParseErrorKind::Duplicate));
+            }
+
+            last_line.insert(id.to_owned(), line_num);
+        }
+
+        for (id, message) in this.messages.iter() {
+            if message.default.is_none() {
+                return Err(ParseError {
+                    line: last_line[id],
+                    kind: ParseErrorKind::MissingDefault,
+                });
+            }
+        }
+
+        Ok(this)
+    }
+
+    /// Get the language tag specified when constructing the catalog.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Get the plural rule of the catalog's language.
+    pub fn plural_rule(&self) -> PluralRule {
+        self.plural_rule
+    }
+
+    /// Get the number of messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Get a flag indicating whether the catalog has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Get a flag indicating whether the catalog has a message with ID `id`.
+    pub fn contains(&self, id: &str) -> bool {
+        self.messages.contains_key(id)
+    }
+
+    /// Iterate over the IDs of the messages in an unspecified order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.messages.keys().map(String::as_str)
+    }
+
+    /// Format the message `id`, replacing the placeholders with `args`.
+    /// Placeholders without a corresponding argument are left as they are.
+    ///
+    /// Returns `None` if the catalog doesn't have the message.
+    pub fn format(&self, id: &str, args: &Args<'_>) -> Option<String> {
+        let message = self.messages.get(id)?;
+        Some(message.default.as_ref().unwrap().format(args))
+    }
+
+    /// Like [`Catalog::format`], but chooses a plural variant based on `n`.
+    pub fn format_plural(&self, id: &str, n: u64, args: &Args<'_>) -> Option<String> {
+        let message = self.messages.get(id)?;
+        let category = self.plural_rule.category(n);
+        let pattern = (message.variants.iter())
+            .find(|&&(c, _)| c == category)
+            .map(|(_, pattern)| pattern)
+            .unwrap_or_else(|| message.default.as_ref().unwrap());
+        Some(pattern.format(args))
+    }
+}
+
+impl Pattern {
+    fn parse(text: &str) -> Result<Self, ParseErrorKind> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) if is_id_char(c) => name.push(c),
+                            _ => return Err(ParseErrorKind::BadPlaceholder),
+                        }
+                    }
+                    if name.is_empty() {
+                        return Err(ParseErrorKind::BadPlaceholder);
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(name));
+                }
+                '}' => return Err(ParseErrorKind::UnmatchedBrace),
+                '\\' => match chars.next() {
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    _ => return Err(ParseErrorKind::BadEscape),
+                },
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Text(literal));
+        }
+
+        Ok(Self(segments))
+    }
+
+    fn format(&self, args: &Args<'_>) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        for segment in self.0.iter() {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Placeholder(name) => {
+                    if let Some((_, value)) = args.iter().find(|(n, _)| n == name) {
+                        // `write!` to `String` never fails
+                        let _ = write!(out, "{}", value);
+                    } else {
+                        let _ = write!(out, "{{{}}}", name);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A list of [`Catalog`]s in the order of preference. A message is taken
+/// from the first catalog that has it.
+///
+/// The formatting methods return the message ID itself if none of the
+/// catalogs have the message, so missing messages remain noticeable but
+/// don't break the user interface.
+///
+/// # Examples
+///
+/// ```
+/// use msgcat::{Bundle, Catalog};
+///
+/// let mut bundle = Bundle::new();
+/// bundle.push(Catalog::parse("ja", "ok = はい").unwrap());
+/// bundle.push(Catalog::parse("en", "ok = OK\ncancel = Cancel").unwrap());
+///
+/// assert_eq!(bundle.text("ok"), "はい");
+/// assert_eq!(bundle.text("cancel"), "Cancel");
+/// assert_eq!(bundle.text("help"), "help");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Bundle {
+    catalogs: Vec<Catalog>,
+}
+
+impl Bundle {
+    /// Construct an empty `Bundle`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a catalog with the lowest preference.
+    pub fn push(&mut self, catalog: Catalog) {
+        self.catalogs.push(catalog);
+    }
+
+    /// Get the catalogs in the order of preference.
+    pub fn catalogs(&self) -> &[Catalog] {
+        &self.catalogs
+    }
+
+    /// Get the language of the most preferred catalog.
+    pub fn language(&self) -> Option<&str> {
+        self.catalogs.first().map(Catalog::language)
+    }
+
+    /// Get the message `id`, which shouldn't have placeholders.
+    pub fn text(&self, id: &str) -> String {
+        self.format(id, &[])
+    }
+
+    /// Format the message `id`. See [`Catalog::format`].
+    pub fn format(&self, id: &str, args: &Args<'_>) -> String {
+        (self.catalogs.iter())
+            .find_map(|catalog| catalog.format(id, args))
+            .unwrap_or_else(|| id.to_owned())
+    }
+
+    /// Format the message `id` based on `n`. See [`Catalog::format_plural`].
+    ///
+    /// The plural variant is chosen by the plural rule of the catalog that
+    /// has the message.
+    pub fn format_plural(&self, id: &str, n: u64, args: &Args<'_>) -> String {
+        (self.catalogs.iter())
+            .find_map(|catalog| catalog.format_plural(id, n, args))
+            .unwrap_or_else(|| id.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(source: &str) -> ParseError {
+        Catalog::parse("en", source).unwrap_err()
+    }
+
+    #[test]
+    fn parse_basic() {
+        let catalog = Catalog::parse(
+            "en",
+            "
+            # Comment
+            a = Hello
+
+            b.c_d-1 =   spaces around
+            empty =
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog.format("a", &[]), Some("Hello".to_owned()));
+        assert_eq!(
+            catalog.format("b.c_d-1", &[]),
+            Some("spaces around".to_owned())
+        );
+        assert_eq!(catalog.format("empty", &[]), Some(String::new()));
+        assert_eq!(catalog.format("missing", &[]), None);
+    }
+
+    #[test]
+    fn placeholders_and_escapes() {
+        let catalog = Catalog::parse("en", r"a = {{{x}}} \\ {y}\n{x} = {x}").unwrap();
+        assert_eq!(
+            catalog.format("a", &[("x", &42), ("z", &"unused")]),
+            Some("{42} \\ {y}\n42 = 42".to_owned())
+        );
+    }
+
+    #[test]
+    fn plural_fallback() {
+        let catalog = Catalog::parse(
+            "ru",
+            "
+            files = {n} файлов
+            files[one] = {n} файл
+            files[few] = {n} файла
+            ",
+        )
+        .unwrap();
+        let format = |n| catalog.format_plural("files", n, &[("n", &n)]).unwrap();
+        assert_eq!(format(1), "1 файл");
+        assert_eq!(format(3), "3 файла");
+        // No variant for `many`
+        assert_eq!(format(5), "5 файлов");
+        assert_eq!(format(21), "21 файл");
+
+        // The default text is used by `format`
+        assert_eq!(
+            catalog.format("files", &[("n", &1)]),
+            Some("1 файлов".to_owned())
+        );
+    }
+
+    #[test]
+    fn plural_other_is_default() {
+        let catalog = Catalog::parse("en", "a[one] = one\na[other] = other").unwrap();
+        assert_eq!(catalog.format_plural("a", 2, &[]), Some("other".to_owned()));
+        assert_eq!(catalog.format_plural("a", 1, &[]), Some("one".to_owned()));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let kind = |source| parse_err(source).kind;
+        assert_eq!(kind("a"), ParseErrorKind::MissingEquals);
+        assert_eq!(kind("= a"), ParseErrorKind::BadId);
+        assert_eq!(kind("a b = c"), ParseErrorKind::BadId);
+        assert_eq!(kind("a]= c"), ParseErrorKind::BadId);
+        assert_eq!(kind("a[lots] = c"), ParseErrorKind::BadPluralCategory);
+        assert_eq!(kind("a = b\na = c"), ParseErrorKind::Duplicate);
+        assert_eq!(kind("a = b\na[other] = c"), ParseErrorKind::Duplicate);
+        assert_eq!(
+            kind("a = b\na[one] = c\na[one] = d"),
+            ParseErrorKind::Duplicate
+        );
+        assert_eq!(kind("a[one] = b"), ParseErrorKind::MissingDefault);
+        assert_eq!(kind("a = {b"), ParseErrorKind::BadPlaceholder);
+        assert_eq!(kind("a = {}"), ParseErrorKind::BadPlaceholder);
+        assert_eq!(kind("a = {b c}"), ParseErrorKind::BadPlaceholder);
+        assert_eq!(kind("a = b}"), ParseErrorKind::UnmatchedBrace);
+        assert_eq!(kind(r"a = \t"), ParseErrorKind::BadEscape);
+    }
+
+    #[test]
+    fn parse_error_line() {
+        let e = parse_err("# comment\n\na = b\nc");
+        assert_eq!(e.line, 4);
+        assert_eq!(e.to_string(), "line 4: expected `=`");
+    }
+
+    #[test]
+    fn bundle_plural_rule() {
+        let mut bundle = Bundle::new();
+        bundle.push(Catalog::parse("ja", "a = {n} 個").unwrap());
+        bundle.push(
+            Catalog::parse(
+                "en",
+                "a = {n} items\na[one] = an item\nb[one] = one\nb = many",
+            )
+            .unwrap(),
+        );
+
+        // The plural rule of the catalog having the message is used
+        assert_eq!(bundle.format_plural("a", 1, &[("n", &1)]), "1 個");
+        assert_eq!(bundle.format_plural("b", 1, &[]), "one");
+        assert_eq!(bundle.language(), Some("ja"));
+    }
+}
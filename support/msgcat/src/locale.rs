@@ -0,0 +1,239 @@
+//! Language identifiers and locale detection
+use std::{env, fmt};
+
+/// A language identifier consisting of a language code and an optional
+/// region code, e.g., `en` and `pt-BR`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageId {
+    language: String,
+    region: Option<String>,
+}
+
+impl LanguageId {
+    /// Parse a BCP 47-style language tag (`pt-BR`) or a POSIX locale name
+    /// (`pt_BR.UTF-8@euro`). Script subtags and other extra parts are
+    /// ignored.
+    ///
+    /// Returns `None` for the `C` and `POSIX` locales, which don't specify
+    /// a language, and malformed inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use msgcat::LanguageId;
+    ///
+    /// let id = LanguageId::parse("ja_JP.UTF-8").unwrap();
+    /// assert_eq!(id.language(), "ja");
+    /// assert_eq!(id.region(), Some("JP"));
+    /// assert_eq!(id.to_string(), "ja-JP");
+    ///
+    /// assert_eq!(LanguageId::parse("C.UTF-8"), None);
+    /// ```
+    pub fn parse(s: &str) -> Option<Self> {
+        // Remove the codeset and the modifier
+        let s = s.split(|c| c == '.' || c == '@').next().unwrap();
+
+        let mut subtags = s.split(|c| c == '-' || c == '_');
+        let language = subtags.next().unwrap();
+
+        let is_alpha = |s: &str| s.bytes().all(|b| b.is_ascii_alphabetic());
+        if !(2..=3).contains(&language.len()) || !is_alpha(language) {
+            // This also rejects `C` and `POSIX`
+            return None;
+        }
+
+        // Skip a script subtag (e.g., `Hant` in `zh-Hant-TW`)
+        let region = subtags
+            .find(|subtag| subtag.len() != 4)
+            .filter(|region| (region.len() == 2 && is_alpha(region)) || region.len() == 3)
+            .map(|region| region.to_ascii_uppercase());
+
+        Some(Self {
+            language: language.to_ascii_lowercase(),
+            region,
+        })
+    }
+
+    /// Get the language code, e.g., `pt` for `pt-BR`. Always in lower case.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Get the region code, e.g., `BR` for `pt-BR`. Always in upper case.
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+}
+
+/// Formats the identifier as a BCP 47 language tag.
+impl fmt::Display for LanguageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.language)?;
+        if let Some(region) = &self.region {
+            write!(f, "-{}", region)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the user's preferred languages from the environment variables, in the
+/// order of preference.
+///
+/// The locale for messages is determined by the first non-empty variable
+/// among `LC_ALL`, `LC_MESSAGES`, and `LANG`. If it specifies a language,
+/// the languages listed in `LANGUAGE` (a colon-separated list used by GNU
+/// gettext) precede it. Returns an empty list if no language is specified,
+/// in which case the application should use its default language.
+pub fn languages_from_env() -> Vec<LanguageId> {
+    languages_from_vars(|name| env::var(name).ok())
+}
+
+fn languages_from_vars(var: impl Fn(&str) -> Option<String>) -> Vec<LanguageId> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty());
+
+    let locale = match locale.as_ref().and_then(|value| LanguageId::parse(value)) {
+        Some(locale) => locale,
+        // `LANGUAGE` is ignored for the `C` locale
+        None => return Vec::new(),
+    };
+
+    let mut languages: Vec<LanguageId> = var("LANGUAGE")
+        .unwrap_or_default()
+        .split(':')
+        .filter_map(LanguageId::parse)
+        .collect();
+    languages.push(locale);
+
+    // Remove duplicates, keeping the first occurrence
+    let mut i = 0;
+    while i < languages.len() {
+        if languages[..i].contains(&languages[i]) {
+            languages.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    languages
+}
+
+/// Choose the best language among `available` for the user who prefers
+/// `requested` (in the order of preference).
+///
+/// A requested language matches an available language with the same
+/// language and region codes, or failing that, the same language code. The
+/// elements of `available` are parsed by [`LanguageId::parse`].
+///
+/// # Examples
+///
+/// ```
+/// use msgcat::{negotiate, LanguageId};
+///
+/// let requested = [LanguageId::parse("pt_BR").unwrap()];
+/// assert_eq!(negotiate(&requested, &["en", "pt-PT", "pt"]), Some("pt"));
+/// assert_eq!(negotiate(&requested, &["en", "pt-PT"]), Some("pt-PT"));
+/// assert_eq!(negotiate(&requested, &["en"]), None);
+/// ```
+pub fn negotiate<'a>(requested: &[LanguageId], available: &[&'a str]) -> Option<&'a str> {
+    let available: Vec<(&str, LanguageId)> = available
+        .iter()
+        .filter_map(|&tag| Some((tag, LanguageId::parse(tag)?)))
+        .collect();
+
+    requested.iter().find_map(|req| {
+        let same_language = || {
+            available
+                .iter()
+                .filter(|(_, id)| id.language == req.language)
+        };
+
+        // Exact match
+        same_language()
+            .find(|(_, id)| *id == *req)
+            // Prefer a generic one (e.g., `pt` over `pt-PT` for `pt-BR`)
+            .or_else(|| same_language().find(|(_, id)| id.region.is_none()))
+            .or_else(|| same_language().next())
+            .map(|&(tag, _)| tag)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn tags(ids: &[LanguageId]) -> Vec<String> {
+        ids.iter().map(ToString::to_string).collect()
+    }
+
+    fn languages(vars: &[(&str, &str)]) -> Vec<String> {
+        let vars: HashMap<&str, &str> = vars.iter().cloned().collect();
+        tags(&languages_from_vars(|name| {
+            vars.get(name).map(|&value| value.to_owned())
+        }))
+    }
+
+    #[test]
+    fn parse() {
+        let parse = |s| LanguageId::parse(s).map(|id| id.to_string());
+        assert_eq!(parse("en"), Some("en".to_owned()));
+        assert_eq!(parse("EN_us"), Some("en-US".to_owned()));
+        assert_eq!(parse("de_DE@euro"), Some("de-DE".to_owned()));
+        assert_eq!(parse("zh-Hant-TW"), Some("zh-TW".to_owned()));
+        assert_eq!(parse("es-419"), Some("es-419".to_owned()));
+        assert_eq!(parse("sr_RS.UTF-8@latin"), Some("sr-RS".to_owned()));
+        assert_eq!(parse("C"), None);
+        assert_eq!(parse("POSIX"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("e1"), None);
+    }
+
+    #[test]
+    fn env_precedence() {
+        assert_eq!(
+            languages(&[("LANG", "fr_FR.UTF-8"), ("LC_MESSAGES", "de_DE")]),
+            ["de-DE"]
+        );
+        assert_eq!(
+            languages(&[
+                ("LANG", "fr_FR.UTF-8"),
+                ("LC_MESSAGES", "de_DE"),
+                ("LC_ALL", "ja_JP.UTF-8")
+            ]),
+            ["ja-JP"]
+        );
+        // Empty variables are ignored
+        assert_eq!(
+            languages(&[("LANG", "fr_FR.UTF-8"), ("LC_ALL", "")]),
+            ["fr-FR"]
+        );
+    }
+
+    #[test]
+    fn env_language_list() {
+        assert_eq!(
+            languages(&[("LANG", "fr_FR.UTF-8"), ("LANGUAGE", "ja:fr_FR:en")]),
+            ["ja", "fr-FR", "en"]
+        );
+        // `LANGUAGE` is ignored for the `C` locale
+        assert_eq!(
+            languages(&[("LANG", "C.UTF-8"), ("LANGUAGE", "ja")]),
+            Vec::<String>::new()
+        );
+        assert_eq!(languages(&[]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn negotiate_order() {
+        let requested = [
+            LanguageId::parse("de").unwrap(),
+            LanguageId::parse("ja-JP").unwrap(),
+        ];
+        assert_eq!(negotiate(&requested, &["en", "ja"]), Some("ja"));
+        assert_eq!(negotiate(&requested, &["de-AT", "ja"]), Some("de-AT"));
+        assert_eq!(negotiate(&[], &["en"]), None);
+    }
+}
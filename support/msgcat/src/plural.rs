@@ -0,0 +1,182 @@
+//! Plural rules
+use std::{fmt, str::FromStr};
+
+/// A plural category defined by [CLDR].
+///
+/// [CLDR]: https://unicode.org/reports/tr35/tr35-numbers.html#Language_Plural_Rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Zero => "zero",
+            Self::One => "one",
+            Self::Two => "two",
+            Self::Few => "few",
+            Self::Many => "many",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for PluralCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PluralCategory {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zero" => Ok(Self::Zero),
+            "one" => Ok(Self::One),
+            "two" => Ok(Self::Two),
+            "few" => Ok(Self::Few),
+            "many" => Ok(Self::Many),
+            "other" => Ok(Self::Other),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A rule to choose a [`PluralCategory`] for a non-negative integer.
+///
+/// Only the rules for integers are implemented, and languages with similar
+/// rules share the same variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralRule {
+    /// Every number is `other` (e.g., Japanese, Chinese, Korean).
+    Invariant,
+    /// `one` for 1, `other` for everything else (e.g., English, German).
+    OneOther,
+    /// `one` for 0 and 1, `other` for everything else (e.g., French).
+    ZeroOneOther,
+    /// `one` for 1, 21, 31, ..., `few` for 2–4, 22–24, ..., `many` for
+    /// everything else (e.g., Russian, Ukrainian).
+    EastSlavic,
+    /// `one` for 1, `few` for 2–4, 22–24, ..., `many` for everything else
+    /// (Polish).
+    Polish,
+    /// `one` for 1, `few` for 2–4, `other` for everything else (Czech,
+    /// Slovak).
+    Czech,
+}
+
+impl PluralRule {
+    /// Get the plural rule for a language. `language` is an ISO 639 language
+    /// code such as `en`. Defaults to [`PluralRule::OneOther`] for unknown
+    /// languages.
+    pub fn for_language(language: &str) -> Self {
+        match &*language.to_ascii_lowercase() {
+            "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" | "lo" | "my" | "km" => Self::Invariant,
+            "fr" | "pt" | "hy" | "ff" | "kab" => Self::ZeroOneOther,
+            "ru" | "uk" | "be" => Self::EastSlavic,
+            "pl" => Self::Polish,
+            "cs" | "sk" => Self::Czech,
+            _ => Self::OneOther,
+        }
+    }
+
+    /// Choose the plural category for `n`.
+    pub fn category(self, n: u64) -> PluralCategory {
+        let (n10, n100) = (n % 10, n % 100);
+        let few = (2..=4).contains(&n10) && !(12..=14).contains(&n100);
+        match self {
+            Self::Invariant => PluralCategory::Other,
+            Self::OneOther if n == 1 => PluralCategory::One,
+            Self::OneOther => PluralCategory::Other,
+            Self::ZeroOneOther if n <= 1 => PluralCategory::One,
+            Self::ZeroOneOther => PluralCategory::Other,
+            Self::EastSlavic if n10 == 1 && n100 != 11 => PluralCategory::One,
+            Self::EastSlavic if few => PluralCategory::Few,
+            Self::EastSlavic => PluralCategory::Many,
+            Self::Polish if n == 1 => PluralCategory::One,
+            Self::Polish if few => PluralCategory::Few,
+            Self::Polish => PluralCategory::Many,
+            Self::Czech if n == 1 => PluralCategory::One,
+            Self::Czech if (2..=4).contains(&n) => PluralCategory::Few,
+            Self::Czech => PluralCategory::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(rule: PluralRule, ns: &[u64]) -> Vec<&'static str> {
+        ns.iter().map(|&n| rule.category(n).as_str()).collect()
+    }
+
+    #[test]
+    fn for_language() {
+        assert_eq!(PluralRule::for_language("JA"), PluralRule::Invariant);
+        assert_eq!(PluralRule::for_language("en"), PluralRule::OneOther);
+        assert_eq!(PluralRule::for_language("xx"), PluralRule::OneOther);
+    }
+
+    #[test]
+    fn one_other() {
+        assert_eq!(
+            categories(PluralRule::OneOther, &[0, 1, 2, 11, 21]),
+            ["other", "one", "other", "other", "other"]
+        );
+    }
+
+    #[test]
+    fn zero_one_other() {
+        assert_eq!(
+            categories(PluralRule::ZeroOneOther, &[0, 1, 2]),
+            ["one", "one", "other"]
+        );
+    }
+
+    #[test]
+    fn east_slavic() {
+        assert_eq!(
+            categories(PluralRule::EastSlavic, &[1, 2, 5, 11, 12, 21, 22, 25, 111]),
+            ["one", "few", "many", "many", "many", "one", "few", "many", "many"]
+        );
+    }
+
+    #[test]
+    fn polish() {
+        assert_eq!(
+            categories(PluralRule::Polish, &[1, 2, 5, 12, 21, 22]),
+            ["one", "few", "many", "many", "many", "few"]
+        );
+    }
+
+    #[test]
+    fn czech() {
+        assert_eq!(
+            categories(PluralRule::Czech, &[1, 2, 4, 5, 22]),
+            ["one", "few", "few", "other", "other"]
+        );
+    }
+
+    #[test]
+    fn category_round_trip() {
+        for &cat in &[
+            PluralCategory::Zero,
+            PluralCategory::One,
+            PluralCategory::Two,
+            PluralCategory::Few,
+            PluralCategory::Many,
+            PluralCategory::Other,
+        ] {
+            assert_eq!(cat.as_str().parse(), Ok(cat));
+        }
+        assert_eq!("plenty".parse::<PluralCategory>(), Err(()));
+    }
+}
@@ -0,0 +1,228 @@
+//! An on-disk inverted index supporting incremental updates and prefix
+//! queries.
+//!
+//! The index maps *terms* (normalized words, see [`tokenize`]) to *documents*,
+//! which are identified by arbitrary byte strings provided by the
+//! application. The index is stored in [`seglog::Store`] as follows:
+//!
+//!  - For each term, a stream named after the term (*a posting list*) contains
+//!    the IDs of the documents containing the term, in the order in which
+//!    they were added.
+//!
+//!  - The stream with an empty key (*the term dictionary*) contains every
+//!    term that has a posting list. The term dictionary is loaded into memory
+//!    when opening the index so that prefix queries can be expanded quickly.
+//!
+//! Documents can only be added and never removed or updated. Newer documents
+//! take precedence in search results.
+//!
+//! `seglog::Store` keeps a bounded number of files open, so the number of
+//! terms doesn't affect the number of open files.
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io,
+    path::Path,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+pub use seglog::Options;
+
+/// Terms longer than this (in bytes) are not indexed.
+pub const MAX_TERM_LEN: usize = 64;
+
+/// The maximum number of terms a single query word is expanded into.
+const MAX_EXPANSIONS: usize = 64;
+
+/// The number of postings initially read from each posting list per
+/// requested search result. This is doubled until enough results are found.
+const MIN_POSTINGS_PER_RESULT: usize = 8;
+
+/// The maximum number of postings read from each posting list per requested
+/// search result. Older documents of common terms may be missing from search
+/// results because of this.
+const MAX_POSTINGS_PER_RESULT: usize = 1024;
+
+/// The key of the term dictionary. Terms are never empty, so this doesn't
+/// conflict with posting lists.
+const TERM_DICT_KEY: &[u8] = b"";
+
+/// Split the text into terms. Words are identified by the Unicode word
+/// boundary rules (UAX #29) and converted to lower case.
+///
+/// # Examples
+///
+/// ```
+/// let terms: Vec<_> = searchindex::tokenize("Hello, World! 日本語").collect();
+/// assert_eq!(terms, ["hello", "world", "日", "本", "語"]);
+/// ```
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.unicode_words()
+        .map(str::to_lowercase)
+        .filter(|term| term.len() <= MAX_TERM_LEN)
+}
+
+/// An on-disk inverted index. See [the crate documentation](crate) for
+/// details.
+#[derive(Debug)]
+pub struct Index {
+    store: seglog::Store,
+    /// The in-memory copy of the term dictionary.
+    terms: BTreeSet<String>,
+}
+
+impl Index {
+    /// Open the index at the specified directory, creating it if it doesn't
+    /// exist.
+    pub fn open(dir: impl AsRef<Path>, options: Options) -> io::Result<Self> {
+        let mut store = seglog::Store::open(dir, options)?;
+
+        let terms = store
+            .read_before(TERM_DICT_KEY, None, usize::MAX)?
+            .into_iter()
+            .filter_map(|record| String::from_utf8(record.data).ok())
+            .collect();
+
+        Ok(Self { store, terms })
+    }
+
+    /// Get the number of distinct terms in the index.
+    pub fn num_terms(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Add a document containing the specified text.
+    ///
+    /// The postings and new terms are written in a single batch, so the
+    /// document is either added completely or not at all.
+    pub fn add(&mut self, doc: &[u8], text: &str) -> io::Result<()> {
+        let terms: HashSet<String> = tokenize(text).collect();
+        let new_terms: Vec<&String> = terms
+            .iter()
+            .filter(|term| !self.terms.contains(*term))
+            .collect();
+
+        // Register the new terms first so that the posting lists are never
+        // unreachable
+        let records: Vec<(&[u8], &[u8])> = new_terms
+            .iter()
+            .map(|term| (TERM_DICT_KEY, term.as_bytes()))
+            .chain(terms.iter().map(|term| (term.as_bytes(), doc)))
+            .collect();
+        self.store.append_batch(&records)?;
+
+        for term in new_terms {
+            self.terms.insert(term.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Find at most `limit` documents containing all words in `query`. Each
+    /// word in `query` matches any terms starting with the word.
+    ///
+    /// The documents are returned from the newest to the oldest.
+    pub fn search(&mut self, query: &str, limit: usize) -> io::Result<Vec<Vec<u8>>> {
+        let words: BTreeSet<String> = tokenize(query).collect();
+        if words.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Only the newest postings are read from each posting list. If they
+        // don't contain enough results, read more postings and retry.
+        let max_postings = limit.saturating_mul(MAX_POSTINGS_PER_RESULT);
+        let mut num_postings = limit.saturating_mul(MIN_POSTINGS_PER_RESULT);
+        loop {
+            let (docs, exhausted) = self.search_inner(&words, limit, num_postings)?;
+            if docs.len() >= limit || exhausted || num_postings >= max_postings {
+                return Ok(docs);
+            }
+            num_postings = num_postings.saturating_mul(2).min(max_postings);
+        }
+    }
+
+    /// Perform a search by reading at most `num_postings` postings from each
+    /// posting list. Returns the found documents and a flag indicating whether
+    /// all posting lists were read completely.
+    fn search_inner(
+        &mut self,
+        words: &BTreeSet<String>,
+        limit: usize,
+        num_postings: usize,
+    ) -> io::Result<(Vec<Vec<u8>>, bool)> {
+        let mut exhausted = true;
+
+        // For each word, find the matching documents and when they were added
+        let mut doc_sets = Vec::with_capacity(words.len());
+        for word in words.iter() {
+            let terms: Vec<String> = self
+                .terms
+                .range(word.clone()..)
+                .take_while(|term| term.starts_with(word.as_str()))
+                .take(MAX_EXPANSIONS)
+                .cloned()
+                .collect();
+
+            let mut docs = HashMap::new();
+            for term in terms {
+                let records = self
+                    .store
+                    .read_before(term.as_bytes(), None, num_postings)?;
+                if records.len() >= num_postings {
+                    exhausted = false;
+                }
+
+                for record in records {
+                    let pos = docs.entry(record.data).or_insert(record.pos);
+                    *pos = (*pos).max(record.pos);
+                }
+            }
+
+            if docs.is_empty() {
+                return Ok((Vec::new(), true));
+            }
+
+            doc_sets.push(docs);
+        }
+
+        // Intersect the sets, starting from the smallest one
+        doc_sets.sort_by_key(|docs| docs.len());
+        let (first, rest) = doc_sets.split_first_mut().unwrap();
+
+        let mut docs: Vec<_> = first
+            .drain()
+            .filter(|(doc, _)| rest.iter().all(|docs| docs.contains_key(doc)))
+            .collect();
+
+        docs.sort_by(|(_, pos1), (_, pos2)| pos2.cmp(pos1));
+        docs.truncate(limit);
+
+        Ok((docs.into_iter().map(|(doc, _)| doc).collect(), exhausted))
+    }
+
+    /// Flush the index to disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.store.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_mixed() {
+        let terms: Vec<_> = tokenize("Don't PANIC: café-au-lait costs 3.50").collect();
+        assert_eq!(
+            terms,
+            ["don't", "panic", "café", "au", "lait", "costs", "3.50"]
+        );
+    }
+
+    #[test]
+    fn tokenize_skips_long_words() {
+        let long_word = "a".repeat(MAX_TERM_LEN + 1);
+        let text = format!("short {} words", long_word);
+        let terms: Vec<_> = tokenize(&text).collect();
+        assert_eq!(terms, ["short", "words"]);
+    }
+}
@@ -0,0 +1,167 @@
+use searchindex::{Index, Options};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+struct TestDir {
+    dir: PathBuf,
+}
+
+impl TestDir {
+    fn new() -> Self {
+        let temp_dir = std::env::temp_dir();
+        let mut i = 0;
+        loop {
+            let dir = temp_dir.join(format!("searchindex_test_{}_{}", std::process::id(), i));
+            match fs::create_dir(&dir) {
+                Ok(()) => return Self { dir },
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    // keep searching
+                    i += 1;
+                }
+                Err(e) => {
+                    panic!("Could not create a temporary directory: {:?}", e);
+                }
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn search(index: &mut Index, query: &str, limit: usize) -> Vec<String> {
+    index
+        .search(query, limit)
+        .unwrap()
+        .into_iter()
+        .map(|doc| String::from_utf8(doc).unwrap())
+        .collect()
+}
+
+fn open_with_docs(dir: &TestDir) -> Index {
+    let mut index = Index::open(dir.path(), Options::default()).unwrap();
+    index.add(b"1", "Hello, world!").unwrap();
+    index
+        .add(b"2", "Has anyone tried the new release?")
+        .unwrap();
+    index.add(b"3", "hello again, WORLD").unwrap();
+    index
+        .add(b"4", "Release notes: https://example.com/")
+        .unwrap();
+    index
+}
+
+#[test]
+fn single_word() {
+    let dir = TestDir::new();
+    let mut index = open_with_docs(&dir);
+
+    // Newer documents come first
+    assert_eq!(search(&mut index, "hello", 10), ["3", "1"]);
+    assert_eq!(search(&mut index, "RELEASE", 10), ["4", "2"]);
+    assert!(search(&mut index, "goodbye", 10).is_empty());
+    assert!(search(&mut index, "", 10).is_empty());
+    assert!(search(&mut index, "!?", 10).is_empty());
+}
+
+#[test]
+fn multiple_words() {
+    let dir = TestDir::new();
+    let mut index = open_with_docs(&dir);
+
+    assert_eq!(search(&mut index, "world hello", 10), ["3", "1"]);
+    assert_eq!(search(&mut index, "hello again", 10), ["3"]);
+    assert!(search(&mut index, "hello release", 10).is_empty());
+}
+
+#[test]
+fn prefix() {
+    let dir = TestDir::new();
+    let mut index = open_with_docs(&dir);
+
+    assert_eq!(search(&mut index, "rel", 10), ["4", "2"]);
+    assert_eq!(search(&mut index, "he", 10), ["3", "1"]);
+    assert_eq!(search(&mut index, "hel wor", 10), ["3", "1"]);
+    assert!(search(&mut index, "helloo", 10).is_empty());
+}
+
+#[test]
+fn limit() {
+    let dir = TestDir::new();
+    let mut index = Index::open(dir.path(), Options::default()).unwrap();
+    for i in 0..20 {
+        index.add(format!("{}", i).as_bytes(), "ping").unwrap();
+    }
+
+    assert_eq!(search(&mut index, "ping", 3), ["19", "18", "17"]);
+}
+
+#[test]
+fn unicode() {
+    let dir = TestDir::new();
+    let mut index = Index::open(dir.path(), Options::default()).unwrap();
+    index.add(b"1", "Ünïcödé text").unwrap();
+    index.add(b"2", "日本語のテキスト").unwrap();
+
+    assert_eq!(search(&mut index, "üNÏ", 10), ["1"]);
+    assert_eq!(search(&mut index, "日本", 10), ["2"]);
+}
+
+#[test]
+fn reopen() {
+    let dir = TestDir::new();
+    {
+        let mut index = open_with_docs(&dir);
+        index.sync().unwrap();
+    }
+
+    let mut index = Index::open(dir.path(), Options::default()).unwrap();
+    assert_eq!(search(&mut index, "hel", 10), ["3", "1"]);
+
+    // Known terms aren't added to the term dictionary again
+    let num_terms = index.num_terms();
+    index.add(b"5", "hello").unwrap();
+    assert_eq!(index.num_terms(), num_terms);
+    assert_eq!(search(&mut index, "hello", 10), ["5", "3", "1"]);
+}
+
+#[test]
+fn rare_match_among_common_postings() {
+    let dir = TestDir::new();
+    let mut index = Index::open(dir.path(), Options::default()).unwrap();
+    index.add(b"old", "ping pong").unwrap();
+    for i in 0..100 {
+        index.add(format!("{}", i).as_bytes(), "ping").unwrap();
+    }
+
+    // The only match is older than the postings initially read for "ping"
+    assert_eq!(search(&mut index, "ping pong", 1), ["old"]);
+}
+
+#[test]
+fn many_terms() {
+    let dir = TestDir::new();
+    {
+        let mut index = Index::open(dir.path(), Options::default()).unwrap();
+        for i in 0..1500 {
+            index
+                .add(format!("{}", i).as_bytes(), &format!("term{}", i))
+                .unwrap();
+        }
+        index.sync().unwrap();
+    }
+
+    let mut index = Index::open(dir.path(), Options::default()).unwrap();
+    assert_eq!(index.num_terms(), 1500);
+    assert_eq!(search(&mut index, "term1499", 10), ["1499"]);
+    assert_eq!(search(&mut index, "term0", 10), ["0"]);
+}
@@ -0,0 +1,78 @@
+//! A small LRU cache of open files
+use std::{fs::File, io};
+
+/// Keeps at most `capacity` files open, closing the least recently used one
+/// when a new file is opened.
+#[derive(Debug)]
+pub(crate) struct FileCache<K> {
+    capacity: usize,
+    /// The cached files, ordered from the least recently used one to the most
+    /// recently used one. The capacity is small enough for a linear search.
+    entries: Vec<(K, File)>,
+}
+
+impl<K: PartialEq + Copy> FileCache<K> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Get the file for `key`, calling `open` to open it if it's not in the
+    /// cache. If the cache is full, the least recently used file is removed
+    /// and passed to `evict` before `open` is called.
+    pub fn get_or_open(
+        &mut self,
+        key: K,
+        open: impl FnOnce() -> io::Result<File>,
+        evict: impl FnOnce(K, File) -> io::Result<()>,
+    ) -> io::Result<&mut File> {
+        if let Some(i) = self.entries.iter().position(|e| e.0 == key) {
+            let entry = self.entries.remove(i);
+            self.entries.push(entry);
+        } else {
+            if self.entries.len() >= self.capacity {
+                let (old_key, old_file) = self.entries.remove(0);
+                evict(old_key, old_file)?;
+            }
+            let file = open()?;
+            self.entries.push((key, file));
+        }
+        Ok(&mut self.entries.last_mut().unwrap().1)
+    }
+
+    /// Get the file for `key` if it's in the cache.
+    pub fn get_mut(&mut self, key: K) -> Option<&mut File> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.0 == key)
+            .map(|e| &mut e.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evict_lru() {
+        let mut cache = FileCache::new(2);
+        let mut evicted = Vec::new();
+        let open = || File::open(std::env::current_exe().unwrap());
+
+        for &key in &[1, 2, 1, 3, 1, 4] {
+            cache
+                .get_or_open(key, open, |key, _| {
+                    evicted.push(key);
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        assert_eq!(evicted, vec![2, 3]);
+        assert!(cache.get_mut(1).is_some());
+        assert!(cache.get_mut(4).is_some());
+    }
+}
@@ -0,0 +1,778 @@
+//! An append-only record store divided into segment files, with an offset
+//! index for each stream of records.
+//!
+//! # Directory layout
+//!
+//! A store occupies a directory containing the following files:
+//!
+//!  - **Segment files** (`<n>.seg`, where `n` is an 8-digit hexadecimal
+//!    sequence number) contain records in the order in which they were
+//!    appended. Only the last segment is ever written to. A new segment is
+//!    started when the last one reaches [`Options::max_segment_len`].
+//!
+//!  - **Index files** (`<h>.idx`, where `h` is a 16-digit hexadecimal hash of
+//!    a stream key) contain a sorted array of [`RecordPos`]es, each of which
+//!    is encoded as a little-endian `u32` segment number followed by a
+//!    little-endian `u64` offset. Streams whose keys have the same hash share
+//!    an index file. Index files can be reconstructed from segment files.
+//!
+//! # Record format
+//!
+//! | Field                               | Size  |
+//! | ----------------------------------- | ----- |
+//! | Body length `n` (LE)                | 4     |
+//! | CRC-32 of the body (LE)             | 4     |
+//! | Body: key length `k` (LE)           | 2     |
+//! | Body: key                           | `k`   |
+//! | Body: data                          | `n - k - 2` |
+//!
+//! # Failure handling
+//!
+//! Appending a record consists of writing the record to the last segment,
+//! flushing the segment to disk, and then writing an index entry. If one of
+//! these steps fails (e.g., because the disk is full), the written data is
+//! rolled back by truncating the files. If the rollback fails as well, it's
+//! retried before the next append.
+//!
+//! If the application crashes in the middle of appending, the last segment
+//! might end with an incomplete record, and some index files might be missing
+//! their last entries. On opening, [`Store`] detects this situation and
+//! truncates the incomplete record and recreates the missing index entries.
+//!
+//! A record in the last segment whose body fails the CRC check is skipped
+//! and left in place. However, if a record's length field is unusable (i.e.,
+//! it extends past the end of the segment), the following records can't be
+//! located, so the segment is truncated at that record.
+//!
+//! # Open files
+//!
+//! [`Store`] keeps a bounded number of index and segment files open
+//! regardless of the number of streams and segments.
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, prelude::*, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+mod filecache;
+use self::filecache::FileCache;
+
+/// The position of a record in [`Store`]. Positions are ordered by the order
+/// in which the records were appended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecordPos {
+    pub segment: u32,
+    pub offset: u64,
+}
+
+impl RecordPos {
+    /// The length of the encoded representation of `RecordPos`.
+    pub const ENCODED_LEN: usize = 12;
+
+    /// Encode `self` as a byte array. The encoded representation is also
+    /// used by index files.
+    pub fn to_bytes(self) -> [u8; RecordPos::ENCODED_LEN] {
+        let mut bytes = [0; RecordPos::ENCODED_LEN];
+        bytes[..4].copy_from_slice(&self.segment.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.offset.to_le_bytes());
+        bytes
+    }
+
+    /// Decode a byte array created by [`RecordPos::to_bytes`]. Returns `None`
+    /// if `bytes` has a wrong length.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::ENCODED_LEN {
+            return None;
+        }
+
+        let mut segment = [0; 4];
+        let mut offset = [0; 8];
+        segment.copy_from_slice(&bytes[..4]);
+        offset.copy_from_slice(&bytes[4..]);
+        Some(Self {
+            segment: u32::from_le_bytes(segment),
+            offset: u64::from_le_bytes(offset),
+        })
+    }
+}
+
+/// A record read from [`Store`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub pos: RecordPos,
+    pub key: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+/// Options for [`Store::open`].
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// The size threshold for starting a new segment. A segment might exceed
+    /// this size if it contains a single large record.
+    pub max_segment_len: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_segment_len: 8 << 20,
+        }
+    }
+}
+
+/// An append-only record store. See [the crate documentation](crate) for
+/// details.
+#[derive(Debug)]
+pub struct Store {
+    dir: PathBuf,
+    options: Options,
+    /// The segment being appended to.
+    head: HeadSegment,
+    /// Cached read handles of segment files.
+    segment_readers: FileCache<u32>,
+    /// Cached handles of index files, indexed by key hashes.
+    index_files: FileCache<u64>,
+    /// The index files written after the last call to `sync`. Index files
+    /// are synced before being evicted from `index_files`, so this is always
+    /// a subset of the cached ones unless the eviction failed.
+    unsynced_indexes: HashSet<u64>,
+    /// The index files to be truncated before the next append, and their
+    /// lengths. This is set when a rollback fails.
+    dirty_indexes: Vec<(u64, u64)>,
+}
+
+#[derive(Debug)]
+struct HeadSegment {
+    number: u32,
+    file: File,
+    /// The length of the valid portion of the segment.
+    len: u64,
+    /// `true` if `file` may contain garbage after `len`.
+    dirty: bool,
+}
+
+const HEADER_LEN: u64 = 8;
+const MAX_OPEN_INDEX_FILES: usize = 16;
+const MAX_OPEN_SEGMENT_READERS: usize = 4;
+const INDEX_ENTRY_LEN: u64 = RecordPos::ENCODED_LEN as u64;
+
+impl Store {
+    /// Open the store at the specified directory, creating it if it doesn't
+    /// exist.
+    pub fn open(dir: impl AsRef<Path>, options: Options) -> io::Result<Self> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir)?;
+
+        // Find the last segment
+        let mut last_segment = None;
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if let Some(number) = name.to_str().and_then(parse_segment_file_name) {
+                last_segment = last_segment.max(Some(number));
+            }
+        }
+
+        let number = last_segment.unwrap_or(0);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(segment_file_name(number)))?;
+
+        let mut this = Self {
+            dir,
+            options,
+            head: HeadSegment {
+                number,
+                file,
+                len: 0,
+                dirty: false,
+            },
+            segment_readers: FileCache::new(MAX_OPEN_SEGMENT_READERS),
+            index_files: FileCache::new(MAX_OPEN_INDEX_FILES),
+            unsynced_indexes: HashSet::new(),
+            dirty_indexes: Vec::new(),
+        };
+
+        this.recover()?;
+
+        Ok(this)
+    }
+
+    /// Validate the last segment and the index files.
+    fn recover(&mut self) -> io::Result<()> {
+        // Find the valid portion of the last segment
+        let file_len = self.head.file.metadata()?.len();
+        let mut records = Vec::new();
+        let mut valid_len = 0;
+        {
+            let mut reader = io::BufReader::new(&self.head.file);
+            reader.seek(SeekFrom::Start(0))?;
+            loop {
+                let pos = RecordPos {
+                    segment: self.head.number,
+                    offset: valid_len,
+                };
+                match read_record(&mut reader, file_len - valid_len)? {
+                    ReadRecord::Valid { key, data } => {
+                        valid_len += record_len(&key, &data);
+                        records.push((pos, key));
+                    }
+                    ReadRecord::Corrupted { len } => {
+                        log::warn!("Skipping a corrupted record at {:?} ({} bytes)", pos, len);
+                        valid_len += len;
+                    }
+                    ReadRecord::Incomplete => break,
+                }
+            }
+        }
+
+        if valid_len < file_len {
+            log::warn!(
+                "Truncating an incomplete record at the end of segment {} ({} bytes)",
+                self.head.number,
+                file_len - valid_len
+            );
+            self.head.file.set_len(valid_len)?;
+        }
+        self.head.len = valid_len;
+
+        let end = RecordPos {
+            segment: self.head.number,
+            offset: valid_len,
+        };
+
+        // Remove index entries pointing to nonexistent records. Each index
+        // file is only opened temporarily so that the number of open files
+        // doesn't depend on the number of streams.
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_str().and_then(parse_index_file_name).is_some() {
+                let file = &mut OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(entry.path())?;
+                let mut len = file.metadata()?.len();
+                let orig_len = len;
+
+                len -= len % INDEX_ENTRY_LEN;
+                while len > 0 && read_index_entry(file, len / INDEX_ENTRY_LEN - 1)? >= end {
+                    len -= INDEX_ENTRY_LEN;
+                }
+
+                if len < orig_len {
+                    log::warn!(
+                        "Truncating {} byte(s) from index file {:?}",
+                        orig_len - len,
+                        name
+                    );
+                    file.set_len(len)?;
+                }
+            }
+        }
+
+        // Recreate missing index entries
+        for (pos, key) in records {
+            let file = self.index_file(key_hash(&key))?;
+            let len = file.metadata()?.len();
+            if len == 0 || read_index_entry(file, len / INDEX_ENTRY_LEN - 1)? < pos {
+                log::warn!("Recreating a missing index entry for {:?}", pos);
+                file.write_all(&pos.to_bytes())?;
+                self.unsynced_indexes.insert(key_hash(&key));
+            }
+        }
+
+        self.sync()
+    }
+
+    /// Get the directory containing the store.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Get a handle of the index file for the specified key hash, creating the
+    /// file if it doesn't exist. The handle is in append mode.
+    fn index_file(&mut self, hash: u64) -> io::Result<&mut File> {
+        let dir = &self.dir;
+        let unsynced_indexes = &mut self.unsynced_indexes;
+        self.index_files.get_or_open(
+            hash,
+            || {
+                OpenOptions::new()
+                    .read(true)
+                    .append(true)
+                    .create(true)
+                    .open(dir.join(index_file_name(hash)))
+            },
+            |old_hash, old_file| {
+                // `sync` can't reach the file after it's closed
+                if unsynced_indexes.contains(&old_hash) {
+                    old_file.sync_data()?;
+                    unsynced_indexes.remove(&old_hash);
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Get a read handle of the specified segment file.
+    fn segment_reader(&mut self, number: u32) -> io::Result<&mut File> {
+        let dir = &self.dir;
+        self.segment_readers.get_or_open(
+            number,
+            || File::open(dir.join(segment_file_name(number))),
+            |_, _| Ok(()),
+        )
+    }
+
+    /// Retry a failed rollback.
+    fn clean_up(&mut self) -> io::Result<()> {
+        if self.head.dirty {
+            self.head.file.set_len(self.head.len)?;
+            self.head.dirty = false;
+        }
+
+        while let Some(&(hash, len)) = self.dirty_indexes.last() {
+            self.index_file(hash)?.set_len(len)?;
+            self.dirty_indexes.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Append a record to the stream identified by `key`.
+    pub fn append(&mut self, key: &[u8], data: &[u8]) -> io::Result<RecordPos> {
+        Ok(self.append_batch(&[(key, data)])?[0])
+    }
+
+    /// Append records, each of which is specified as a pair of a stream key
+    /// and data. The records are flushed to disk at once, which is much
+    /// faster than calling [`Store::append`] for each of them.
+    ///
+    /// Either all or none of the records are appended. The records are
+    /// always written to a single segment, which might exceed
+    /// [`Options::max_segment_len`] as a result.
+    pub fn append_batch(&mut self, records: &[(&[u8], &[u8])]) -> io::Result<Vec<RecordPos>> {
+        let too_large = |&(key, data): &(&[u8], &[u8])| {
+            key.len() > u16::MAX as usize || 2 + key.len() + data.len() > u32::MAX as usize
+        };
+        if records.iter().any(too_large) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the record is too large",
+            ));
+        }
+
+        if records.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.clean_up()?;
+
+        let batch_len: u64 = records
+            .iter()
+            .map(|&(key, data)| record_len(key, data))
+            .sum();
+
+        if self.head.len > 0 && self.head.len + batch_len > self.options.max_segment_len {
+            self.start_new_segment()?;
+        }
+
+        let mut bytes = Vec::with_capacity(batch_len as usize);
+        let mut positions = Vec::with_capacity(records.len());
+        for &(key, data) in records {
+            positions.push(RecordPos {
+                segment: self.head.number,
+                offset: self.head.len + bytes.len() as u64,
+            });
+            encode_record(&mut bytes, key, data);
+        }
+
+        // Write the records. Flush them before writing the index entries so
+        // that the index never refers to a record lost by a power failure.
+        let head = &mut self.head;
+        let result = (|| {
+            head.file.seek(SeekFrom::Start(head.len))?;
+            head.file.write_all(&bytes)?;
+            head.file.sync_data()
+        })();
+        if let Err(e) = result {
+            self.roll_back_segment();
+            return Err(e);
+        }
+
+        // Write the index entries. Remember the original length of each
+        // index file for rollback.
+        let mut index_lens: Vec<(u64, u64)> = Vec::new();
+        let result = (|| -> io::Result<()> {
+            for (&(key, _), pos) in records.iter().zip(positions.iter()) {
+                let hash = key_hash(key);
+                self.unsynced_indexes.insert(hash);
+                let file = self.index_file(hash)?;
+                if !index_lens.iter().any(|&(h, _)| h == hash) {
+                    index_lens.push((hash, file.metadata()?.len()));
+                }
+                file.write_all(&pos.to_bytes())?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            for (hash, len) in index_lens {
+                let result = self.index_file(hash).and_then(|f| f.set_len(len));
+                if result.is_err() {
+                    self.dirty_indexes.push((hash, len));
+                }
+            }
+            self.roll_back_segment();
+            return Err(e);
+        }
+
+        self.head.len += batch_len;
+
+        Ok(positions)
+    }
+
+    /// Remove the partially-written record from the last segment.
+    fn roll_back_segment(&mut self) {
+        self.head.dirty = true;
+        if let Err(e) = self.head.file.set_len(self.head.len) {
+            log::warn!("Failed to roll back segment {}: {:?}", self.head.number, e);
+        } else {
+            self.head.dirty = false;
+        }
+    }
+
+    fn start_new_segment(&mut self) -> io::Result<()> {
+        // `recover` only recreates the index entries of the last segment, so
+        // the index entries of the current one must reach the disk first
+        self.sync()?;
+
+        let number =
+            self.head.number.checked_add(1).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Other, "ran out of segment numbers")
+            })?;
+
+        // If a segment file with the same name already exists, that's
+        // probably a leftover of a failed attempt, and it doesn't contain
+        // any records
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(self.dir.join(segment_file_name(number)))?;
+
+        self.head = HeadSegment {
+            number,
+            file,
+            len: 0,
+            dirty: false,
+        };
+
+        Ok(())
+    }
+
+    /// Read the record at the specified position.
+    pub fn read(&mut self, pos: RecordPos) -> io::Result<Record> {
+        let reader = self.segment_reader(pos.segment)?;
+        let file_len = reader.metadata()?.len();
+        if pos.offset > file_len {
+            return Err(invalid_record());
+        }
+
+        reader.seek(SeekFrom::Start(pos.offset))?;
+        let mut reader = io::BufReader::new(reader);
+        match read_record(&mut reader, file_len - pos.offset)? {
+            ReadRecord::Valid { key, data } => Ok(Record { pos, key, data }),
+            ReadRecord::Corrupted { .. } | ReadRecord::Incomplete => Err(invalid_record()),
+        }
+    }
+
+    /// Read at most `limit` records of the stream identified by `key`
+    /// appended before `before` (or the last ones if `before` is `None`).
+    /// The records are returned in the order in which they were appended.
+    ///
+    /// Records failing validation are skipped.
+    pub fn read_before(
+        &mut self,
+        key: &[u8],
+        before: Option<RecordPos>,
+        limit: usize,
+    ) -> io::Result<Vec<Record>> {
+        let hash = key_hash(key);
+        if !self.dir.join(index_file_name(hash)).exists() {
+            return Ok(Vec::new());
+        }
+
+        let index = self.index_file(hash)?;
+        let num_entries = index.metadata()?.len() / INDEX_ENTRY_LEN;
+
+        // Find the first entry `>= before`
+        let mut end = num_entries;
+        if let Some(before) = before {
+            let mut start = 0;
+            while start < end {
+                let mid = start + (end - start) / 2;
+                if read_index_entry(index, mid)? < before {
+                    start = mid + 1;
+                } else {
+                    end = mid;
+                }
+            }
+        }
+
+        let mut records = Vec::new();
+        const CHUNK_LEN: u64 = 64;
+
+        while end > 0 && records.len() < limit {
+            // Read a chunk of index entries
+            let start = end.saturating_sub(CHUNK_LEN);
+            let mut chunk = vec![0u8; ((end - start) * INDEX_ENTRY_LEN) as usize];
+            let index = self.index_file(hash)?;
+            index.seek(SeekFrom::Start(start * INDEX_ENTRY_LEN))?;
+            index.read_exact(&mut chunk)?;
+
+            for entry in chunk.chunks_exact(INDEX_ENTRY_LEN as usize).rev() {
+                let pos = RecordPos::from_bytes(entry).unwrap();
+                match self.read(pos) {
+                    // The index file may be shared with other streams
+                    Ok(record) if record.key == key => records.push(record),
+                    Ok(_) => {}
+                    Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+                        log::warn!("Skipping a corrupted record at {:?}", pos);
+                    }
+                    Err(e) => return Err(e),
+                }
+
+                if records.len() >= limit {
+                    break;
+                }
+            }
+
+            end = start;
+        }
+
+        records.reverse();
+        Ok(records)
+    }
+
+    /// Flush all index files to disk. (Segment files are flushed on every
+    /// append.)
+    pub fn sync(&mut self) -> io::Result<()> {
+        let hashes: Vec<u64> = self.unsynced_indexes.iter().cloned().collect();
+        for hash in hashes {
+            match self.index_files.get_mut(hash) {
+                Some(file) => file.sync_data()?,
+                // The eviction failed to sync the file. Reopen it and retry.
+                None => self.index_file(hash)?.sync_data()?,
+            }
+            self.unsynced_indexes.remove(&hash);
+        }
+        Ok(())
+    }
+}
+
+fn segment_file_name(number: u32) -> String {
+    format!("{:08x}.seg", number)
+}
+
+fn parse_segment_file_name(name: &str) -> Option<u32> {
+    if name.len() != 12 || !name.ends_with(".seg") {
+        return None;
+    }
+    u32::from_str_radix(&name[..8], 16).ok()
+}
+
+fn index_file_name(hash: u64) -> String {
+    format!("{:016x}.idx", hash)
+}
+
+fn parse_index_file_name(name: &str) -> Option<u64> {
+    if name.len() != 20 || !name.ends_with(".idx") {
+        return None;
+    }
+    u64::from_str_radix(&name[..16], 16).ok()
+}
+
+/// The FNV-1a hash function. This is used to choose an index file, so it
+/// must not change between versions.
+fn key_hash(key: &[u8]) -> u64 {
+    key.iter().fold(0xcbf29ce484222325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn invalid_record() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid record")
+}
+
+fn read_index_entry(file: &mut File, i: u64) -> io::Result<RecordPos> {
+    let mut entry = [0; RecordPos::ENCODED_LEN];
+    file.seek(SeekFrom::Start(i * INDEX_ENTRY_LEN))?;
+    file.read_exact(&mut entry)?;
+    Ok(RecordPos::from_bytes(&entry).unwrap())
+}
+
+/// The result of [`read_record`].
+#[derive(Debug, PartialEq, Eq)]
+enum ReadRecord {
+    /// A valid record.
+    Valid { key: Vec<u8>, data: Vec<u8> },
+    /// A record with a plausible length field but an invalid body. `len` is
+    /// the length of the record including the header, and the reader is
+    /// positioned at the end of the record.
+    Corrupted { len: u64 },
+    /// There's no complete record at the current position.
+    Incomplete,
+}
+
+/// Read and validate a record. `max_len` specifies the number of remaining
+/// bytes in the file.
+fn read_record(reader: &mut impl Read, max_len: u64) -> io::Result<ReadRecord> {
+    if max_len < HEADER_LEN {
+        return Ok(ReadRecord::Incomplete);
+    }
+
+    let mut header = [0u8; HEADER_LEN as usize];
+    reader.read_exact(&mut header)?;
+
+    let mut body_len = [0; 4];
+    let mut crc = [0; 4];
+    body_len.copy_from_slice(&header[..4]);
+    crc.copy_from_slice(&header[4..]);
+    let body_len = u32::from_le_bytes(body_len) as u64;
+    let crc = u32::from_le_bytes(crc);
+
+    if body_len < 2 || body_len > max_len - HEADER_LEN {
+        return Ok(ReadRecord::Incomplete);
+    }
+
+    let mut body = vec![0u8; body_len as usize];
+    reader.read_exact(&mut body)?;
+
+    let corrupted = ReadRecord::Corrupted {
+        len: HEADER_LEN + body_len,
+    };
+
+    if crc32(&body) != crc {
+        return Ok(corrupted);
+    }
+
+    let key_len = u16::from_le_bytes([body[0], body[1]]) as usize;
+    if key_len > body.len() - 2 {
+        return Ok(corrupted);
+    }
+
+    let data = body.split_off(2 + key_len);
+    body.drain(..2);
+
+    Ok(ReadRecord::Valid { key: body, data })
+}
+
+/// Encode a record and append it to `out`.
+fn encode_record(out: &mut Vec<u8>, key: &[u8], data: &[u8]) {
+    let start = out.len();
+    let body_len = 2 + key.len() + data.len();
+    out.extend_from_slice(&(body_len as u32).to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(key.len() as u16).to_le_bytes());
+    out.extend_from_slice(key);
+    out.extend_from_slice(data);
+
+    let crc = crc32(&out[start + HEADER_LEN as usize..]);
+    out[start + 4..start + 8].copy_from_slice(&crc.to_le_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Get the length of a record including the header.
+fn record_len(key: &[u8], data: &[u8]) -> u64 {
+    HEADER_LEN + 2 + key.len() as u64 + data.len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_pos_roundtrip() {
+        let pos = RecordPos {
+            segment: 0x12345678,
+            offset: 0x123456789abcdef0,
+        };
+        assert_eq!(RecordPos::from_bytes(&pos.to_bytes()), Some(pos));
+        assert_eq!(RecordPos::from_bytes(&[0; 11]), None);
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(parse_segment_file_name(&segment_file_name(42)), Some(42));
+        assert_eq!(parse_index_file_name(&index_file_name(!0)), Some(!0));
+        assert_eq!(parse_segment_file_name("0000002a.idx"), None);
+        assert_eq!(parse_index_file_name("lock"), None);
+    }
+
+    #[test]
+    fn record_roundtrip() {
+        let mut record = Vec::new();
+        encode_record(&mut record, b"key", b"data");
+
+        assert_eq!(
+            read_record(&mut &record[..], record.len() as u64).unwrap(),
+            ReadRecord::Valid {
+                key: b"key".to_vec(),
+                data: b"data".to_vec()
+            }
+        );
+        assert_eq!(record_len(b"key", b"data"), record.len() as u64);
+
+        // Truncated
+        assert_eq!(
+            read_record(&mut &record[..], record.len() as u64 - 1).unwrap(),
+            ReadRecord::Incomplete
+        );
+
+        // Corrupted
+        let last = record.len() - 1;
+        record[last] ^= 1;
+        assert_eq!(
+            read_record(&mut &record[..], record.len() as u64).unwrap(),
+            ReadRecord::Corrupted {
+                len: record.len() as u64
+            }
+        );
+    }
+
+    #[test]
+    fn new_segment_syncs_index() {
+        let dir = std::env::temp_dir().join(format!("seglog_unit_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let options = Options {
+            max_segment_len: 64,
+        };
+        let mut store = Store::open(&dir, options).unwrap();
+        for _ in 0..3 {
+            store.append(b"a", b"0123456789").unwrap();
+        }
+        assert_eq!(store.head.number, 0);
+        assert!(store.unsynced_indexes.contains(&key_hash(b"a")));
+
+        // Only the index entries of the last segment may be left unsynced
+        store.append(b"b", b"0123456789").unwrap();
+        assert_eq!(store.head.number, 1);
+        assert!(!store.unsynced_indexes.contains(&key_hash(b"a")));
+        assert!(store.unsynced_indexes.contains(&key_hash(b"b")));
+
+        drop(store);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,360 @@
+use seglog::{Options, Record, RecordPos, Store};
+use std::{
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+struct TestDir {
+    dir: PathBuf,
+}
+
+impl TestDir {
+    fn new() -> Self {
+        let temp_dir = std::env::temp_dir();
+        let mut i = 0;
+        loop {
+            let dir = temp_dir.join(format!("seglog_test_{}_{}", std::process::id(), i));
+            match fs::create_dir(&dir) {
+                Ok(()) => return Self { dir },
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    // keep searching
+                    i += 1;
+                }
+                Err(e) => {
+                    panic!("Could not create a temporary directory: {:?}", e);
+                }
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+const SMALL_SEGMENTS: Options = Options {
+    max_segment_len: 256,
+};
+
+fn data_of(records: &[Record]) -> Vec<String> {
+    records
+        .iter()
+        .map(|r| String::from_utf8(r.data.clone()).unwrap())
+        .collect()
+}
+
+fn numbered(range: std::ops::Range<usize>) -> Vec<String> {
+    range.map(|i| format!("message {}", i)).collect()
+}
+
+fn segment_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension() == Some("seg".as_ref()))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn index_paths(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension() == Some("idx".as_ref()))
+        .collect()
+}
+
+#[test]
+fn paginate() {
+    let dir = TestDir::new();
+    let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+
+    let positions: Vec<RecordPos> = numbered(0..100)
+        .iter()
+        .map(|data| store.append(b"chan", data.as_bytes()).unwrap())
+        .collect();
+
+    // The records span multiple segments
+    assert!(segment_paths(dir.path()).len() > 1);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    let page = store.read_before(b"chan", None, 30).unwrap();
+    assert_eq!(data_of(&page), numbered(70..100));
+
+    let page = store.read_before(b"chan", Some(page[0].pos), 30).unwrap();
+    assert_eq!(data_of(&page), numbered(40..70));
+
+    let page = store.read_before(b"chan", Some(positions[10]), 30).unwrap();
+    assert_eq!(data_of(&page), numbered(0..10));
+
+    let page = store.read_before(b"chan", Some(positions[0]), 30).unwrap();
+    assert!(page.is_empty());
+
+    let record = store.read(positions[42]).unwrap();
+    assert_eq!(record.key, b"chan");
+    assert_eq!(record.data, b"message 42");
+
+    assert!(store.read_before(b"other", None, 30).unwrap().is_empty());
+}
+
+#[test]
+fn interleaved_keys() {
+    let dir = TestDir::new();
+    let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+
+    for i in 0..50 {
+        store.append(b"a", format!("a{}", i).as_bytes()).unwrap();
+        store.append(b"b", format!("b{}", i).as_bytes()).unwrap();
+    }
+
+    let page = store.read_before(b"a", None, 100).unwrap();
+    assert_eq!(
+        data_of(&page),
+        (0..50).map(|i| format!("a{}", i)).collect::<Vec<_>>()
+    );
+    assert!(page.iter().all(|r| r.key == b"a"));
+
+    let page = store.read_before(b"b", None, 3).unwrap();
+    assert_eq!(data_of(&page), vec!["b47", "b48", "b49"]);
+}
+
+#[test]
+fn reopen() {
+    let dir = TestDir::new();
+    {
+        let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+        for data in numbered(0..50) {
+            store.append(b"chan", data.as_bytes()).unwrap();
+        }
+        store.sync().unwrap();
+    }
+
+    let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+    for data in numbered(50..60) {
+        store.append(b"chan", data.as_bytes()).unwrap();
+    }
+
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), numbered(0..60));
+}
+
+#[test]
+fn torn_tail_is_truncated() {
+    let dir = TestDir::new();
+    {
+        let mut store = Store::open(dir.path(), Options::default()).unwrap();
+        for data in numbered(0..10) {
+            store.append(b"chan", data.as_bytes()).unwrap();
+        }
+    }
+
+    // Simulate a crash in the middle of writing the last record
+    let segment = segment_paths(dir.path()).pop().unwrap();
+    let len = fs::metadata(&segment).unwrap().len();
+    let file = OpenOptions::new().write(true).open(&segment).unwrap();
+    file.set_len(len - 3).unwrap();
+    drop(file);
+
+    let mut store = Store::open(dir.path(), Options::default()).unwrap();
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), numbered(0..9));
+
+    // New records are appended after the last valid record
+    store.append(b"chan", b"message 9").unwrap();
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), numbered(0..10));
+}
+
+#[test]
+fn lost_index_entries_are_recreated() {
+    let dir = TestDir::new();
+    {
+        let mut store = Store::open(dir.path(), Options::default()).unwrap();
+        for data in numbered(0..10) {
+            store.append(b"chan", data.as_bytes()).unwrap();
+        }
+    }
+
+    // Simulate a crash before the last index entries reach the disk
+    let index = index_paths(dir.path()).pop().unwrap();
+    let file = OpenOptions::new().write(true).open(&index).unwrap();
+    file.set_len(12 * 6 + 5).unwrap();
+    drop(file);
+
+    let mut store = Store::open(dir.path(), Options::default()).unwrap();
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), numbered(0..10));
+}
+
+#[test]
+fn new_segment_without_sync() {
+    let dir = TestDir::new();
+    {
+        let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+        for data in numbered(0..50) {
+            store.append(b"chan", data.as_bytes()).unwrap();
+        }
+        // `sync` is not called
+    }
+    let last_segment = segment_paths(dir.path()).len() as u32 - 1;
+    assert!(last_segment > 0);
+
+    // Simulate a power failure before the unsynced index entries reach the
+    // disk. They can only point to the last segment.
+    let index = index_paths(dir.path()).pop().unwrap();
+    let entries = fs::read(&index).unwrap();
+    let num_synced = entries
+        .chunks_exact(RecordPos::ENCODED_LEN)
+        .take_while(|e| RecordPos::from_bytes(e).unwrap().segment < last_segment)
+        .count();
+    let file = OpenOptions::new().write(true).open(&index).unwrap();
+    file.set_len((num_synced * RecordPos::ENCODED_LEN) as u64)
+        .unwrap();
+    drop(file);
+
+    let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), numbered(0..50));
+}
+
+#[test]
+fn dangling_index_entries_are_removed() {
+    let dir = TestDir::new();
+    {
+        let mut store = Store::open(dir.path(), Options::default()).unwrap();
+        for data in numbered(0..10) {
+            store.append(b"chan", data.as_bytes()).unwrap();
+        }
+    }
+
+    // Simulate a crash after an index entry is written but before the
+    // record reaches the disk
+    let segment = segment_paths(dir.path()).pop().unwrap();
+    let len = fs::metadata(&segment).unwrap().len();
+    let file = OpenOptions::new().write(true).open(&segment).unwrap();
+    file.set_len(len - 1).unwrap();
+    drop(file);
+
+    let mut store = Store::open(dir.path(), Options::default()).unwrap();
+    assert_eq!(
+        fs::metadata(index_paths(dir.path()).pop().unwrap())
+            .unwrap()
+            .len(),
+        12 * 9
+    );
+
+    store.append(b"chan", b"new").unwrap();
+    let page = store.read_before(b"chan", None, 3).unwrap();
+    assert_eq!(data_of(&page), vec!["message 7", "message 8", "new"]);
+}
+
+#[test]
+fn corrupted_record_is_skipped() {
+    let dir = TestDir::new();
+    let positions: Vec<RecordPos> = {
+        let mut store = Store::open(dir.path(), Options::default()).unwrap();
+        numbered(0..10)
+            .iter()
+            .map(|data| store.append(b"chan", data.as_bytes()).unwrap())
+            .collect()
+    };
+
+    // Flip the last byte of the body of record 4
+    let segment = segment_paths(dir.path()).pop().unwrap();
+    let mut bytes = fs::read(&segment).unwrap();
+    bytes[positions[5].offset as usize - 1] ^= 1;
+    fs::write(&segment, &bytes).unwrap();
+
+    let mut store = Store::open(dir.path(), Options::default()).unwrap();
+    assert_eq!(fs::metadata(&segment).unwrap().len(), bytes.len() as u64);
+
+    let mut expected = numbered(0..10);
+    expected.remove(4);
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), expected);
+
+    store.append(b"chan", b"message 10").unwrap();
+    expected.push("message 10".to_owned());
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), expected);
+}
+
+#[test]
+fn corrupted_length_truncates_segment() {
+    let dir = TestDir::new();
+    let positions: Vec<RecordPos> = {
+        let mut store = Store::open(dir.path(), Options::default()).unwrap();
+        numbered(0..10)
+            .iter()
+            .map(|data| store.append(b"chan", data.as_bytes()).unwrap())
+            .collect()
+    };
+
+    // Make the length field of record 4 extend past the end of the segment.
+    // The following records can't be located anymore.
+    let segment = segment_paths(dir.path()).pop().unwrap();
+    let mut bytes = fs::read(&segment).unwrap();
+    let offset = positions[4].offset as usize;
+    bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write(&segment, &bytes).unwrap();
+
+    let mut store = Store::open(dir.path(), Options::default()).unwrap();
+    assert_eq!(fs::metadata(&segment).unwrap().len(), positions[4].offset);
+
+    let page = store.read_before(b"chan", None, 100).unwrap();
+    assert_eq!(data_of(&page), numbered(0..4));
+}
+
+#[test]
+fn many_streams() {
+    let dir = TestDir::new();
+    let keys: Vec<String> = (0..100).map(|i| format!("chan{}", i)).collect();
+    {
+        let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+        for i in 0..3 {
+            for key in keys.iter() {
+                let data = format!("{} {}", key, i);
+                store.append(key.as_bytes(), data.as_bytes()).unwrap();
+            }
+        }
+        store.sync().unwrap();
+    }
+
+    assert!(index_paths(dir.path()).len() >= keys.len());
+
+    let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+    for key in keys.iter() {
+        let page = store.read_before(key.as_bytes(), None, 10).unwrap();
+        assert_eq!(
+            data_of(&page),
+            (0..3).map(|i| format!("{} {}", key, i)).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn append_batch() {
+    let dir = TestDir::new();
+    let mut store = Store::open(dir.path(), SMALL_SEGMENTS).unwrap();
+    store.append(b"a", b"a0").unwrap();
+
+    let records: Vec<(&[u8], &[u8])> = vec![(b"a", b"a1"), (b"b", b"b0"), (b"a", b"a2")];
+    let positions = store.append_batch(&records).unwrap();
+    assert_eq!(positions.len(), 3);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(store.read(positions[1]).unwrap().data, b"b0");
+
+    assert!(store.append_batch(&[]).unwrap().is_empty());
+
+    let page = store.read_before(b"a", None, 10).unwrap();
+    assert_eq!(data_of(&page), vec!["a0", "a1", "a2"]);
+    let page = store.read_before(b"b", None, 10).unwrap();
+    assert_eq!(data_of(&page), vec!["b0"]);
+}
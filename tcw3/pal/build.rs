@@ -35,5 +35,10 @@ fn main() {
         }
 
         build.file("src/gtk/wndwidget.c").compile("tcwsupport_gtk");
+
+        // `unix/text.rs` calls these libraries directly to load font files
+        for lib in &["fontconfig", "pangoft2"] {
+            pkg_config::probe_library(lib).unwrap();
+        }
     }
 }
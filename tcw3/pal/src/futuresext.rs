@@ -6,6 +6,7 @@ use std::{
     fmt,
     future::Future,
     ops::Range,
+    path::PathBuf,
     pin::Pin,
     ptr::NonNull,
     rc::Rc,
@@ -13,7 +14,7 @@ use std::{
     time::Duration,
 };
 
-use crate::{prelude::*, HInvoke, MtSticky, Wm};
+use crate::{prelude::*, FileDialogOptions, HInvoke, HWnd, MtSticky, Wm};
 
 /// Extends [`Wm`] for interoperability with futures (`std::future::Future`).
 ///
@@ -35,6 +36,15 @@ pub trait WmFuturesExt {
     ///
     /// [`Wm::invoke_after`]: crate::iface::Wm::invoke_after
     fn sleep(self, dur: Range<Duration>) -> Sleep;
+
+    /// Display a file dialog and wait until the user closes it.
+    ///
+    /// This method is a "futures" version of [`Wm::show_file_dialog`] and is
+    /// internally implemented by this. The returned future resolves to the
+    /// chosen paths, or `None` if the dialog was cancelled.
+    ///
+    /// [`Wm::show_file_dialog`]: crate::iface::Wm::show_file_dialog
+    fn file_dialog(self, owner: Option<&HWnd>, options: FileDialogOptions) -> FileDialog;
 }
 
 impl WmFuturesExt for Wm {
@@ -45,6 +55,10 @@ impl WmFuturesExt for Wm {
     fn sleep(self, dur: Range<Duration>) -> Sleep {
         Sleep::new(self, dur)
     }
+
+    fn file_dialog(self, owner: Option<&HWnd>, options: FileDialogOptions) -> FileDialog {
+        FileDialog::new(self, owner, options)
+    }
 }
 
 // ============================================================================
@@ -272,3 +286,62 @@ impl SleepInner {
         }
     }
 }
+
+// ============================================================================
+
+/// Represents an open file dialog.
+pub struct FileDialog {
+    inner: Rc<FileDialogInner>,
+}
+
+struct FileDialogInner {
+    waker: Cell<Option<Waker>>,
+    result: RefCell<Option<Option<Vec<PathBuf>>>>,
+}
+
+impl FileDialog {
+    fn new(wm: Wm, owner: Option<&HWnd>, options: FileDialogOptions) -> Self {
+        let inner = Rc::new(FileDialogInner {
+            waker: Cell::new(None),
+            result: RefCell::new(None),
+        });
+
+        let inner_weak = Rc::downgrade(&inner);
+        wm.show_file_dialog(
+            owner,
+            options,
+            Box::new(move |_, result| {
+                if let Some(inner) = inner_weak.upgrade() {
+                    *inner.result.borrow_mut() = Some(result);
+                    if let Some(waker) = inner.waker.take() {
+                        waker.wake();
+                    }
+                }
+            }),
+        );
+
+        Self { inner }
+    }
+}
+
+impl fmt::Debug for FileDialog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileDialog")
+            .field("result", &self.inner.result)
+            .finish()
+    }
+}
+
+impl Future for FileDialog {
+    /// The chosen paths, or `None` if the dialog was cancelled.
+    type Output = Option<Vec<PathBuf>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if let Some(result) = self.inner.result.borrow_mut().take() {
+            Poll::Ready(result)
+        } else {
+            self.inner.waker.set(Some(cx.waker().clone()));
+            Poll::Pending
+        }
+    }
+}
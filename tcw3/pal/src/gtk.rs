@@ -1,6 +1,13 @@
 //! The GTK backend.
 use super::iface;
-use std::{cell::RefCell, marker::PhantomData, mem::MaybeUninit, ops::Range, time::Duration};
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::MtLock;
 
@@ -18,7 +25,10 @@ pub use self::{
     text::{CharStyle, TextLayout},
 };
 
+mod clipboard;
 mod comp;
+mod dnd;
+mod filedialog;
 mod textinput;
 mod timer;
 mod window;
@@ -147,6 +157,8 @@ impl iface::Wm for Wm {
 
     fn terminate(self) {
         debug_assert!(Self::is_main_thread());
+        clipboard::store_on_exit(self);
+
         // This is safe because the posession of `Wm` means GTK is already
         // initialized and we are currently in the main thread.
         unsafe {
@@ -182,6 +194,22 @@ impl iface::Wm for Wm {
         window.is_wnd_focused(self)
     }
 
+    fn activate_wnd(self, window: &Self::HWnd) {
+        window.activate_wnd(self)
+    }
+
+    fn get_wnd_position(self, window: &Self::HWnd) -> Option<[i32; 2]> {
+        window.get_wnd_position(self)
+    }
+
+    fn get_wnd_mode(self, window: &Self::HWnd) -> iface::WndMode {
+        window.get_wnd_mode(self)
+    }
+
+    fn screens(self) -> Vec<iface::ScreenInfo> {
+        window::screens()
+    }
+
     fn request_update_ready_wnd(self, window: &Self::HWnd) {
         window.request_update_ready_wnd(self)
     }
@@ -230,6 +258,35 @@ impl iface::Wm for Wm {
     fn remove_text_input_ctx(self, htictx: &Self::HTextInputCtx) {
         htictx.remove(self);
     }
+
+    fn clipboard_formats(self) -> iface::ClipboardFormats {
+        clipboard::formats(self)
+    }
+
+    fn clipboard_read_text(self) -> Option<String> {
+        clipboard::read_text(self)
+    }
+
+    fn clipboard_write_text(self, text: &str) {
+        clipboard::write_text(self, text)
+    }
+
+    fn set_clipboard_listener(self, listener: Box<dyn iface::ClipboardListener<Self>>) {
+        clipboard::set_listener(self, listener)
+    }
+
+    fn show_file_dialog(
+        self,
+        owner: Option<&Self::HWnd>,
+        options: iface::FileDialogOptions,
+        handler: Box<dyn FnOnce(Self, Option<Vec<PathBuf>>)>,
+    ) {
+        filedialog::show_file_dialog(self, owner, options, handler)
+    }
+
+    fn register_font_file(self, path: &Path) -> Result<(), iface::RegisterFontError> {
+        text::register_font_file(path)
+    }
 }
 
 struct AssertSend<T>(T);
@@ -0,0 +1,158 @@
+//! Implements the clipboard API on top of `GtkClipboard`.
+use gtk::prelude::*;
+use std::{
+    cell::RefCell,
+    os::raw::{c_char, c_int},
+    ptr::null_mut,
+    rc::Rc,
+};
+
+use super::Wm;
+use crate::{iface, MtSticky};
+
+struct ClipboardState {
+    listener: Option<Rc<dyn iface::ClipboardListener<Wm>>>,
+    /// `true` if we've already connected to `GtkClipboard::owner-change`.
+    connected: bool,
+    /// `true` if the clipboard contains text as of the last response to
+    /// `gtk_clipboard_request_targets`. `formats` uses this instead of
+    /// `gtk_clipboard_wait_is_text_available`, which runs a nested main loop.
+    has_text: bool,
+    /// `true` if we've written to the clipboard. The contents are handed over
+    /// to the clipboard manager on exit.
+    wrote_text: bool,
+}
+
+static STATE: MtSticky<RefCell<ClipboardState>, Wm> = MtSticky::new(RefCell::new(ClipboardState {
+    listener: None,
+    connected: false,
+    has_text: false,
+    wrote_text: false,
+}));
+
+fn gtk_clipboard() -> gtk::Clipboard {
+    gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD)
+}
+
+/// Implements `Wm::clipboard_formats`.
+///
+/// This returns a cached value, which is updated asynchronously. The listener
+/// is notified when the value changes.
+pub fn formats(wm: Wm) -> iface::ClipboardFormats {
+    watch_owner_change(wm);
+
+    let mut formats = iface::ClipboardFormats::empty();
+    if STATE.get_with_wm(wm).borrow().has_text {
+        formats |= iface::ClipboardFormats::PLAIN_TEXT;
+    }
+    formats
+}
+
+/// Implements `Wm::clipboard_read_text`.
+pub fn read_text(_: Wm) -> Option<String> {
+    gtk_clipboard().wait_for_text().map(String::from)
+}
+
+/// Implements `Wm::clipboard_write_text`.
+pub fn write_text(wm: Wm, text: &str) {
+    gtk_clipboard().set_text(text);
+
+    let mut state = STATE.get_with_wm(wm).borrow_mut();
+    state.has_text = true;
+    state.wrote_text = true;
+}
+
+/// Make the contents written by `write_text` available even after the
+/// application exits (if supported by the clipboard manager). Called by
+/// `Wm::terminate`.
+pub fn store_on_exit(wm: Wm) {
+    if STATE.get_with_wm(wm).borrow().wrote_text {
+        gtk_clipboard().store();
+    }
+}
+
+/// Implements `Wm::set_clipboard_listener`.
+pub fn set_listener(wm: Wm, listener: Box<dyn iface::ClipboardListener<Wm>>) {
+    STATE.get_with_wm(wm).borrow_mut().listener = Some(listener.into());
+    watch_owner_change(wm);
+}
+
+/// Start updating `ClipboardState::has_text` when the clipboard contents
+/// change.
+fn watch_owner_change(wm: Wm) {
+    {
+        let mut state = STATE.get_with_wm(wm).borrow_mut();
+        if state.connected {
+            return;
+        }
+        state.connected = true;
+
+        // `gtk-rs` doesn't expose `owner-change` (the event type isn't
+        // supported by the binding generator), so connect to it by ourselves
+        let clipboard = gtk_clipboard();
+        unsafe {
+            let handler: unsafe extern "C" fn(
+                *mut gtk_sys::GtkClipboard,
+                *mut gdk_sys::GdkEventOwnerChange,
+                glib_sys::gpointer,
+            ) = handle_owner_change;
+
+            gobject_sys::g_signal_connect_data(
+                clipboard.as_ptr() as _,
+                b"owner-change\0".as_ptr() as *const c_char,
+                Some(std::mem::transmute(handler)),
+                null_mut(),
+                None,
+                0,
+            );
+        }
+    }
+
+    request_targets();
+}
+
+/// Update `ClipboardState::has_text` asynchronously.
+fn request_targets() {
+    unsafe {
+        gtk_sys::gtk_clipboard_request_targets(
+            gtk_clipboard().as_ptr(),
+            Some(handle_targets_received),
+            null_mut(),
+        );
+    }
+}
+
+unsafe extern "C" fn handle_targets_received(
+    _: *mut gtk_sys::GtkClipboard,
+    atoms: *mut gdk_sys::GdkAtom,
+    n_atoms: c_int,
+    _: glib_sys::gpointer,
+) {
+    // Callbacks are called on the main thread, so this is safe
+    let wm = Wm::global_unchecked();
+
+    // `atoms` is null if the clipboard is empty or the request failed
+    let has_text =
+        !atoms.is_null() && gtk_sys::gtk_targets_include_text(atoms, n_atoms) != glib_sys::GFALSE;
+
+    let listener = {
+        let mut state = STATE.get_with_wm(wm).borrow_mut();
+        state.has_text = has_text;
+        state.listener.clone()
+    };
+
+    // Notify after updating `has_text` so that the listener sees the new
+    // clipboard formats
+    if let Some(listener) = listener {
+        listener.change(wm);
+    }
+}
+
+unsafe extern "C" fn handle_owner_change(
+    _: *mut gtk_sys::GtkClipboard,
+    _: *mut gdk_sys::GdkEventOwnerChange,
+    _: glib_sys::gpointer,
+) {
+    // The listener is notified when the new contents' targets are known
+    request_targets();
+}
@@ -0,0 +1,188 @@
+//! Implements drop targets on top of GTK's drag-and-drop API.
+use cgmath::Point2;
+use gtk::prelude::*;
+use std::{cell::RefCell, rc::Rc};
+
+use super::{HWnd, Wm};
+use crate::{iface, iface::Wm as _};
+
+#[derive(Debug, Default)]
+struct DropState {
+    /// `true` if `WndListener::drag_enter` has been called, and
+    /// `WndListener::drag_leave` or `WndListener::drag_drop` hasn't been
+    /// called yet.
+    entered: bool,
+    /// `true` if a call to `WndListener::drag_leave` is scheduled.
+    leave_pending: bool,
+    /// The location where the data was dropped. Set by `drag-drop` and
+    /// consumed by `drag-data-received`.
+    drop_loc: Option<Point2<f32>>,
+}
+
+/// Register `widget` as a drop target. The events are delivered to the
+/// `WndListener` returned by `get_listener`.
+pub(super) fn connect_drop_target(
+    wm: Wm,
+    hwnd: HWnd,
+    widget: &gtk::Widget,
+    get_listener: impl Fn() -> Option<Rc<dyn iface::WndListener<Wm>>> + 'static,
+) {
+    widget.drag_dest_set(
+        gtk::DestDefaults::empty(),
+        &[],
+        gdk::DragAction::COPY | gdk::DragAction::MOVE | gdk::DragAction::LINK,
+    );
+    widget.drag_dest_add_uri_targets();
+    widget.drag_dest_add_text_targets();
+
+    let state = Rc::new(RefCell::new(DropState::default()));
+    let get_listener = Rc::new(get_listener);
+
+    {
+        let (state, get_listener, hwnd) =
+            (Rc::clone(&state), Rc::clone(&get_listener), hwnd.clone());
+        widget.connect_drag_motion(move |_, ctx, x, y, time| {
+            let listener = if let Some(x) = get_listener() {
+                x
+            } else {
+                return Inhibit(false);
+            };
+
+            let loc = Point2::new(x as f32, y as f32);
+            let formats = formats_from_targets(&ctx.list_targets());
+            let ops = ops_from_gdk(ctx.get_actions());
+
+            let entered = {
+                let mut state = state.borrow_mut();
+                state.leave_pending = false;
+                std::mem::replace(&mut state.entered, true)
+            };
+
+            let op = if entered {
+                listener.drag_over(wm, &hwnd, loc, formats, ops)
+            } else {
+                listener.drag_enter(wm, &hwnd, loc, formats, ops)
+            };
+
+            ctx.drag_status(op_to_gdk(op), time);
+            Inhibit(true)
+        });
+    }
+
+    {
+        let (state, get_listener, hwnd) =
+            (Rc::clone(&state), Rc::clone(&get_listener), hwnd.clone());
+        widget.connect_drag_leave(move |_, _, _| {
+            {
+                let mut state = state.borrow_mut();
+                if !state.entered {
+                    return;
+                }
+                state.entered = false;
+                state.leave_pending = true;
+            }
+
+            // GTK emits `drag-leave` right before `drag-drop`. Defer the call
+            // so that it can be cancelled by `drag-drop`.
+            let (state, get_listener, hwnd) =
+                (Rc::clone(&state), Rc::clone(&get_listener), hwnd.clone());
+            wm.invoke(move |wm| {
+                if !std::mem::replace(&mut state.borrow_mut().leave_pending, false) {
+                    return;
+                }
+                if let Some(listener) = get_listener() {
+                    listener.drag_leave(wm, &hwnd);
+                }
+            });
+        });
+    }
+
+    {
+        let state = Rc::clone(&state);
+        widget.connect_drag_drop(move |widget, ctx, x, y, time| {
+            {
+                let mut state = state.borrow_mut();
+                state.leave_pending = false;
+                state.drop_loc = Some(Point2::new(x as f32, y as f32));
+            }
+
+            // Request the data. The drop is completed in `drag-data-received`.
+            if let Some(target) = widget.drag_dest_find_target(ctx, None) {
+                widget.drag_get_data(ctx, &target, time);
+            } else {
+                state.borrow_mut().drop_loc = None;
+                ctx.drag_finish(false, false, time);
+            }
+
+            Inhibit(true)
+        });
+    }
+
+    widget.connect_drag_data_received(move |_, ctx, x, y, selection, _, time| {
+        let loc = state
+            .borrow_mut()
+            .drop_loc
+            .take()
+            .unwrap_or_else(|| Point2::new(x as f32, y as f32));
+
+        let listener = if let Some(x) = get_listener() {
+            x
+        } else {
+            ctx.drag_finish(false, false, time);
+            return;
+        };
+
+        let mut data = iface::DragData::default();
+        for uri in selection.get_uris() {
+            match glib::filename_from_uri(&uri) {
+                Ok((path, _)) => data.files.push(path),
+                Err(_) => data.uris.push(uri.into()),
+            }
+        }
+        data.text = selection.get_text().map(Into::into);
+
+        let op = listener.drag_drop(wm, &hwnd, loc, &data, ops_from_gdk(ctx.get_actions()));
+
+        ctx.drag_finish(op.is_some(), op == Some(iface::DragOp::Move), time);
+    });
+}
+
+fn formats_from_targets(targets: &[gdk::Atom]) -> iface::DragFormats {
+    let mut formats = iface::DragFormats::empty();
+    for target in targets {
+        match target.name().as_str() {
+            // A URI list may contain both local files and other URIs, which
+            // can't be distinguished until the data is received
+            "text/uri-list" => formats |= iface::DragFormats::FILES | iface::DragFormats::URIS,
+            "text/plain" | "text/plain;charset=utf-8" | "UTF8_STRING" | "STRING" | "TEXT" => {
+                formats |= iface::DragFormats::TEXT
+            }
+            _ => {}
+        }
+    }
+    formats
+}
+
+fn ops_from_gdk(actions: gdk::DragAction) -> iface::DragOps {
+    let mut ops = iface::DragOps::empty();
+    if actions.contains(gdk::DragAction::COPY) {
+        ops |= iface::DragOps::COPY;
+    }
+    if actions.contains(gdk::DragAction::MOVE) {
+        ops |= iface::DragOps::MOVE;
+    }
+    if actions.contains(gdk::DragAction::LINK) {
+        ops |= iface::DragOps::LINK;
+    }
+    ops
+}
+
+fn op_to_gdk(op: Option<iface::DragOp>) -> gdk::DragAction {
+    match op {
+        Some(iface::DragOp::Copy) => gdk::DragAction::COPY,
+        Some(iface::DragOp::Move) => gdk::DragAction::MOVE,
+        Some(iface::DragOp::Link) => gdk::DragAction::LINK,
+        // An empty set rejects the drop
+        None => gdk::DragAction::empty(),
+    }
+}
@@ -0,0 +1,85 @@
+//! Implements file dialogs on top of `GtkFileChooserDialog`.
+use gtk::prelude::*;
+use std::{cell::Cell, path::PathBuf};
+
+use super::{HWnd, Wm};
+use crate::iface::{FileDialogKind, FileDialogOptions};
+
+/// Implements `Wm::show_file_dialog`.
+pub fn show_file_dialog(
+    wm: Wm,
+    owner: Option<&HWnd>,
+    options: FileDialogOptions,
+    handler: Box<dyn FnOnce(Wm, Option<Vec<PathBuf>>)>,
+) {
+    let (action, default_title, accept_label) = match options.kind {
+        FileDialogKind::Open => (gtk::FileChooserAction::Open, "Open File", "_Open"),
+        FileDialogKind::OpenMultiple => (gtk::FileChooserAction::Open, "Open Files", "_Open"),
+        FileDialogKind::Save => (gtk::FileChooserAction::Save, "Save File", "_Save"),
+        FileDialogKind::ChooseFolder => (
+            gtk::FileChooserAction::SelectFolder,
+            "Select Folder",
+            "_Select",
+        ),
+    };
+
+    let owner = owner.map(|hwnd| hwnd.gtk_window(wm));
+    let title = options.title.as_deref().unwrap_or(default_title);
+
+    let dialog = gtk::FileChooserDialog::with_buttons(
+        Some(title),
+        owner.as_ref(),
+        action,
+        &[
+            ("_Cancel", gtk::ResponseType::Cancel),
+            (accept_label, gtk::ResponseType::Accept),
+        ],
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept);
+    dialog.set_modal(owner.is_some());
+    dialog.set_select_multiple(options.kind == FileDialogKind::OpenMultiple);
+
+    if options.kind == FileDialogKind::Save {
+        dialog.set_do_overwrite_confirmation(true);
+        if let Some(name) = &options.file_name {
+            dialog.set_current_name(name);
+        }
+    }
+
+    if let Some(folder) = &options.folder {
+        dialog.set_current_folder(folder);
+    }
+
+    if options.kind != FileDialogKind::ChooseFolder {
+        for filter in options.filters.iter() {
+            let gtk_filter = gtk::FileFilter::new();
+            gtk_filter.set_name(Some(&filter.name));
+            for pattern in filter.patterns.iter() {
+                gtk_filter.add_pattern(pattern);
+            }
+            dialog.add_filter(&gtk_filter);
+        }
+    }
+
+    // `connect_response` requires `Fn`, but `handler` is `FnOnce`
+    let handler = Cell::new(Some(handler));
+    dialog.connect_response(move |dialog, response| {
+        let handler = if let Some(x) = handler.take() {
+            x
+        } else {
+            return;
+        };
+
+        let result = if response == gtk::ResponseType::Accept {
+            Some(dialog.get_filenames())
+        } else {
+            None
+        };
+
+        dialog.destroy();
+
+        handler(wm, result);
+    });
+
+    dialog.show();
+}
@@ -1,3 +1,4 @@
+use cggeom::{box2, prelude::*};
 use cgmath::{Point2, Vector2};
 use gdk::prelude::*;
 use glib::{
@@ -14,7 +15,7 @@ use std::{
     rc::Rc,
 };
 
-use super::{comp, Wm, WndAttrs};
+use super::{comp, dnd, Wm, WndAttrs};
 use crate::{actions, iface, prelude::*, MtSticky};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -33,12 +34,20 @@ pub(super) static COMPOSITOR: MtSticky<RefCell<comp::Compositor>, Wm> =
 
 static DRAWING_WND: MtSticky<Cell<Option<WndPoolPtr>>, Wm> = MtSticky::new(Cell::new(None));
 
+/// The window having the keyboard focus.
+static FOCUSED_WND: MtSticky<Cell<Option<WndPoolPtr>>, Wm> = MtSticky::new(Cell::new(None));
+
+/// The windows having `WndFlags::POPUP`.
+static POPUP_WNDS: MtSticky<RefCell<Vec<WndPoolPtr>>, Wm> = MtSticky::new(RefCell::new(Vec::new()));
+
 struct Wnd {
     gtk_wnd: gtk::Window,
     gtk_widget: WndWidget,
     comp_wnd: comp::Wnd,
     listener: Rc<dyn iface::WndListener<Wm>>,
     flags: iface::WndFlags,
+    owner: Option<WndPoolPtr>,
+    popup_anchor: Option<iface::PopupAnchor>,
 
     /// The last known size of the window.
     size: [i32; 2],
@@ -110,6 +119,8 @@ impl HWnd {
             gtk_widget,
             comp_wnd,
             flags: iface::WndFlags::default(),
+            owner: None,
+            popup_anchor: None,
             listener: Rc::new(()),
             size: [0, 0],
             tick_callback_active: false,
@@ -145,6 +156,31 @@ impl HWnd {
             listener.focus(wm, &Self { ptr });
         });
 
+        wnd.gtk_wnd.connect_focus_in_event(move |_, _| {
+            FOCUSED_WND.get_with_wm(wm).set(Some(ptr));
+            Inhibit(false)
+        });
+
+        wnd.gtk_wnd.connect_focus_out_event(move |_, _| {
+            let focused_wnd = FOCUSED_WND.get_with_wm(wm);
+            if focused_wnd.get() == Some(ptr) {
+                focused_wnd.set(None);
+            }
+
+            // The focus might be moving to a popup window owned by this one.
+            // Wait until the window receiving the focus is known.
+            wm.invoke(move |wm| {
+                let focused_wnd = FOCUSED_WND.get_with_wm(wm).get();
+                dismiss_popups(wm, focused_wnd);
+            });
+            Inhibit(false)
+        });
+
+        dnd::connect_drop_target(wm, Self { ptr }, wnd.gtk_widget.upcast_ref(), move || {
+            let wnds = WNDS.get_with_wm(wm).borrow();
+            wnds.get(ptr).map(|wnd| Rc::clone(&wnd.listener))
+        });
+
         // `set_wnd_attr` borrows `WNDS`, so unborrow it before calling that
         drop(wnds);
 
@@ -156,6 +192,17 @@ impl HWnd {
     /// Implements `Wm::set_wnd_attr`.
     pub(super) fn set_wnd_attr(&self, wm: Wm, attrs: WndAttrs<'_>) {
         let mut wnds = WNDS.get_with_wm(wm).borrow_mut();
+
+        let should_place_popup = attrs.size.is_some()
+            || attrs.owner.is_some()
+            || attrs.popup_anchor.is_some()
+            || attrs.visible == Some(true);
+
+        // Get the owner's `gtk::Window` before borrowing `wnd`
+        let owner = attrs.owner.map(|owner_or_none| {
+            owner_or_none.map(|hwnd| (hwnd.ptr, wnds[hwnd.ptr].gtk_wnd.clone()))
+        });
+
         let wnd = &mut wnds[self.ptr];
 
         if let Some(size) = attrs.size {
@@ -163,6 +210,10 @@ impl HWnd {
             wnd.gtk_wnd.set_default_size(size[0] as i32, size[1] as i32);
         }
 
+        if let Some([x, y]) = attrs.position {
+            wnd.gtk_wnd.move_(x, y);
+        }
+
         if let Some(size) = attrs.min_size {
             wnd.gtk_widget
                 .set_size_request(size[0] as i32, size[1] as i32);
@@ -187,9 +238,38 @@ impl HWnd {
                     });
             }
 
+            if (wnd.flags ^ flags).contains(iface::WndFlags::POPUP) {
+                let popup = flags.contains(iface::WndFlags::POPUP);
+                wnd.gtk_wnd.set_type_hint(if popup {
+                    gdk::WindowTypeHint::PopupMenu
+                } else {
+                    gdk::WindowTypeHint::Normal
+                });
+                wnd.gtk_wnd.set_skip_taskbar_hint(popup);
+                wnd.gtk_wnd.set_skip_pager_hint(popup);
+                wnd.gtk_wnd.set_decorated(!popup);
+
+                let mut popup_wnds = POPUP_WNDS.get_with_wm(wm).borrow_mut();
+                if popup {
+                    popup_wnds.push(self.ptr);
+                } else {
+                    popup_wnds.retain(|&ptr| ptr != self.ptr);
+                }
+            }
+
             wnd.flags = flags;
         }
 
+        if let Some(owner) = owner {
+            wnd.gtk_wnd
+                .set_transient_for(owner.as_ref().map(|(_, gtk_wnd)| gtk_wnd));
+            wnd.owner = owner.map(|(ptr, _)| ptr);
+        }
+
+        if let Some(anchor) = attrs.popup_anchor {
+            wnd.popup_anchor = anchor;
+        }
+
         if let Some(layer) = attrs.layer {
             COMPOSITOR
                 .get_with_wm(wm)
@@ -250,10 +330,37 @@ impl HWnd {
                 .set_cursor(cursor.as_ref());
         }
 
+        if should_place_popup {
+            place_popup(&wnds, self.ptr);
+        }
+        let wnd = &wnds[self.ptr];
+
         if let Some(caption) = attrs.caption {
             wnd.gtk_wnd.set_title(&caption);
         }
 
+        if let Some(mode) = attrs.mode {
+            use self::iface::WndMode;
+            let gtk_wnd = &wnd.gtk_wnd;
+            match mode {
+                WndMode::Normal => {
+                    gtk_wnd.unfullscreen();
+                    gtk_wnd.unmaximize();
+                    gtk_wnd.deiconify();
+                }
+                WndMode::Minimized => gtk_wnd.iconify(),
+                WndMode::Maximized => {
+                    gtk_wnd.unfullscreen();
+                    gtk_wnd.maximize();
+                    gtk_wnd.deiconify();
+                }
+                WndMode::Fullscreen => {
+                    gtk_wnd.fullscreen();
+                    gtk_wnd.deiconify();
+                }
+            }
+        }
+
         if let Some(visible) = attrs.visible {
             if visible {
                 wnd.gtk_wnd.show_all();
@@ -275,6 +382,11 @@ impl HWnd {
             .deallocate(self.ptr)
             .unwrap();
 
+        POPUP_WNDS
+            .get_with_wm(wm)
+            .borrow_mut()
+            .retain(|&ptr| ptr != self.ptr);
+
         // Delete scroll tick callback
         if let Some(scroll_state) = &wnd.scroll_state {
             if let Some(momentum_state) = &scroll_state.momentum {
@@ -356,6 +468,47 @@ impl HWnd {
             .contains(gtk::StateFlags::BACKDROP)
     }
 
+    /// Implements `Wm::get_wnd_position`.
+    pub(super) fn get_wnd_position(&self, wm: Wm) -> Option<[i32; 2]> {
+        // Wayland doesn't let clients know the positions of toplevel windows.
+        // `gtk_window_get_position` returns a meaningless value there.
+        if is_wayland() {
+            return None;
+        }
+
+        let wnds = WNDS.get_with_wm(wm).borrow();
+        let (x, y) = wnds[self.ptr].gtk_wnd.get_position();
+        Some([x, y])
+    }
+
+    /// Implements `Wm::get_wnd_mode`.
+    pub(super) fn get_wnd_mode(&self, wm: Wm) -> iface::WndMode {
+        let wnds = WNDS.get_with_wm(wm).borrow();
+        let state = if let Some(gdk_wnd) = wnds[self.ptr].gtk_wnd.get_window() {
+            gdk_wnd.get_state()
+        } else {
+            // The window isn't realized yet
+            return iface::WndMode::Normal;
+        };
+
+        if state.contains(gdk::WindowState::ICONIFIED) {
+            iface::WndMode::Minimized
+        } else if state.contains(gdk::WindowState::FULLSCREEN) {
+            iface::WndMode::Fullscreen
+        } else if state.contains(gdk::WindowState::MAXIMIZED) {
+            iface::WndMode::Maximized
+        } else {
+            iface::WndMode::Normal
+        }
+    }
+
+    /// Implements `Wm::activate_wnd`.
+    pub(super) fn activate_wnd(&self, wm: Wm) {
+        let wnds = WNDS.get_with_wm(wm).borrow();
+        let gtk_wnd = &wnds[self.ptr].gtk_wnd;
+        gtk_wnd.present();
+    }
+
     /// Implements `Wm::request_update_ready_wnd`.
     pub(super) fn request_update_ready_wnd(&self, wm: Wm) {
         let mut wnds = WNDS.get_with_wm(wm).borrow_mut();
@@ -450,6 +603,133 @@ impl HWnd {
     }
 }
 
+/// Implements `Wm::screens`.
+pub(super) fn screens() -> Vec<iface::ScreenInfo> {
+    let display = if let Some(x) = gdk::Display::get_default() {
+        x
+    } else {
+        return Vec::new();
+    };
+
+    let rect_to_box2 = |r: gdk::Rectangle| {
+        box2! {
+            min: [r.x, r.y],
+            max: [r.x + r.width, r.y + r.height],
+        }
+    };
+
+    (0..display.get_n_monitors())
+        .filter_map(|i| display.get_monitor(i))
+        .map(|monitor| iface::ScreenInfo {
+            bounds: rect_to_box2(monitor.get_geometry()),
+            work_area: rect_to_box2(monitor.get_workarea()),
+            dpi_scale: monitor.get_scale_factor() as f32,
+            primary: monitor.is_primary(),
+        })
+        .collect()
+}
+
+/// Check if the default display is a Wayland display.
+fn is_wayland() -> bool {
+    gdk::Display::get_default()
+        .map(|display| glib::ObjectExt::get_type(&display).name() == "GdkWaylandDisplay")
+        .unwrap_or(false)
+}
+
+/// Move a popup window to the location specified by `Wnd::popup_anchor`.
+///
+/// Note that this has no effect on Wayland, where clients can't position
+/// toplevel windows by themselves.
+fn place_popup(wnds: &WndPool, ptr: WndPoolPtr) {
+    let wnd = &wnds[ptr];
+    let (owner, anchor) = match (wnd.owner, &wnd.popup_anchor) {
+        (Some(owner), Some(anchor)) if wnd.flags.contains(iface::WndFlags::POPUP) => {
+            (&wnds[owner], anchor)
+        }
+        _ => return,
+    };
+
+    let owner_gdk_wnd = if let Some(x) = owner.gtk_wnd.get_window() {
+        x
+    } else {
+        // The owner isn't realized yet
+        return;
+    };
+
+    // Find the origin of the owner's client region in the screen coordinates
+    let (_, mut x, mut y) = owner_gdk_wnd.get_origin();
+    if let Some((cx, cy)) = owner.gtk_widget.translate_coordinates(&owner.gtk_wnd, 0, 0) {
+        x += cx;
+        y += cy;
+    }
+    let origin = Vector2::new(x as f32, y as f32);
+
+    let bounds = if let Some(monitor) = owner_gdk_wnd
+        .get_display()
+        .get_monitor_at_window(&owner_gdk_wnd)
+    {
+        let r = monitor.get_workarea();
+        box2! {
+            min: [r.x as f32, r.y as f32],
+            max: [(r.x + r.width) as f32, (r.y + r.height) as f32],
+        }
+    } else {
+        box2! { min: [-1.0e6, -1.0e6], max: [1.0e6, 1.0e6] }
+    };
+
+    let (width, height) = wnd.gtk_wnd.get_size();
+    let anchor = iface::PopupAnchor {
+        rect: anchor.rect.translate(origin),
+        ..*anchor
+    };
+    let loc = anchor.place([width as f32, height as f32], bounds);
+
+    wnd.gtk_wnd.move_(loc.x as i32, loc.y as i32);
+}
+
+/// Raise `close_requested` for the visible popup windows except those
+/// containing `keep`, i.e., the popup windows that are not `keep` and don't
+/// own `keep` directly or indirectly.
+///
+/// This is called when the keyboard focus moves (with `keep` set to the
+/// window receiving the focus) and when the user presses a mouse button in a
+/// window (with `keep` set to that window), so that popups are dismissed even
+/// if they don't have the keyboard focus.
+fn dismiss_popups(wm: Wm, keep: Option<WndPoolPtr>) {
+    let listeners: Vec<_> = {
+        let wnds = WNDS.get_with_wm(wm).borrow();
+        let popup_wnds = POPUP_WNDS.get_with_wm(wm).borrow();
+
+        // Return `true` if `ptr` is identical to or owned by `owner`
+        let is_owned_by = |mut ptr: WndPoolPtr, owner: WndPoolPtr| loop {
+            if ptr == owner {
+                return true;
+            }
+            ptr = match wnds.get(ptr).and_then(|wnd| wnd.owner) {
+                Some(x) => x,
+                None => return false,
+            };
+        };
+
+        popup_wnds
+            .iter()
+            .filter_map(|&ptr| {
+                let wnd = wnds.get(ptr)?;
+                let contains_keep = keep.map_or(false, |keep| is_owned_by(keep, ptr));
+                if wnd.gtk_wnd.is_visible() && !contains_keep {
+                    Some((ptr, Rc::clone(&wnd.listener)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+
+    for (ptr, listener) in listeners {
+        listener.close_requested(wm, &HWnd { ptr });
+    }
+}
+
 fn comp_surf_props_for_widget(w: &WndWidget) -> ([usize; 2], f32) {
     let factor = w.get_scale_factor() as usize;
 
@@ -698,6 +978,13 @@ extern "C" fn tcw_wnd_widget_button_handler(
         let loc = Point2::new(x, y);
         let button_mask = 1 << button;
 
+        if is_pressed != 0 {
+            // Clicking outside popup windows dismisses them. Popup windows
+            // don't necessarily have the keyboard focus, so we can't rely on
+            // focus events for this.
+            dismiss_popups(wm, Some(ptr));
+        }
+
         let mut wnds = WNDS.get_with_wm(wm).borrow_mut();
 
         // Stop any ongoing scroll gesture (just in case)
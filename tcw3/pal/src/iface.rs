@@ -7,10 +7,18 @@
 //! specialized for the default backend, as well as simple re-exports of
 //! non-generic types.
 use bitflags::bitflags;
-use cggeom::{box2, Box2};
+use cggeom::{box2, prelude::*, Box2};
 use cgmath::{Matrix3, Point2, Vector2};
 use rgb::RGBA;
-use std::{borrow::Cow, fmt, fmt::Debug, hash::Hash, ops::Range, time::Duration};
+use std::{
+    borrow::Cow,
+    fmt,
+    fmt::Debug,
+    hash::Hash,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 pub type RGBAF32 = RGBA<f32>;
 
@@ -163,6 +171,37 @@ pub trait Wm: Clone + Copy + Sized + Debug + 'static {
     /// Get a flag indicating whether the specified window has focus.
     fn is_wnd_focused(self, window: &Self::HWnd) -> bool;
 
+    /// Bring a window to the front and give it focus.
+    ///
+    /// The system may refuse to do this, e.g., if another application is
+    /// being used by the user. In this case, the implementation may draw the
+    /// user's attention to the window in some other way.
+    fn activate_wnd(self, window: &Self::HWnd);
+
+    /// Get the position of a window.
+    ///
+    /// The returned value is in the same coordinate space as
+    /// [`ScreenInfo::bounds`] and can be passed to [`WndAttrs::position`] to
+    /// restore the placement later. Returns `None` if the backend can't
+    /// determine window positions.
+    fn get_wnd_position(self, _window: &Self::HWnd) -> Option<[i32; 2]> {
+        None
+    }
+
+    /// Get the current mode of a window. The mode can be changed by the user
+    /// as well as by [`WndAttrs::mode`].
+    fn get_wnd_mode(self, _window: &Self::HWnd) -> WndMode {
+        WndMode::Normal
+    }
+
+    /// Get the list of screens (monitors) connected to the system.
+    ///
+    /// Returns an empty list if the backend doesn't support screen
+    /// enumeration.
+    fn screens(self) -> Vec<ScreenInfo> {
+        Vec::new()
+    }
+
     /// Create a layer.
     fn new_layer(self, attrs: LayerAttrs<Self::Bitmap, Self::HLayer>) -> Self::HLayer;
 
@@ -226,6 +265,72 @@ pub trait Wm: Clone + Copy + Sized + Debug + 'static {
     ///
     /// [`TextInputCtxListener::edit`] may be called in this method.
     fn remove_text_input_ctx(self, ctx: &Self::HTextInputCtx);
+
+    /// Get the set of data formats currently available in the system
+    /// clipboard.
+    ///
+    /// This method doesn't block. The returned value may lag behind the
+    /// actual clipboard contents, in which case [`ClipboardListener::change`]
+    /// is called when it's updated.
+    ///
+    /// The default implementation returns an empty set, which indicates that
+    /// the backend doesn't support the clipboard.
+    fn clipboard_formats(self) -> ClipboardFormats {
+        ClipboardFormats::empty()
+    }
+
+    /// Read a plain text from the system clipboard.
+    ///
+    /// Returns `None` if the clipboard doesn't contain a text.
+    ///
+    /// This method may block the calling thread until the clipboard owner
+    /// responds to the request.
+    fn clipboard_read_text(self) -> Option<String> {
+        None
+    }
+
+    /// Replace the contents of the system clipboard with a plain text.
+    ///
+    /// This may cause [`ClipboardListener::change`] to be called later (but
+    /// not in this method).
+    fn clipboard_write_text(self, _text: &str) {}
+
+    /// Set the event handlers for receiving clipboard change notifications.
+    ///
+    /// Replaces the previously set one (if any). It's not allowed to call this
+    /// method while a method of the current one is being called.
+    fn set_clipboard_listener(self, _listener: Box<dyn ClipboardListener<Self>>) {}
+
+    /// Display a file dialog.
+    ///
+    /// `handler` is called with the chosen paths when the dialog is closed, or
+    /// with `None` if the user cancelled it. [`FileDialogKind::Open`],
+    /// [`FileDialogKind::Save`], and [`FileDialogKind::ChooseFolder`] produce
+    /// exactly one path. `handler` is never called in this method.
+    ///
+    /// The dialog is modal to `owner` if it's specified.
+    ///
+    /// The default implementation cancels the dialog immediately, which
+    /// indicates that the backend doesn't support file dialogs.
+    fn show_file_dialog(
+        self,
+        _owner: Option<&Self::HWnd>,
+        _options: FileDialogOptions,
+        handler: Box<dyn FnOnce(Self, Option<Vec<PathBuf>>)>,
+    ) {
+        self.invoke(move |wm| handler(wm, None));
+    }
+
+    /// Load the fonts in a font file (e.g., a TrueType file bundled with the
+    /// application) and make them available to [`CharStyleAttrs::family`]
+    /// for the rest of the process's lifetime. The fonts can be used by text
+    /// layouts created afterward on any thread.
+    ///
+    /// The default implementation returns
+    /// [`RegisterFontError::Unsupported`].
+    fn register_font_file(self, _path: &Path) -> Result<(), RegisterFontError> {
+        Err(RegisterFontError::Unsupported)
+    }
 }
 
 /// Returned when a function/method is called from an invalid thread.
@@ -240,32 +345,74 @@ impl std::fmt::Display for BadThread {
 
 impl std::error::Error for BadThread {}
 
+/// Returned by [`Wm::register_font_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegisterFontError {
+    /// The backend doesn't support loading font files.
+    Unsupported,
+    /// The file couldn't be read or doesn't contain any fonts.
+    BadFile,
+}
+
+impl std::fmt::Display for RegisterFontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "loading font files is not supported by the backend"),
+            Self::BadFile => write!(f, "the font file could not be loaded"),
+        }
+    }
+}
+
+impl std::error::Error for RegisterFontError {}
+
 #[allow(clippy::option_option)] // for consistency between fields
 pub struct WndAttrs<'a, T: Wm, TLayer> {
     /// The size of the content region.
     pub size: Option<[u32; 2]>,
+    /// The position of the window's top-left corner in the coordinate space
+    /// of [`ScreenInfo::bounds`]. Whether the window decoration is included
+    /// depends on the backend, but a value returned by
+    /// [`Wm::get_wnd_position`] always reproduces the same placement.
+    ///
+    /// The system may ignore this, e.g., on Wayland.
+    pub position: Option<[i32; 2]>,
     pub min_size: Option<[u32; 2]>,
     pub max_size: Option<[u32; 2]>,
     pub flags: Option<WndFlags>,
     pub caption: Option<Cow<'a, str>>,
     pub visible: Option<bool>,
+    /// Minimizes, maximizes, or enters the full screen mode. See also
+    /// [`Wm::get_wnd_mode`].
+    pub mode: Option<WndMode>,
     pub listener: Option<Box<dyn WndListener<T>>>,
     pub layer: Option<Option<TLayer>>,
     pub cursor_shape: Option<CursorShape>,
+    /// The owner of the window. An owned window is always displayed in front
+    /// of its owner.
+    ///
+    /// This is mainly used for popup windows (see [`WndFlags::POPUP`]).
+    pub owner: Option<Option<T::HWnd>>,
+    /// The placement of a popup window. This field is ignored unless the
+    /// window has [`WndFlags::POPUP`] and an owner.
+    pub popup_anchor: Option<Option<PopupAnchor>>,
 }
 
 impl<'a, T: Wm, TLayer> Default for WndAttrs<'a, T, TLayer> {
     fn default() -> Self {
         Self {
             size: None,
+            position: None,
             min_size: None,
             max_size: None,
             flags: None,
             caption: None,
             visible: None,
+            mode: None,
             listener: None,
             layer: None,
             cursor_shape: None,
+            owner: None,
+            popup_anchor: None,
         }
     }
 }
@@ -298,6 +445,18 @@ bitflags! {
         /// On macOS, the standard window buttons (a.k.a. “stoplight”) are
         /// displayed.
         const FULL_SIZE_CONTENT = 1 << 3;
+
+        /// Makes the window a transient popup window, such as a menu. This
+        /// flag must be specified when the window is created.
+        ///
+        /// A popup window has no decoration and doesn't appear in the taskbar.
+        /// It's positioned based on `WndAttrs::popup_anchor`. The system calls
+        /// [`WndListener::close_requested`] when the user clicks outside the
+        /// window or it loses focus, unless the click or the focus is inside
+        /// another popup window owned (directly or indirectly) by it.
+        ///
+        /// This flag is currently ignored by the macOS and Windows backends.
+        const POPUP = 1 << 4;
     }
 }
 
@@ -311,20 +470,159 @@ impl<T: Wm, TLayer: Debug> Debug for WndAttrs<'_, T, TLayer> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("WndAttrs")
             .field("size", &self.size)
+            .field("position", &self.position)
             .field("min_size", &self.min_size)
             .field("max_size", &self.max_size)
             .field("flags", &self.flags)
             .field("caption", &self.caption)
             .field("visible", &self.visible)
+            .field("mode", &self.mode)
             .field(
                 "listener",
                 &self.listener.as_ref().map(|bx| (&*bx) as *const _),
             )
             .field("layer", &self.layer)
+            .field("cursor_shape", &self.cursor_shape)
+            .field("owner", &self.owner)
+            .field("popup_anchor", &self.popup_anchor)
             .finish()
     }
 }
 
+/// Specifies the display mode of a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WndMode {
+    Normal,
+    Minimized,
+    Maximized,
+    Fullscreen,
+}
+
+impl Default for WndMode {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+/// Describes a screen (monitor). Returned by [`Wm::screens`].
+///
+/// All coordinates are measured in the virtual screen coordinate space, whose
+/// unit is identical to that of [`WndAttrs::size`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenInfo {
+    /// The region occupied by the screen.
+    pub bounds: Box2<i32>,
+    /// The region of the screen usable by application windows, i.e., `bounds`
+    /// excluding taskbars, docks, and so on.
+    pub work_area: Box2<i32>,
+    /// The DPI scaling factor of the screen.
+    pub dpi_scale: f32,
+    /// `true` if this is the primary screen.
+    pub primary: bool,
+}
+
+/// Adjust the position of a window of size `size` so that the window fits in
+/// the work area of one of `screens` whenever possible. This is useful for
+/// restoring a saved window position after the screen configuration has
+/// changed.
+///
+/// The screen overlapping the window most is chosen. If there's no such
+/// screen, the primary screen is used instead. `position` is returned
+/// unmodified if `screens` is empty.
+pub fn fit_wnd_position(screens: &[ScreenInfo], position: [i32; 2], size: [u32; 2]) -> [i32; 2] {
+    let wnd_rect = box2! {
+        min: [position[0], position[1]],
+        max: [
+            position[0].saturating_add(size[0] as i32),
+            position[1].saturating_add(size[1] as i32),
+        ],
+    };
+
+    let overlap = |screen: &ScreenInfo| {
+        screen.work_area.intersection(&wnd_rect).map_or(0, |r| {
+            let size = r.size();
+            i64::from(size.x) * i64::from(size.y)
+        })
+    };
+
+    let screen = screens
+        .iter()
+        .filter(|screen| overlap(screen) > 0)
+        .max_by_key(|screen| overlap(screen))
+        .or_else(|| screens.iter().find(|screen| screen.primary))
+        .or_else(|| screens.first());
+
+    let work_area = if let Some(screen) = screen {
+        &screen.work_area
+    } else {
+        return position;
+    };
+
+    // Prefer keeping the top-left corner visible if the window is larger
+    // than the work area
+    let fit =
+        |pos: i32, len: u32, min: i32, max: i32| pos.min(max.saturating_sub(len as i32)).max(min);
+
+    [
+        fit(position[0], size[0], work_area.min.x, work_area.max.x),
+        fit(position[1], size[1], work_area.min.y, work_area.max.y),
+    ]
+}
+
+/// Specifies the placement of a popup window relative to its owner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PopupAnchor {
+    /// The rectangle in the owner's client coordinates to place the popup
+    /// window next to, e.g., a button that opened a menu.
+    pub rect: Box2<f32>,
+    pub direction: PopupDirection,
+}
+
+/// Specifies on which side of [`PopupAnchor::rect`] a popup window is placed.
+///
+/// The opposite side is used instead if there isn't enough room on the
+/// screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupDirection {
+    /// Below the rectangle, left-aligned. Used for context menus and drop-down
+    /// menus.
+    Down,
+    /// Right of the rectangle, top-aligned. Used for submenus.
+    Right,
+}
+
+impl PopupAnchor {
+    /// Calculate the origin of a popup window of size `size` in the same
+    /// coordinate space as `rect` and `bounds`.
+    ///
+    /// `bounds` represents the usable area of the screen. The popup window
+    /// is flipped or shifted to stay inside it whenever possible.
+    pub fn place(&self, size: [f32; 2], bounds: Box2<f32>) -> Point2<f32> {
+        let rect = &self.rect;
+        let fit = |start: f32, end: f32, len: f32, min: f32, max: f32| {
+            // Prefer `start`, flip to `end - len` if `start` overflows,
+            // and shift if neither fits
+            if start + len <= max {
+                start
+            } else if end - len >= min {
+                end - len
+            } else {
+                (max - len).max(min)
+            }
+        };
+        match self.direction {
+            PopupDirection::Down => Point2::new(
+                fit(rect.min.x, rect.max.x, size[0], bounds.min.x, bounds.max.x),
+                fit(rect.max.y, rect.min.y, size[1], bounds.min.y, bounds.max.y),
+            ),
+            PopupDirection::Right => Point2::new(
+                fit(rect.max.x, rect.min.x, size[0], bounds.min.x, bounds.max.x),
+                fit(rect.min.y, rect.max.y, size[1], bounds.min.y, bounds.max.y),
+            ),
+        }
+    }
+}
+
 #[cfg_attr(doc, svgbobdoc::transform)]
 /// Specifies layer attributes.
 #[allow(clippy::option_option)] // for consistency between fields
@@ -591,6 +889,61 @@ pub trait WndListener<T: Wm> {
         Box::new(())
     }
 
+    /// A drag-and-drop operation has entered a window.
+    ///
+    /// `formats` specifies the data formats the dragged data is available in,
+    /// and `ops` specifies the operations allowed by the drag source. The
+    /// data itself is not available until it's dropped. Some backends might
+    /// report formats that turn out to be missing from the dropped data.
+    ///
+    /// Returns the operation to be performed if the data is dropped at `loc`,
+    /// or `None` to reject the data. The returned operation must be one of
+    /// `ops`.
+    fn drag_enter(
+        &self,
+        _: T,
+        _: &T::HWnd,
+        _loc: Point2<f32>,
+        _formats: DragFormats,
+        _ops: DragOps,
+    ) -> Option<DragOp> {
+        None
+    }
+
+    /// The mouse pointer has moved inside a window during a drag-and-drop
+    /// operation.
+    ///
+    /// The parameters and the return value have the same meaning as those of
+    /// [`WndListener::drag_enter`].
+    fn drag_over(
+        &self,
+        _: T,
+        _: &T::HWnd,
+        _loc: Point2<f32>,
+        _formats: DragFormats,
+        _ops: DragOps,
+    ) -> Option<DragOp> {
+        None
+    }
+
+    /// A drag-and-drop operation has left a window or was cancelled.
+    fn drag_leave(&self, _: T, _: &T::HWnd) {}
+
+    /// The data of a drag-and-drop operation was dropped inside a window.
+    ///
+    /// Returns the operation that was actually performed, or `None` if the
+    /// data was rejected. `drag_leave` is not called after this method.
+    fn drag_drop(
+        &self,
+        _: T,
+        _: &T::HWnd,
+        _loc: Point2<f32>,
+        _data: &DragData,
+        _ops: DragOps,
+    ) -> Option<DragOp> {
+        None
+    }
+
     // TODO: more events
     //  - Pointer device gestures (swipe, zoom, rotate)
 }
@@ -598,6 +951,35 @@ pub trait WndListener<T: Wm> {
 /// A default implementation of [`WndListener`].
 impl<T: Wm> WndListener<T> for () {}
 
+bitflags! {
+    /// Specifies a set of data formats stored in the system clipboard.
+    pub struct ClipboardFormats: u8 {
+        /// A plain text.
+        const PLAIN_TEXT = 1;
+    }
+}
+
+impl Default for ClipboardFormats {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// Clipboard event handlers.
+///
+/// The receiver is immutable because event handlers may manipulate the
+/// clipboard and windows.
+pub trait ClipboardListener<T: Wm> {
+    /// The contents of the system clipboard have changed.
+    ///
+    /// The implementation may call spuriously, i.e., even when the contents
+    /// did not actually change.
+    fn change(&self, _: T) {}
+}
+
+/// A default implementation of [`ClipboardListener`].
+impl<T: Wm> ClipboardListener<T> for () {}
+
 /// Represents a key event.
 pub trait KeyEvent<AccelTable> {
     /// Interpret the event using an accelerator table.
@@ -840,6 +1222,155 @@ pub trait ScrollListener<T: Wm> {
 /// A default implementation of [`ScrollListener`].
 impl<T: Wm> ScrollListener<T> for () {}
 
+bitflags! {
+    /// Specifies a set of drag-and-drop operations.
+    pub struct DragOps: u8 {
+        /// The data is copied to the destination.
+        const COPY = 1;
+        /// The data is moved to the destination. The drag source is
+        /// responsible for deleting the original data.
+        const MOVE = 1 << 1;
+        /// The destination creates a reference to the data.
+        const LINK = 1 << 2;
+    }
+}
+
+impl Default for DragOps {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl DragOps {
+    /// Choose an operation from `self`. `Copy`, `Move`, and `Link` are
+    /// preferred in this order.
+    pub fn first(self) -> Option<DragOp> {
+        [DragOp::Copy, DragOp::Move, DragOp::Link]
+            .iter()
+            .cloned()
+            .find(|op| self.contains(DragOps::from(*op)))
+    }
+}
+
+/// A drag-and-drop operation chosen by a drop target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DragOp {
+    Copy,
+    Move,
+    Link,
+}
+
+impl From<DragOp> for DragOps {
+    fn from(x: DragOp) -> Self {
+        match x {
+            DragOp::Copy => DragOps::COPY,
+            DragOp::Move => DragOps::MOVE,
+            DragOp::Link => DragOps::LINK,
+        }
+    }
+}
+
+bitflags! {
+    /// Specifies a set of data formats carried by a drag-and-drop operation.
+    pub struct DragFormats: u8 {
+        /// Local files. See [`DragData::files`].
+        const FILES = 1;
+        /// URIs. See [`DragData::uris`].
+        const URIS = 1 << 1;
+        /// A plain text. See [`DragData::text`].
+        const TEXT = 1 << 2;
+    }
+}
+
+impl Default for DragFormats {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// The data carried by a drag-and-drop operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DragData {
+    /// The paths of local files.
+    pub files: Vec<PathBuf>,
+    /// URIs, excluding the ones representing local files (which are
+    /// included in `files` instead).
+    pub uris: Vec<String>,
+    /// A plain text.
+    pub text: Option<String>,
+}
+
+impl DragData {
+    /// Get the set of data formats present in `self`.
+    pub fn formats(&self) -> DragFormats {
+        let mut formats = DragFormats::empty();
+        if !self.files.is_empty() {
+            formats |= DragFormats::FILES;
+        }
+        if !self.uris.is_empty() {
+            formats |= DragFormats::URIS;
+        }
+        if self.text.is_some() {
+            formats |= DragFormats::TEXT;
+        }
+        formats
+    }
+}
+
+/// Specifies the type of a file dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileDialogKind {
+    /// Choose an existing file.
+    Open,
+    /// Choose one or more existing files.
+    OpenMultiple,
+    /// Choose a file name to save a file as.
+    Save,
+    /// Choose an existing folder.
+    ChooseFolder,
+}
+
+impl Default for FileDialogKind {
+    fn default() -> Self {
+        Self::Open
+    }
+}
+
+/// A file type filter displayed in a file dialog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFilter {
+    /// The user-visible name of the filter, e.g., `Text files`.
+    pub name: String,
+    /// Glob patterns matching file names, e.g., `*.txt`.
+    pub patterns: Vec<String>,
+}
+
+impl FileFilter {
+    pub fn new(name: impl Into<String>, patterns: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            patterns: patterns.iter().map(|&p| p.to_owned()).collect(),
+        }
+    }
+}
+
+/// The parameters of a file dialog. See [`Wm::show_file_dialog`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDialogOptions {
+    pub kind: FileDialogKind,
+    /// The title of the dialog. A backend-specific default title is used if
+    /// `None` is specified.
+    pub title: Option<String>,
+    /// The file type filters, the first of which is selected initially. The
+    /// user can choose any files if this is empty. Ignored by
+    /// [`FileDialogKind::ChooseFolder`].
+    pub filters: Vec<FileFilter>,
+    /// The folder initially displayed.
+    pub folder: Option<PathBuf>,
+    /// The file name initially entered. Only used by [`FileDialogKind::Save`].
+    pub file_name: Option<String>,
+}
+
 /// Describes the appearance of the mouse cursor.
 ///
 /// This type contains the same set of variants as `winit::window::CursorIcon`
@@ -1234,7 +1765,26 @@ pub trait TextLayout: Send + Sync + Sized {
     type CharStyle: CharStyle;
 
     fn from_text(text: &str, style: &Self::CharStyle, width: Option<f32>) -> Self;
-    // TODO: construct a `TextLayout` from an attributed text
+
+    /// Construct a `TextLayout` from an attributed text.
+    ///
+    /// `style` specifies the default character style. Each element of `runs`
+    /// overrides the character style of the specified range. The ranges must
+    /// be sorted, must not overlap with each other, and must start and end at
+    /// `char` boundaries of `text`.
+    ///
+    /// The default implementation ignores `runs` and calls [`from_text`].
+    ///
+    /// [`from_text`]: TextLayout::from_text
+    fn from_attr_text(
+        text: &str,
+        style: &Self::CharStyle,
+        runs: &[TextRun<Self::CharStyle>],
+        width: Option<f32>,
+    ) -> Self {
+        let _ = runs;
+        Self::from_text(text, style, width)
+    }
 
     /// Get the visual bounds of a `TextLayout`.
     fn visual_bounds(&self) -> Box2<f32>;
@@ -1497,6 +2047,15 @@ impl Default for RunFlags {
     }
 }
 
+/// Specifies the character style of a portion of an attributed text. Used by
+/// [`TextLayout::from_attr_text`].
+#[derive(Debug, Clone)]
+pub struct TextRun<TCharStyle> {
+    /// The UTF-8 range of the text to which `style` is applied.
+    pub range: Range<usize>,
+    pub style: TCharStyle,
+}
+
 pub trait CanvasText<TLayout>: Canvas {
     fn draw_text(&mut self, layout: &TLayout, origin: Point2<f32>, color: RGBAF32);
 }
@@ -1515,6 +2074,19 @@ pub trait CharStyle: Clone + Send + Sync + Sized {
 pub struct CharStyleAttrs<TCharStyle> {
     pub template: Option<TCharStyle>,
     pub sys: Option<SysFontType>,
+    /// The font family name, e.g., `Noto Sans`. Overrides the family chosen
+    /// by `sys`. Fonts registered by [`Wm::register_font_file`] can be
+    /// specified here as well.
+    ///
+    /// The system falls back to other fonts for characters not supported by
+    /// the specified family.
+    ///
+    /// This field (as well as `weight` and `italic`) is currently ignored by
+    /// the macOS and Windows backends.
+    pub family: Option<Cow<'static, str>>,
+    /// The font weight. Overrides the weight chosen by `sys`.
+    pub weight: Option<FontWeight>,
+    pub italic: Option<bool>,
     pub size: Option<f32>,
     pub decor: Option<TextDecorFlags>,
     /// The text color.
@@ -1529,6 +2101,9 @@ impl<TCharStyle> Default for CharStyleAttrs<TCharStyle> {
         Self {
             template: None,
             sys: None,
+            family: None,
+            weight: None,
+            italic: None,
             size: None,
             decor: None,
             color: None,
@@ -1544,6 +2119,27 @@ bitflags! {
     }
 }
 
+/// A font weight. The values follow the convention of CSS's `font-weight`
+/// property, e.g., `400` for normal and `700` for bold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FontWeight(pub u16);
+
+impl FontWeight {
+    pub const THIN: Self = Self(100);
+    pub const LIGHT: Self = Self(300);
+    pub const NORMAL: Self = Self(400);
+    pub const MEDIUM: Self = Self(500);
+    pub const SEMIBOLD: Self = Self(600);
+    pub const BOLD: Self = Self(700);
+    pub const HEAVY: Self = Self(900);
+}
+
+impl Default for FontWeight {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SysFontType {
     /// The font used for UI elements.
@@ -21,9 +21,9 @@ pub mod iface;
 pub mod prelude {
     pub use super::cells::{Init, MtLazyStatic, SendInit};
     pub use super::iface::{
-        Bitmap, BitmapBuilder, BitmapBuilderNew, Canvas, CanvasText, CharStyle, KeyEvent,
-        MouseDragListener, ScrollListener, TextInputCtxEdit, TextInputCtxListener, TextLayout,
-        Wm as WmTrait, WndListener,
+        Bitmap, BitmapBuilder, BitmapBuilderNew, Canvas, CanvasText, CharStyle, ClipboardListener,
+        KeyEvent, MouseDragListener, ScrollListener, TextInputCtxEdit, TextInputCtxListener,
+        TextLayout, Wm as WmTrait, WndListener,
     };
 
     pub use super::futuresext::WmFuturesExt;
@@ -205,9 +205,11 @@ pub type TextLayout = current::TextLayout;
 // the default backend.
 
 pub use self::iface::{
-    actions, ActionId, ActionStatus, BadThread, Beam, CursorShape, IndexFromPointFlags,
-    InterpretEventCtx, LayerFlags, LineCap, LineJoin, NcHit, RunFlags, RunMetrics, ScrollDelta,
-    SysFontType, TextDecorFlags, TextInputCtxEventFlags, WndFlags, RGBAF32,
+    actions, fit_wnd_position, ActionId, ActionStatus, BadThread, Beam, ClipboardFormats,
+    CursorShape, DragData, DragFormats, DragOp, DragOps, FileDialogKind, FileDialogOptions,
+    FileFilter, FontWeight, IndexFromPointFlags, InterpretEventCtx, LayerFlags, LineCap, LineJoin,
+    NcHit, PopupAnchor, PopupDirection, RegisterFontError, RunFlags, RunMetrics, ScreenInfo,
+    ScrollDelta, SysFontType, TextDecorFlags, TextInputCtxEventFlags, WndFlags, WndMode, RGBAF32,
 };
 
 /// The window handle type of [`Wm`].
@@ -234,6 +236,9 @@ pub type LayerAttrs = iface::LayerAttrs<Bitmap, HLayer>;
 /// A specialization of `CharStyleAttrs` for the default backend.
 pub type CharStyleAttrs = iface::CharStyleAttrs<CharStyle>;
 
+/// A specialization of `TextRun` for the default backend.
+pub type TextRun = iface::TextRun<CharStyle>;
+
 // Trait aliases (unstable at the point of writing) actually do not work
 // exactly like type aliases. Specifically, they cannot be used in every place
 // where traits can be used, like `impl` blocks.
@@ -137,6 +137,10 @@ impl iface::Wm for Wm {
         window.is_focused(self)
     }
 
+    fn activate_wnd(self, window: &Self::HWnd) {
+        window.activate(self)
+    }
+
     fn new_layer(self, attrs: LayerAttrs) -> Self::HLayer {
         HLayer::new(self, attrs)
     }
@@ -164,6 +164,12 @@ extern CGSConnection CGSDefaultConnectionForThread();
     [self->window makeKeyAndOrderFront:nil];
 }
 
+/** Called by `window.rs` */
+- (void)activate {
+    [NSApp activateIgnoringOtherApps:YES];
+    [self->window makeKeyAndOrderFront:nil];
+}
+
 /** Called by `window.rs` */
 - (void)orderOut {
     [self->window orderOut:nil];
@@ -213,6 +213,10 @@ impl HWnd {
         let value: BOOL = unsafe { msg_send![*self.ctrler, isKeyWindow] };
         value != 0
     }
+
+    pub(super) fn activate(&self, _: Wm) {
+        let () = unsafe { msg_send![*self.ctrler, activate] };
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -109,6 +109,7 @@ use std::{
     marker::PhantomData,
     ops::Range,
     panic,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{
         mpsc::{channel, sync_channel},
@@ -120,7 +121,9 @@ use std::{
 
 use super::{iface, native, prelude::MtLazyStatic, prelude::*};
 
+mod clipboard;
 mod eventloop;
+mod filedialog;
 mod logging;
 mod screen;
 mod textinput;
@@ -133,6 +136,7 @@ pub use self::{logging::Logger, wmapi::TestingWm};
 pub type WndAttrs<'a> = iface::WndAttrs<'a, Wm, HLayer>;
 pub type LayerAttrs = iface::LayerAttrs<Bitmap, HLayer>;
 pub type CharStyleAttrs = iface::CharStyleAttrs<CharStyle>;
+pub type TextRun = iface::TextRun<CharStyle>;
 
 // Borrow some modules from `unix` backend
 #[path = "unix/bitmap.rs"]
@@ -338,6 +342,8 @@ impl Wm {
         self.eradicate_events();
         SCREEN.get_with_wm(self).reset();
         textinput::reset(self);
+        clipboard::reset(self);
+        filedialog::reset(self);
     }
 }
 
@@ -390,6 +396,11 @@ impl wmapi::TestingWm for Wm {
         SCREEN.get_with_wm(*self).set_wnd_size(*self, hwnd, size)
     }
 
+    fn set_screens(&self, screens: Vec<iface::ScreenInfo>) {
+        debug!("set_screens({:?})", screens);
+        SCREEN.get_with_wm(*self).set_screens(screens)
+    }
+
     fn set_wnd_focused(&self, hwnd: &HWnd, focused: bool) {
         let hwnd = hwnd.testing_hwnd_ref().unwrap();
         SCREEN
@@ -440,6 +451,50 @@ impl wmapi::TestingWm for Wm {
             .raise_scroll_gesture(*self, hwnd, loc)
     }
 
+    fn raise_drag_enter(
+        &self,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        let hwnd = hwnd.testing_hwnd_ref().unwrap();
+        SCREEN
+            .get_with_wm(*self)
+            .raise_drag_enter(*self, hwnd, loc, formats, ops)
+    }
+
+    fn raise_drag_over(
+        &self,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        let hwnd = hwnd.testing_hwnd_ref().unwrap();
+        SCREEN
+            .get_with_wm(*self)
+            .raise_drag_over(*self, hwnd, loc, formats, ops)
+    }
+
+    fn raise_drag_leave(&self, hwnd: &HWnd) {
+        let hwnd = hwnd.testing_hwnd_ref().unwrap();
+        SCREEN.get_with_wm(*self).raise_drag_leave(*self, hwnd)
+    }
+
+    fn raise_drag_drop(
+        &self,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        data: &iface::DragData,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        let hwnd = hwnd.testing_hwnd_ref().unwrap();
+        SCREEN
+            .get_with_wm(*self)
+            .raise_drag_drop(*self, hwnd, loc, data, ops)
+    }
+
     fn active_text_input_ctxs(&self) -> Vec<HTextInputCtx> {
         textinput::HTextInputCtx::active_ctxs(*self)
             .into_iter()
@@ -526,6 +581,24 @@ impl wmapi::TestingWm for Wm {
 
         self.raise_key_up(hwnd, source, pattern);
     }
+
+    fn clipboard_text(&self) -> Option<String> {
+        clipboard::read_text(*self)
+    }
+
+    fn set_clipboard_text(&self, text: Option<&str>) {
+        debug!("set_clipboard_text({:?})", text);
+        clipboard::write_text(*self, text);
+    }
+
+    fn file_dialogs(&self) -> Vec<wmapi::FileDialog> {
+        filedialog::dialogs(*self)
+    }
+
+    fn respond_file_dialog(&self, dialog: &wmapi::FileDialog, result: Option<Vec<PathBuf>>) {
+        debug!("respond_file_dialog({:?}, {:?})", dialog.id, result);
+        filedialog::respond(*self, dialog.id, result);
+    }
 }
 
 impl iface::Wm for Wm {
@@ -747,6 +820,52 @@ impl iface::Wm for Wm {
         }
     }
 
+    fn get_wnd_position(self, hwnd: &Self::HWnd) -> Option<[i32; 2]> {
+        match (self.backend_and_wm(), &hwnd.inner) {
+            (BackendAndWm::Native { wm }, HWndInner::Native(hwnd)) => wm.get_wnd_position(hwnd),
+            (BackendAndWm::Testing, HWndInner::Testing(tc_hwnd)) => {
+                let position = SCREEN.get_with_wm(self).get_wnd_position(tc_hwnd);
+                trace!("get_wnd_position({:?}) -> {:?}", hwnd, position);
+                Some(position)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_wnd_mode(self, hwnd: &Self::HWnd) -> iface::WndMode {
+        match (self.backend_and_wm(), &hwnd.inner) {
+            (BackendAndWm::Native { wm }, HWndInner::Native(hwnd)) => wm.get_wnd_mode(hwnd),
+            (BackendAndWm::Testing, HWndInner::Testing(tc_hwnd)) => {
+                let mode = SCREEN.get_with_wm(self).get_wnd_mode(tc_hwnd);
+                trace!("get_wnd_mode({:?}) -> {:?}", hwnd, mode);
+                mode
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn screens(self) -> Vec<iface::ScreenInfo> {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => wm.screens(),
+            BackendAndWm::Testing => {
+                let screens = SCREEN.get_with_wm(self).screens();
+                trace!("screens() -> {:?}", screens);
+                screens
+            }
+        }
+    }
+
+    fn activate_wnd(self, hwnd: &Self::HWnd) {
+        match (self.backend_and_wm(), &hwnd.inner) {
+            (BackendAndWm::Native { wm }, HWndInner::Native(hwnd)) => wm.activate_wnd(hwnd),
+            (BackendAndWm::Testing, HWndInner::Testing(_)) => {
+                // The focus state is controlled by `TestingWm::set_wnd_focused`
+                debug!("activate_wnd({:?})", hwnd);
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn new_layer(self, attrs: LayerAttrs) -> Self::HLayer {
         match self.backend_and_wm() {
             BackendAndWm::Native { wm } => {
@@ -884,6 +1003,86 @@ impl iface::Wm for Wm {
             _ => unreachable!(),
         }
     }
+
+    fn clipboard_formats(self) -> iface::ClipboardFormats {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => wm.clipboard_formats(),
+            BackendAndWm::Testing => {
+                let formats = clipboard::formats(self);
+                trace!("clipboard_formats() -> {:?}", formats);
+                formats
+            }
+        }
+    }
+
+    fn clipboard_read_text(self) -> Option<String> {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => wm.clipboard_read_text(),
+            BackendAndWm::Testing => {
+                let text = clipboard::read_text(self);
+                trace!("clipboard_read_text() -> {:?}", text);
+                text
+            }
+        }
+    }
+
+    fn clipboard_write_text(self, text: &str) {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => wm.clipboard_write_text(text),
+            BackendAndWm::Testing => {
+                debug!("clipboard_write_text({:?})", text);
+                clipboard::write_text(self, Some(text));
+            }
+        }
+    }
+
+    fn set_clipboard_listener(self, listener: Box<dyn iface::ClipboardListener<Self>>) {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => {
+                let listener = Box::new(clipboard::NativeClipboardListener(listener));
+                wm.set_clipboard_listener(listener);
+            }
+            BackendAndWm::Testing => {
+                debug!("set_clipboard_listener(...)");
+                clipboard::set_listener(self, listener);
+            }
+        }
+    }
+
+    fn show_file_dialog(
+        self,
+        owner: Option<&Self::HWnd>,
+        options: iface::FileDialogOptions,
+        handler: Box<dyn FnOnce(Self, Option<Vec<PathBuf>>)>,
+    ) {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => {
+                let owner = owner.map(|hwnd| hwnd.clone().native_hwnd().unwrap());
+                wm.show_file_dialog(
+                    owner.as_ref(),
+                    options,
+                    Box::new(move |native_wm, result| {
+                        handler(Self::from_native_wm(native_wm), result)
+                    }),
+                );
+            }
+            BackendAndWm::Testing => {
+                debug!("show_file_dialog({:?}, {:?}, ...)", owner, options);
+                filedialog::show(self, owner, options, handler);
+            }
+        }
+    }
+
+    fn register_font_file(self, path: &Path) -> Result<(), iface::RegisterFontError> {
+        match self.backend_and_wm() {
+            BackendAndWm::Native { wm } => wm.register_font_file(path),
+            BackendAndWm::Testing => {
+                let result = text::register_font_file(path);
+                debug!("register_font_file({:?}) -> {:?}", path, result);
+                result
+            }
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -909,6 +1108,13 @@ impl From<&screen::HWnd> for HWnd {
 }
 
 impl HWnd {
+    fn native_hwnd(self) -> Option<native::HWnd> {
+        match self.inner {
+            HWndInner::Native(imp) => Some(imp),
+            HWndInner::Testing(_) => None,
+        }
+    }
+
     fn testing_hwnd_ref(&self) -> Option<&screen::HWnd> {
         match &self.inner {
             HWndInner::Native(_) => None,
@@ -967,16 +1173,22 @@ fn wnd_attrs_to_native(attrs: WndAttrs<'_>) -> native::WndAttrs<'_> {
         .map(|layer_or_none| layer_or_none.map(|hlayer| hlayer.native_hlayer().unwrap()));
     native::WndAttrs {
         size: attrs.size,
+        position: attrs.position,
         min_size: attrs.min_size,
         max_size: attrs.max_size,
         flags: attrs.flags,
         caption: attrs.caption,
         visible: attrs.visible,
+        mode: attrs.mode,
         listener: attrs
             .listener
             .map(|listener| Box::new(wndlistenershim::NativeWndListener(listener)) as _),
         layer,
         cursor_shape: attrs.cursor_shape,
+        owner: attrs
+            .owner
+            .map(|owner_or_none| owner_or_none.map(|hwnd| hwnd.native_hwnd().unwrap())),
+        popup_anchor: attrs.popup_anchor,
     }
 }
 
@@ -1061,14 +1273,18 @@ fn wnd_attrs_to_testing(attrs: WndAttrs<'_>) -> screen::WndAttrs<'_> {
         .map(|layer_or_none| layer_or_none.map(|hlayer| hlayer.testing_hlayer().unwrap()));
     screen::WndAttrs {
         size: attrs.size,
+        position: attrs.position,
         min_size: attrs.min_size,
         max_size: attrs.max_size,
         flags: attrs.flags,
         caption: attrs.caption,
         visible: attrs.visible,
+        mode: attrs.mode,
         listener: attrs.listener,
         layer,
         cursor_shape: attrs.cursor_shape,
+        owner: attrs.owner,
+        popup_anchor: attrs.popup_anchor,
     }
 }
 
@@ -1325,6 +1541,9 @@ impl iface::CharStyle for CharStyle {
                 inner: CharStyleInner::Native(native::CharStyle::new(iface::CharStyleAttrs {
                     template: style,
                     sys: attrs.sys,
+                    family: attrs.family,
+                    weight: attrs.weight,
+                    italic: attrs.italic,
                     size: attrs.size,
                     decor: attrs.decor,
                     color: attrs.color,
@@ -1334,6 +1553,9 @@ impl iface::CharStyle for CharStyle {
                 inner: CharStyleInner::Testing(text::CharStyle::new(iface::CharStyleAttrs {
                     template: style,
                     sys: attrs.sys,
+                    family: attrs.family,
+                    weight: attrs.weight,
+                    italic: attrs.italic,
                     size: attrs.size,
                     decor: attrs.decor,
                     color: attrs.color,
@@ -1380,6 +1602,52 @@ impl iface::TextLayout for TextLayout {
         }
     }
 
+    fn from_attr_text(
+        text: &str,
+        style: &Self::CharStyle,
+        runs: &[TextRun],
+        width: Option<f32>,
+    ) -> Self {
+        const MISMATCH: &str = "Given CharStyles belong to different backends";
+
+        match &style.inner {
+            CharStyleInner::Native(style) => {
+                let runs: Vec<_> = runs
+                    .iter()
+                    .map(|run| match &run.style.inner {
+                        CharStyleInner::Native(run_style) => iface::TextRun {
+                            range: run.range.clone(),
+                            style: run_style.clone(),
+                        },
+                        _ => panic!("{}", MISMATCH),
+                    })
+                    .collect();
+                Self {
+                    inner: TextLayoutInner::Native(native::TextLayout::from_attr_text(
+                        text, style, &runs, width,
+                    )),
+                }
+            }
+            CharStyleInner::Testing(style) => {
+                let runs: Vec<_> = runs
+                    .iter()
+                    .map(|run| match &run.style.inner {
+                        CharStyleInner::Testing(run_style) => iface::TextRun {
+                            range: run.range.clone(),
+                            style: run_style.clone(),
+                        },
+                        _ => panic!("{}", MISMATCH),
+                    })
+                    .collect();
+                Self {
+                    inner: TextLayoutInner::Testing(text::TextLayout::from_attr_text(
+                        text, style, &runs, width,
+                    )),
+                }
+            }
+        }
+    }
+
     forward! {
         inner_type: TextLayoutInner;
         fn visual_bounds(&self) -> Box2<f32>;
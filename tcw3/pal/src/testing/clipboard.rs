@@ -0,0 +1,90 @@
+//! Emulates the system clipboard.
+use log::trace;
+use std::{cell::RefCell, rc::Rc};
+
+use super::{native, Wm};
+use crate::{iface, prelude::*};
+
+struct Clipboard {
+    text: Option<String>,
+    listener: Rc<dyn iface::ClipboardListener<Wm>>,
+}
+
+mt_lazy_static! {
+    static <Wm> ref CLIPBOARD: RefCell<Clipboard> =>
+        |_| RefCell::new(Clipboard::new());
+}
+
+const BORROW_ERROR: &str = "Couldn't lock the clipboard state. \
+     This error can be caused by an unsupported reentrant call to `Wm`'s functions.";
+
+impl Clipboard {
+    fn new() -> Self {
+        Self {
+            text: None,
+            listener: Rc::new(()),
+        }
+    }
+}
+
+pub fn reset(wm: Wm) {
+    *CLIPBOARD.get_with_wm(wm).borrow_mut() = Clipboard::new();
+}
+
+pub fn formats(wm: Wm) -> iface::ClipboardFormats {
+    let clipboard = CLIPBOARD.get_with_wm(wm).try_borrow().expect(BORROW_ERROR);
+    if clipboard.text.is_some() {
+        iface::ClipboardFormats::PLAIN_TEXT
+    } else {
+        iface::ClipboardFormats::empty()
+    }
+}
+
+pub fn read_text(wm: Wm) -> Option<String> {
+    let clipboard = CLIPBOARD.get_with_wm(wm).try_borrow().expect(BORROW_ERROR);
+    clipboard.text.clone()
+}
+
+/// Replace the clipboard contents and schedule a call to
+/// `ClipboardListener::change`.
+pub fn write_text(wm: Wm, text: Option<&str>) {
+    let mut clipboard = CLIPBOARD
+        .get_with_wm(wm)
+        .try_borrow_mut()
+        .expect(BORROW_ERROR);
+    clipboard.text = text.map(ToOwned::to_owned);
+    drop(clipboard);
+
+    wm.invoke_unsend(|wm| {
+        trace!("Automatically calling ClipboardListener::change");
+        raise_change(wm);
+    });
+}
+
+pub fn set_listener(wm: Wm, listener: Box<dyn iface::ClipboardListener<Wm>>) {
+    let mut clipboard = CLIPBOARD
+        .get_with_wm(wm)
+        .try_borrow_mut()
+        .expect(BORROW_ERROR);
+    clipboard.listener = listener.into();
+}
+
+fn raise_change(wm: Wm) {
+    let listener = Rc::clone(
+        &CLIPBOARD
+            .get_with_wm(wm)
+            .try_borrow()
+            .expect(BORROW_ERROR)
+            .listener,
+    );
+    listener.change(wm);
+}
+
+/// Wraps `ClipboardListener<Wm>` to create a `ClipboardListener<native::Wm>`.
+pub struct NativeClipboardListener(pub Box<dyn iface::ClipboardListener<Wm>>);
+
+impl iface::ClipboardListener<native::Wm> for NativeClipboardListener {
+    fn change(&self, wm: native::Wm) {
+        self.0.change(Wm::from_native_wm(wm))
+    }
+}
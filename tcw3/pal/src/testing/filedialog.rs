@@ -0,0 +1,83 @@
+//! Emulates file dialogs. The dialogs stay open until a test driver calls
+//! `TestingWm::respond_file_dialog`.
+use std::{cell::RefCell, path::PathBuf};
+
+use super::{wmapi, HWnd, Wm};
+use crate::{iface, prelude::*};
+
+struct Dialog {
+    info: wmapi::FileDialog,
+    handler: Box<dyn FnOnce(Wm, Option<Vec<PathBuf>>)>,
+}
+
+struct State {
+    dialogs: Vec<Dialog>,
+    next_id: u64,
+}
+
+mt_lazy_static! {
+    static <Wm> ref STATE: RefCell<State> =>
+        |_| RefCell::new(State::new());
+}
+
+const BORROW_ERROR: &str = "Couldn't lock the file dialog state. \
+     This error can be caused by an unsupported reentrant call to `Wm`'s functions.";
+
+impl State {
+    fn new() -> Self {
+        Self {
+            dialogs: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+pub fn reset(wm: Wm) {
+    *STATE.get_with_wm(wm).borrow_mut() = State::new();
+}
+
+pub fn show(
+    wm: Wm,
+    owner: Option<&HWnd>,
+    options: iface::FileDialogOptions,
+    handler: Box<dyn FnOnce(Wm, Option<Vec<PathBuf>>)>,
+) {
+    let mut state = STATE.get_with_wm(wm).try_borrow_mut().expect(BORROW_ERROR);
+
+    let id = state.next_id;
+    state.next_id += 1;
+
+    state.dialogs.push(Dialog {
+        info: wmapi::FileDialog {
+            id,
+            owner: owner.cloned(),
+            options,
+        },
+        handler,
+    });
+}
+
+pub fn dialogs(wm: Wm) -> Vec<wmapi::FileDialog> {
+    let state = STATE.get_with_wm(wm).try_borrow().expect(BORROW_ERROR);
+    state.dialogs.iter().map(|d| d.info.clone()).collect()
+}
+
+pub fn respond(wm: Wm, id: u64, result: Option<Vec<PathBuf>>) {
+    let dialog = {
+        let mut state = STATE.get_with_wm(wm).try_borrow_mut().expect(BORROW_ERROR);
+        let i = (state.dialogs.iter())
+            .position(|d| d.info.id == id)
+            .unwrap_or_else(|| panic!("file dialog {} is not open", id));
+        state.dialogs.remove(i)
+    };
+
+    if let Some(paths) = &result {
+        if dialog.info.options.kind == iface::FileDialogKind::OpenMultiple {
+            assert!(!paths.is_empty(), "at least one path must be given");
+        } else {
+            assert_eq!(paths.len(), 1, "exactly one path must be given");
+        }
+    }
+
+    (dialog.handler)(wm, result);
+}
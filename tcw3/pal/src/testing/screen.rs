@@ -45,6 +45,36 @@ struct State {
     binner: swrast::Binner<Bitmap>,
     sr_scrn: swrast::Screen<Bitmap>,
     wnds: UniqPool<Wnd>,
+    screens: Vec<iface::ScreenInfo>,
+}
+
+impl State {
+    /// Get the owner of a window.
+    fn owner_ptr(&self, ptr: PoolPtr) -> Option<PoolPtr> {
+        let owner = self.wnds.get(ptr)?.attrs.owner.as_ref()?;
+        Some(owner.testing_hwnd_ref().unwrap().ptr)
+    }
+
+    /// Return `true` if `ptr` is identical to `owner` or owned by `owner`
+    /// (directly or indirectly).
+    fn is_owned_by(&self, mut ptr: PoolPtr, owner: PoolPtr) -> bool {
+        loop {
+            if ptr == owner {
+                return true;
+            }
+            ptr = match self.owner_ptr(ptr) {
+                Some(x) => x,
+                None => return false,
+            };
+        }
+    }
+
+    /// Return `true` if `ptr` represents a visible popup window.
+    fn is_popup(&self, ptr: PoolPtr) -> bool {
+        self.wnds.get(ptr).map_or(false, |wnd| {
+            wnd.attrs.visible && wnd.attrs.flags.contains(iface::WndFlags::POPUP)
+        })
+    }
 }
 
 pub struct Wnd {
@@ -67,6 +97,7 @@ impl Screen {
             binner: swrast::Binner::new(),
             sr_scrn: swrast::Screen::new(),
             wnds: UniqPool::new(),
+            screens: default_screens(),
         };
 
         Self {
@@ -83,6 +114,7 @@ impl Screen {
 
         state.sr_scrn = swrast::Screen::new();
         state.wnds = UniqPool::new();
+        state.screens = default_screens();
     }
 
     pub(super) fn new_wnd(&self, attrs: WndAttrs<'_>) -> HWnd {
@@ -97,12 +129,16 @@ impl Screen {
             dirty_rect: None,
             attrs: wmapi::WndAttrs {
                 size: attrs.size.unwrap_or([100, 100]),
+                position: attrs.position.unwrap_or([0, 0]),
                 min_size: attrs.min_size.unwrap_or([0; 2]),
                 max_size: attrs.max_size.unwrap_or([u32::max_value(); 2]),
                 flags: attrs.flags.unwrap_or(iface::WndFlags::default()),
                 caption: attrs.caption.unwrap_or("Default title".into()).into_owned(),
                 visible: attrs.visible.unwrap_or(false),
+                mode: attrs.mode.unwrap_or_default(),
                 cursor_shape: attrs.cursor_shape.unwrap_or_default(),
+                owner: attrs.owner.unwrap_or(None),
+                popup_anchor: attrs.popup_anchor.unwrap_or(None),
             },
             listener: Rc::from(attrs.listener.unwrap_or_else(|| Box::new(()))),
             img_size: [0, 0],
@@ -132,12 +168,16 @@ impl Screen {
             };
         }
         apply!(size);
+        apply!(position);
         apply!(min_size);
         apply!(max_size);
         apply!(flags);
         apply!(caption);
         apply!(visible);
+        apply!(mode);
         apply!(cursor_shape);
+        apply!(owner);
+        apply!(popup_anchor);
 
         if let Some(layer) = attrs.layer {
             state
@@ -183,6 +223,18 @@ impl Screen {
         let state = self.state.borrow();
         state.wnds[hwnd.ptr].focused
     }
+    pub(super) fn get_wnd_position(&self, hwnd: &HWnd) -> [i32; 2] {
+        let state = self.state.borrow();
+        state.wnds[hwnd.ptr].attrs.position
+    }
+    pub(super) fn get_wnd_mode(&self, hwnd: &HWnd) -> iface::WndMode {
+        let state = self.state.borrow();
+        state.wnds[hwnd.ptr].attrs.mode
+    }
+    pub(super) fn screens(&self) -> Vec<iface::ScreenInfo> {
+        let state = self.state.borrow();
+        state.screens.clone()
+    }
 
     pub(super) fn new_layer(&self, attrs: LayerAttrs) -> HLayer {
         let mut state = self.state.borrow_mut();
@@ -266,6 +318,18 @@ impl Screen {
         listener.resize(wm, &hwnd.into());
     }
 
+    /// Implements `TestingWm::set_screens`.
+    pub(super) fn set_screens(&self, screens: Vec<iface::ScreenInfo>) {
+        for screen in screens.iter() {
+            assert!(screen.bounds.contains_box(&screen.work_area));
+            assert!(screen.dpi_scale > 0.0);
+            assert!(screen.dpi_scale.is_finite());
+        }
+
+        let mut state = self.state.borrow_mut();
+        state.screens = screens;
+    }
+
     /// Implements `TestingWm::set_wnd_focused`.
     pub(super) fn set_wnd_focused(&self, wm: Wm, hwnd: &HWnd, focused: bool) {
         let mut state = self.state.borrow_mut();
@@ -274,6 +338,37 @@ impl Screen {
 
         let listener = self.wnd_listener(hwnd).unwrap();
         listener.focus(wm, &hwnd.into());
+
+        if !focused {
+            // Dismiss the popup windows that no longer contain the focus
+            let state = self.state.borrow();
+            let mut popups = Vec::new();
+            let mut ptr = Some(hwnd.ptr);
+            while let Some(p) = ptr {
+                let has_focus = state
+                    .wnds
+                    .ptr_iter()
+                    .any(|(other, wnd)| wnd.focused && state.is_owned_by(other, p));
+                if state.is_popup(p) && !has_focus {
+                    popups.push(p);
+                }
+                ptr = state.owner_ptr(p);
+            }
+            drop(state);
+
+            self.dismiss_popups(wm, popups);
+        }
+    }
+
+    /// Raise `close_requested` for the specified popup windows.
+    fn dismiss_popups(&self, wm: Wm, popups: Vec<PoolPtr>) {
+        for ptr in popups {
+            let hwnd = HWnd { ptr };
+            // The window might have been closed by a previous handler
+            if let Ok(listener) = self.wnd_listener(&hwnd) {
+                listener.close_requested(wm, &(&hwnd).into());
+            }
+        }
     }
 
     /// Implements `TestingWm::read_wnd_snapshot`.
@@ -351,6 +446,18 @@ impl Screen {
         loc: Point2<f32>,
         button: u8,
     ) -> Box<dyn wmapi::MouseDrag> {
+        // Dismiss the popup windows outside which the mouse button was pressed
+        let popups = {
+            let state = self.state.borrow();
+            state
+                .wnds
+                .ptr_iter()
+                .map(|(ptr, _)| ptr)
+                .filter(|&ptr| state.is_popup(ptr) && !state.is_owned_by(hwnd.ptr, ptr))
+                .collect()
+        };
+        self.dismiss_popups(wm, popups);
+
         let listener = self.wnd_listener(hwnd).unwrap();
 
         let inner = listener.mouse_drag(wm, &hwnd.into(), loc, button);
@@ -393,6 +500,55 @@ impl Screen {
         })
     }
 
+    /// Implements `TestingWm::raise_drag_enter`.
+    pub(super) fn raise_drag_enter(
+        &self,
+        wm: Wm,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        let listener = self.wnd_listener(hwnd).unwrap();
+
+        listener.drag_enter(wm, &hwnd.into(), loc, formats, ops)
+    }
+
+    /// Implements `TestingWm::raise_drag_over`.
+    pub(super) fn raise_drag_over(
+        &self,
+        wm: Wm,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        let listener = self.wnd_listener(hwnd).unwrap();
+
+        listener.drag_over(wm, &hwnd.into(), loc, formats, ops)
+    }
+
+    /// Implements `TestingWm::raise_drag_leave`.
+    pub(super) fn raise_drag_leave(&self, wm: Wm, hwnd: &HWnd) {
+        let listener = self.wnd_listener(hwnd).unwrap();
+
+        listener.drag_leave(wm, &hwnd.into());
+    }
+
+    /// Implements `TestingWm::raise_drag_drop`.
+    pub(super) fn raise_drag_drop(
+        &self,
+        wm: Wm,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        data: &iface::DragData,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        let listener = self.wnd_listener(hwnd).unwrap();
+
+        listener.drag_drop(wm, &hwnd.into(), loc, data, ops)
+    }
+
     /// Implements `TestingWm::translate_action`.
     pub(super) fn translate_action(
         &self,
@@ -461,6 +617,17 @@ impl Screen {
 #[derive(Debug)]
 struct BadHWndError;
 
+/// The initial screen configuration: a single 1920×1080 screen.
+fn default_screens() -> Vec<iface::ScreenInfo> {
+    let bounds = box2! { min: [0, 0], max: [1920, 1080] };
+    vec![iface::ScreenInfo {
+        bounds,
+        work_area: bounds,
+        dpi_scale: 1.0,
+        primary: true,
+    }]
+}
+
 /// Convert the `LayerAttrs` of `Wm` to the `LayerAttrs` of `swrast`.
 /// Copied straight from `unix/comp.rs`.
 fn layer_attrs_to_sr_layer_attrs(
@@ -1,5 +1,5 @@
 use cgmath::{Point2, Vector2};
-use std::time::Instant;
+use std::{path::PathBuf, time::Instant};
 
 use crate::{iface, HTextInputCtx, HWnd};
 
@@ -47,6 +47,12 @@ pub trait TestingWm: 'static {
     /// `size` is not automatically clipped by `min_size` or `max_size`.
     fn set_wnd_size(&self, hwnd: &HWnd, size: [u32; 2]);
 
+    /// Replace the list of virtual screens returned by `Wm::screens`.
+    ///
+    /// Initially, there is a single 1920×1080 screen whose work area covers
+    /// the whole screen. Each screen's `work_area` must be inside `bounds`.
+    fn set_screens(&self, screens: Vec<iface::ScreenInfo>);
+
     /// Set the focus state of a given window and trigger `WndListener::focus`.
     fn set_wnd_focused(&self, hwnd: &HWnd, focused: bool);
 
@@ -70,6 +76,36 @@ pub trait TestingWm: 'static {
     /// Trigger `WndListener::scroll_gesture`.
     fn raise_scroll_gesture(&self, hwnd: &HWnd, loc: Point2<f32>) -> Box<dyn ScrollGesture>;
 
+    /// Trigger `WndListener::drag_enter`.
+    fn raise_drag_enter(
+        &self,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp>;
+
+    /// Trigger `WndListener::drag_over`.
+    fn raise_drag_over(
+        &self,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp>;
+
+    /// Trigger `WndListener::drag_leave`.
+    fn raise_drag_leave(&self, hwnd: &HWnd);
+
+    /// Trigger `WndListener::drag_drop`.
+    fn raise_drag_drop(
+        &self,
+        hwnd: &HWnd,
+        loc: Point2<f32>,
+        data: &iface::DragData,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp>;
+
     /// Get the list of currently active text input contexts.
     fn active_text_input_ctxs(&self) -> Vec<HTextInputCtx>;
 
@@ -113,18 +149,52 @@ pub trait TestingWm: 'static {
     ///
     /// It doesn't simulate the pressing and releasing of modifier keys, though.
     fn simulate_key(&self, hwnd: &HWnd, source: &str, pattern: &str);
+
+    /// Get the text stored in the emulated system clipboard.
+    fn clipboard_text(&self) -> Option<String>;
+
+    /// Replace the contents of the emulated system clipboard (or clear it if
+    /// `None` is given) and trigger `ClipboardListener::change`.
+    fn set_clipboard_text(&self, text: Option<&str>);
+
+    /// Get the list of currently open file dialogs in the order they were
+    /// opened.
+    fn file_dialogs(&self) -> Vec<FileDialog>;
+
+    /// Close an open file dialog and pass `result` to the handler given to
+    /// [`Wm::show_file_dialog`].
+    ///
+    /// Panics if the dialog isn't open, or if `result` doesn't contain
+    /// exactly one path for a dialog that doesn't accept multiple files.
+    ///
+    /// [`Wm::show_file_dialog`]: crate::iface::Wm::show_file_dialog
+    fn respond_file_dialog(&self, dialog: &FileDialog, result: Option<Vec<PathBuf>>);
 }
 
 /// A snapshot of window attributes.
 #[derive(Debug, Clone)]
 pub struct WndAttrs {
     pub size: [u32; 2],
+    pub position: [i32; 2],
     pub min_size: [u32; 2],
     pub max_size: [u32; 2],
     pub flags: iface::WndFlags,
     pub caption: String,
     pub visible: bool,
+    pub mode: iface::WndMode,
     pub cursor_shape: iface::CursorShape,
+    pub owner: Option<HWnd>,
+    pub popup_anchor: Option<iface::PopupAnchor>,
+}
+
+/// An open file dialog.
+#[derive(Debug, Clone)]
+pub struct FileDialog {
+    /// An identifier that is unique among the file dialogs opened during a
+    /// test.
+    pub id: u64,
+    pub owner: Option<HWnd>,
+    pub options: iface::FileDialogOptions,
 }
 
 /// Provides an interface for simulating a mouse drag geature.
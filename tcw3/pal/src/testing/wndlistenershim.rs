@@ -134,6 +134,51 @@ impl iface::WndListener<native::Wm> for NativeWndListener {
 
         Box::new(NativeScrollListener(scroll_listener))
     }
+
+    fn drag_enter(
+        &self,
+        wm: native::Wm,
+        hwnd: &native::HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        forward!(
+            self.0,
+            drag_enter,
+            [wm: wm],
+            [hwnd: hwnd],
+            loc,
+            formats,
+            ops
+        )
+    }
+
+    fn drag_over(
+        &self,
+        wm: native::Wm,
+        hwnd: &native::HWnd,
+        loc: Point2<f32>,
+        formats: iface::DragFormats,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        forward!(self.0, drag_over, [wm: wm], [hwnd: hwnd], loc, formats, ops)
+    }
+
+    fn drag_leave(&self, wm: native::Wm, hwnd: &native::HWnd) {
+        forward!(self.0, drag_leave, [wm: wm], [hwnd: hwnd])
+    }
+
+    fn drag_drop(
+        &self,
+        wm: native::Wm,
+        hwnd: &native::HWnd,
+        loc: Point2<f32>,
+        data: &iface::DragData,
+        ops: iface::DragOps,
+    ) -> Option<iface::DragOp> {
+        forward!(self.0, drag_drop, [wm: wm], [hwnd: hwnd], loc, data, ops)
+    }
 }
 
 /// Wraps `InterpretEventCtx<native::AccelTable>` to create a `InterpretEventCtx<AccelTable>`.
@@ -5,7 +5,8 @@ use flags_macro::flags;
 use pango::{FontDescription, FontMapExt, Layout, LayoutLine};
 use rgb::RGBA16;
 use std::{
-    convert::TryInto, ffi::CStr, mem::MaybeUninit, ops::Range, os::raw::c_uint, sync::Mutex,
+    convert::TryInto, ffi::CStr, mem::MaybeUninit, ops::Range, os::raw::c_uint, path::Path,
+    sync::Mutex,
 };
 use unicount::{num_scalars_in_utf8_str, str_next, str_prev};
 
@@ -77,6 +78,24 @@ impl iface::CharStyle for CharStyle {
             }
         }
 
+        if let Some(family) = &attrs.family {
+            font_desc.set_family(family);
+        }
+
+        if let Some(weight) = attrs.weight {
+            use glib::translate::from_glib;
+            let weight: pango::Weight = from_glib(weight.0 as pango_sys::PangoWeight);
+            font_desc.set_weight(weight);
+        }
+
+        if let Some(italic) = attrs.italic {
+            font_desc.set_style(if italic {
+                pango::Style::Italic
+            } else {
+                pango::Style::Normal
+            });
+        }
+
         if let Some(size) = attrs.size {
             // pangocairo's default DPI is 96 and we don't want to change it, so
             // apply a scaling factor here
@@ -84,16 +103,20 @@ impl iface::CharStyle for CharStyle {
             font_desc.set_size((size * (pango::SCALE as f32 * FACTOR)) as i32);
         }
 
-        let mut color = attrs.color.unwrap_or(None).map(rgbaf32_to_rgba16);
+        let mut color = attrs.color.map(|c| c.map(rgbaf32_to_rgba16));
 
-        let mut decor = attrs.decor.unwrap_or(iface::TextDecorFlags::empty());
+        let mut decor = attrs.decor;
 
         if let Some(tmpl) = attrs.template {
             font_desc.merge(Some(&tmpl.pango_font_desc.inner), false);
-            color = tmpl.color;
-            decor = tmpl.decor;
+            // Attributes explicitly specified by `attrs` take precedence
+            color = color.or(Some(tmpl.color));
+            decor = decor.or(Some(tmpl.decor));
         }
 
+        let color = color.unwrap_or(None);
+        let decor = decor.unwrap_or(iface::TextDecorFlags::empty());
+
         Self {
             pango_font_desc: ImmutableFontDesc { inner: font_desc },
             color,
@@ -106,6 +129,59 @@ impl iface::CharStyle for CharStyle {
     }
 }
 
+/// Insert Pango attributes representing `style` to `attr_list`.
+///
+/// If `is_run` is `true`, attributes are generated for the font description and
+/// decorations even if they are default values, so that they override the
+/// default character style.
+fn insert_char_style_attrs(
+    attr_list: &pango::AttrList,
+    style: &CharStyle,
+    range: Range<usize>,
+    is_run: bool,
+) {
+    use glib::translate::{from_glib_full, ToGlibPtr};
+
+    let insert = |attr: Option<pango::Attribute>| {
+        let mut attr = attr.expect("failed to create a Pango attribute");
+        attr.set_start_index(range.start.try_into().unwrap());
+        attr.set_end_index(range.end.try_into().unwrap());
+        attr_list.insert(attr);
+    };
+
+    if is_run {
+        // `pango::Attribute` doesn't have a safe wrapper of this
+        insert(unsafe {
+            from_glib_full(pango_sys::pango_attr_font_desc_new(
+                style.pango_font_desc.inner.to_glib_none().0,
+            ))
+        });
+    }
+
+    if let Some(color) = style.color {
+        insert(pango::Attribute::new_foreground(color.r, color.g, color.b));
+        insert(pango::Attribute::new_foreground_alpha(color.a));
+    }
+
+    let decor = style.decor;
+    if is_run || decor.contains(iface::TextDecorFlags::UNDERLINE) {
+        insert(pango::Attribute::new_underline(
+            if decor.contains(iface::TextDecorFlags::UNDERLINE) {
+                pango::Underline::Single
+            } else {
+                pango::Underline::None
+            },
+        ));
+    }
+    if is_run || decor.contains(iface::TextDecorFlags::STRIKETHROUGH) {
+        insert(pango::Attribute::new_strikethrough(
+            decor.contains(iface::TextDecorFlags::STRIKETHROUGH),
+        ));
+    }
+
+    // TODO: `OVERLINE` (requires Pango 1.46)
+}
+
 fn rgbaf32_to_rgba16(c: iface::RGBAF32) -> RGBA16 {
     use rgb::ComponentMap;
 
@@ -146,7 +222,16 @@ impl iface::TextLayout for TextLayout {
     type CharStyle = CharStyle;
 
     fn from_text(text: &str, style: &Self::CharStyle, width: Option<f32>) -> Self {
-        let font_map = pangocairo::FontMap::get_default().expect("failed to get a Pango font map");
+        Self::from_attr_text(text, style, &[], width)
+    }
+
+    fn from_attr_text(
+        text: &str,
+        style: &Self::CharStyle,
+        runs: &[iface::TextRun<Self::CharStyle>],
+        width: Option<f32>,
+    ) -> Self {
+        let font_map = default_font_map();
 
         let ctx = font_map
             .create_context()
@@ -167,7 +252,16 @@ impl iface::TextLayout for TextLayout {
 
         layout.set_text(text);
 
-        // TODO: `decor`
+        // Apply the attributes that can't be specified by `FontDescription`
+        // and the style runs
+        let attr_list = pango::AttrList::new();
+        insert_char_style_attrs(&attr_list, style, 0..text.len(), false);
+        for run in runs.iter() {
+            debug_assert!(run.range.start <= run.range.end);
+            debug_assert!(run.range.end <= text.len());
+            insert_char_style_attrs(&attr_list, &run.style, run.range.clone(), true);
+        }
+        layout.set_attributes(Some(&attr_list));
 
         let num_lines = layout.get_line_count() as usize;
         log::trace!("The text {:?} generated {:?} line(s)", text, num_lines);
@@ -499,6 +593,79 @@ impl TextLayout {
     }
 }
 
+/// Implements `Wm::register_font_file`.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn register_font_file(path: &Path) -> Result<(), iface::RegisterFontError> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, ptr::null_mut, sync::atomic::Ordering};
+
+    let path =
+        CString::new(path.as_os_str().as_bytes()).map_err(|_| iface::RegisterFontError::BadFile)?;
+
+    // Add the file to the current fontconfig configuration, which is shared
+    // by all threads. This fails if the file doesn't contain any fonts.
+    if unsafe { fc::FcConfigAppFontAddFile(null_mut(), path.as_ptr() as _) } == 0 {
+        return Err(iface::RegisterFontError::BadFile);
+    }
+
+    // Font maps are per-thread and cache font information. Tell every thread
+    // to discard the cache the next time it calls `default_font_map`.
+    FONT_CONFIG_GEN.fetch_add(1, Ordering::AcqRel);
+
+    Ok(())
+}
+
+/// Incremented by `register_font_file` whenever the fontconfig configuration
+/// changes.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+static FONT_CONFIG_GEN: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Get the calling thread's default font map, making sure it knows the fonts
+/// registered by `register_font_file` (possibly on another thread).
+fn default_font_map() -> pangocairo::FontMap {
+    let font_map = pangocairo::FontMap::get_default().expect("failed to get a Pango font map");
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        use glib::translate::ToGlibPtr;
+        use std::{cell::Cell, sync::atomic::Ordering};
+
+        thread_local! {
+            /// The value of `FONT_CONFIG_GEN` last seen by this thread.
+            static SEEN_FONT_CONFIG_GEN: Cell<usize> = Cell::new(0);
+        }
+
+        let gen = FONT_CONFIG_GEN.load(Ordering::Acquire);
+        if SEEN_FONT_CONFIG_GEN.with(|seen| seen.replace(gen)) != gen {
+            // Make Pango discard the cached font information. On this
+            // platform, pangocairo's font map is a `PangoFcFontMap`.
+            unsafe {
+                fc::pango_fc_font_map_config_changed(font_map.to_glib_none().0 as _);
+            }
+        }
+    }
+
+    font_map
+}
+
+/// Implements `Wm::register_font_file`.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub fn register_font_file(_path: &Path) -> Result<(), iface::RegisterFontError> {
+    // Pango doesn't use fontconfig on these platforms
+    Err(iface::RegisterFontError::Unsupported)
+}
+
+/// The subset of fontconfig and PangoFT2 API used by `register_font_file`.
+/// They are linked by `build.rs`.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod fc {
+    use std::os::raw::{c_int, c_uchar, c_void};
+
+    extern "C" {
+        pub fn FcConfigAppFontAddFile(config: *mut c_void, file: *const c_uchar) -> c_int;
+        pub fn pango_fc_font_map_config_changed(fcfontmap: *mut c_void);
+    }
+}
+
 fn pango_for_each_run_in_line(iter: &mut pango::LayoutIter, mut f: impl FnMut(pango::LayoutRun)) {
     while let Some(run) = iter.get_run_readonly() {
         f(run);
@@ -121,6 +121,10 @@ impl iface::Wm for Wm {
         window::is_wnd_focused(self, window)
     }
 
+    fn activate_wnd(self, window: &Self::HWnd) {
+        window::activate_wnd(self, window)
+    }
+
     fn request_update_ready_wnd(self, window: &Self::HWnd) {
         window::request_update_ready_wnd(self, window)
     }
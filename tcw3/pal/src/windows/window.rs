@@ -459,6 +459,21 @@ pub fn is_wnd_focused(_: Wm, pal_hwnd: &HWnd) -> bool {
     hwnd == unsafe { winuser::GetForegroundWindow() }
 }
 
+pub fn activate_wnd(_: Wm, pal_hwnd: &HWnd) {
+    let hwnd = pal_hwnd.expect_hwnd();
+
+    unsafe {
+        if winuser::IsIconic(hwnd) != 0 {
+            winuser::ShowWindow(hwnd, winuser::SW_RESTORE);
+        }
+
+        // This fails if the calling process is not allowed to set the
+        // foreground window. The system flashes the taskbar button instead
+        // in this case.
+        winuser::SetForegroundWindow(hwnd);
+    }
+}
+
 static FRAME_CLOCK_MANAGER: frameclock::FrameClockManager<HWnd> =
     frameclock::FrameClockManager::new();
 
@@ -0,0 +1,210 @@
+#!/usr/bin/env python3
+"""Generates `TCW3TestWide.ttf`, a font used by the tests.
+
+The font's family name is `TCW3 Test Wide`. It maps the space and ASCII
+letters to glyphs that are two em wide, which is much wider than any glyph of
+a usual font. This makes it easy to tell if the font is used. The letters are
+drawn as rectangles.
+
+Usage: ./make_test_font.py > TCW3TestWide.ttf
+"""
+import struct
+import sys
+
+UNITS_PER_EM = 1000
+ADVANCE = 2 * UNITS_PER_EM
+ASCENT = 800
+DESCENT = 200
+FAMILY = "TCW3 Test Wide"
+
+# (first code point, last code point). Glyph 0 is `.notdef`.
+RANGES = [(0x20, 0x20), (0x41, 0x5A), (0x61, 0x7A)]
+NUM_GLYPHS = 1 + sum(last - first + 1 for first, last in RANGES)
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def head():
+    return struct.pack(
+        ">IIIIHHqqhhhhHHhhh",
+        0x00010000,  # version
+        0x00010000,  # fontRevision
+        0,  # checksumAdjustment, filled later
+        0x5F0F3CF5,  # magicNumber
+        0b1011,  # flags
+        UNITS_PER_EM,
+        0,  # created
+        0,  # modified
+        0, -DESCENT, ADVANCE, ASCENT,  # xMin, yMin, xMax, yMax
+        0,  # macStyle
+        8,  # lowestRecPPEM
+        2,  # fontDirectionHint
+        0,  # indexToLocFormat (short)
+        0,  # glyphDataFormat
+    )
+
+
+def hhea():
+    return struct.pack(
+        ">IhhhHhhhhhhhhhhhH",
+        0x00010000, ASCENT, -DESCENT, 0,  # version, ascender, descender, lineGap
+        ADVANCE, 0, 0, ADVANCE,  # advanceWidthMax, minLSB, minRSB, xMaxExtent
+        1, 0, 0,  # caretSlopeRise, caretSlopeRun, caretOffset
+        0, 0, 0, 0,  # reserved
+        0,  # metricDataFormat
+        1,  # numberOfHMetrics; all glyphs share the last advance
+    )
+
+
+def maxp():
+    # maxPoints = 4, maxContours = 1, maxZones = 2
+    fields = [4, 1, 0, 0, 2] + [0] * 8
+    return struct.pack(">IH13H", 0x00010000, NUM_GLYPHS, *fields)
+
+
+def rect_glyph():
+    x_min, x_max, y_max = 100, ADVANCE - 100, ASCENT - 100
+    return struct.pack(
+        ">hhhhhHH4B4h4h",
+        1,  # numberOfContours
+        x_min, 0, x_max, y_max,
+        3,  # endPtsOfContours[0]
+        0,  # instructionLength
+        1, 1, 1, 1,  # flags (on-curve)
+        x_min, 0, x_max - x_min, 0,  # x deltas
+        0, y_max, 0, -y_max,  # y deltas
+    )
+
+
+def glyf_and_loca():
+    # `.notdef` and the space are empty
+    glyphs = [b"", b""] + [rect_glyph()] * (NUM_GLYPHS - 2)
+    glyf = b""
+    loca = b""
+    for glyph in glyphs:
+        loca += struct.pack(">H", len(glyf) // 2)
+        glyf += glyph
+    loca += struct.pack(">H", len(glyf) // 2)
+    return glyf, loca
+
+
+def os2():
+    return struct.pack(
+        ">HhHHHhhhhhhhhhhh10s4I4sHHHhhhHHII",
+        4,  # version
+        ADVANCE,  # xAvgCharWidth
+        400,  # usWeightClass
+        5,  # usWidthClass
+        0,  # fsType
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,  # subscript/superscript/strikeout
+        0,  # sFamilyClass
+        b"\0" * 10,  # panose
+        1, 0, 0, 0,  # ulUnicodeRange (Basic Latin)
+        b"TCW3",  # achVendID
+        0x40,  # fsSelection (REGULAR)
+        0x20, 0x7A,  # usFirstCharIndex, usLastCharIndex
+        ASCENT, -DESCENT, 0,  # sTypoAscender, sTypoDescender, sTypoLineGap
+        ASCENT, DESCENT,  # usWinAscent, usWinDescent
+        1, 0,  # ulCodePageRange (Latin 1)
+    ) + struct.pack(">hhHHH", 0, ASCENT, 0x20, 0x20, 0)
+
+
+def name():
+    names = {
+        1: FAMILY,
+        2: "Regular",
+        3: FAMILY + " Regular",
+        4: FAMILY,
+        6: FAMILY.replace(" ", ""),
+    }
+    records = b""
+    strings = b""
+    for name_id, text in sorted(names.items()):
+        data = text.encode("utf-16-be")
+        records += struct.pack(">6H", 3, 1, 0x409, name_id, len(data), len(strings))
+        strings += data
+    return struct.pack(">3H", 0, len(names), 6 + len(records)) + records + strings
+
+
+def cmap():
+    segments = []
+    glyph = 1
+    for first, last in RANGES:
+        segments.append((first, last, (glyph - first) & 0xFFFF))
+        glyph += last - first + 1
+    segments.append((0xFFFF, 0xFFFF, 1))
+
+    seg_count = len(segments)
+    search_range = 2 * 2 ** (seg_count.bit_length() - 1)
+    subtable = struct.pack(
+        ">7H",
+        4,  # format
+        16 + 8 * seg_count,  # length
+        0,  # language
+        2 * seg_count,
+        search_range,
+        seg_count.bit_length() - 1,
+        2 * seg_count - search_range,
+    )
+    subtable += struct.pack(">%dH" % seg_count, *(last for _, last, _ in segments))
+    subtable += struct.pack(">H", 0)
+    subtable += struct.pack(">%dH" % seg_count, *(first for first, _, _ in segments))
+    subtable += struct.pack(">%dH" % seg_count, *(delta for _, _, delta in segments))
+    subtable += struct.pack(">%dH" % seg_count, *([0] * seg_count))
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def post():
+    return struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0)
+
+
+def main():
+    glyf, loca = glyf_and_loca()
+    tables = {
+        b"OS/2": os2(),
+        b"cmap": cmap(),
+        b"glyf": glyf,
+        b"head": head(),
+        b"hhea": hhea(),
+        b"hmtx": struct.pack(">Hh", ADVANCE, 0),
+        b"loca": loca,
+        b"maxp": maxp(),
+        b"name": name(),
+        b"post": post(),
+    }
+
+    num_tables = len(tables)
+    search_range = 16 * 2 ** (num_tables.bit_length() - 1)
+    header = struct.pack(
+        ">IHHHH",
+        0x00010000,
+        num_tables,
+        search_range,
+        num_tables.bit_length() - 1,
+        16 * num_tables - search_range,
+    )
+
+    offset = len(header) + 16 * num_tables
+    directory = b""
+    body = b""
+    for tag, data in sorted(tables.items()):
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+
+    font = bytearray(header + directory + body)
+
+    # Fill `head.checksumAdjustment`
+    head_offset = offset + sum(
+        len(data) + (-len(data) % 4) for tag, data in sorted(tables.items()) if tag < b"head"
+    )
+    adjustment = (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF
+    struct.pack_into(">I", font, head_offset + 8, adjustment)
+
+    sys.stdout.buffer.write(font)
+
+
+if __name__ == "__main__":
+    main()
@@ -1,9 +1,11 @@
 use cggeom::{box2, prelude::*, Box2};
 use cgmath::{Deg, Matrix3, Point2, Vector2};
+use futures::task::LocalSpawnExt;
 use log::info;
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     ops::Range,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -1083,3 +1085,419 @@ fn wnd_key_events() {
         assert_eq!(state.get(), 3);
     });
 }
+
+#[test]
+fn wnd_drag_events() {
+    init_logger();
+    testing::run_test(|twm| {
+        let wm = twm.wm();
+
+        let state = Rc::new(Cell::new(0));
+
+        #[derive(Clone)]
+        struct Listener(Rc<Cell<u8>>);
+        impl WndListener<pal::Wm> for Listener {
+            fn drag_enter(
+                &self,
+                _: pal::Wm,
+                _: &pal::HWnd,
+                loc: Point2<f32>,
+                formats: pal::DragFormats,
+                ops: pal::DragOps,
+            ) -> Option<pal::DragOp> {
+                assert_eq!(self.0.get(), 0);
+                assert_eq!(loc, Point2::new(10.0, 20.0));
+                assert_eq!(formats, pal::DragFormats::TEXT);
+                self.0.set(1);
+                ops.first()
+            }
+
+            fn drag_over(
+                &self,
+                _: pal::Wm,
+                _: &pal::HWnd,
+                _: Point2<f32>,
+                _: pal::DragFormats,
+                ops: pal::DragOps,
+            ) -> Option<pal::DragOp> {
+                assert_eq!(self.0.get(), 1);
+                self.0.set(2);
+                if ops.contains(pal::DragOps::LINK) {
+                    Some(pal::DragOp::Link)
+                } else {
+                    None
+                }
+            }
+
+            fn drag_leave(&self, _: pal::Wm, _: &pal::HWnd) {
+                assert_eq!(self.0.get(), 2);
+                self.0.set(3);
+            }
+
+            fn drag_drop(
+                &self,
+                _: pal::Wm,
+                _: &pal::HWnd,
+                _: Point2<f32>,
+                data: &pal::DragData,
+                _: pal::DragOps,
+            ) -> Option<pal::DragOp> {
+                assert_eq!(self.0.get(), 3);
+                assert_eq!(data.text.as_deref(), Some("hello"));
+                self.0.set(4);
+                Some(pal::DragOp::Copy)
+            }
+        }
+
+        let hwnd = wm.new_wnd(pal::WndAttrs {
+            visible: Some(true),
+            listener: Some(Box::new(Listener(Rc::clone(&state)))),
+            ..Default::default()
+        });
+
+        let data = pal::DragData {
+            text: Some("hello".to_owned()),
+            ..Default::default()
+        };
+        let ops = pal::DragOps::MOVE | pal::DragOps::LINK;
+
+        assert_eq!(
+            twm.raise_drag_enter(&hwnd, Point2::new(10.0, 20.0), data.formats(), ops),
+            Some(pal::DragOp::Move)
+        );
+        assert_eq!(
+            twm.raise_drag_over(&hwnd, Point2::new(30.0, 20.0), data.formats(), ops),
+            Some(pal::DragOp::Link)
+        );
+        twm.raise_drag_leave(&hwnd);
+        assert_eq!(
+            twm.raise_drag_drop(&hwnd, Point2::new(30.0, 20.0), &data, ops),
+            Some(pal::DragOp::Copy)
+        );
+        assert_eq!(state.get(), 4);
+    });
+}
+
+#[test]
+fn popup_anchor_place() {
+    let bounds = box2! { min: [0.0, 0.0], max: [100.0, 100.0] };
+    let anchor = pal::PopupAnchor {
+        rect: box2! { min: [60.0, 60.0], max: [80.0, 80.0] },
+        direction: pal::PopupDirection::Down,
+    };
+    assert_eq!(anchor.place([30.0, 20.0], bounds), Point2::new(60.0, 80.0));
+    // Flipped vertically
+    assert_eq!(anchor.place([30.0, 40.0], bounds), Point2::new(60.0, 20.0));
+    // Flipped horizontally
+    assert_eq!(anchor.place([50.0, 20.0], bounds), Point2::new(30.0, 80.0));
+    // Shifted vertically because it doesn't fit in either side
+    assert_eq!(anchor.place([20.0, 70.0], bounds), Point2::new(60.0, 30.0));
+
+    let anchor = pal::PopupAnchor {
+        direction: pal::PopupDirection::Right,
+        ..anchor
+    };
+    assert_eq!(anchor.place([15.0, 20.0], bounds), Point2::new(80.0, 60.0));
+    // Flipped horizontally and vertically
+    assert_eq!(anchor.place([30.0, 50.0], bounds), Point2::new(30.0, 30.0));
+}
+
+#[test]
+fn popup_dismissal() {
+    init_logger();
+    testing::run_test(|twm| {
+        let wm = twm.wm();
+
+        #[derive(Clone)]
+        struct Listener(Rc<Cell<u8>>);
+        impl WndListener<pal::Wm> for Listener {
+            fn close_requested(&self, _: pal::Wm, _: &pal::HWnd) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let owner = wm.new_wnd(pal::WndAttrs {
+            visible: Some(true),
+            ..Default::default()
+        });
+
+        let new_popup = |owner: &pal::HWnd, count: &Rc<Cell<u8>>| {
+            wm.new_wnd(pal::WndAttrs {
+                visible: Some(true),
+                flags: Some(pal::WndFlags::POPUP),
+                owner: Some(Some(owner.clone())),
+                popup_anchor: Some(Some(pal::PopupAnchor {
+                    rect: box2! { min: [10.0, 20.0], max: [30.0, 40.0] },
+                    direction: pal::PopupDirection::Down,
+                })),
+                listener: Some(Box::new(Listener(Rc::clone(count)))),
+                ..Default::default()
+            })
+        };
+
+        let (menu_count, submenu_count) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let menu = new_popup(&owner, &menu_count);
+        let submenu = new_popup(&menu, &submenu_count);
+
+        let attrs = twm.wnd_attrs(&submenu).unwrap();
+        assert_eq!(attrs.owner, Some(menu.clone()));
+        assert!(attrs.popup_anchor.is_some());
+
+        // Clicking inside the submenu doesn't dismiss anything
+        twm.raise_mouse_drag(&submenu, Point2::new(1.0, 1.0), 0)
+            .cancel();
+        assert_eq!((menu_count.get(), submenu_count.get()), (0, 0));
+
+        // Clicking inside the menu dismisses the submenu
+        twm.raise_mouse_drag(&menu, Point2::new(1.0, 1.0), 0)
+            .cancel();
+        assert_eq!((menu_count.get(), submenu_count.get()), (0, 1));
+
+        // Moving the focus from the menu to the submenu doesn't dismiss
+        // anything, but moving it away from both does
+        twm.set_wnd_focused(&menu, true);
+        twm.set_wnd_focused(&submenu, true);
+        twm.set_wnd_focused(&menu, false);
+        assert_eq!((menu_count.get(), submenu_count.get()), (0, 1));
+        twm.set_wnd_focused(&submenu, false);
+        assert_eq!((menu_count.get(), submenu_count.get()), (1, 2));
+
+        // Clicking inside the owner dismisses both
+        twm.raise_mouse_drag(&owner, Point2::new(1.0, 1.0), 0)
+            .cancel();
+        assert_eq!((menu_count.get(), submenu_count.get()), (2, 3));
+
+        wm.remove_wnd(&submenu);
+        wm.remove_wnd(&menu);
+        wm.remove_wnd(&owner);
+    });
+}
+
+#[test]
+fn file_dialog() {
+    init_logger();
+    testing::run_test(|twm| {
+        let wm = twm.wm();
+        let owner = wm.new_wnd(Default::default());
+
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let options = pal::FileDialogOptions {
+            kind: pal::FileDialogKind::OpenMultiple,
+            filters: vec![pal::FileFilter::new("Text files", &["*.txt"])],
+            ..Default::default()
+        };
+
+        {
+            let (results, dialog) = (
+                Rc::clone(&results),
+                wm.file_dialog(Some(&owner), options.clone()),
+            );
+            wm.spawner()
+                .spawn_local(async move {
+                    results.borrow_mut().push(dialog.await);
+                })
+                .unwrap();
+        }
+        {
+            let (results, dialog) = (
+                Rc::clone(&results),
+                wm.file_dialog(None, Default::default()),
+            );
+            wm.spawner()
+                .spawn_local(async move {
+                    results.borrow_mut().push(dialog.await);
+                })
+                .unwrap();
+        }
+        twm.step_unsend();
+
+        let dialogs = twm.file_dialogs();
+        assert_eq!(dialogs.len(), 2);
+        assert_eq!(dialogs[0].owner, Some(owner.clone()));
+        assert_eq!(dialogs[0].options, options);
+        assert_eq!(dialogs[1].owner, None);
+        assert!(results.borrow().is_empty());
+
+        // Complete the second one first
+        twm.respond_file_dialog(&dialogs[1], None);
+        twm.step_unsend();
+        assert_eq!(*results.borrow(), vec![None]);
+
+        let paths = vec![PathBuf::from("/a.txt"), PathBuf::from("/b.txt")];
+        twm.respond_file_dialog(&dialogs[0], Some(paths.clone()));
+        twm.step_unsend();
+        assert_eq!(*results.borrow(), vec![None, Some(paths)]);
+
+        assert!(twm.file_dialogs().is_empty());
+
+        wm.remove_wnd(&owner);
+    });
+}
+
+#[test]
+fn screens_and_wnd_geometry() {
+    init_logger();
+    testing::run_test(|twm| {
+        let wm = twm.wm();
+
+        // The default configuration
+        let screens = wm.screens();
+        assert_eq!(screens.len(), 1);
+        assert!(screens[0].primary);
+
+        let screens = vec![
+            pal::ScreenInfo {
+                bounds: box2! { min: [0, 0], max: [1280, 800] },
+                work_area: box2! { min: [0, 30], max: [1280, 800] },
+                dpi_scale: 2.0,
+                primary: true,
+            },
+            pal::ScreenInfo {
+                bounds: box2! { min: [1280, 0], max: [3200, 1080] },
+                work_area: box2! { min: [1280, 0], max: [3200, 1040] },
+                dpi_scale: 1.0,
+                primary: false,
+            },
+        ];
+        twm.set_screens(screens.clone());
+        assert_eq!(wm.screens(), screens);
+
+        let hwnd = wm.new_wnd(pal::WndAttrs {
+            size: Some([400, 300]),
+            position: Some([1500, 100]),
+            ..Default::default()
+        });
+
+        assert_eq!(wm.get_wnd_position(&hwnd), Some([1500, 100]));
+        assert_eq!(wm.get_wnd_mode(&hwnd), pal::WndMode::Normal);
+
+        wm.set_wnd_attr(
+            &hwnd,
+            pal::WndAttrs {
+                position: Some([20, 40]),
+                mode: Some(pal::WndMode::Maximized),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(wm.get_wnd_position(&hwnd), Some([20, 40]));
+        assert_eq!(wm.get_wnd_mode(&hwnd), pal::WndMode::Maximized);
+        let attrs = twm.wnd_attrs(&hwnd).unwrap();
+        assert_eq!(attrs.position, [20, 40]);
+        assert_eq!(attrs.mode, pal::WndMode::Maximized);
+
+        wm.remove_wnd(&hwnd);
+    });
+}
+
+#[test]
+fn fit_wnd_position() {
+    let screens = [
+        pal::ScreenInfo {
+            bounds: box2! { min: [0, 0], max: [1000, 800] },
+            work_area: box2! { min: [0, 30], max: [1000, 800] },
+            dpi_scale: 1.0,
+            primary: true,
+        },
+        pal::ScreenInfo {
+            bounds: box2! { min: [1000, 0], max: [2000, 800] },
+            work_area: box2! { min: [1000, 0], max: [2000, 760] },
+            dpi_scale: 1.0,
+            primary: false,
+        },
+    ];
+
+    // Already inside a work area
+    assert_eq!(
+        pal::fit_wnd_position(&screens, [100, 100], [200, 200]),
+        [100, 100]
+    );
+    // Partially outside the primary screen's work area
+    assert_eq!(
+        pal::fit_wnd_position(&screens, [-50, 0], [200, 200]),
+        [0, 30]
+    );
+    // Mostly inside the secondary screen
+    assert_eq!(
+        pal::fit_wnd_position(&screens, [1700, 700], [400, 200]),
+        [1600, 560]
+    );
+    // Completely off-screen; moved to the primary screen
+    assert_eq!(
+        pal::fit_wnd_position(&screens, [5000, 5000], [200, 200]),
+        [800, 600]
+    );
+    // Larger than the work area
+    assert_eq!(
+        pal::fit_wnd_position(&screens, [-100, 0], [1200, 900]),
+        [0, 30]
+    );
+    // No screens
+    assert_eq!(
+        pal::fit_wnd_position(&[], [5000, 5000], [200, 200]),
+        [5000, 5000]
+    );
+}
+
+#[test]
+fn register_bad_font_file() {
+    init_logger();
+    testing::run_test(|twm| {
+        let wm = twm.wm();
+        assert!(wm
+            .register_font_file(Path::new("/nonexistent/font.ttf"))
+            .is_err());
+    });
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[test]
+fn register_font_file() {
+    use std::sync::mpsc::channel;
+
+    init_logger();
+    testing::run_test(|twm| {
+        // The glyphs of this font are two em wide
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fonts/TCW3TestWide.ttf");
+        let style = pal::CharStyle::new(pal::CharStyleAttrs {
+            family: Some("TCW3 Test Wide".into()),
+            size: Some(10.0),
+            ..Default::default()
+        });
+        let width = |style: &pal::CharStyle| {
+            let layout = pal::TextLayout::from_text("wide", style, None);
+            layout.layout_bounds().size().x
+        };
+
+        // Create a font map on another thread before registering the font
+        let (send_registered, recv_registered) = channel();
+        let (send_width, recv_width) = channel();
+        let style2 = style.clone();
+        let thread = spawn(move || {
+            send_width.send(width(&style2)).unwrap();
+            recv_registered.recv().unwrap();
+            send_width.send(width(&style2)).unwrap();
+        });
+
+        // Not registered yet, so a fallback font is used
+        let width_before = width(&style);
+        info!("width_before = {}", width_before);
+        assert!(width_before < 50.0);
+        assert!(recv_width.recv().unwrap() < 50.0);
+
+        twm.wm().register_font_file(&path).unwrap();
+
+        // "wide" is 8 em wide in the registered font
+        let width_after = width(&style);
+        info!("width_after = {}", width_after);
+        assert!(width_after >= 75.0, "{}", width_after);
+
+        // The other thread sees the registered font, too
+        send_registered.send(()).unwrap();
+        let width_after_other_thread = recv_width.recv().unwrap();
+        info!("width_after_other_thread = {}", width_after_other_thread);
+        assert!(width_after_other_thread >= 75.0);
+
+        thread.join().unwrap();
+    });
+}
@@ -20,6 +20,7 @@ pub mod views {
     mod checkbox;
     mod entry;
     mod label;
+    mod menu;
     pub mod scrollbar;
     pub mod slider;
     mod spacer;
@@ -30,6 +31,7 @@ pub mod views {
         checkbox::{Checkbox, RadioButton},
         entry::{Entry, EntryCore},
         label::Label,
+        menu::{Menu, MenuItem},
         scrollbar::ScrollbarRaw,
         slider::{Slider, SliderRaw},
         spacer::{new_spacer, Spacer},
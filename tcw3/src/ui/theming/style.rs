@@ -112,6 +112,11 @@ pub mod elem_id {
                 , SLIDER_KNOB
                 , SLIDER_TICKS
                 , SLIDER_LABELS
+                , MENU
+                , MENU_ITEM
+                , MENU_ITEM_DISABLED
+                , MENU_SEPARATOR
+                , MENU_ACCEL
     }
 }
 
@@ -139,6 +144,7 @@ pub mod roles {
                 , SLIDER_KNOB
                 , SLIDER_TICKS
                 , SLIDER_LABELS
+                , MENU_ACCEL
     }
 }
 
@@ -568,6 +568,13 @@ const SLIDER_LABELS_MARGIN: f32 = 2.0;
 
 const FIELD_HEIGHT: f32 = 20.0;
 
+/// The vertical padding of a menu and a menu separator.
+const MENU_PADDING: f32 = 4.0;
+const MENU_ITEM_METRICS: Metrics = Metrics {
+    margin: [3.0, 12.0, 3.0, 20.0],
+    ..Metrics::default()
+};
+
 /// Replace blue with a global tint color, and create a `HImg`.
 fn recolor_tint(data: &(&'static [u8], [f32; 2])) -> HImg {
     use alt_fp::fma;
@@ -1055,6 +1062,62 @@ lazy_static! {
             layer_bg_color[0]: RGBAF32::new(0.5, 0.5, 0.5, 0.8),
             min_size: Vector2::new(1.0, 1.0),
         },
+
+        // Menu
+        ([#MENU]) (priority = 100) {
+            num_layers: 2,
+
+            // Border
+            layer_bg_color[0]: RGBAF32::new(0.0, 0.0, 0.0, 0.3),
+
+            layer_bg_color[1]: RGBAF32::new(0.98, 0.98, 0.98, 1.0),
+            layer_metrics[1]: Metrics {
+                margin: [1.0; 4],
+                ..Metrics::default()
+            },
+
+            subview_metrics[roles::GENERIC]: Metrics {
+                margin: [MENU_PADDING, 1.0, MENU_PADDING, 1.0],
+                ..Metrics::default()
+            },
+        },
+        ([#MENU_ITEM]) (priority = 100) {
+            num_layers: 1,
+
+            // Highlight
+            layer_bg_color[0]: RGBAF32::new(0.2, 0.5, 0.9, 1.0),
+            layer_opacity[0]: 0.0,
+
+            subview_metrics[roles::GENERIC]: MENU_ITEM_METRICS,
+        },
+        ([#MENU_ITEM.ACTIVE]) (priority = 200) {
+            layer_opacity[0]: 1.0,
+        },
+        ([#MENU_ITEM_DISABLED]) (priority = 100) {
+            subview_metrics[roles::GENERIC]: MENU_ITEM_METRICS,
+        },
+        // Menu item labels
+        ([] < [#MENU_ITEM]) (priority = 100) {
+            fg_color: RGBAF32::new(0.0, 0.0, 0.0, 1.0),
+        },
+        ([#MENU_ACCEL] < [#MENU_ITEM]) (priority = 150) {
+            fg_color: RGBAF32::new(0.4, 0.4, 0.4, 1.0),
+        },
+        ([] < [#MENU_ITEM.ACTIVE]) (priority = 200) {
+            fg_color: RGBAF32::new(1.0, 1.0, 1.0, 1.0),
+        },
+        ([] < [#MENU_ITEM_DISABLED]) (priority = 100) {
+            fg_color: RGBAF32::new(0.6, 0.6, 0.6, 1.0),
+        },
+        ([#MENU_SEPARATOR]) (priority = 100) {
+            num_layers: 1,
+            layer_bg_color[0]: RGBAF32::new(0.0, 0.0, 0.0, 0.15),
+            layer_metrics[0]: Metrics {
+                margin: [MENU_PADDING, 0.0, MENU_PADDING, 0.0],
+                ..Metrics::default()
+            },
+            min_size: Vector2::new(0.0, MENU_PADDING * 2.0 + 1.0),
+        },
     };
 }
 
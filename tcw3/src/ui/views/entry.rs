@@ -101,12 +101,26 @@ impl Entry {
         self.core.set_text(value)
     }
 
+    /// Replace the selected text with `text`.
+    ///
+    /// See [`EntryCore::insert_text`].
+    pub fn insert_text(&self, text: &str) {
+        self.core.insert_text(text)
+    }
+
     /// Add a function called after the text content is modified.
     ///
     /// See [`EntryCore::subscribe_changed`].
     pub fn subscribe_changed(&self, cb: Box<dyn Fn(pal::Wm)>) -> Sub {
         self.core.subscribe_changed(cb)
     }
+
+    /// Add a function called when the user presses the Enter key.
+    ///
+    /// See [`EntryCore::subscribe_activated`].
+    pub fn subscribe_activated(&self, cb: Box<dyn Fn(pal::Wm)>) -> Sub {
+        self.core.subscribe_activated(cb)
+    }
 }
 
 impl Widget for Entry {
@@ -121,6 +135,10 @@ impl Widget for Entry {
 
 /// A widget implementing the core functionality of a text input field.
 ///
+/// `EntryCore` is a single-line input widget by default. In the multi-line
+/// mode (see [`EntryCore::set_multiline`]), the text is wrapped at the view's
+/// width, and the view's preferred height follows the height of the text.
+///
 /// # Styling
 ///
 ///  - `style_elem` - `FgColor`, `Padding`
@@ -139,11 +157,18 @@ struct Inner {
     style_elem: theming::Elem,
     style_sel_elem: theming::Elem,
     tictx_event_mask: Cell<pal::TextInputCtxEventFlags>,
+    multiline: Cell<bool>,
 
     /// The list of subscribers of the `change` event.
     change_handlers: RefCell<SubscriberList<Box<dyn Fn(pal::Wm)>>>,
     /// `true` means the calls to `change_handlers` are pended.
     pending_change_handler: Cell<bool>,
+    /// The list of subscribers of the `activated` event.
+    activate_handlers: RefCell<SubscriberList<Box<dyn Fn(pal::Wm)>>>,
+    /// The list of subscribers of the `preferred_height_changed` event.
+    preferred_height_handlers: RefCell<SubscriberList<Box<dyn Fn(pal::Wm)>>>,
+    /// `true` means the call to `update_size_traits` is pended.
+    pending_size_traits: Cell<bool>,
 }
 
 impl fmt::Debug for Inner {
@@ -155,7 +180,9 @@ impl fmt::Debug for Inner {
             .field("style_elem", &self.style_elem)
             .field("style_sel_elem", &self.style_sel_elem)
             .field("tictx_event_mask", &self.tictx_event_mask)
+            .field("multiline", &self.multiline)
             .field("pending_change_handler", &self.pending_change_handler)
+            .field("pending_size_traits", &self.pending_size_traits)
             .finish()
     }
 }
@@ -164,7 +191,15 @@ impl fmt::Debug for Inner {
 struct State {
     text: String,
     text_layout_info: Option<TextLayoutInfo>,
-    scroll: f32,
+    /// The width at which the text is wrapped. Always `None` in the
+    /// single-line mode.
+    wrap_width: Option<f32>,
+    /// The scroll offset. The X and Y components are used in the single-line
+    /// and multi-line mode, respectively.
+    scroll: Vector2<f32>,
+    /// The preferred height last given to the view's layout (only used in the
+    /// multi-line mode).
+    content_height: f32,
     canvas: CanvasMixin,
     tictx: Option<pal::HTextInputCtx>,
     sel_range: [usize; 2],
@@ -182,6 +217,8 @@ struct State {
 struct TextLayoutInfo {
     text_layout: pal::TextLayout,
     layout_bounds: Box2<f32>,
+    /// `true` if the text is laid out in the multi-line mode.
+    multiline: bool,
 
     line_height: f32,
 
@@ -217,7 +254,9 @@ impl EntryCore {
                 state: RefCell::new(State {
                     text: String::new(),
                     text_layout_info: None,
-                    scroll: 0.0,
+                    wrap_width: None,
+                    scroll: Vector2::new(0.0, 0.0),
+                    content_height: 0.0,
                     canvas: CanvasMixin::new(),
                     tictx: None,
                     sel_range: [0; 2],
@@ -231,8 +270,12 @@ impl EntryCore {
                 style_elem,
                 style_sel_elem,
                 tictx_event_mask: Cell::new(pal::TextInputCtxEventFlags::empty()),
+                multiline: Cell::new(false),
                 change_handlers: RefCell::new(SubscriberList::new()),
                 pending_change_handler: Cell::new(false),
+                activate_handlers: RefCell::new(SubscriberList::new()),
+                preferred_height_handlers: RefCell::new(SubscriberList::new()),
+                pending_size_traits: Cell::new(false),
             }),
         };
 
@@ -311,6 +354,13 @@ impl EntryCore {
         );
     }
 
+    /// Replace the selected text with `text` (or insert `text` at the cursor
+    /// if nothing is selected) as if the user had pasted it. Unlike
+    /// [`EntryCore::set_text`], this operation can be undone.
+    pub fn insert_text(&self, text: &str) {
+        insert_text(self.view.as_ref(), RcBorrow::from(&self.inner), text);
+    }
+
     /// Add a function called when the text content is modified.
     ///
     /// The function may be called spuriously, i.e., even when the text content
@@ -322,6 +372,83 @@ impl EntryCore {
     pub fn subscribe_changed(&self, cb: Box<dyn Fn(pal::Wm)>) -> Sub {
         self.inner.change_handlers.borrow_mut().insert(cb).untype()
     }
+
+    /// Add a function called when the user presses the Enter key
+    /// (`INSERT_PARAGRAPH_BREAK`).
+    ///
+    /// While there is at least one subscriber, `INSERT_PARAGRAPH_BREAK` raises
+    /// this event instead of inserting a line break in the multi-line mode.
+    /// `INSERT_LINE_BREAK` (Shift+Enter) still inserts a line break.
+    ///
+    /// The function is called via `Wm::invoke_on_update`, thus allowed to
+    /// modify view hierarchy, view attributes, and the text content.
+    pub fn subscribe_activated(&self, cb: Box<dyn Fn(pal::Wm)>) -> Sub {
+        self.inner
+            .activate_handlers
+            .borrow_mut()
+            .insert(cb)
+            .untype()
+    }
+
+    /// Get the preferred height of the view, including the padding. The
+    /// value changes with the height of the text in the multi-line mode.
+    /// Returns `0.0` in the single-line mode.
+    pub fn preferred_height(&self) -> f32 {
+        if !self.inner.multiline.get() {
+            return 0.0;
+        }
+
+        let content_height = self.inner.state.borrow().content_height;
+        let [padding_top, _, padding_bottom, _] = self.inner.style_elem.computed_values().padding();
+        content_height + padding_top + padding_bottom
+    }
+
+    /// Add a function called when the value of [`EntryCore::preferred_height`]
+    /// changes.
+    ///
+    /// The function is called via `Wm::invoke`, thus allowed to modify view
+    /// hierarchy and view attributes.
+    pub fn subscribe_preferred_height_changed(&self, cb: Box<dyn Fn(pal::Wm)>) -> Sub {
+        self.inner
+            .preferred_height_handlers
+            .borrow_mut()
+            .insert(cb)
+            .untype()
+    }
+
+    /// Enable or disable the multi-line mode. Defaults to `false`.
+    ///
+    /// In the multi-line mode, line breaks in the text are preserved, the text
+    /// is wrapped at the view's width, and the view's preferred height is
+    /// updated to fit the text.
+    pub fn set_multiline(&self, multiline: bool) {
+        if self.inner.multiline.get() == multiline {
+            return;
+        }
+        self.inner.multiline.set(multiline);
+
+        {
+            let mut state = self.inner.state.borrow_mut();
+            state.wrap_width = None;
+            state.scroll = Vector2::new(0.0, 0.0);
+            state.content_height = 0.0;
+            state.invalidate_text_layout();
+            state.canvas.pend_draw(self.view.as_ref());
+        }
+
+        if multiline {
+            update_size_traits(RcBorrow::from(&self.inner), self.view.as_ref());
+        } else {
+            self.view
+                .set_layout(EmptyLayout::new(SizeTraits::default()));
+            raise_preferred_height_changed(RcBorrow::from(&self.inner));
+        }
+    }
+
+    /// Get a flag indicating whether the multi-line mode is enabled.
+    pub fn multiline(&self) -> bool {
+        self.inner.multiline.get()
+    }
 }
 
 impl State {
@@ -333,13 +460,14 @@ impl State {
                 sys: Some(font_type),
                 ..Default::default()
             });
-            let text_layout = pal::TextLayout::from_text(&self.text, &char_style, None);
+            let text_layout = pal::TextLayout::from_text(&self.text, &char_style, self.wrap_width);
 
             let layout_bounds = text_layout.layout_bounds();
 
             self.text_layout_info = Some(TextLayoutInfo {
                 text_layout,
                 layout_bounds,
+                multiline: self.wrap_width.is_some(),
                 runs: Vec::new(),
                 line_vertical_bounds: 0.0..0.0,
                 line_height: char_style.size(),
@@ -423,16 +551,33 @@ impl State {
     fn scroll_cursor_into_view(&mut self, hview: HViewRef<'_>, elem: &theming::Elem) -> bool {
         let cursor_i = self.sel_range[1];
         let layout_info = self.ensure_text_layout(elem);
-        let cursor_x = layout_info.text_layout.cursor_pos(cursor_i)[0].x;
-        let [_, padding_right, _, padding_left] = elem.computed_values().padding();
-        let text_width = layout_info.layout_bounds.max.x;
-        let viewport_width = hview.frame().size().x - (padding_right + padding_left);
-
-        let new_scroll = self
-            .scroll
-            .fmax(cursor_x - viewport_width)
-            .fmin(cursor_x)
-            .fmin((text_width - viewport_width).fmax(0.0));
+        let cursor = layout_info.text_layout.cursor_pos(cursor_i)[0];
+        let [padding_top, padding_right, padding_bottom, padding_left] =
+            elem.computed_values().padding();
+
+        let mut new_scroll = self.scroll;
+        if layout_info.multiline {
+            // Scroll vertically
+            let text_top = layout_info.layout_bounds.min.y;
+            let text_height = layout_info.layout_bounds.size().y;
+            let viewport_height = hview.frame().size().y - (padding_top + padding_bottom);
+
+            new_scroll.y = new_scroll
+                .y
+                .fmax(cursor.bottom - text_top - viewport_height)
+                .fmin(cursor.top - text_top)
+                .fmin((text_height - viewport_height).fmax(0.0))
+                .fmax(0.0);
+        } else {
+            let text_width = layout_info.layout_bounds.max.x;
+            let viewport_width = hview.frame().size().x - (padding_right + padding_left);
+
+            new_scroll.x = new_scroll
+                .x
+                .fmax(cursor.x - viewport_width)
+                .fmin(cursor.x)
+                .fmin((text_width - viewport_width).fmax(0.0));
+        }
 
         if new_scroll != self.scroll {
             self.scroll = new_scroll;
@@ -444,21 +589,36 @@ impl State {
 }
 
 impl TextLayoutInfo {
-    fn text_origin(&self, view: HViewRef<'_>, scroll: f32, elem: &theming::Elem) -> Vector2<f32> {
-        let baseline = self.text_layout.line_baseline(0);
-        let height = view.frame().size().y;
+    fn text_origin(
+        &self,
+        view: HViewRef<'_>,
+        scroll: Vector2<f32>,
+        elem: &theming::Elem,
+    ) -> Vector2<f32> {
         let [padding_top, _, padding_bottom, padding_left] = elem.computed_values().padding();
-        [
-            padding_left - scroll,
-            (height + self.line_height + padding_top - padding_bottom) * 0.5 - baseline,
-        ]
-        .into()
+        if self.multiline {
+            // Align the text to the top edge
+            [
+                padding_left,
+                padding_top - self.layout_bounds.min.y - scroll.y,
+            ]
+            .into()
+        } else {
+            // Center the line vertically
+            let baseline = self.text_layout.line_baseline(0);
+            let height = view.frame().size().y;
+            [
+                padding_left - scroll.x,
+                (height + self.line_height + padding_top - padding_bottom) * 0.5 - baseline,
+            ]
+            .into()
+        }
     }
 
     fn text_origin_global(
         &self,
         view: HViewRef<'_>,
-        scroll: f32,
+        scroll: Vector2<f32>,
         elem: &theming::Elem,
     ) -> Vector2<f32> {
         let global_loc: [f32; 2] = view.global_frame().min.into();
@@ -468,13 +628,24 @@ impl TextLayoutInfo {
     fn cursor_index_from_global_point(
         &self,
         view: HViewRef<'_>,
-        scroll: f32,
+        scroll: Vector2<f32>,
         elem: &theming::Elem,
-        x: f32,
+        point: Point2<f32>,
     ) -> usize {
-        self.text_layout.cursor_index_from_point(
-            [x - self.text_origin_global(view, scroll, elem).x, 0.0].into(),
-        )
+        let origin = self.text_origin_global(view, scroll, elem);
+        let y = if self.multiline {
+            point.y - origin.y
+        } else {
+            0.0
+        };
+        self.text_layout
+            .cursor_index_from_point([point.x - origin.x, y].into())
+    }
+
+    /// Get the height of the text, which is used to calculate the preferred
+    /// height of the view in the multi-line mode.
+    fn content_height(&self) -> f32 {
+        self.layout_bounds.size().y.fmax(self.line_height)
     }
 }
 
@@ -515,11 +686,97 @@ struct EntryCoreListener {
 
 type MoveHandler = fn([usize; 2], &pal::TextLayout, &str) -> usize;
 
+/// Find the position in the previous or next line closest to the cursor
+/// position `i` horizontally. Returns the start or end of the text if there's
+/// no such line.
+fn move_vertically(i: usize, layout: &pal::TextLayout, text: &str, down: bool) -> usize {
+    let line = layout.line_from_index(i);
+    let target_line = if down {
+        if line + 1 >= layout.num_lines() {
+            return text.len();
+        }
+        line + 1
+    } else {
+        if line == 0 {
+            return 0;
+        }
+        line - 1
+    };
+
+    let x = layout.cursor_pos(i)[0].x;
+    let vert_bounds = layout.line_vertical_bounds(target_line);
+    let y = (vert_bounds.start + vert_bounds.end) * 0.5;
+    layout.cursor_index_from_point([x, y].into())
+}
+
 impl EntryCoreListener {
     fn new(inner: Rc<Inner>) -> Self {
         Self { inner }
     }
 
+    fn has_activate_handlers(&self) -> bool {
+        self.inner
+            .activate_handlers
+            .borrow()
+            .iter()
+            .next()
+            .is_some()
+    }
+
+    /// Choose a `MoveHandler` for a "move to start" command (`MOVE_UP`,
+    /// `MOVE_START_OF_LINE`, etc.) or its selecting variant.
+    fn start_move_handler(&self, action: ActionId) -> MoveHandler {
+        if !self.inner.multiline.get() {
+            return |_, _, _| 0;
+        }
+
+        match action {
+            actions::MOVE_UP | actions::MOVE_UP_SELECTING => {
+                |sel, layout, text| move_vertically(sel[0], layout, text, false)
+            }
+            actions::MOVE_START_OF_LINE | actions::MOVE_START_OF_LINE_SELECTING => {
+                |sel, layout, _| {
+                    layout
+                        .line_index_range(layout.line_from_index(sel[0]))
+                        .start
+                }
+            }
+            actions::MOVE_START_OF_PARAGRAPH | actions::MOVE_START_OF_PARAGRAPH_SELECTING => {
+                |sel, _, text| text[..sel[0]].rfind('\n').map(|i| i + 1).unwrap_or(0)
+            }
+            _ => |_, _, _| 0,
+        }
+    }
+
+    /// Choose a `MoveHandler` for a "move to end" command (`MOVE_DOWN`,
+    /// `MOVE_END_OF_LINE`, etc.) or its selecting variant.
+    fn end_move_handler(&self, action: ActionId) -> MoveHandler {
+        if !self.inner.multiline.get() {
+            return |_, _, text| text.len();
+        }
+
+        match action {
+            actions::MOVE_DOWN | actions::MOVE_DOWN_SELECTING => {
+                |sel, layout, text| move_vertically(sel[1], layout, text, true)
+            }
+            actions::MOVE_END_OF_LINE | actions::MOVE_END_OF_LINE_SELECTING => {
+                |sel, layout, text| {
+                    let range = layout.line_index_range(layout.line_from_index(sel[1]));
+                    // Exclude the trailing line break
+                    range.start + text[range].trim_end_matches('\n').len()
+                }
+            }
+            actions::MOVE_END_OF_PARAGRAPH | actions::MOVE_END_OF_PARAGRAPH_SELECTING => {
+                |sel, _, text| {
+                    (text[sel[1]..].find('\n'))
+                        .map(|i| sel[1] + i)
+                        .unwrap_or_else(|| text.len())
+                }
+            }
+            _ => |_, _, text| text.len(),
+        }
+    }
+
     fn handle_delete(
         &self,
         view: HViewRef<'_>,
@@ -570,6 +827,24 @@ impl EntryCoreListener {
         });
     }
 
+    /// Copy the selected text to the clipboard. Does nothing if the selection
+    /// is empty.
+    fn handle_copy(&self, wm: pal::Wm) {
+        let state = self.inner.state.borrow();
+        let [mut start, mut end] = state.sel_range;
+        if start > end {
+            std::mem::swap(&mut start, &mut end);
+        }
+
+        if start == end {
+            log::trace!("... there's no selection text, ignoring the request");
+            return;
+        }
+
+        log::trace!("... copying the selection at {:?}", start..end);
+        wm.clipboard_write_text(&state.text[start..end]);
+    }
+
     fn handle_move(&self, view: HViewRef<'_>, selecting: bool, get_new_pos: MoveHandler) {
         update_state(view, RcBorrow::from(&self.inner), &mut |state| {
             log::trace!("... original sel_range = {:?}", state.sel_range);
@@ -710,7 +985,7 @@ impl ViewListener for EntryCoreListener {
         state.reset_timer(hview, RcBorrow::from(&self.inner), Some(false));
     }
 
-    fn validate_action(&self, _: pal::Wm, _: HViewRef<'_>, action: ActionId) -> ActionStatus {
+    fn validate_action(&self, wm: pal::Wm, _: HViewRef<'_>, action: ActionId) -> ActionStatus {
         let mut status = ActionStatus::empty();
         match action {
             actions::SELECT_ALL
@@ -771,10 +1046,20 @@ impl ViewListener for EntryCoreListener {
                 }
                 status |= ActionStatus::VALID;
             }
-            actions::PASTE => {
-                // TODO: Check if the clipboard contains a text
+            actions::PASTE | actions::PASTE_AS_PLAIN_TEXT => {
+                if (wm.clipboard_formats()).contains(pal::ClipboardFormats::PLAIN_TEXT) {
+                    status |= ActionStatus::ENABLED;
+                }
                 status |= ActionStatus::VALID;
             }
+            actions::INSERT_LINE_BREAK if self.inner.multiline.get() => {
+                status |= ActionStatus::VALID | ActionStatus::ENABLED;
+            }
+            actions::INSERT_PARAGRAPH_BREAK
+                if self.inner.multiline.get() || self.has_activate_handlers() =>
+            {
+                status |= ActionStatus::VALID | ActionStatus::ENABLED;
+            }
             actions::UNDO => {
                 if self.inner.state.borrow().history.can_undo() {
                     status |= ActionStatus::ENABLED;
@@ -792,7 +1077,7 @@ impl ViewListener for EntryCoreListener {
         status
     }
 
-    fn perform_action(&self, _: pal::Wm, view: HViewRef<'_>, action: ActionId) {
+    fn perform_action(&self, wm: pal::Wm, view: HViewRef<'_>, action: ActionId) {
         let move_backward: MoveHandler = |sel, layout, _| {
             if sel[0] == sel[1] {
                 layout.next_char(sel[0], false)
@@ -810,16 +1095,13 @@ impl ViewListener for EntryCoreListener {
         let move_forward_word: MoveHandler = |sel, layout, _| layout.next_word(sel[1], true);
         let move_backward_word: MoveHandler = |sel, layout, _| layout.next_word(sel[0], false);
 
-        let move_start: MoveHandler = |_, _, _| 0;
-        let move_end: MoveHandler = |_, _, text| text.len();
-
         // TODO: Use the primary writing direction
         let move_left = move_backward;
         let move_right = move_forward;
         let move_left_word = move_backward_word;
         let move_right_word = move_forward_word;
-        let move_left_end = move_start;
-        let move_right_end = move_end;
+        let move_left_end = self.start_move_handler(actions::MOVE_START_OF_LINE);
+        let move_right_end = self.end_move_handler(actions::MOVE_END_OF_LINE);
 
         match action {
             actions::SELECT_ALL | actions::SELECT_LINE | actions::SELECT_PARAGRAPH => {
@@ -852,13 +1134,21 @@ impl ViewListener for EntryCoreListener {
                 });
             }
             actions::COPY => {
-                log::warn!("TODO: Copy");
+                log::trace!("Handling COPY");
+                self.handle_copy(wm);
             }
             actions::CUT => {
-                log::warn!("TODO: Cut");
-            }
-            actions::PASTE => {
-                log::warn!("TODO: Paste");
+                log::trace!("Handling CUT");
+                self.handle_copy(wm);
+                self.handle_delete(view, |i, _, _| i);
+            }
+            actions::PASTE | actions::PASTE_AS_PLAIN_TEXT => {
+                log::trace!("Handling a 'paste' command (PASTE, etc.)");
+                if let Some(text) = wm.clipboard_read_text() {
+                    insert_text(view, RcBorrow::from(&self.inner), &text);
+                } else {
+                    log::debug!("... the clipboard doesn't contain a text");
+                }
             }
             actions::DELETE_BACKWARD => {
                 log::trace!("Handling DELETE_BACKWARD");
@@ -956,7 +1246,7 @@ impl ViewListener for EntryCoreListener {
                     "Handling a 'move to start' command \
                     (MOVE_START_OF_LINE, etc.)"
                 );
-                self.handle_move(view, false, move_start);
+                self.handle_move(view, false, self.start_move_handler(action));
             }
             actions::MOVE_UP_SELECTING
             | actions::MOVE_UP_PAGE_SELECTING
@@ -967,7 +1257,7 @@ impl ViewListener for EntryCoreListener {
                     "Handling a 'move to start and modify selection' \
                     command (MOVE_START_OF_LINE_SELECTING, etc.)"
                 );
-                self.handle_move(view, true, move_start);
+                self.handle_move(view, true, self.start_move_handler(action));
             }
 
             actions::MOVE_DOWN
@@ -979,7 +1269,7 @@ impl ViewListener for EntryCoreListener {
                     "Handling a 'move to end' command \
                     (MOVE_END_OF_LINE, etc.)"
                 );
-                self.handle_move(view, false, move_end);
+                self.handle_move(view, false, self.end_move_handler(action));
             }
             actions::MOVE_DOWN_SELECTING
             | actions::MOVE_DOWN_PAGE_SELECTING
@@ -990,7 +1280,7 @@ impl ViewListener for EntryCoreListener {
                     "Handling a 'move to end and modify selection' \
                     command (MOVE_END_OF_LINE_SELECTING, etc.)"
                 );
-                self.handle_move(view, true, move_end);
+                self.handle_move(view, true, self.end_move_handler(action));
             }
 
             actions::MOVE_LEFT_END_OF_LINE => {
@@ -1011,6 +1301,19 @@ impl ViewListener for EntryCoreListener {
                 self.handle_move(view, true, move_right_end);
             }
 
+            actions::INSERT_LINE_BREAK => {
+                log::trace!("Handling INSERT_LINE_BREAK");
+                insert_text(view, RcBorrow::from(&self.inner), "\n");
+            }
+            actions::INSERT_PARAGRAPH_BREAK => {
+                log::trace!("Handling INSERT_PARAGRAPH_BREAK");
+                if self.has_activate_handlers() {
+                    pend_raise_activate(RcBorrow::from(&self.inner));
+                } else {
+                    insert_text(view, RcBorrow::from(&self.inner), "\n");
+                }
+            }
+
             actions::UNDO => {
                 log::trace!("Handling UNDO");
                 self.handle_undo(view);
@@ -1047,6 +1350,19 @@ impl ViewListener for EntryCoreListener {
         let mut state = self.inner.state.borrow_mut();
         state.canvas.position(wm, view);
 
+        if self.inner.multiline.get() {
+            // Re-wrap the text if the width has changed
+            let [_, padding_right, _, padding_left] =
+                self.inner.style_elem.computed_values().padding();
+            let wrap_width = (view.frame().size().x - (padding_right + padding_left)).fmax(0.0);
+            if state.wrap_width != Some(wrap_width) {
+                state.wrap_width = Some(wrap_width);
+                state.invalidate_text_layout();
+                state.canvas.pend_draw(view);
+                pend_update_size_traits(RcBorrow::from(&self.inner));
+            }
+        }
+
         if state.scroll_cursor_into_view(view, &self.inner.style_elem) {
             state.canvas.pend_draw(view);
         }
@@ -1096,23 +1412,17 @@ impl ViewListener for EntryCoreListener {
                     if sel_range[1] < sel_range[0] {
                         sel_range.reverse();
                     }
-                    // TODO: Make sure the text is really single-lined. Otherwise,
-                    //       we might break the contract of `run_metrics_of_range`
-                    //       when the range is in a different line
-                    let line = 0;
-                    let vert_bounds = text_layout.line_vertical_bounds(line);
-                    let runs = text_layout.run_metrics_of_range(sel_range[0]..sel_range[1]);
                     log::trace!("sel_range = {:?}", sel_range[0]..sel_range[1]);
-                    log::trace!("runs({:?}) = {:?}", sel_range[0]..sel_range[1], runs);
 
                     // Fill the selection
                     c.set_fill_rgb(sel_color);
-                    for run in runs.iter() {
+                    for_each_line_run(text_layout, sel_range[0]..sel_range[1], |line, run| {
+                        let vert_bounds = text_layout.line_vertical_bounds(line);
                         c.fill_rect(box2! {
                             min: [run.bounds.start, vert_bounds.start],
                             max: [run.bounds.end, vert_bounds.end],
                         });
-                    }
+                    });
                 }
 
                 c.draw_text(&text_layout, Point2::new(0.0, 0.0), color);
@@ -1121,20 +1431,20 @@ impl ViewListener for EntryCoreListener {
                     // Draw an underline below the preedit text
                     // TODO: The backend shouldn't give a zero-length composition range
                     if comp_range[1] > comp_range[0] {
-                        // TODO: See the above TODO regarding `line`
-                        let line = 0;
-                        let y = text_layout.line_baseline(line);
-                        let runs = text_layout.run_metrics_of_range(comp_range[0]..comp_range[1]);
                         log::trace!("comp_range = {:?}", comp_range[0]..comp_range[1]);
-                        log::trace!("runs({:?}) = {:?}", comp_range[0]..comp_range[1], runs);
 
                         c.set_fill_rgb([color.r, color.g, color.b, color.a * 0.6].into());
-                        for run in runs.iter() {
-                            c.fill_rect(box2! {
-                                min: [run.bounds.start, y + 1.0],
-                                max: [run.bounds.end, y + 2.0],
-                            });
-                        }
+                        for_each_line_run(
+                            text_layout,
+                            comp_range[0]..comp_range[1],
+                            |line, run| {
+                                let y = text_layout.line_baseline(line);
+                                c.fill_rect(box2! {
+                                    min: [run.bounds.start, y + 1.0],
+                                    max: [run.bounds.end, y + 2.0],
+                                });
+                            },
+                        );
                     }
                 }
 
@@ -1180,9 +1490,13 @@ impl ViewListener for EntryCoreListener {
 
             // Hide the caret if it's out of view or `caret_blink == false`
             for i in 0..2 {
-                if !state.caret_blink
-                    || !(0.0..global_frame.size().x).contains(&(pos[i].x + text_origin.x))
-                {
+                let in_view = if text_layout_info.multiline {
+                    let mid_y = (pos[i].top + pos[i].bottom) * 0.5 + text_origin.y;
+                    (0.0..global_frame.size().y).contains(&mid_y)
+                } else {
+                    (0.0..global_frame.size().x).contains(&(pos[i].x + text_origin.x))
+                };
+                if !state.caret_blink || !in_view {
                     layer_attrs[i].opacity = Some(0.0);
                 }
             }
@@ -1279,6 +1593,7 @@ impl Drop for Edit<'_> {
             // `text` might have changed, so raise `changed`
             // (False positives are positive because of `set_composition_range`)
             pend_raise_change(self.inner);
+            pend_update_size_traits(self.inner);
         }
 
         if self
@@ -1436,8 +1751,9 @@ impl pal::iface::TextInputCtxEdit<pal::Wm> for Edit<'_> {
         // backend may call `slice_bounds` repeatedly until all bounding boxes
         // for a given string range is known)
         let run_i: usize = run_i.unwrap_or_else(|| {
-            // Find the line contianing `range.start`.
-            // (Note: `EntryCore` is supposed to be a single-line input widget)
+            // Find the line contianing `range.start`. The range may span
+            // multiple lines in the multi-line mode, in which case the
+            // remaining part is handled by subsequent calls.
             let line = text_layout.line_from_index(range.start);
             let line_end = text_layout.line_index_range(line).end;
 
@@ -1472,6 +1788,51 @@ bitflags::bitflags! {
     }
 }
 
+/// Replace the selection with the given text as a single undoable
+/// operation. In the single-line mode, line breaks are replaced with
+/// spaces. In the multi-line mode, they are normalized to `\n`.
+fn insert_text(view: HViewRef<'_>, inner: RcBorrow<'_, Inner>, text: &str) {
+    let text: String = if inner.multiline.get() {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text.split(|c| c == '\r' || c == '\n')
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    update_state(view, inner, &mut |state| {
+        let [mut start, mut end] = state.sel_range;
+        if start > end {
+            std::mem::swap(&mut start, &mut end);
+        }
+
+        if start == end && text.is_empty() {
+            // There's nothing to insert
+            return UpdateStateFlags::empty();
+        }
+
+        log::trace!("... replacing {:?} with {:?}", start..end, text);
+
+        // Record the change to the undo history. Pasting is never
+        // coalesced with typing.
+        state.history.mark_logical_op_break();
+        {
+            let mut tx = state.history.start_transaction();
+            tx.replace_range(&mut state.history, &state.text, start..end, text.clone());
+            tx.finish(&mut state.history, &state.text);
+        }
+        state.history.mark_logical_op_break();
+
+        // Update `text`
+        state.text.replace_range(start..end, &text);
+        let i = start + text.len();
+        state.sel_range = [i, i];
+
+        UpdateStateFlags::ANY
+    });
+}
+
 /// Update the text and/or selection using a given closure. This method mustn't
 /// be used in an implementation of `TextInputCtxEdit` because it calls
 /// `text_input_ctx_on_selection_change` and/or `text_input_ctx_reset`.
@@ -1532,6 +1893,7 @@ fn update_state(
     // Raise `changed`
     if flags.contains(UpdateStateFlags::ANY) {
         pend_raise_change(inner);
+        pend_update_size_traits(inner);
     }
 
     // Invalidate the remembered caret position
@@ -1554,6 +1916,33 @@ fn update_state(
     }
 }
 
+/// Call `f` for each run in `range`, which may span over multiple lines,
+/// along with the index of the line containing the run.
+fn for_each_line_run(
+    text_layout: &pal::TextLayout,
+    range: Range<usize>,
+    mut f: impl FnMut(usize, &pal::RunMetrics),
+) {
+    let first_line = text_layout.line_from_index(range.start);
+    let last_line = text_layout.line_from_index(range.end);
+
+    for line in first_line..=last_line {
+        // `run_metrics_of_range` only accepts a range in a single line
+        let line_range = text_layout.line_index_range(line);
+        let start = range.start.max(line_range.start);
+        let end = range.end.min(line_range.end);
+        if start >= end {
+            continue;
+        }
+
+        let runs = text_layout.run_metrics_of_range(start..end);
+        log::trace!("runs({:?}) = {:?}", start..end, runs);
+        for run in runs.iter() {
+            f(line, run);
+        }
+    }
+}
+
 /// Pend calls to the `change` event handlers.
 fn pend_raise_change(inner: RcBorrow<'_, Inner>) {
     if inner.pending_change_handler.get() {
@@ -1574,6 +1963,87 @@ fn pend_raise_change(inner: RcBorrow<'_, Inner>) {
     });
 }
 
+/// Pend calls to the `activated` event handlers.
+fn pend_raise_activate(inner: RcBorrow<'_, Inner>) {
+    let inner_weak = RcBorrow::to_weak(inner);
+
+    inner.wm.invoke_on_update(move |wm| {
+        if let Some(inner) = inner_weak.upgrade() {
+            let handlers = inner.activate_handlers.borrow();
+            for handler in handlers.iter() {
+                handler(wm);
+            }
+        }
+    });
+}
+
+/// Pend a call to `update_size_traits`. Does nothing in the single-line mode.
+fn pend_update_size_traits(inner: RcBorrow<'_, Inner>) {
+    if !inner.multiline.get() || inner.pending_size_traits.get() {
+        return;
+    }
+    inner.pending_size_traits.set(true);
+
+    let inner_weak = RcBorrow::to_weak(inner);
+
+    inner.wm.invoke_on_update(move |_| {
+        if let Some(inner) = inner_weak.upgrade() {
+            inner.pending_size_traits.set(false);
+
+            if let Some(view) = inner.view.upgrade() {
+                update_size_traits(RcBorrow::from(&inner), view.as_ref());
+            }
+        }
+    });
+}
+
+/// Update the view's preferred height to fit the text. Does nothing in the
+/// single-line mode.
+///
+/// This must not be called from `ViewListener::update` or `Layout`'s methods
+/// because it calls `HView::set_layout`.
+fn update_size_traits(inner: RcBorrow<'_, Inner>, view: HViewRef<'_>) {
+    if !inner.multiline.get() {
+        return;
+    }
+
+    let mut state = inner.state.borrow_mut();
+    let text_layout_info = state.ensure_text_layout(&inner.style_elem);
+    let line_height = text_layout_info.line_height;
+    let content_height = text_layout_info.content_height();
+
+    if content_height == state.content_height {
+        return;
+    }
+    state.content_height = content_height;
+    drop(state);
+
+    let [padding_top, _, padding_bottom, _] = inner.style_elem.computed_values().padding();
+    let padding = padding_top + padding_bottom;
+
+    view.set_layout(EmptyLayout::new(SizeTraits {
+        min: [0.0, line_height + padding].into(),
+        preferred: [0.0, content_height + padding].into(),
+        ..SizeTraits::default()
+    }));
+
+    raise_preferred_height_changed(inner);
+}
+
+/// Call the `preferred_height_changed` event handlers via `Wm::invoke`.
+fn raise_preferred_height_changed(inner: RcBorrow<'_, Inner>) {
+    let inner_weak = RcBorrow::to_weak(inner);
+
+    inner.wm.invoke(move |wm| {
+        if let Some(inner) = inner_weak.upgrade() {
+            let handlers = inner.preferred_height_handlers.borrow();
+            for handler in handlers.iter() {
+                handler(wm);
+            }
+        }
+    });
+}
+
 struct EntryCoreDragListener {
     view: HView,
     inner: Rc<Inner>,
@@ -1611,7 +2081,7 @@ impl MouseDragListener for EntryCoreDragListener {
                     hview,
                     state.scroll,
                     &self.inner.style_elem,
-                    loc.x,
+                    loc,
                 );
                 state.sel_range = [i, i];
             }
@@ -1625,7 +2095,7 @@ impl MouseDragListener for EntryCoreDragListener {
                     hview,
                     state.scroll,
                     &self.inner.style_elem,
-                    loc.x,
+                    loc,
                 );
                 state.sel_range[1] = i;
             }
@@ -7,12 +7,15 @@ use crate::{
         views::Spacer,
         AlignFlags,
     },
-    uicore::{HView, HWnd, SizeTraits, ViewFlags},
+    uicore::{actions, ActionStatus, HView, HWnd, SizeTraits, ViewFlags},
 };
 use cggeom::prelude::*;
 use enclose::enc;
 use log::info;
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 use try_match::try_match;
 
 use super::Entry;
@@ -166,3 +169,216 @@ fn set_text(twm: &dyn TestingWm) {
     // .. and a `changed` event should be generated
     assert_eq!(changed_events.borrow()[..], ["hello", "world"][..]);
 }
+
+#[use_testing_wm(testing = "crate::testing")]
+#[test]
+fn insert_text(twm: &dyn TestingWm) {
+    let TestWithOneEntry {
+        entry,
+        hwnd: _hwnd,
+        pal_hwnd,
+        changed_events,
+        ..
+    } = init_test_with_one_entry(twm);
+
+    // Focus the text field by clicking it
+    let bounds = entry.view_ref().global_frame();
+    simulate_click(twm, &pal_hwnd, bounds.min.average2(&bounds.min));
+
+    // Type something and select "world"
+    {
+        let mut edit = twm.raise_edit(&twm.expect_unique_active_text_input_ctx().unwrap(), true);
+        edit.replace(0..0, "hello world");
+        edit.set_selected_range(6..11);
+    }
+    twm.step_unsend();
+
+    // Replace the selection
+    entry.insert_text("alice");
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello alice");
+    assert_eq!(changed_events.borrow().last().unwrap(), "hello alice");
+    assert_eq!(
+        changed_events.borrow()[..],
+        ["hello world", "hello alice"][..]
+    );
+
+    // The insertion can be undone
+    twm.raise_perform_action(&pal_hwnd, actions::UNDO);
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello world");
+}
+
+#[use_testing_wm(testing = "crate::testing")]
+#[test]
+fn clipboard(twm: &dyn TestingWm) {
+    let TestWithOneEntry {
+        entry,
+        hwnd: _hwnd,
+        pal_hwnd,
+        ..
+    } = init_test_with_one_entry(twm);
+
+    // Focus the text field by clicking it
+    let bounds = entry.view_ref().global_frame();
+    simulate_click(twm, &pal_hwnd, bounds.min.average2(&bounds.min));
+
+    let is_enabled = |action| {
+        twm.raise_validate_action(&pal_hwnd, action)
+            .contains(ActionStatus::VALID | ActionStatus::ENABLED)
+    };
+
+    // The clipboard is empty, so we can't paste anything
+    assert!(!is_enabled(actions::PASTE));
+
+    // Type something and select "hello"
+    {
+        let mut edit = twm.raise_edit(&twm.expect_unique_active_text_input_ctx().unwrap(), true);
+        edit.replace(0..0, "hello world");
+        edit.set_selected_range(0..5);
+    }
+    twm.step_unsend();
+
+    // Copy "hello"
+    assert!(is_enabled(actions::COPY));
+    twm.raise_perform_action(&pal_hwnd, actions::COPY);
+    twm.step_unsend();
+    assert_eq!(twm.clipboard_text().as_deref(), Some("hello"));
+    assert_eq!(entry.text(), "hello world");
+    assert!(is_enabled(actions::PASTE));
+
+    // Cut " world"
+    {
+        let mut edit = twm.raise_edit(&twm.expect_unique_active_text_input_ctx().unwrap(), true);
+        edit.set_selected_range(5..11);
+    }
+    twm.step_unsend();
+
+    assert!(is_enabled(actions::CUT));
+    twm.raise_perform_action(&pal_hwnd, actions::CUT);
+    twm.step_unsend();
+    assert_eq!(twm.clipboard_text().as_deref(), Some(" world"));
+    assert_eq!(entry.text(), "hello");
+
+    // Paste " world" twice
+    twm.raise_perform_action(&pal_hwnd, actions::PASTE);
+    twm.raise_perform_action(&pal_hwnd, actions::PASTE);
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello world world");
+
+    // Each paste operation is undone separately
+    twm.raise_perform_action(&pal_hwnd, actions::UNDO);
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello world");
+
+    // Line breaks are replaced with spaces
+    twm.set_clipboard_text(Some("\nfoo\r\nbar\n"));
+    twm.raise_perform_action(&pal_hwnd, actions::PASTE_AS_PLAIN_TEXT);
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello worldfoo bar");
+}
+
+#[use_testing_wm(testing = "crate::testing")]
+#[test]
+fn multiline(twm: &dyn TestingWm) {
+    let TestWithOneEntry {
+        entry,
+        hwnd: _hwnd,
+        pal_hwnd,
+        ..
+    } = init_test_with_one_entry(twm);
+
+    // Focus the text field by clicking it
+    let bounds = entry.view_ref().global_frame();
+    simulate_click(twm, &pal_hwnd, bounds.min.average2(&bounds.min));
+
+    let is_enabled = |action| {
+        twm.raise_validate_action(&pal_hwnd, action)
+            .contains(ActionStatus::VALID | ActionStatus::ENABLED)
+    };
+
+    // A single-line entry doesn't accept line breaks
+    assert!(!is_enabled(actions::INSERT_LINE_BREAK));
+    assert!(!is_enabled(actions::INSERT_PARAGRAPH_BREAK));
+
+    entry.core().set_multiline(true);
+    twm.step_unsend();
+
+    assert!(is_enabled(actions::INSERT_LINE_BREAK));
+    assert!(is_enabled(actions::INSERT_PARAGRAPH_BREAK));
+
+    // Type something and insert line breaks
+    {
+        let mut edit = twm.raise_edit(&twm.expect_unique_active_text_input_ctx().unwrap(), true);
+        edit.replace(0..0, "hello");
+        edit.set_selected_range(5..5);
+    }
+    twm.raise_perform_action(&pal_hwnd, actions::INSERT_LINE_BREAK);
+    twm.raise_perform_action(&pal_hwnd, actions::INSERT_PARAGRAPH_BREAK);
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello\n\n");
+
+    // Line breaks in a pasted text are preserved and normalized
+    twm.set_clipboard_text(Some("foo\r\nbar\rbaz"));
+    twm.raise_perform_action(&pal_hwnd, actions::PASTE_AS_PLAIN_TEXT);
+    twm.step_unsend();
+    assert_eq!(entry.text(), "hello\n\nfoo\nbar\nbaz");
+
+    // If there's an `activated` handler, `INSERT_PARAGRAPH_BREAK` raises
+    // `activated` instead of inserting a line break
+    let num_activated = Rc::new(Cell::new(0));
+    entry.subscribe_activated(Box::new(enc!((num_activated) move |_| {
+        num_activated.set(num_activated.get() + 1);
+    })));
+
+    twm.raise_perform_action(&pal_hwnd, actions::INSERT_PARAGRAPH_BREAK);
+    twm.step_unsend();
+    assert_eq!(num_activated.get(), 1);
+    assert_eq!(entry.text(), "hello\n\nfoo\nbar\nbaz");
+
+    twm.raise_perform_action(&pal_hwnd, actions::INSERT_LINE_BREAK);
+    twm.step_unsend();
+    assert_eq!(num_activated.get(), 1);
+    assert_eq!(entry.text(), "hello\n\nfoo\nbar\nbaz\n");
+}
+
+#[use_testing_wm(testing = "crate::testing")]
+#[test]
+fn multiline_preferred_height(twm: &dyn TestingWm) {
+    let TestWithOneEntry { entry, .. } = init_test_with_one_entry(twm);
+
+    let num_changed = Rc::new(Cell::new(0));
+    entry
+        .core()
+        .subscribe_preferred_height_changed(Box::new(enc!((num_changed) move |_| {
+            num_changed.set(num_changed.get() + 1);
+        })));
+
+    // The single-line mode doesn't have a preferred height
+    assert_eq!(entry.core().preferred_height(), 0.0);
+
+    entry.core().set_multiline(true);
+    twm.step_unsend();
+    assert_ne!(num_changed.get(), 0);
+
+    let one_line_height = entry.core().preferred_height();
+    info!("one_line_height = {:?}", one_line_height);
+    assert!(one_line_height > 0.0);
+
+    // The height grows with the number of lines
+    num_changed.set(0);
+    entry.set_text("a\nb\nc");
+    twm.step_unsend();
+    assert_ne!(num_changed.get(), 0);
+
+    let three_lines_height = entry.core().preferred_height();
+    info!("three_lines_height = {:?}", three_lines_height);
+    assert!(three_lines_height > one_line_height);
+
+    // ...and shrinks when lines are removed
+    num_changed.set(0);
+    entry.set_text("a");
+    twm.step_unsend();
+    assert_ne!(num_changed.get(), 0);
+    assert_eq!(entry.core().preferred_height(), one_line_height);
+}
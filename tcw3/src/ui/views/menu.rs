@@ -0,0 +1,902 @@
+//! Context menus displayed in popup windows.
+use cggeom::prelude::*;
+use cgmath::Point2;
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    pal,
+    prelude::*,
+    ui::{
+        layouts::{FillLayout, TableLayout},
+        theming::{elem_id, roles, ClassSet, Manager, StyledBox},
+        views::{Label, Spacer},
+        AlignFlags,
+    },
+    uicore::{
+        HView, HViewRef, HWnd, HWndRef, KeyEvent, MouseDragListener, PopupAnchor, PopupDirection,
+        ViewFlags, ViewListener, WndListener, WndStyleFlags,
+    },
+};
+
+/// The minimum distance between the label and the accelerator hint of a
+/// menu item.
+const ACCEL_HINT_SPACING: f32 = 20.0;
+
+/// The text displayed in place of an accelerator hint for an item having a
+/// submenu.
+const SUBMENU_INDICATOR: &str = "▸";
+
+/// A context menu.
+///
+/// A menu is displayed in a popup window (see [`WndStyleFlags::POPUP`]) by
+/// calling [`Menu::popup`]. It's closed when an item is activated, when the
+/// user clicks outside the menu, or when the popup window loses focus.
+///
+/// Supported keys: Up and Down move the highlight, Right opens the
+/// highlighted item's submenu, Left closes the current submenu, Return
+/// activates the highlighted item, and Escape closes the current menu.
+///
+/// `Menu` is a handle type. Cloning it only clones the handle.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    inner: Rc<Inner>,
+}
+
+/// An item in a [`Menu`].
+pub struct MenuItem {
+    label: String,
+    accel_hint: Option<String>,
+    enabled: bool,
+    separator: bool,
+    submenu: Option<Menu>,
+    activate_handler: Option<Rc<dyn Fn(pal::Wm)>>,
+}
+
+impl fmt::Debug for MenuItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MenuItem")
+            .field("label", &self.label)
+            .field("accel_hint", &self.accel_hint)
+            .field("enabled", &self.enabled)
+            .field("separator", &self.separator)
+            .field("submenu", &self.submenu)
+            .field(
+                "activate_handler",
+                &self.activate_handler.as_ref().map(|_| ()),
+            )
+            .finish()
+    }
+}
+
+impl MenuItem {
+    /// Construct a menu item with the specified label.
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            accel_hint: None,
+            enabled: true,
+            separator: false,
+            submenu: None,
+            activate_handler: None,
+        }
+    }
+
+    /// Construct a separator.
+    pub fn separator() -> Self {
+        Self {
+            enabled: false,
+            separator: true,
+            ..Self::new("")
+        }
+    }
+
+    /// Set the text describing the item's keyboard shortcut, e.g., `Ctrl+C`.
+    ///
+    /// The text is only displayed; the shortcut must be handled elsewhere.
+    pub fn with_accel_hint(self, accel_hint: impl Into<String>) -> Self {
+        Self {
+            accel_hint: Some(accel_hint.into()),
+            ..self
+        }
+    }
+
+    /// Set whether the item can be highlighted and activated. Defaults to
+    /// `true`.
+    pub fn with_enabled(self, enabled: bool) -> Self {
+        Self { enabled, ..self }
+    }
+
+    /// Set the submenu opened by the item.
+    pub fn with_submenu(self, submenu: Menu) -> Self {
+        Self {
+            submenu: Some(submenu),
+            ..self
+        }
+    }
+
+    /// Set the function called when the item is activated.
+    ///
+    /// The function is called via `Wm::invoke` after the menu is closed.
+    pub fn with_activate_handler(self, handler: impl Fn(pal::Wm) + 'static) -> Self {
+        Self {
+            activate_handler: Some(Rc::new(handler)),
+            ..self
+        }
+    }
+
+    fn is_selectable(&self) -> bool {
+        self.enabled && !self.separator
+    }
+}
+
+struct Inner {
+    style_manager: &'static Manager,
+    items: RefCell<Vec<MenuItem>>,
+    popup: RefCell<Option<Popup>>,
+    /// The menu that opened this menu as a submenu.
+    parent: RefCell<Weak<Inner>>,
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("items", &self.items)
+            .field("popup", &self.popup)
+            .finish()
+    }
+}
+
+/// The state of an open menu.
+#[derive(Debug)]
+struct Popup {
+    hwnd: HWnd,
+    /// The rows, one for each item.
+    rows: Vec<Row>,
+    highlight: Option<usize>,
+    /// The index of the item whose submenu is open.
+    open_submenu: Option<(usize, Menu)>,
+}
+
+#[derive(Debug)]
+struct Row {
+    view: HView,
+    styled_box: StyledBox,
+}
+
+impl Menu {
+    pub fn new(style_manager: &'static Manager) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                style_manager,
+                items: RefCell::new(Vec::new()),
+                popup: RefCell::new(None),
+                parent: RefCell::new(Weak::new()),
+            }),
+        }
+    }
+
+    /// Replace the items of the menu.
+    ///
+    /// The change takes effect the next time the menu is opened.
+    pub fn set_items(&self, items: Vec<MenuItem>) {
+        *self.inner.items.borrow_mut() = items;
+    }
+
+    /// Append an item to the menu.
+    ///
+    /// The change takes effect the next time the menu is opened.
+    pub fn push_item(&self, item: MenuItem) {
+        self.inner.items.borrow_mut().push(item);
+    }
+
+    /// Builder-style version of [`Menu::push_item`].
+    pub fn with_item(self, item: MenuItem) -> Self {
+        self.push_item(item);
+        self
+    }
+
+    /// Open the menu in a new popup window owned by `owner`. `anchor` is
+    /// specified in `owner`'s client coordinates.
+    ///
+    /// If the menu is already open, it's closed first. `owner` must be
+    /// materialized (i.e., [`HWndRef::pal_hwnd`] must return `Some(_)`).
+    pub fn popup(&self, owner: HWndRef<'_>, anchor: PopupAnchor) {
+        self.inner.popup(owner, anchor);
+    }
+
+    /// Close the menu and its submenus. Does nothing if the menu is not open.
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    /// Get a flag indicating whether the menu is open.
+    pub fn is_open(&self) -> bool {
+        self.inner.popup.borrow().is_some()
+    }
+}
+
+impl Inner {
+    fn popup(self: &Rc<Self>, owner: HWndRef<'_>, anchor: PopupAnchor) {
+        self.close();
+
+        let rows: Vec<Row> = self
+            .items
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, item)| Row::new(self.style_manager, item, Rc::downgrade(self), i))
+            .collect();
+
+        let list = HView::new(ViewFlags::default());
+        list.set_layout(TableLayout::stack_vert(
+            rows.iter()
+                .map(|row| (row.view.clone(), AlignFlags::JUSTIFY)),
+        ));
+
+        let styled_box = StyledBox::new(self.style_manager, ViewFlags::default());
+        styled_box.set_class_set(elem_id::MENU);
+        styled_box.set_subview(roles::GENERIC, Some(list));
+
+        let hwnd = HWnd::new(owner.wm());
+        hwnd.set_style_flags(WndStyleFlags::POPUP);
+        hwnd.set_owner(Some(owner.cloned()));
+        hwnd.set_popup_anchor(Some(anchor));
+        hwnd.content_view()
+            .set_layout(FillLayout::new(styled_box.view()));
+        hwnd.set_listener(MenuWndListener {
+            inner: Rc::downgrade(self),
+        });
+        hwnd.set_visibility(true);
+
+        *self.popup.borrow_mut() = Some(Popup {
+            hwnd,
+            rows,
+            highlight: None,
+            open_submenu: None,
+        });
+    }
+
+    fn close(&self) {
+        let popup = self.popup.borrow_mut().take();
+
+        if let Some(popup) = popup {
+            if let Some((_, submenu)) = popup.open_submenu {
+                submenu.inner.close();
+            }
+            popup.hwnd.close();
+        }
+
+        // Detach from the parent menu
+        let parent = std::mem::replace(&mut *self.parent.borrow_mut(), Weak::new());
+        if let Some(parent) = parent.upgrade() {
+            if let Some(popup) = &mut *parent.popup.borrow_mut() {
+                let is_self = match &popup.open_submenu {
+                    Some((_, submenu)) => std::ptr::eq(&*submenu.inner, self),
+                    None => false,
+                };
+                if is_self {
+                    popup.open_submenu = None;
+                }
+            }
+        }
+    }
+
+    fn root(self: &Rc<Self>) -> Rc<Self> {
+        let mut menu = Rc::clone(self);
+        loop {
+            let parent = menu.parent.borrow().upgrade();
+            match parent {
+                Some(parent) => menu = parent,
+                None => return menu,
+            }
+        }
+    }
+
+    fn highlight(&self) -> Option<usize> {
+        self.popup.borrow().as_ref().and_then(|p| p.highlight)
+    }
+
+    fn open_submenu(&self) -> Option<Menu> {
+        (self.popup.borrow().as_ref())
+            .and_then(|p| p.open_submenu.as_ref())
+            .map(|(_, submenu)| submenu.clone())
+    }
+
+    /// Get the menu that should receive keyboard input. This is the deepest
+    /// open submenu having a highlighted item, or `self`.
+    fn keyboard_target(self: &Rc<Self>) -> Rc<Self> {
+        let mut menu = Rc::clone(self);
+        while let Some(submenu) = menu.open_submenu() {
+            if submenu.inner.highlight().is_none() {
+                break;
+            }
+            menu = submenu.inner;
+        }
+        menu
+    }
+
+    fn set_highlight(&self, index: Option<usize>) {
+        let mut popup = self.popup.borrow_mut();
+        let popup = if let Some(popup) = &mut *popup {
+            popup
+        } else {
+            return;
+        };
+
+        if popup.highlight == index {
+            return;
+        }
+
+        for &i in popup.highlight.iter().chain(index.iter()) {
+            let styled_box = &popup.rows[i].styled_box;
+            let mut class_set = styled_box.class_set();
+            class_set.set(ClassSet::ACTIVE, Some(i) == index);
+            styled_box.set_class_set(class_set);
+        }
+
+        popup.highlight = index;
+    }
+
+    /// Move the highlight to the next (`forward`) or previous selectable
+    /// item, wrapping around at the ends.
+    fn move_highlight(&self, forward: bool) {
+        let index = {
+            let items = self.items.borrow();
+            let len = match &*self.popup.borrow() {
+                Some(popup) => popup.rows.len(),
+                None => return,
+            };
+            if len == 0 {
+                return;
+            }
+
+            let start = match (self.highlight(), forward) {
+                (Some(i), true) => i + 1,
+                (Some(i), false) => i + len - 1,
+                (None, true) => 0,
+                (None, false) => len - 1,
+            };
+
+            (0..len)
+                .map(|k| {
+                    if forward {
+                        (start + k) % len
+                    } else {
+                        (start + len - k) % len
+                    }
+                })
+                .find(|&i| items[i].is_selectable())
+        };
+
+        if index.is_some() {
+            self.close_submenu();
+            self.set_highlight(index);
+        }
+    }
+
+    fn close_submenu(&self) {
+        if let Some(submenu) = self.open_submenu() {
+            submenu.close();
+        }
+    }
+
+    /// Open the submenu of the item at `index`. Returns the submenu if it's
+    /// open when this method returns.
+    fn open_submenu_at(self: &Rc<Self>, index: usize) -> Option<Menu> {
+        let submenu = self.items.borrow()[index].submenu.clone()?;
+
+        let (hwnd, row_frame) = {
+            let popup = self.popup.borrow();
+            let popup = popup.as_ref()?;
+            match &popup.open_submenu {
+                Some((i, _)) if *i == index => return Some(submenu),
+                _ => {}
+            }
+            (popup.hwnd.clone(), popup.rows[index].view.global_frame())
+        };
+
+        self.close_submenu();
+
+        submenu.popup(
+            hwnd.as_ref(),
+            PopupAnchor {
+                rect: row_frame,
+                direction: PopupDirection::Right,
+            },
+        );
+        *submenu.inner.parent.borrow_mut() = Rc::downgrade(self);
+
+        if let Some(popup) = &mut *self.popup.borrow_mut() {
+            popup.open_submenu = Some((index, submenu.clone()));
+        }
+
+        Some(submenu)
+    }
+
+    /// Activate the item at `index`. If the item has a submenu, the submenu
+    /// is opened. Otherwise, the whole menu hierarchy is closed, and then the
+    /// item's handler is called.
+    fn activate(self: &Rc<Self>, wm: pal::Wm, index: usize, by_keyboard: bool) {
+        let (selectable, has_submenu, handler) = {
+            let items = self.items.borrow();
+            let item = &items[index];
+            (
+                item.is_selectable(),
+                item.submenu.is_some(),
+                item.activate_handler.clone(),
+            )
+        };
+
+        if !selectable {
+            return;
+        }
+
+        if has_submenu {
+            self.set_highlight(Some(index));
+            if let Some(submenu) = self.open_submenu_at(index) {
+                if by_keyboard && submenu.inner.highlight().is_none() {
+                    submenu.inner.move_highlight(true);
+                }
+            }
+            return;
+        }
+
+        self.root().close();
+
+        if let Some(handler) = handler {
+            wm.invoke(move |wm| handler(wm));
+        }
+    }
+
+    fn key_down(self: &Rc<Self>, wm: pal::Wm, e: &KeyEvent<'_>) -> bool {
+        let action = if let Some(action) = e.translate_accel(&ACCEL_TABLE) {
+            action
+        } else {
+            return false;
+        };
+
+        // The action may close the window, which must stay open until the
+        // key stroke is complete
+        let this = Rc::clone(self);
+        wm.invoke(move |wm| this.perform_action(wm, action));
+
+        true
+    }
+
+    fn perform_action(self: &Rc<Self>, wm: pal::Wm, action: pal::ActionId) {
+        let menu = self.keyboard_target();
+
+        match action {
+            ACTION_UP => menu.move_highlight(false),
+            ACTION_DOWN => menu.move_highlight(true),
+            ACTION_OPEN => {
+                if let Some(i) = menu.highlight() {
+                    if menu.items.borrow()[i].submenu.is_some() {
+                        menu.activate(wm, i, true);
+                    }
+                }
+            }
+            ACTION_BACK => {
+                if menu.parent.borrow().upgrade().is_some() {
+                    menu.close();
+                }
+            }
+            ACTION_ACTIVATE => {
+                if let Some(i) = menu.highlight() {
+                    menu.activate(wm, i, true);
+                }
+            }
+            ACTION_CANCEL => menu.close(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+const ACTION_UP: pal::ActionId = 0;
+const ACTION_DOWN: pal::ActionId = 1;
+const ACTION_OPEN: pal::ActionId = 2;
+const ACTION_BACK: pal::ActionId = 3;
+const ACTION_ACTIVATE: pal::ActionId = 4;
+const ACTION_CANCEL: pal::ActionId = 5;
+
+static ACCEL_TABLE: pal::AccelTable = pal::accel_table![
+    (ACTION_UP, windows("Up"), macos("Up"), gtk("Up")),
+    (ACTION_DOWN, windows("Down"), macos("Down"), gtk("Down")),
+    (ACTION_OPEN, windows("Right"), macos("Right"), gtk("Right")),
+    (ACTION_BACK, windows("Left"), macos("Left"), gtk("Left")),
+    (
+        ACTION_ACTIVATE,
+        windows("Return"),
+        macos("Return"),
+        gtk("Return")
+    ),
+    (
+        ACTION_CANCEL,
+        windows("Escape"),
+        macos("Escape"),
+        gtk("Escape")
+    ),
+];
+
+impl Row {
+    fn new(
+        style_manager: &'static Manager,
+        item: &MenuItem,
+        menu: Weak<Inner>,
+        index: usize,
+    ) -> Self {
+        let styled_box = StyledBox::new(style_manager, ViewFlags::default());
+
+        if item.separator {
+            styled_box.set_class_set(elem_id::MENU_SEPARATOR);
+            return Self {
+                view: styled_box.view(),
+                styled_box,
+            };
+        }
+
+        let label = Label::new(style_manager);
+        label.set_text(item.label.clone());
+
+        let accel_hint = Label::new(style_manager);
+        accel_hint.set_class_set(ClassSet::LABEL | elem_id::MENU_ACCEL);
+        if item.submenu.is_some() {
+            accel_hint.set_text(SUBMENU_INDICATOR);
+        } else if let Some(text) = &item.accel_hint {
+            accel_hint.set_text(text.clone());
+        }
+
+        let content = HView::new(ViewFlags::default());
+        content.set_layout(TableLayout::stack_horz(vec![
+            (label.view(), AlignFlags::LEFT),
+            (
+                Spacer::new()
+                    .with_min([ACCEL_HINT_SPACING, 0.0])
+                    .into_view(),
+                AlignFlags::JUSTIFY,
+            ),
+            (accel_hint.view(), AlignFlags::RIGHT),
+        ]));
+
+        styled_box.set_class_set(if item.enabled {
+            elem_id::MENU_ITEM
+        } else {
+            elem_id::MENU_ITEM_DISABLED
+        });
+        styled_box.set_subview(roles::GENERIC, Some(content));
+        styled_box.set_subelement(roles::GENERIC, Some(label.style_elem()));
+        styled_box.set_subelement(roles::MENU_ACCEL, Some(accel_hint.style_elem()));
+
+        let view = HView::new(ViewFlags::ACCEPT_MOUSE_DRAG | ViewFlags::ACCEPT_MOUSE_OVER);
+        view.set_layout(FillLayout::new(styled_box.view()));
+        view.set_listener(RowViewListener { menu, index });
+
+        Self { view, styled_box }
+    }
+}
+
+struct RowViewListener {
+    menu: Weak<Inner>,
+    index: usize,
+}
+
+impl ViewListener for RowViewListener {
+    fn mouse_over(&self, _: pal::Wm, _: HViewRef<'_>) {
+        if let Some(menu) = self.menu.upgrade() {
+            if !menu.items.borrow()[self.index].is_selectable() {
+                return;
+            }
+            menu.set_highlight(Some(self.index));
+            if menu.open_submenu_at(self.index).is_none() {
+                menu.close_submenu();
+            }
+        }
+    }
+
+    fn mouse_out(&self, _: pal::Wm, _: HViewRef<'_>) {
+        if let Some(menu) = self.menu.upgrade() {
+            // Keep the highlight while the item's submenu is open
+            let submenu_open = match &*menu.popup.borrow() {
+                Some(Popup {
+                    open_submenu: Some((i, _)),
+                    ..
+                }) => *i == self.index,
+                _ => false,
+            };
+            if !submenu_open && menu.highlight() == Some(self.index) {
+                menu.set_highlight(None);
+            }
+        }
+    }
+
+    fn mouse_drag(
+        &self,
+        _: pal::Wm,
+        _: HViewRef<'_>,
+        _loc: Point2<f32>,
+        _button: u8,
+    ) -> Box<dyn MouseDragListener> {
+        Box::new(RowMouseDragListener {
+            menu: self.menu.clone(),
+            index: self.index,
+        })
+    }
+}
+
+struct RowMouseDragListener {
+    menu: Weak<Inner>,
+    index: usize,
+}
+
+impl MouseDragListener for RowMouseDragListener {
+    fn mouse_up(&self, wm: pal::Wm, view: HViewRef<'_>, loc: Point2<f32>, button: u8) {
+        if button != 0 || !view.global_frame().contains_point(&loc) {
+            return;
+        }
+
+        // Activating an item may close the window, which is not allowed
+        // during a mouse drag gesture
+        let (menu, index) = (self.menu.clone(), self.index);
+        wm.invoke(move |wm| {
+            if let Some(menu) = menu.upgrade() {
+                menu.activate(wm, index, false);
+            }
+        });
+    }
+}
+
+struct MenuWndListener {
+    inner: Weak<Inner>,
+}
+
+impl WndListener for MenuWndListener {
+    fn close(&self, _: pal::Wm, _: HWndRef<'_>) {
+        // Dismissed by the system (e.g., by clicking outside the menu)
+        if let Some(inner) = self.inner.upgrade() {
+            inner.close();
+        }
+    }
+
+    fn key_down(&self, wm: pal::Wm, _: HWndRef<'_>, e: &KeyEvent<'_>) -> bool {
+        if let Some(inner) = self.inner.upgrade() {
+            inner.key_down(wm, e)
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::testing::{prelude::*, use_testing_wm};
+
+    struct Fixture {
+        owner: HWnd,
+        menu: Menu,
+        submenu: Menu,
+        /// The number of times each item was activated.
+        activated: Rc<[Cell<u32>; 3]>,
+    }
+
+    const ANCHOR: PopupAnchor = PopupAnchor {
+        rect: cggeom::Box2 {
+            min: Point2::new(10.0, 10.0),
+            max: Point2::new(30.0, 20.0),
+        },
+        direction: PopupDirection::Down,
+    };
+
+    fn make_menu(twm: &dyn TestingWm) -> Fixture {
+        let wm = twm.wm();
+        let style_manager = Manager::global(wm);
+
+        let owner = HWnd::new(wm);
+        owner.set_visibility(true);
+        twm.step_unsend();
+
+        let activated: Rc<[Cell<u32>; 3]> = Rc::new(Default::default());
+        let item = |label: &str, i: usize| {
+            let activated = Rc::clone(&activated);
+            MenuItem::new(label).with_activate_handler(move |_| {
+                activated[i].set(activated[i].get() + 1);
+            })
+        };
+
+        let submenu = Menu::new(style_manager)
+            .with_item(item("Sub", 2))
+            .with_item(MenuItem::new("Disabled").with_enabled(false));
+
+        let menu = Menu::new(style_manager)
+            .with_item(item("First", 0).with_accel_hint("Ctrl+F"))
+            .with_item(MenuItem::separator())
+            .with_item(MenuItem::new("Disabled").with_enabled(false))
+            .with_item(item("Second", 1))
+            .with_item(MenuItem::new("More").with_submenu(submenu.clone()));
+
+        menu.popup(owner.as_ref(), ANCHOR);
+        twm.step_unsend();
+
+        Fixture {
+            owner,
+            menu,
+            submenu,
+            activated,
+        }
+    }
+
+    fn popup_hwnd(menu: &Menu) -> pal::HWnd {
+        let popup = menu.inner.popup.borrow();
+        popup.as_ref().unwrap().hwnd.pal_hwnd().unwrap()
+    }
+
+    fn row_frame(menu: &Menu, i: usize) -> cggeom::Box2<f32> {
+        let popup = menu.inner.popup.borrow();
+        popup.as_ref().unwrap().rows[i].view.global_frame()
+    }
+
+    fn press_key(twm: &dyn TestingWm, hwnd: &pal::HWnd, pattern: &str) {
+        twm.simulate_key(hwnd, "windows", pattern);
+        twm.step_unsend();
+    }
+
+    fn click(twm: &dyn TestingWm, hwnd: &pal::HWnd, loc: Point2<f32>) {
+        let drag = twm.raise_mouse_drag(hwnd, loc, 0);
+        drag.mouse_down(loc, 0);
+        drag.mouse_up(loc, 0);
+        twm.step_unsend();
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn popup_attrs(twm: &dyn TestingWm) {
+        let f = make_menu(twm);
+        assert!(f.menu.is_open());
+
+        let attrs = twm.wnd_attrs(&popup_hwnd(&f.menu)).unwrap();
+        assert!(attrs.visible);
+        assert!(attrs.flags.contains(pal::WndFlags::POPUP));
+        assert_eq!(attrs.owner, f.owner.pal_hwnd());
+        assert_eq!(attrs.popup_anchor, Some(ANCHOR));
+
+        f.menu.close();
+        assert!(!f.menu.is_open());
+        twm.step_unsend();
+        assert_eq!(twm.hwnds().len(), 1);
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn keyboard_navigation(twm: &dyn TestingWm) {
+        let f = make_menu(twm);
+        let hwnd = popup_hwnd(&f.menu);
+
+        press_key(twm, &hwnd, "Down");
+        assert_eq!(f.menu.inner.highlight(), Some(0));
+
+        // Skip the separator and the disabled item
+        press_key(twm, &hwnd, "Down");
+        assert_eq!(f.menu.inner.highlight(), Some(3));
+
+        press_key(twm, &hwnd, "Down");
+        assert_eq!(f.menu.inner.highlight(), Some(4));
+
+        // Wrap around
+        press_key(twm, &hwnd, "Down");
+        assert_eq!(f.menu.inner.highlight(), Some(0));
+        press_key(twm, &hwnd, "Up");
+        assert_eq!(f.menu.inner.highlight(), Some(4));
+        press_key(twm, &hwnd, "Up");
+        assert_eq!(f.menu.inner.highlight(), Some(3));
+
+        press_key(twm, &hwnd, "Return");
+        assert!(!f.menu.is_open());
+        twm.step_unsend();
+        assert_eq!(f.activated[1].get(), 1);
+        assert_eq!(twm.hwnds().len(), 1);
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn submenu_keyboard(twm: &dyn TestingWm) {
+        let f = make_menu(twm);
+        let hwnd = popup_hwnd(&f.menu);
+
+        press_key(twm, &hwnd, "Up");
+        assert_eq!(f.menu.inner.highlight(), Some(4));
+
+        // Open the submenu
+        press_key(twm, &hwnd, "Right");
+        assert!(f.submenu.is_open());
+        assert_eq!(f.submenu.inner.highlight(), Some(0));
+
+        let attrs = twm.wnd_attrs(&popup_hwnd(&f.submenu)).unwrap();
+        assert_eq!(attrs.owner, Some(hwnd.clone()));
+        assert_eq!(
+            attrs.popup_anchor,
+            Some(PopupAnchor {
+                rect: row_frame(&f.menu, 4),
+                direction: PopupDirection::Right,
+            })
+        );
+
+        // The key events are routed to the submenu. The disabled item is
+        // skipped.
+        press_key(twm, &hwnd, "Down");
+        assert_eq!(f.submenu.inner.highlight(), Some(0));
+        assert_eq!(f.menu.inner.highlight(), Some(4));
+
+        // Close the submenu
+        press_key(twm, &hwnd, "Left");
+        assert!(!f.submenu.is_open());
+        assert!(f.menu.is_open());
+
+        // Activate the submenu's item
+        press_key(twm, &hwnd, "Right");
+        press_key(twm, &popup_hwnd(&f.submenu), "Return");
+        assert!(!f.submenu.is_open());
+        assert!(!f.menu.is_open());
+        twm.step_unsend();
+        assert_eq!(f.activated[2].get(), 1);
+
+        // Escape closes the menu
+        f.menu.popup(f.owner.as_ref(), ANCHOR);
+        twm.step_unsend();
+        press_key(twm, &popup_hwnd(&f.menu), "Escape");
+        assert!(!f.menu.is_open());
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn mouse(twm: &dyn TestingWm) {
+        let f = make_menu(twm);
+        let hwnd = popup_hwnd(&f.menu);
+
+        // Clicking a disabled item does nothing
+        click(twm, &hwnd, row_frame(&f.menu, 2).mid());
+        assert!(f.menu.is_open());
+
+        // Hovering over an item with a submenu opens the submenu
+        twm.raise_mouse_motion(&hwnd, row_frame(&f.menu, 4).mid());
+        twm.step_unsend();
+        assert_eq!(f.menu.inner.highlight(), Some(4));
+        assert!(f.submenu.is_open());
+
+        // ... and hovering over another item closes it
+        twm.raise_mouse_motion(&hwnd, row_frame(&f.menu, 0).mid());
+        assert_eq!(f.menu.inner.highlight(), Some(0));
+        assert!(!f.submenu.is_open());
+
+        click(twm, &hwnd, row_frame(&f.menu, 0).mid());
+        assert!(!f.menu.is_open());
+        twm.step_unsend();
+        assert_eq!(f.activated[0].get(), 1);
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn dismiss(twm: &dyn TestingWm) {
+        let f = make_menu(twm);
+        let hwnd = popup_hwnd(&f.menu);
+
+        twm.raise_mouse_motion(&hwnd, row_frame(&f.menu, 4).mid());
+        twm.step_unsend();
+        assert!(f.submenu.is_open());
+
+        // Clicking outside the menus closes both of them
+        let owner = f.owner.pal_hwnd().unwrap();
+        twm.raise_mouse_drag(&owner, Point2::new(1.0, 1.0), 0)
+            .cancel();
+        twm.step_unsend();
+        assert!(!f.menu.is_open());
+        assert!(!f.submenu.is_open());
+        assert_eq!(twm.hwnds(), vec![owner]);
+        assert_eq!(f.activated.iter().map(Cell::get).sum::<u32>(), 0);
+    }
+}
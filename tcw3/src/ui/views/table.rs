@@ -143,6 +143,21 @@ bitflags! {
         const GROW_LAST_COL = 1;
         /// Expand the last row to fill the remaining space.
         const GROW_LAST_ROW = 1 << 1;
+        /// Keep the rightmost part of the table in view while the table is
+        /// scrolled to the right end. See [`STICK_TO_LAST_ROW`].
+        ///
+        /// [`STICK_TO_LAST_ROW`]: TableFlags::STICK_TO_LAST_ROW
+        const STICK_TO_LAST_COL = 1 << 2;
+        /// Keep the bottommost part of the table in view while the table is
+        /// scrolled to the bottom end, e.g., when rows are appended or the
+        /// last row grows, or when the table view is resized.
+        ///
+        /// The table is considered to be scrolled to the end if the scroll
+        /// position was at the end when it was last set by
+        /// [`TableEdit::set_scroll_pos`], or if it has never been set. Thus,
+        /// the table stops following the end once the user scrolls away from
+        /// it, and resumes when the user scrolls back to the end.
+        const STICK_TO_LAST_ROW = 1 << 3;
     }
 }
 
@@ -229,6 +244,13 @@ struct State {
 
     /// Display offset - see `TableEdit::set_display_offset`.
     display_offset: VpPos,
+
+    /// Indicates whether the primary viewport was at the right/bottom end
+    /// when it was last moved by `TableEdit::set_scroll_pos`. See
+    /// `TableFlags::STICK_TO_LAST_ROW`.
+    ///
+    /// The indices correspond to `LineTy`'s integer values.
+    vp_at_end: [bool; 2],
 }
 
 impl fmt::Debug for State {
@@ -240,6 +262,7 @@ impl fmt::Debug for State {
             .field("linesets", &self.linesets)
             .field("vp_set", &self.vp_set)
             .field("display_offset", &self.display_offset)
+            .field("vp_at_end", &self.vp_at_end)
             .finish()
     }
 }
@@ -504,6 +527,7 @@ impl Table {
                 linesets: [Lineset::new(), Lineset::new()],
                 vp_set: VpSet::new(),
                 display_offset: [0.0; 2],
+                vp_at_end: [true; 2],
             }),
             size: Cell::new(Vector2::new(0, 0)),
             size_traits: Cell::new(SizeTraits::default()),
@@ -568,18 +592,23 @@ impl Table {
 
     /// Set new table flags.
     ///
-    /// Must not have an active edit (the table model must be in the unlocked
-    /// state).
+    /// This may be called while there is an active edit, in which case the
+    /// new flags take effect when the edit is dropped.
     pub fn set_flags(&self, value: TableFlags) {
         let diff = value ^ self.inner.flags.get();
         self.inner.flags.set(value);
+        if diff.intersects(TableFlags::STICK_TO_LAST_COL | TableFlags::STICK_TO_LAST_ROW) {
+            // The primary viewport might have to be moved to the end
+            self.inner.set_dirty_flags(DirtyFlags::CELLS);
+        }
         if diff.intersects(TableFlags::GROW_LAST_COL | TableFlags::GROW_LAST_ROW) {
             self.inner.set_dirty_flags(DirtyFlags::LAYOUT);
-            Inner::update_layout_if_needed(
-                &self.inner,
-                &self.inner.state.borrow(),
-                self.view.as_ref(),
-            );
+        }
+
+        // Dropping `TableEdit` processes the update. If there's an active
+        // edit, the update is processed when that edit is dropped instead.
+        if let Ok(edit) = self.edit() {
+            drop(edit);
         }
     }
 
@@ -59,7 +59,21 @@ impl TableEdit<'_> {
     /// Set the primary viewport position (the current scrolling position).
     ///
     /// `pos[i]` is automatically clamped to range `0.0..scroll_limit()[i]`.
+    ///
+    /// If [`TableFlags::STICK_TO_LAST_ROW`] is set, the table starts or stops
+    /// following the bottom end depending on whether the new position is at
+    /// the end. The same goes for [`TableFlags::STICK_TO_LAST_COL`].
+    ///
+    /// [`TableFlags::STICK_TO_LAST_ROW`]: super::TableFlags::STICK_TO_LAST_ROW
+    /// [`TableFlags::STICK_TO_LAST_COL`]: super::TableFlags::STICK_TO_LAST_COL
     pub fn set_scroll_pos(&mut self, pos: VpPos) {
+        let new_pos = self.pos_to_fix(pos);
+        let vp_at_end = [
+            new_pos[0] >= self.scroll_limit_raw(0),
+            new_pos[1] >= self.scroll_limit_raw(1),
+        ];
+        self.state.vp_at_end = vp_at_end;
+
         self.set_vp_pos_inner(super::primary_vp_ptr(), pos);
     }
 
@@ -174,8 +174,8 @@ impl ScrollableTable {
 
     /// Set new table flags (delegated to the inner `Table`).
     ///
-    /// Must not have an active edit (the table model must be in the unlocked
-    /// state).
+    /// This may be called while there is an active edit, in which case the
+    /// new flags take effect when the edit is dropped.
     pub fn set_flags(&self, value: TableFlags) {
         self.inner.table.set_flags(value);
     }
@@ -319,9 +319,14 @@ mod tests {
     use super::*;
     use crate::{
         testing::{prelude::*, use_testing_wm},
+        ui::views::table::{
+            CellCtrler, CellIdx, TableModelEdit, TableModelEditExt, TableModelQuery,
+        },
         uicore::HWnd,
     };
     use cggeom::prelude::*;
+    use std::ops::Range;
+    use try_match::try_match;
 
     #[use_testing_wm(testing = "crate::testing")]
     #[test]
@@ -337,4 +342,181 @@ mod tests {
 
         twm.step_unsend();
     }
+
+    struct RowsModel {
+        row_heights: Vec<f64>,
+    }
+
+    impl TableModelQuery for RowsModel {
+        fn new_view(&mut self, _cell: CellIdx) -> (HView, Box<dyn CellCtrler>) {
+            (HView::new(Default::default()), Box::new(()))
+        }
+
+        fn range_size(&mut self, line_ty: LineTy, range: Range<u64>, _approx: bool) -> f64 {
+            match line_ty {
+                LineTy::Row => self.row_heights[range.start as usize..range.end as usize]
+                    .iter()
+                    .sum(),
+                LineTy::Col => 20.0 * (range.end - range.start) as f64,
+            }
+        }
+    }
+
+    fn append_rows(table: &ScrollableTable, heights: &[f64]) {
+        let mut edit = table.table().edit().unwrap();
+        let model: &mut RowsModel = edit.model_downcast_mut().unwrap();
+        let start = model.row_heights.len() as u64;
+        model.row_heights.extend_from_slice(heights);
+        let end = model.row_heights.len() as u64;
+        edit.insert(LineTy::Row, start..end);
+    }
+
+    fn scroll_pos_and_limit(table: &ScrollableTable) -> (f64, f64) {
+        let edit = table.table().edit().unwrap();
+        (edit.scroll_pos()[1], edit.scroll_limit()[1])
+    }
+
+    /// Create a window containing a `ScrollableTable` with 20 rows, each 30
+    /// points high. The window is 300 points high.
+    fn make_wnd(twm: &dyn TestingWm, flags: TableFlags) -> (Rc<ScrollableTable>, HWnd, pal::HWnd) {
+        let wm = twm.wm();
+
+        let style_manager = Manager::global(wm);
+        let table = Rc::new(ScrollableTable::new(style_manager));
+        table.set_flags(flags);
+
+        {
+            let mut edit = table.table().edit().unwrap();
+            edit.set_model(RowsModel {
+                row_heights: vec![30.0; 20],
+            });
+            edit.insert(LineTy::Col, 0..1);
+            edit.insert(LineTy::Row, 0..20);
+        }
+
+        let wnd = HWnd::new(wm);
+        wnd.content_view().set_layout(FillLayout::new(table.view()));
+        wnd.set_visibility(true);
+
+        twm.step_unsend();
+
+        let pal_hwnd = try_match!([x] = twm.hwnds().as_slice() => x.clone())
+            .expect("could not get a single window");
+
+        twm.set_wnd_size(&pal_hwnd, [100, 300]);
+        twm.step_unsend();
+
+        (table, wnd, pal_hwnd)
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn stick_to_last_row(twm: &dyn TestingWm) {
+        let (table, _wnd, pal_hwnd) = make_wnd(
+            twm,
+            TableFlags::GROW_LAST_COL | TableFlags::STICK_TO_LAST_ROW,
+        );
+
+        // The table follows the end from the beginning
+        let (pos, limit) = scroll_pos_and_limit(&table);
+        assert!(limit > 0.0);
+        assert_eq!(pos, limit);
+
+        // Append rows
+        append_rows(&table, &[30.0; 5]);
+        twm.step_unsend();
+
+        let (pos, new_limit) = scroll_pos_and_limit(&table);
+        assert_eq!(new_limit, limit + 150.0);
+        assert_eq!(pos, new_limit);
+
+        // Grow the last row
+        {
+            let mut edit = table.table().edit().unwrap();
+            let model: &mut RowsModel = edit.model_downcast_mut().unwrap();
+            model.row_heights[24] = 100.0;
+            edit.resize(LineTy::Row, 24..25);
+        }
+        twm.step_unsend();
+
+        let (pos, limit) = scroll_pos_and_limit(&table);
+        assert_eq!(limit, new_limit + 70.0);
+        assert_eq!(pos, limit);
+
+        // Shrink the table view
+        twm.set_wnd_size(&pal_hwnd, [100, 150]);
+        twm.step_unsend();
+
+        let (pos, limit) = scroll_pos_and_limit(&table);
+        assert_eq!(pos, limit);
+
+        // Scroll up. The table should stop following the end.
+        table
+            .table()
+            .edit()
+            .unwrap()
+            .set_scroll_pos([0.0, limit - 50.0]);
+
+        append_rows(&table, &[30.0; 5]);
+        twm.step_unsend();
+
+        let (new_pos, new_limit) = scroll_pos_and_limit(&table);
+        assert_eq!(new_limit, limit + 150.0);
+        assert_eq!(new_pos, limit - 50.0);
+
+        // Scroll back to the end. The table should resume following the end.
+        table
+            .table()
+            .edit()
+            .unwrap()
+            .set_scroll_pos([0.0, new_limit]);
+
+        append_rows(&table, &[30.0; 5]);
+        twm.step_unsend();
+
+        let (pos, limit) = scroll_pos_and_limit(&table);
+        assert_eq!(limit, new_limit + 150.0);
+        assert_eq!(pos, limit);
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn no_stick_to_last_row(twm: &dyn TestingWm) {
+        let (table, _wnd, pal_hwnd) = make_wnd(twm, TableFlags::GROW_LAST_COL);
+
+        let (_, limit) = scroll_pos_and_limit(&table);
+        table.table().edit().unwrap().set_scroll_pos([0.0, limit]);
+
+        // Without the flag, the top edge stays in place when the table view
+        // is resized
+        twm.set_wnd_size(&pal_hwnd, [100, 150]);
+        twm.step_unsend();
+
+        let (pos, new_limit) = scroll_pos_and_limit(&table);
+        assert_eq!(pos, limit);
+        assert!(new_limit > limit);
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn set_flags_during_edit(twm: &dyn TestingWm) {
+        let (table, _wnd, _pal_hwnd) = make_wnd(twm, TableFlags::GROW_LAST_COL);
+
+        let (_, limit) = scroll_pos_and_limit(&table);
+
+        {
+            let mut edit = table.table().edit().unwrap();
+            edit.set_scroll_pos([0.0, limit]);
+
+            // The new flags take effect when `edit` is dropped
+            table.set_flags(TableFlags::GROW_LAST_COL | TableFlags::STICK_TO_LAST_ROW);
+        }
+
+        append_rows(&table, &[30.0; 5]);
+        twm.step_unsend();
+
+        let (pos, new_limit) = scroll_pos_and_limit(&table);
+        assert_eq!(new_limit, limit + 150.0);
+        assert_eq!(pos, new_limit);
+    }
 }
@@ -69,16 +69,31 @@ impl Inner {
         self.dirty.set(self.dirty.get() - DirtyFlags::CELLS);
         self.dirty.set(self.dirty.get() | DirtyFlags::LAYOUT);
 
+        let flags = self.flags.get();
+
         // Regroup line groups. This makes sure every line group in the viewport
         // correspond to a single line.
         for &ty in &[LineTy::Col, LineTy::Row] {
             let size = self.size.get()[ty.i()];
             let lineset = &mut state.linesets[ty.i()];
 
+            let stick_to_end = state.vp_at_end[ty.i()]
+                && flags.contains(
+                    [TableFlags::STICK_TO_LAST_COL, TableFlags::STICK_TO_LAST_ROW][ty.i()],
+                );
+
             // Regrouping might shrink some line groups. A set of line groups
             // that covered the viewport might no longer after regrouping. If
             // this happens, we try regrouping again.
             loop {
+                // Follow the end if requested. This overrides the displacement
+                // policy.
+                if stick_to_end {
+                    state
+                        .vp_set
+                        .move_primary_vp_to_end(ty, lineset.total_size(), size);
+                }
+
                 // Bound the viewport offset first
                 state.vp_set.bound_by(ty, lineset.total_size(), size);
 
@@ -241,6 +256,13 @@ impl VpSet {
         }
     }
 
+    /// Move the primary viewport to the right/bottom end.
+    fn move_primary_vp_to_end(&mut self, line_ty: LineTy, total_size: Size, vp_size: Size) {
+        let vp = &mut self.vp_pool[super::primary_vp_ptr()][line_ty.i()];
+
+        *vp = max(0, total_size - vp_size);
+    }
+
     /// Restrict viewport positions by the total size of lines.
     fn bound_by(&mut self, line_ty: LineTy, total_size: Size, vp_size: Size) {
         debug_assert!(total_size >= 0);
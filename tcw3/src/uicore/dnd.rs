@@ -0,0 +1,525 @@
+//! Drag-and-drop
+use cgmath::Point2;
+use log::{trace, warn};
+use std::{fmt, rc::Rc};
+
+use super::{HView, HViewRef, HWnd, ViewFlags, Wnd};
+use crate::pal::{self, DragData, DragFormats, DragOp, DragOps};
+
+/// Event handlers for in-app drag sources.
+///
+/// See [`HViewRef::start_drag`].
+pub trait DragSourceListener {
+    /// The drag-and-drop operation has ended.
+    ///
+    /// `op` is the operation performed by the drop target, or `None` if the
+    /// data was rejected or the operation was cancelled. When `op` is
+    /// `Some(DragOp::Move)`, the drag source should delete the original data.
+    fn finish(&self, _: pal::Wm, _: HViewRef<'_>, _op: Option<DragOp>) {}
+}
+
+/// A default implementation of [`DragSourceListener`].
+impl DragSourceListener for () {}
+
+#[derive(Debug)]
+pub(super) struct WndDndState {
+    /// The view receiving the events of the current drag-and-drop operation.
+    target_view: Option<HView>,
+    /// The active in-app drag-and-drop operation.
+    session: Option<Rc<DragSession>>,
+}
+
+impl WndDndState {
+    pub fn new() -> Self {
+        Self {
+            target_view: None,
+            session: None,
+        }
+    }
+}
+
+/// Represents an active in-app drag-and-drop operation.
+struct DragSession {
+    source_view: HView,
+    data: DragData,
+    ops: DragOps,
+    listener: Box<dyn DragSourceListener>,
+}
+
+impl fmt::Debug for DragSession {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DragSession")
+            .field("source_view", &self.source_view)
+            .field("data", &self.data)
+            .field("ops", &self.ops)
+            .field("listener", &((&*self.listener) as *const _))
+            .finish()
+    }
+}
+
+impl HWnd {
+    /// The core implementation of `pal::WndListener::drag_enter` and
+    /// `pal::WndListener::drag_over`.
+    pub(super) fn handle_drag_motion(
+        &self,
+        loc: Point2<f32>,
+        formats: DragFormats,
+        ops: DragOps,
+    ) -> Option<DragOp> {
+        let wm = self.wnd.wm;
+
+        let hit_view = {
+            let content_view = self.wnd.content_view.borrow();
+            content_view.as_ref()?.as_ref().hit_test(
+                loc,
+                ViewFlags::ACCEPT_DROP,
+                ViewFlags::DENY_MOUSE,
+            )
+        };
+
+        let old_view = std::mem::replace(
+            &mut self.wnd.dnd_state.borrow_mut().target_view,
+            hit_view.clone(),
+        );
+
+        let op = if hit_view == old_view {
+            let view = hit_view?;
+            let listener = view.view.listener.borrow();
+            listener.drag_over(wm, view.as_ref(), loc, formats, ops)
+        } else {
+            trace!(
+                "{:?}: The drop target changed from {:?} to {:?}",
+                self,
+                old_view,
+                hit_view
+            );
+
+            if let Some(view) = &old_view {
+                let listener = view.view.listener.borrow();
+                listener.drag_leave(wm, view.as_ref());
+            }
+
+            let view = hit_view?;
+            let listener = view.view.listener.borrow();
+            listener.drag_enter(wm, view.as_ref(), loc, formats, ops)
+        };
+
+        // Reject the operations not allowed by the drag source
+        op.filter(|&op| ops.contains(op.into()))
+    }
+
+    /// The core implementation of `pal::WndListener::drag_leave`.
+    pub(super) fn handle_drag_leave(&self) {
+        leave_drop_target(&self.wnd);
+    }
+
+    /// The core implementation of `pal::WndListener::drag_drop`.
+    pub(super) fn handle_drag_drop(
+        &self,
+        loc: Point2<f32>,
+        data: &DragData,
+        ops: DragOps,
+    ) -> Option<DragOp> {
+        // Make sure the view under the mouse pointer has received
+        // `drag_enter` and accepts the data
+        if self.handle_drag_motion(loc, data.formats(), ops).is_none() {
+            leave_drop_target(&self.wnd);
+            return None;
+        }
+
+        let view = self.wnd.dnd_state.borrow_mut().target_view.take()?;
+
+        trace!("{:?}: The data was dropped onto {:?}", self, view);
+
+        let listener = view.view.listener.borrow();
+        let op = listener.drag_drop(self.wnd.wm, view.as_ref(), loc, data, ops);
+
+        op.filter(|&op| ops.contains(op.into()))
+    }
+
+    /// Returns `true` if there's an active in-app drag-and-drop operation.
+    pub(super) fn has_drag_session(&self) -> bool {
+        self.wnd.dnd_state.borrow().session.is_some()
+    }
+
+    /// Route a mouse motion event to the active in-app drag-and-drop
+    /// operation. Returns `false` if there's no such operation.
+    pub(super) fn drag_session_motion(&self, loc: Point2<f32>) -> bool {
+        let session = self.wnd.dnd_state.borrow().session.clone();
+        if let Some(session) = session {
+            self.handle_drag_motion(loc, session.data.formats(), session.ops);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drop the data of the active in-app drag-and-drop operation. Returns
+    /// `false` if there's no such operation.
+    pub(super) fn drag_session_drop(&self, loc: Point2<f32>) -> bool {
+        let session = self.wnd.dnd_state.borrow_mut().session.take();
+        if let Some(session) = session {
+            let op = self.handle_drag_drop(loc, &session.data, session.ops);
+
+            trace!("{:?}: In-app drag-and-drop ended with {:?}", self, op);
+
+            session
+                .listener
+                .finish(self.wnd.wm, session.source_view.as_ref(), op);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Cancel the active in-app drag-and-drop operation. Returns `false` if
+    /// there's no such operation.
+    pub(super) fn cancel_drag_session(&self) -> bool {
+        let session = self.wnd.dnd_state.borrow_mut().session.take();
+        if let Some(session) = session {
+            trace!("{:?}: In-app drag-and-drop was cancelled", self);
+
+            end_session(&self.wnd, &session);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Call `drag_leave` on the current drop target (if any) and forget it.
+fn leave_drop_target(wnd: &Wnd) {
+    let view = wnd.dnd_state.borrow_mut().target_view.take();
+    if let Some(view) = view {
+        let listener = view.view.listener.borrow();
+        listener.drag_leave(wnd.wm, view.as_ref());
+    }
+}
+
+/// Finish a cancelled drag-and-drop operation.
+fn end_session(wnd: &Wnd, session: &DragSession) {
+    leave_drop_target(wnd);
+    session
+        .listener
+        .finish(wnd.wm, session.source_view.as_ref(), None);
+}
+
+impl HViewRef<'_> {
+    /// Start an in-app drag-and-drop operation with the view as the drag
+    /// source.
+    ///
+    /// This method must be called while the view has an active mouse drag
+    /// gesture, e.g., from [`MouseDragListener::mouse_motion`]. The
+    /// drag-and-drop operation takes over the gesture, and the view's
+    /// `MouseDragListener` doesn't receive any more events. The data is
+    /// dropped when a mouse button is released. After that or when the
+    /// operation is cancelled, `listener.finish` is called.
+    ///
+    /// Only the views in the same window (with [`ViewFlags::ACCEPT_DROP`])
+    /// can be drop targets.
+    ///
+    /// Returns `false` (and drops `listener` without calling it) if the view
+    /// doesn't have an active mouse drag gesture.
+    ///
+    /// [`MouseDragListener::mouse_motion`]: super::MouseDragListener::mouse_motion
+    pub fn start_drag(
+        self,
+        data: DragData,
+        ops: DragOps,
+        listener: Box<dyn DragSourceListener>,
+    ) -> bool {
+        let hwnd = if let Some(hwnd) = self.containing_wnd() {
+            hwnd
+        } else {
+            return false;
+        };
+
+        if !hwnd.wnd.mouse_state.borrow_mut().take_drag_gesture(self) {
+            warn!(
+                "{:?}: Can't start drag-and-drop because the view doesn't \
+                 have an active mouse drag gesture",
+                self
+            );
+            return false;
+        }
+
+        trace!("{:?}: Starting in-app drag-and-drop from {:?}", hwnd, self);
+
+        hwnd.wnd.dnd_state.borrow_mut().session = Some(Rc::new(DragSession {
+            source_view: self.cloned(),
+            data,
+            ops,
+            listener,
+        }));
+
+        true
+    }
+
+    /// Cancel the in-app drag-and-drop operation initiated by the view or
+    /// its subviews. Also, forget the current drop target if it's the view or
+    /// its subview.
+    pub(super) fn cancel_drag_and_drop_of_subviews(self, wnd: &Wnd) {
+        let session = {
+            let mut st = wnd.dnd_state.borrow_mut();
+            let cancel_session = st.session.as_ref().map_or(false, |session| {
+                session.source_view.as_ref().is_improper_subview_of(self)
+            });
+            if cancel_session {
+                st.session.take()
+            } else {
+                None
+            }
+        };
+
+        if let Some(session) = session {
+            end_session(wnd, &session);
+        } else {
+            let is_target = (wnd.dnd_state.borrow().target_view.as_ref())
+                .map_or(false, |view| view.as_ref().is_improper_subview_of(self));
+            if is_target {
+                leave_drop_target(wnd);
+            }
+        }
+    }
+
+    /// Forget the current drop target if it's the view.
+    pub(super) fn cancel_drop_target(self, wnd: &Wnd) {
+        let is_target = (wnd.dnd_state.borrow().target_view.as_ref())
+            .map_or(false, |view| view.as_ref() == self);
+        if is_target {
+            leave_drop_target(wnd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cggeom::box2;
+    use std::cell::RefCell;
+    use try_match::try_match;
+
+    use super::*;
+    use crate::{
+        pal::Wm,
+        testing::{prelude::*, use_testing_wm},
+        ui::{layouts::AbsLayout, AlignFlags},
+        uicore::{HView, MouseDragListener, SizeTraits, ViewListener},
+    };
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct TargetListener {
+        name: &'static str,
+        log: Log,
+        op: Option<DragOp>,
+    }
+
+    impl ViewListener for TargetListener {
+        fn drag_enter(
+            &self,
+            _: Wm,
+            _: HViewRef<'_>,
+            _: Point2<f32>,
+            _: DragFormats,
+            _: DragOps,
+        ) -> Option<DragOp> {
+            self.log.borrow_mut().push(format!("enter {}", self.name));
+            self.op
+        }
+
+        fn drag_over(
+            &self,
+            _: Wm,
+            _: HViewRef<'_>,
+            _: Point2<f32>,
+            _: DragFormats,
+            _: DragOps,
+        ) -> Option<DragOp> {
+            self.log.borrow_mut().push(format!("over {}", self.name));
+            self.op
+        }
+
+        fn drag_leave(&self, _: Wm, _: HViewRef<'_>) {
+            self.log.borrow_mut().push(format!("leave {}", self.name));
+        }
+
+        fn drag_drop(
+            &self,
+            _: Wm,
+            _: HViewRef<'_>,
+            _: Point2<f32>,
+            data: &DragData,
+            _: DragOps,
+        ) -> Option<DragOp> {
+            let text = data.text.as_deref().unwrap_or("");
+            (self.log.borrow_mut()).push(format!("drop {} {}", self.name, text));
+            self.op
+        }
+    }
+
+    struct SourceListener(Log);
+
+    impl ViewListener for SourceListener {
+        fn mouse_drag(
+            &self,
+            _: Wm,
+            _: HViewRef<'_>,
+            _: Point2<f32>,
+            _: u8,
+        ) -> Box<dyn MouseDragListener> {
+            Box::new(SourceDragListener(Rc::clone(&self.0)))
+        }
+    }
+
+    struct SourceDragListener(Log);
+
+    impl MouseDragListener for SourceDragListener {
+        fn mouse_motion(&self, _: Wm, hview: HViewRef<'_>, _: Point2<f32>) {
+            let data = DragData {
+                text: Some("hello".to_owned()),
+                ..Default::default()
+            };
+            let listener = Box::new(SourceDragListener(Rc::clone(&self.0)));
+            assert!(hview.start_drag(data, DragOps::COPY | DragOps::MOVE, listener));
+        }
+    }
+
+    impl DragSourceListener for SourceDragListener {
+        fn finish(&self, _: Wm, _: HViewRef<'_>, op: Option<DragOp>) {
+            self.0.borrow_mut().push(format!("finish {:?}", op));
+        }
+    }
+
+    /// Create a window containing two drop targets (`1` accepting `Copy` and
+    /// `2` accepting `Link`) and a drag source.
+    fn make_wnd(twm: &dyn TestingWm, log: &Log) -> (HWnd, crate::pal::HWnd) {
+        let wm = twm.wm();
+
+        let new_target = |name, op| {
+            let view = HView::new(ViewFlags::ACCEPT_DROP);
+            view.set_listener(TargetListener {
+                name,
+                log: Rc::clone(log),
+                op,
+            });
+            view
+        };
+
+        let source = HView::new(ViewFlags::ACCEPT_MOUSE_DRAG);
+        source.set_listener(SourceListener(Rc::clone(log)));
+
+        let wnd = HWnd::new(wm);
+        wnd.content_view().set_layout(AbsLayout::new(
+            SizeTraits {
+                min: [100.0; 2].into(),
+                max: [100.0; 2].into(),
+                preferred: [100.0; 2].into(),
+            },
+            vec![
+                (
+                    new_target("1", Some(DragOp::Copy)),
+                    box2! { min: [10.0, 10.0], max: [30.0, 30.0] },
+                    AlignFlags::JUSTIFY,
+                ),
+                (
+                    new_target("2", Some(DragOp::Link)),
+                    box2! { min: [50.0, 10.0], max: [90.0, 30.0] },
+                    AlignFlags::JUSTIFY,
+                ),
+                (
+                    source,
+                    box2! { min: [10.0, 60.0], max: [30.0, 90.0] },
+                    AlignFlags::JUSTIFY,
+                ),
+            ],
+        ));
+        wnd.set_visibility(true);
+        twm.step_unsend();
+
+        let pal_hwnd = try_match!([x] = twm.hwnds().as_slice() => x.clone())
+            .expect("could not get a single window");
+
+        (wnd, pal_hwnd)
+    }
+
+    fn take_log(log: &Log) -> Vec<String> {
+        std::mem::replace(&mut *log.borrow_mut(), Vec::new())
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn external_drag(twm: &dyn TestingWm) {
+        let log = Log::default();
+        let (_wnd, pal_hwnd) = make_wnd(twm, &log);
+
+        let data = DragData {
+            text: Some("hello".to_owned()),
+            ..Default::default()
+        };
+        let (formats, ops) = (data.formats(), DragOps::COPY | DragOps::MOVE);
+
+        let op = twm.raise_drag_enter(&pal_hwnd, Point2::new(20.0, 20.0), formats, ops);
+        assert_eq!(op, Some(DragOp::Copy));
+        assert_eq!(take_log(&log), ["enter 1"]);
+
+        let op = twm.raise_drag_over(&pal_hwnd, Point2::new(25.0, 20.0), formats, ops);
+        assert_eq!(op, Some(DragOp::Copy));
+        assert_eq!(take_log(&log), ["over 1"]);
+
+        // `2` chooses an operation not allowed by the drag source
+        let op = twm.raise_drag_over(&pal_hwnd, Point2::new(70.0, 20.0), formats, ops);
+        assert_eq!(op, None);
+        assert_eq!(take_log(&log), ["leave 1", "enter 2"]);
+
+        let op = twm.raise_drag_over(&pal_hwnd, Point2::new(70.0, 50.0), formats, ops);
+        assert_eq!(op, None);
+        assert_eq!(take_log(&log), ["leave 2"]);
+
+        twm.raise_drag_leave(&pal_hwnd);
+        assert_eq!(take_log(&log), Vec::<String>::new());
+
+        let op = twm.raise_drag_enter(&pal_hwnd, Point2::new(70.0, 20.0), formats, ops);
+        assert_eq!(op, None);
+        let op = twm.raise_drag_drop(&pal_hwnd, Point2::new(70.0, 20.0), &data, ops);
+        assert_eq!(op, None);
+        assert_eq!(take_log(&log), ["enter 2", "over 2", "leave 2"]);
+
+        let op = twm.raise_drag_enter(&pal_hwnd, Point2::new(20.0, 20.0), formats, ops);
+        assert_eq!(op, Some(DragOp::Copy));
+        let op = twm.raise_drag_drop(&pal_hwnd, Point2::new(20.0, 20.0), &data, ops);
+        assert_eq!(op, Some(DragOp::Copy));
+        assert_eq!(take_log(&log), ["enter 1", "over 1", "drop 1 hello"]);
+    }
+
+    #[use_testing_wm(testing = "crate::testing")]
+    #[test]
+    fn in_app_drag(twm: &dyn TestingWm) {
+        let log = Log::default();
+        let (_wnd, pal_hwnd) = make_wnd(twm, &log);
+
+        // Drop the data onto `1`
+        let drag = twm.raise_mouse_drag(&pal_hwnd, Point2::new(20.0, 75.0), 0);
+        drag.mouse_down(Point2::new(20.0, 75.0), 0);
+        drag.mouse_motion(Point2::new(20.0, 70.0));
+        assert_eq!(take_log(&log), Vec::<String>::new());
+
+        drag.mouse_motion(Point2::new(20.0, 20.0));
+        assert_eq!(take_log(&log), ["enter 1"]);
+
+        drag.mouse_up(Point2::new(20.0, 20.0), 0);
+        drop(drag);
+        assert_eq!(
+            take_log(&log),
+            ["over 1", "drop 1 hello", "finish Some(Copy)"]
+        );
+
+        // Cancel the operation
+        let drag = twm.raise_mouse_drag(&pal_hwnd, Point2::new(20.0, 75.0), 0);
+        drag.mouse_down(Point2::new(20.0, 75.0), 0);
+        drag.mouse_motion(Point2::new(20.0, 70.0));
+        drag.mouse_motion(Point2::new(20.0, 20.0));
+        drag.cancel();
+        drop(drag);
+        assert_eq!(take_log(&log), ["enter 1", "leave 1", "finish None"]);
+    }
+}
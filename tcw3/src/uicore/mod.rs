@@ -59,6 +59,7 @@ use subscriber_list::{SubscriberList, UntypedSubscription};
 
 use crate::pal::{self, prelude::*, Wm};
 
+mod dnd;
 mod images;
 mod invocation;
 mod keybd;
@@ -69,13 +70,15 @@ mod mouse;
 mod taborder;
 mod window;
 
+pub use self::dnd::DragSourceListener;
 pub use self::layer::{UpdateCtx, UpdateReason};
 pub use self::layout::{Layout, LayoutCtx, SizeTraits};
 pub use self::mouse::{MouseDragListener, ScrollListener};
 pub use self::taborder::TabOrderSibling;
 
 pub use crate::pal::{
-    actions, ActionId, ActionStatus, CursorShape, ScrollDelta, WndFlags as WndStyleFlags,
+    actions, ActionId, ActionStatus, CursorShape, DragData, DragFormats, DragOp, DragOps,
+    PopupAnchor, PopupDirection, ScrollDelta, WndFlags as WndStyleFlags, WndMode,
 };
 
 /// The maxiumum supported depth of view hierarchy.
@@ -274,6 +277,9 @@ struct Wnd {
     mouse_state: RefCell<mouse::WndMouseState>,
     cursor_shape: Cell<CursorShape>,
 
+    // Drag-and-drop
+    dnd_state: RefCell<dnd::WndDndState>,
+
     // Keyboard inputs
     focused_view: RefCell<Option<HView>>,
 }
@@ -295,6 +301,7 @@ impl fmt::Debug for Wnd {
             .field("dpi_scale_changed_handlers", &())
             .field("frame_handlers", &())
             .field("mouse_state", &self.mouse_state)
+            .field("dnd_state", &self.dnd_state)
             .field("focus_handlers", &())
             .field("focused_view", &self.focused_view)
             .finish()
@@ -321,6 +328,7 @@ impl Wnd {
             frame_handlers: LinkedListCell::new(),
             mouse_state: RefCell::new(mouse::WndMouseState::new()),
             cursor_shape: Cell::new(CursorShape::default()),
+            dnd_state: RefCell::new(dnd::WndDndState::new()),
             focus_handlers: RefCell::new(SubscriberList::new()),
             focused_view: RefCell::new(None),
         }
@@ -465,6 +473,10 @@ bitflags! {
         ///
         /// This flag cannot be added or removed once a view is created.
         const CLIP_VISIBLE_FRAME = 1 << 10;
+
+        /// The view accepts drag-and-drop events. The hit testing follows the
+        /// same rules as mouse drag events.
+        const ACCEPT_DROP = 1 << 11;
     }
 }
 
@@ -478,7 +490,7 @@ impl Default for ViewFlags {
 impl ViewFlags {
     fn mutable_flags() -> Self {
         flags![ViewFlags::{NO_CLIP_HITTEST | DENY_MOUSE | ACCEPT_MOUSE_DRAG |
-            TAB_STOP | STRONG_FOCUS}]
+            TAB_STOP | STRONG_FOCUS | ACCEPT_DROP}]
     }
 }
 
@@ -603,6 +615,61 @@ pub trait ViewListener {
         Box::new(())
     }
 
+    /// A drag-and-drop operation entered the view's region.
+    ///
+    /// `formats` specifies the data formats the dragged data is available in,
+    /// and `ops` specifies the operations allowed by the drag source.
+    /// Returns the operation to be performed if the data is dropped at `loc`,
+    /// or `None` to reject the data.
+    ///
+    /// You must set [`ViewFlags::ACCEPT_DROP`] for this to be called.
+    fn drag_enter(
+        &self,
+        _: Wm,
+        _: HViewRef<'_>,
+        _loc: Point2<f32>,
+        _formats: DragFormats,
+        _ops: DragOps,
+    ) -> Option<DragOp> {
+        None
+    }
+
+    /// The mouse pointer has moved inside the view's region during a
+    /// drag-and-drop operation.
+    ///
+    /// The parameters and the return value have the same meaning as those of
+    /// [`ViewListener::drag_enter`].
+    fn drag_over(
+        &self,
+        _: Wm,
+        _: HViewRef<'_>,
+        _loc: Point2<f32>,
+        _formats: DragFormats,
+        _ops: DragOps,
+    ) -> Option<DragOp> {
+        None
+    }
+
+    /// A drag-and-drop operation left the view's region or was cancelled.
+    fn drag_leave(&self, _: Wm, _: HViewRef<'_>) {}
+
+    /// The data of a drag-and-drop operation was dropped onto the view.
+    ///
+    /// This is called only if the preceding call to `drag_enter` or
+    /// `drag_over` accepted the data. Returns the operation that was actually
+    /// performed, or `None` if the data was rejected. `drag_leave` is not
+    /// called after this method.
+    fn drag_drop(
+        &self,
+        _: Wm,
+        _: HViewRef<'_>,
+        _loc: Point2<f32>,
+        _data: &DragData,
+        _ops: DragOps,
+    ) -> Option<DragOp> {
+        None
+    }
+
     /// `focus_got` is called for this view or its descendants.
     fn focus_enter(&self, _: Wm, _: HViewRef<'_>) {}
     /// `focus_lost` is called for this view or its descendants.
@@ -830,6 +897,7 @@ impl HWnd {
         pub fn dpi_scale(&self) -> f32;
         pub fn subscribe_dpi_scale_changed(&self, cb: WndCb) -> Sub;
         pub fn is_focused(&self) -> bool;
+        pub fn activate(&self);
         pub fn subscribe_focus(&self, cb: WndCb) -> Sub;
         pub fn content_view(&self) -> HView;
         pub fn set_content_view(&self, view: HView);
@@ -840,6 +908,14 @@ impl HWnd {
         pub fn caption(&self) -> String;
         pub fn set_style_flags(&self, flags: WndStyleFlags);
         pub fn style_flags(&self) -> WndStyleFlags;
+        pub fn set_owner(&self, owner: Option<HWnd>);
+        pub fn owner(&self) -> Option<HWnd>;
+        pub fn set_popup_anchor(&self, anchor: Option<PopupAnchor>);
+        pub fn popup_anchor(&self) -> Option<PopupAnchor>;
+        pub fn set_position(&self, position: [i32; 2]);
+        pub fn position(&self) -> Option<[i32; 2]>;
+        pub fn set_mode(&self, mode: WndMode);
+        pub fn mode(&self) -> WndMode;
         pub fn invoke_on_next_frame(&self, f: impl FnOnce(pal::Wm, HWndRef<'_>) + 'static);
 
         // `keybd.rs`
@@ -916,6 +992,15 @@ impl HWndRef<'_> {
         }
     }
 
+    /// Bring the window to the front and give it focus.
+    ///
+    /// This function does nothing if the window is not materialized yet.
+    pub fn activate(self) {
+        if let Some(ref pal_wnd) = &*self.wnd.pal_wnd.borrow() {
+            self.wnd.wm.activate_wnd(pal_wnd);
+        }
+    }
+
     /// Register a function that gets called whenever the window gets or loses
     /// focus.
     ///
@@ -1043,6 +1128,99 @@ impl HWndRef<'_> {
         self.wnd.style_attrs.borrow().flags
     }
 
+    /// Set the owner of a window.
+    ///
+    /// The default value is `None`. The owner must be materialized (see
+    /// [`HWndRef::pal_hwnd`]) before this window is materialized or updated.
+    /// Otherwise, this setting is not reflected to the PAL window.
+    pub fn set_owner(self, owner: Option<HWnd>) {
+        let owner = owner.map(|hwnd| hwnd.downgrade());
+        let mut style_attrs = self.wnd.style_attrs.borrow_mut();
+        let unchanged = match (&style_attrs.owner, &owner) {
+            (Some(old), Some(new)) => Weak::ptr_eq(&old.wnd, &new.wnd),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+        style_attrs.owner = owner;
+        self.wnd.set_dirty_flags(window::WndDirtyFlags::STYLE_OWNER);
+        self.pend_update();
+    }
+
+    /// Get the owner of a window.
+    pub fn owner(self) -> Option<HWnd> {
+        self.wnd.style_attrs.borrow().owner.as_ref()?.upgrade()
+    }
+
+    /// Set the placement of a popup window (a window with
+    /// [`WndStyleFlags::POPUP`]) relative to its owner.
+    ///
+    /// The default value is `None`.
+    pub fn set_popup_anchor(self, anchor: Option<PopupAnchor>) {
+        let mut style_attrs = self.wnd.style_attrs.borrow_mut();
+        if style_attrs.popup_anchor == anchor {
+            return;
+        }
+        style_attrs.popup_anchor = anchor;
+        self.wnd
+            .set_dirty_flags(window::WndDirtyFlags::STYLE_POPUP_ANCHOR);
+        self.pend_update();
+    }
+
+    /// Get the placement of a popup window.
+    pub fn popup_anchor(self) -> Option<PopupAnchor> {
+        self.wnd.style_attrs.borrow().popup_anchor
+    }
+
+    /// Move a window. See [`pal::WndAttrs::position`] for the coordinate
+    /// space.
+    ///
+    /// The window is placed by the system by default.
+    pub fn set_position(self, position: [i32; 2]) {
+        // The window might have been moved by the user since the last call,
+        // so always send the value to the PAL window
+        self.wnd.style_attrs.borrow_mut().position = Some(position);
+        self.wnd
+            .set_dirty_flags(window::WndDirtyFlags::STYLE_POSITION);
+        self.pend_update();
+    }
+
+    /// Get the current position of a window.
+    ///
+    /// Returns the value passed to [`HWndRef::set_position`] if the window
+    /// hasn't been materialized yet. Returns `None` if the position is
+    /// unknown.
+    pub fn position(self) -> Option<[i32; 2]> {
+        if let Some(ref pal_wnd) = &*self.wnd.pal_wnd.borrow() {
+            self.wnd.wm.get_wnd_position(pal_wnd)
+        } else {
+            self.wnd.style_attrs.borrow().position
+        }
+    }
+
+    /// Minimize, maximize, or restore a window, or make it full-screen.
+    ///
+    /// The default value is [`WndMode::Normal`].
+    pub fn set_mode(self, mode: WndMode) {
+        // The mode might have been changed by the user since the last call,
+        // so always send the value to the PAL window
+        self.wnd.style_attrs.borrow_mut().mode = mode;
+        self.wnd.set_dirty_flags(window::WndDirtyFlags::STYLE_MODE);
+        self.pend_update();
+    }
+
+    /// Get the current mode of a window, which might have been changed by
+    /// the user.
+    pub fn mode(self) -> WndMode {
+        if let Some(ref pal_wnd) = &*self.wnd.pal_wnd.borrow() {
+            self.wnd.wm.get_wnd_mode(pal_wnd)
+        } else {
+            self.wnd.style_attrs.borrow().mode
+        }
+    }
+
     /// Enqueue a call to the specified function. The function will be called
     /// when the system is ready to accept a new displayed frame.
     ///
@@ -1310,6 +1488,13 @@ impl<'a> HViewRef<'a> {
             }
         }
 
+        if (!value & changed).contains(ViewFlags::ACCEPT_DROP) {
+            // The view is no longer allowed to be a drop target
+            if let Some(hwnd) = self.containing_wnd() {
+                self.cancel_drop_target(&hwnd.wnd);
+            }
+        }
+
         if (!value & changed).contains(ViewFlags::TAB_STOP) {
             // The view is no longer allowed to have a keyboard focus so
             // cancel it if it has one
@@ -405,6 +405,8 @@ impl HViewRef<'_> {
         if let Some(drag) = cancelled_drag {
             drag.listener.cancel(wnd.wm, drag.view.as_ref());
         }
+
+        self.cancel_drag_and_drop_of_subviews(wnd);
     }
 
     /// Cancel active mouse drag gestures for the specified view (but not
@@ -441,6 +443,17 @@ impl HViewRef<'_> {
 }
 
 impl WndMouseState {
+    /// Remove the drag gesture of `view` without cancelling it. Returns
+    /// `false` if `view` doesn't have one.
+    pub(super) fn take_drag_gesture(&mut self, view: HViewRef<'_>) -> bool {
+        if (self.drag_gestures.as_ref()).map_or(false, |drag| drag.view.as_ref() == view) {
+            self.drag_gestures = None;
+            true
+        } else {
+            false
+        }
+    }
+
     /// Cancel drag gestures for `view` (if any).
     ///
     /// If `subview` is `true`, the subviews of `view` are also affected.
@@ -505,6 +518,10 @@ impl Drop for PalDragListener {
 
             let drag = hwnd.wnd.mouse_state.borrow_mut().drag_gestures.take();
             drop(drag);
+
+            // The gesture might have been taken over by an in-app
+            // drag-and-drop operation
+            hwnd.cancel_drag_session();
         } else {
             trace!("Mouse drag gesture ended, but the owner is gone");
         }
@@ -513,24 +530,47 @@ impl Drop for PalDragListener {
 
 /// Forwards events from `pal::iface::MouseDragListener` to
 /// `uicore::MouseDragListener`.
+///
+/// The events are routed to an in-app drag-and-drop operation instead if the
+/// gesture was taken over by one.
 impl pal::iface::MouseDragListener<pal::Wm> for PalDragListener {
     fn mouse_motion(&self, wm: Wm, _: &pal::HWnd, loc: Point2<f32>) {
+        if let Some(hwnd) = self.hwnd() {
+            if hwnd.drag_session_motion(loc) {
+                return;
+            }
+        }
         self.with_drag_gesture(|drag| {
             drag.listener.mouse_motion(wm, drag.view.as_ref(), loc);
         })
     }
     fn mouse_down(&self, wm: Wm, _: &pal::HWnd, loc: Point2<f32>, button: u8) {
+        if let Some(hwnd) = self.hwnd() {
+            if hwnd.has_drag_session() {
+                return;
+            }
+        }
         self.with_drag_gesture(|drag| {
             drag.listener
                 .mouse_down(wm, drag.view.as_ref(), loc, button);
         })
     }
     fn mouse_up(&self, wm: Wm, _: &pal::HWnd, loc: Point2<f32>, button: u8) {
+        if let Some(hwnd) = self.hwnd() {
+            if hwnd.drag_session_drop(loc) {
+                return;
+            }
+        }
         self.with_drag_gesture(|drag| {
             drag.listener.mouse_up(wm, drag.view.as_ref(), loc, button);
         })
     }
     fn cancel(&self, wm: Wm, _: &pal::HWnd) {
+        if let Some(hwnd) = self.hwnd() {
+            if hwnd.cancel_drag_session() {
+                return;
+            }
+        }
         self.with_drag_gesture(|drag| {
             drag.listener.cancel(wm, drag.view.as_ref());
         })
@@ -14,7 +14,7 @@ use std::{
 
 use super::{
     invocation::process_pending_invocations, CursorShape, HView, HViewRef, HWnd, HWndRef,
-    Superview, SuperviewStrong, UpdateCtx, ViewDirtyFlags, ViewFlags, ViewListener, Wnd,
+    Superview, SuperviewStrong, UpdateCtx, ViewDirtyFlags, ViewFlags, ViewListener, WeakHWnd, Wnd,
     WndStyleFlags,
 };
 use crate::pal::{self, prelude::*, Wm};
@@ -622,6 +622,45 @@ impl pal::iface::WndListener<Wm> for PalWndListener {
             Box::new(())
         }
     }
+
+    fn drag_enter(
+        &self,
+        _: Wm,
+        _: &pal::HWnd,
+        loc: Point2<f32>,
+        formats: pal::DragFormats,
+        ops: pal::DragOps,
+    ) -> Option<pal::DragOp> {
+        self.hwnd()?.handle_drag_motion(loc, formats, ops)
+    }
+
+    fn drag_over(
+        &self,
+        _: Wm,
+        _: &pal::HWnd,
+        loc: Point2<f32>,
+        formats: pal::DragFormats,
+        ops: pal::DragOps,
+    ) -> Option<pal::DragOp> {
+        self.hwnd()?.handle_drag_motion(loc, formats, ops)
+    }
+
+    fn drag_leave(&self, _: Wm, _: &pal::HWnd) {
+        if let Some(hwnd) = self.hwnd() {
+            hwnd.handle_drag_leave();
+        }
+    }
+
+    fn drag_drop(
+        &self,
+        _: Wm,
+        _: &pal::HWnd,
+        loc: Point2<f32>,
+        data: &pal::DragData,
+        ops: pal::DragOps,
+    ) -> Option<pal::DragOp> {
+        self.hwnd()?.handle_drag_drop(loc, data, ops)
+    }
 }
 
 pub(crate) fn new_root_content_view() -> HView {
@@ -682,7 +721,7 @@ bitflags! {
     /// called for the next time.
     ///
     /// Be aware that the usage is different from that of `ViewDirtyFlags`.
-    pub struct WndDirtyFlags: u8 {
+    pub struct WndDirtyFlags: u16 {
         /// The root layer should be updated.
         const LAYER = 1;
         /// The window should be resized to the default size.
@@ -691,11 +730,16 @@ bitflags! {
         const STYLE_VISIBLE = 1 << 2;
         const STYLE_FLAGS = 1 << 3;
         const STYLE_CAPTION = 1 << 4;
+        const STYLE_OWNER = 1 << 5;
+        const STYLE_POPUP_ANCHOR = 1 << 6;
 
-        const CONTENTS = 1 << 5;
+        const CONTENTS = 1 << 7;
 
         /// `update` is queued to the main event queue.
-        const UPDATE = 1 << 6;
+        const UPDATE = 1 << 8;
+
+        const STYLE_POSITION = 1 << 9;
+        const STYLE_MODE = 1 << 10;
     }
 }
 
@@ -707,7 +751,10 @@ impl Default for WndDirtyFlags {
 
 impl WndDirtyFlags {
     fn style() -> Self {
-        flags![WndDirtyFlags::{STYLE_VISIBLE | STYLE_FLAGS | STYLE_CAPTION}]
+        flags![WndDirtyFlags::{
+            STYLE_VISIBLE | STYLE_FLAGS | STYLE_CAPTION | STYLE_OWNER | STYLE_POPUP_ANCHOR |
+            STYLE_POSITION | STYLE_MODE
+        }]
     }
 }
 
@@ -716,6 +763,14 @@ pub(super) struct WndStyleAttrs {
     pub flags: WndStyleFlags,
     pub caption: String,
     pub visible: bool,
+    /// The owner window. A weak reference is used so that a popup window
+    /// doesn't keep its owner open.
+    pub owner: Option<WeakHWnd>,
+    pub popup_anchor: Option<pal::PopupAnchor>,
+    /// The position requested by `set_position`. `None` lets the system
+    /// decide.
+    pub position: Option<[i32; 2]>,
+    pub mode: pal::WndMode,
 }
 
 impl Default for WndStyleAttrs {
@@ -724,6 +779,10 @@ impl Default for WndStyleAttrs {
             flags: WndStyleFlags::default(),
             caption: "TCW3 Window".to_owned(),
             visible: false,
+            owner: None,
+            popup_anchor: None,
+            position: None,
+            mode: pal::WndMode::Normal,
         }
     }
 }
@@ -739,5 +798,18 @@ impl WndStyleAttrs {
         if dirty.contains(WndDirtyFlags::STYLE_CAPTION) {
             attrs.caption = Some(self.caption[..].into());
         }
+        if dirty.contains(WndDirtyFlags::STYLE_OWNER) {
+            let owner = self.owner.as_ref().and_then(WeakHWnd::upgrade);
+            attrs.owner = Some(owner.and_then(|hwnd| hwnd.pal_hwnd()));
+        }
+        if dirty.contains(WndDirtyFlags::STYLE_POPUP_ANCHOR) {
+            attrs.popup_anchor = Some(self.popup_anchor);
+        }
+        if dirty.contains(WndDirtyFlags::STYLE_POSITION) {
+            attrs.position = self.position;
+        }
+        if dirty.contains(WndDirtyFlags::STYLE_MODE) {
+            attrs.mode = Some(self.mode);
+        }
     }
 }
@@ -90,3 +90,29 @@ fn invoke_on_next_frame(twm: &dyn TestingWm) {
 
     assert_eq!(count.get(), 3);
 }
+
+#[use_testing_wm]
+#[test]
+fn wnd_position_and_mode(twm: &dyn TestingWm) {
+    let wm = twm.wm();
+    let wnd = HWnd::new(wm);
+    wnd.set_position([120, 80]);
+    wnd.set_mode(pal::WndMode::Maximized);
+
+    // Not materialized yet
+    assert_eq!(wnd.position(), Some([120, 80]));
+    assert_eq!(wnd.mode(), pal::WndMode::Maximized);
+
+    wnd.set_visibility(true);
+    twm.step_unsend();
+
+    let pal_hwnd = try_match!([x] = twm.hwnds().as_slice() => x.clone())
+        .expect("could not get a single window");
+    let attrs = twm.wnd_attrs(&pal_hwnd).unwrap();
+    assert_eq!(attrs.position, [120, 80]);
+    assert_eq!(attrs.mode, pal::WndMode::Maximized);
+
+    wnd.set_mode(pal::WndMode::Normal);
+    twm.step_unsend();
+    assert_eq!(wnd.mode(), pal::WndMode::Normal);
+}